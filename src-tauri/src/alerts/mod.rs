@@ -0,0 +1,162 @@
+//! Internal event bus and user-defined alert rules
+//!
+//! Scans, monitoring rounds and presence tracking publish what changed as
+//! [`AlertEvent`]s. A single subscriber evaluates the user's rules (see
+//! [`rules`]) and emits an `alert-notifications` event for the rules that
+//! match, so "only critical issues on cameras" is decided in one place.
+
+use crate::scanner::{Device, DeviceType, SecurityIssue};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tauri::async_runtime::JoinHandle;
+use tauri::Emitter;
+use tokio::sync::broadcast;
+
+pub mod rules;
+
+/// Events buffered for a slow subscriber before the oldest are dropped
+const BUS_CAPACITY: usize = 256;
+
+static BUS: OnceLock<broadcast::Sender<AlertEvent>> = OnceLock::new();
+
+/// What happened
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A device not in the previous scan
+    NewDevice,
+    /// A finding the device did not have before
+    NewIssue,
+    /// Monitoring saw a risky port open
+    PortOpened,
+    /// A device online at an unusual time, or an unknown one while away
+    UnusualPresence,
+}
+
+/// A change worth telling the user about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub kind: EventKind,
+    pub device_id: String,
+    pub mac: String,
+    pub ip: String,
+    pub name: Option<String>,
+    pub device_type: DeviceType,
+    /// The finding, for events about one
+    pub issue: Option<SecurityIssue>,
+    pub message: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AlertEvent {
+    pub fn new(kind: EventKind, device: &Device, issue: Option<SecurityIssue>, message: String) -> Self {
+        Self {
+            kind,
+            device_id: device.id.clone(),
+            mac: device.mac.clone(),
+            ip: device.ip.clone(),
+            name: device.name.clone(),
+            device_type: device.device_type,
+            issue,
+            message,
+            at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// A rule matched an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub event: AlertEvent,
+}
+
+fn bus() -> &'static broadcast::Sender<AlertEvent> {
+    BUS.get_or_init(|| broadcast::channel(BUS_CAPACITY).0)
+}
+
+/// Publish events; without a subscriber they are dropped
+pub fn publish(events: impl IntoIterator<Item = AlertEvent>) {
+    for event in events {
+        let _ = bus().send(event);
+    }
+}
+
+/// Evaluate the alert rules against published events in the background
+pub fn start(app: tauri::AppHandle) -> JoinHandle<()> {
+    let mut receiver = bus().subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Alert rules skipped {} events", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let rules = match rules::list() {
+                Ok(rules) => rules,
+                Err(e) => {
+                    eprintln!("Failed to load alert rules: {}", e);
+                    continue;
+                }
+            };
+            let local_time = event.at.with_timezone(&chrono::Local).time();
+            let notifications: Vec<Notification> = rules
+                .into_iter()
+                .filter(|rule| rule.matches(&event, local_time))
+                .map(|rule| Notification {
+                    rule_id: rule.id,
+                    rule_name: rule.name,
+                    event: event.clone(),
+                })
+                .collect();
+            if !notifications.is_empty() {
+                let _ = app.emit("alert-notifications", notifications);
+            }
+        }
+    })
+}
+
+fn label(device: &Device) -> String {
+    match &device.name {
+        Some(name) => format!("{} ({})", name, device.ip),
+        None => device.ip.clone(),
+    }
+}
+
+/// Events for what changed between two scans. Nothing is reported without a
+/// previous scan, where every device would be "new".
+pub fn scan_events(previous: &[Device], devices: &[Device]) -> Vec<AlertEvent> {
+    if previous.is_empty() {
+        return Vec::new();
+    }
+
+    let mut events = Vec::new();
+    for device in devices {
+        let before = previous.iter().find(|p| p.mac.eq_ignore_ascii_case(&device.mac));
+        if before.is_none() {
+            events.push(AlertEvent::new(
+                EventKind::NewDevice,
+                device,
+                None,
+                format!("新しい機器が見つかりました: {}", label(device)),
+            ));
+        }
+        for issue in &device.issues {
+            if before.is_some_and(|b| b.issues.iter().any(|i| i.id == issue.id)) {
+                continue;
+            }
+            events.push(AlertEvent::new(
+                EventKind::NewIssue,
+                device,
+                Some(issue.clone()),
+                format!("{}: {}", label(device), issue.title),
+            ));
+        }
+    }
+    events
+}
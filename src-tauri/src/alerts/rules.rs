@@ -0,0 +1,105 @@
+//! Alert rules
+//!
+//! A rule narrows down which events notify the user, e.g. "critical issues
+//! on cameras" or "any new device between 22:00 and 06:00". Every condition
+//! left empty matches everything.
+
+use super::{AlertEvent, EventKind};
+use crate::database::{self, DbError};
+use crate::scanner::{DeviceType, IssueSeverity};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// Local time range; `end` before `start` spans midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.end < self.start {
+            time >= self.start || time < self.end
+        } else {
+            time >= self.start && time < self.end
+        }
+    }
+}
+
+/// A user-defined alert rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertRule {
+    /// Assigned when the rule is first saved
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Events the rule applies to; empty for all
+    pub events: Vec<EventKind>,
+    /// Lowest severity of the finding; events without a finding never match
+    pub min_severity: Option<IssueSeverity>,
+    /// Device types the rule applies to; empty for all
+    pub device_types: Vec<DeviceType>,
+    /// Only events at these local times
+    pub time_window: Option<TimeWindow>,
+}
+
+impl Default for AlertRule {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            enabled: true,
+            events: Vec::new(),
+            min_severity: None,
+            device_types: Vec::new(),
+            time_window: None,
+        }
+    }
+}
+
+impl AlertRule {
+    /// Whether the rule notifies about `event` seen at `local_time`
+    pub fn matches(&self, event: &AlertEvent, local_time: NaiveTime) -> bool {
+        self.enabled
+            && (self.events.is_empty() || self.events.contains(&event.kind))
+            && self
+                .min_severity
+                .map_or(true, |min| event.issue.as_ref().is_some_and(|i| i.severity >= min))
+            && (self.device_types.is_empty() || self.device_types.contains(&event.device_type))
+            && self.time_window.as_ref().map_or(true, |w| w.contains(local_time))
+    }
+}
+
+/// Saved rules
+pub fn list() -> Result<Vec<AlertRule>, DbError> {
+    database::alert_rules::list()
+}
+
+/// Events of the latest scan, compared with the one before, that `rule`
+/// would have notified about
+pub fn test(rule: &AlertRule) -> Result<Vec<AlertEvent>, DbError> {
+    let history = database::get_scan_history()?;
+    let devices = match history.first() {
+        Some(latest) => database::get_scan_devices(&latest.id)?,
+        None => return Ok(Vec::new()),
+    };
+    let previous = match history.get(1) {
+        Some(record) => database::get_scan_devices(&record.id)?,
+        None => Vec::new(),
+    };
+
+    let rule = AlertRule {
+        enabled: true,
+        ..rule.clone()
+    };
+    Ok(super::scan_events(&previous, &devices)
+        .into_iter()
+        .map(|event| AlertEvent {
+            at: history[0].timestamp,
+            ..event
+        })
+        .filter(|event| rule.matches(event, event.at.with_timezone(&chrono::Local).time()))
+        .collect())
+}
@@ -0,0 +1,79 @@
+//! Maintenance of the data shipped with the app
+//!
+//! Usage:
+//! - `iot-doctor-data oui <oui.csv> <output.tsv.gz>`
+//! - `iot-doctor-data keygen <key.pk8>`
+//! - `iot-doctor-data sign-pack <pack.json> <key.pk8> <output.json>`
+//!
+//! `oui` converts the IEEE's MA-L registry (https://standards-oui.ieee.org/oui/oui.csv)
+//! into the bundled `src/scanner/oui.tsv.gz`. `keygen` creates the Ed25519
+//! key fingerprint data packs are signed with and prints its public key;
+//! `sign-pack` signs a pack for publishing.
+
+use iot_doctor_lib::scanner::{datapack, oui};
+use ring::signature::KeyPair;
+
+const USAGE: &str = "Usage: iot-doctor-data oui <oui.csv> <output.tsv.gz>
+       iot-doctor-data keygen <key.pk8>
+       iot-doctor-data sign-pack <pack.json> <key.pk8> <output.json>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["oui", csv, output] => build_oui(csv, output),
+        ["keygen", key] => keygen(key),
+        ["sign-pack", pack, key, output] => sign_pack(pack, key, output),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+fn write(path: &str, contents: &[u8]) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+fn build_oui(csv: &str, output: &str) -> Result<(), String> {
+    let text = String::from_utf8_lossy(&read(csv)?).into_owned();
+    let (compressed, entries) = oui::compress_registry(&text).map_err(|e| e.to_string())?;
+    write(output, &compressed)?;
+    println!("{} assignments written to {}", entries, output);
+    Ok(())
+}
+
+fn keygen(path: &str) -> Result<(), String> {
+    if std::path::Path::new(path).exists() {
+        return Err(format!("{} already exists", path));
+    }
+    let pkcs8 = ring::signature::Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+        .map_err(|_| "Failed to generate key".to_string())?;
+    let key = ring::signature::Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(|e| e.to_string())?;
+    write(path, pkcs8.as_ref())?;
+
+    let bytes: Vec<String> = key.public_key().as_ref().iter().map(|b| format!("0x{:02x}", b)).collect();
+    println!("Private key written to {}; keep it out of the repository.", path);
+    println!("const DATAPACK_PUBLIC_KEY: [u8; 32] = [");
+    for row in bytes.chunks(16) {
+        println!("    {},", row.join(", "));
+    }
+    println!("];");
+    Ok(())
+}
+
+fn sign_pack(pack: &str, key: &str, output: &str) -> Result<(), String> {
+    let payload = String::from_utf8(read(pack)?).map_err(|e| format!("{}: {}", pack, e))?;
+    let signed = datapack::sign(payload, &read(key)?).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&signed).map_err(|e| e.to_string())?;
+    write(output, json.as_bytes())?;
+    println!("Signed pack written to {}", output);
+    Ok(())
+}
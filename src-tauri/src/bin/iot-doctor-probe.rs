@@ -0,0 +1,49 @@
+//! Headless remote probe
+//!
+//! Runs discovery on the network it is attached to for a paired IoT Doctor
+//! app. Prints the pairing code on start.
+//!
+//! Usage: `iot-doctor-probe [--listen <address:port>] [--reset-key]`
+
+use iot_doctor_lib::probe::{self, agent};
+use std::net::SocketAddr;
+
+#[tokio::main]
+async fn main() {
+    let mut listen = SocketAddr::from(([0, 0, 0, 0], probe::DEFAULT_PORT));
+    let mut reset = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => match args.next().and_then(|a| a.parse().ok()) {
+                Some(addr) => listen = addr,
+                None => {
+                    eprintln!("--listen expects an address such as 0.0.0.0:{}", probe::DEFAULT_PORT);
+                    std::process::exit(2);
+                }
+            },
+            "--reset-key" => reset = true,
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                eprintln!("Usage: iot-doctor-probe [--listen <address:port>] [--reset-key]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let key = if reset { agent::reset_key() } else { agent::load_or_create_key() };
+    let key = match key {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("Failed to load pairing key: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Pairing code: {}", probe::pairing_code(&key));
+
+    if let Err(e) = agent::run(listen, key).await {
+        eprintln!("Probe stopped: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,146 @@
+//! User-editable check configuration
+//!
+//! Files in `<data dir>/iot-doctor/config/` customize how scans are evaluated.
+//! They are loaded into memory once and reloaded either on demand
+//! (`reload_configuration`) or automatically when a file changes, so the
+//! next scan picks up edits without restarting the app.
+
+use crate::database;
+use crate::scanner::scoring::SeverityOverride;
+use crate::scanner::{ScanOptions, ScoringPolicy};
+use crate::settings::Settings;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use tauri::Emitter;
+use thiserror::Error;
+
+/// Scoring policy overrides file
+const SCORING_FILE: &str = "scoring.json";
+
+static ACTIVE: RwLock<Option<CheckConfig>> = RwLock::new(None);
+
+/// Keeps the file watcher alive for the lifetime of the app
+static WATCHER: Mutex<Option<notify::RecommendedWatcher>> = Mutex::new(None);
+
+/// Configuration errors
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid configuration in {file}: {source}")]
+    Parse {
+        file: String,
+        source: serde_json::Error,
+    },
+
+    #[error("File watcher error: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+/// Contents of `scoring.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringConfig {
+    pub severity_overrides: Vec<SeverityOverride>,
+}
+
+/// All configuration loaded from the config directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckConfig {
+    pub scoring: ScoringConfig,
+}
+
+/// Directory holding user-editable configuration files
+pub fn config_dir() -> PathBuf {
+    database::app_data_dir().join("config")
+}
+
+fn read_json<T: Default + for<'de> Deserialize<'de>>(path: &Path) -> Result<T, ConfigError> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|source| ConfigError::Parse {
+            file: path.display().to_string(),
+            source,
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Re-read every configuration file. On error the previous configuration stays active.
+pub fn reload() -> Result<CheckConfig, ConfigError> {
+    let dir = config_dir();
+    let config = CheckConfig {
+        scoring: read_json(&dir.join(SCORING_FILE))?,
+    };
+
+    *ACTIVE.write().unwrap() = Some(config.clone());
+    Ok(config)
+}
+
+/// Currently active configuration, loading it on first use
+pub fn active() -> CheckConfig {
+    if let Some(config) = ACTIVE.read().unwrap().as_ref() {
+        return config.clone();
+    }
+    reload().unwrap_or_default()
+}
+
+fn policy_for(settings: &Settings) -> ScoringPolicy {
+    ScoringPolicy::new(settings.context).with_overrides(active().scoring.severity_overrides)
+}
+
+/// Scoring policy built from the current settings and configuration files
+pub fn scoring_policy() -> ScoringPolicy {
+    policy_for(&crate::settings::load().unwrap_or_default())
+}
+
+/// Options for the next scan from the current settings and configuration files
+pub fn scan_options() -> ScanOptions {
+    let settings = crate::settings::load().unwrap_or_default();
+    ScanOptions {
+        policy: policy_for(&settings),
+        exposure: settings.exposure,
+        router: settings.router,
+        max_duration: settings.scan_budget.max_duration(),
+        sweep: settings.sweep,
+        strictly_passive: settings.safe_mode.strictly_passive,
+        port_config: crate::scanner::ports::load_config().unwrap_or_default(),
+        excluded: crate::database::metadata::all()
+            .unwrap_or_default()
+            .into_values()
+            .filter(|m| m.excluded)
+            .map(|m| m.mac)
+            .collect(),
+        ..ScanOptions::default()
+    }
+}
+
+/// Watch the config directory and reload whenever a file changes.
+/// Emits `configuration-reloaded` on success and `configuration-error` on failure.
+pub fn watch(app: tauri::AppHandle) -> Result<(), ConfigError> {
+    let dir = config_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        if !(event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove()) {
+            return;
+        }
+        match reload() {
+            Ok(config) => {
+                let _ = app.emit("configuration-reloaded", config);
+            }
+            Err(e) => {
+                let _ = app.emit("configuration-error", e.to_string());
+            }
+        }
+    })?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    *WATCHER.lock().unwrap() = Some(watcher);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+//! Local control interface for automation
+//!
+//! Shell scripts and cron jobs on the same machine can drive the app through
+//! a Unix socket (`<data dir>/control/control.sock`, in an owner-only
+//! directory) or, on Windows, the named pipe `\\.\pipe\iot-doctor`. Each
+//! request is one line of JSON and gets one line of JSON back:
+//!
+//! ```text
+//! {"command": "scan", "level": "level2"}
+//! {"command": "status"}
+//! {"command": "last_report", "format": "text"}
+//! ```
+//!
+//! Requests are served by the same handlers as the UI's commands.
+
+use crate::database::{self, ScanRecord};
+use crate::error::{AppError, ErrorKind};
+use crate::monitor::quiet;
+use crate::report::{ReportFormat, ReportMode};
+use crate::scanner::{self, ScanLevel, ScanProgress};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Named pipe used on Windows
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\iot-doctor";
+
+/// Control interface settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+    /// Listen for local automation requests (takes effect on restart)
+    pub enabled: bool,
+}
+
+/// A request line
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Scan {
+        #[serde(default)]
+        level: ScanLevel,
+    },
+    Status,
+    LastReport {
+        #[serde(default = "default_format")]
+        format: ReportFormat,
+        #[serde(default)]
+        mode: Option<ReportMode>,
+    },
+}
+
+fn default_format() -> ReportFormat {
+    ReportFormat::Text
+}
+
+/// A response line
+#[derive(Debug, Serialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
+}
+
+/// Result of the `status` command
+#[derive(Debug, Serialize)]
+pub struct ControlStatus {
+    pub scanning: bool,
+    pub progress: Option<ScanProgress>,
+    pub last_scan: Option<ScanRecord>,
+}
+
+/// Start serving the control interface in the background
+pub fn start(app: tauri::AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(app).await {
+            eprintln!("Control interface stopped: {}", e);
+        }
+    })
+}
+
+#[cfg(unix)]
+async fn listen(app: tauri::AppHandle) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    // The socket is created with the process umask; binding it inside an
+    // owner-only directory keeps other users out from the start
+    let dir = database::app_data_dir().join("control");
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+
+    let path = dir.join("control.sock");
+    // Left behind if the previous run did not exit cleanly
+    let _ = std::fs::remove_file(&path);
+
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(serve(app.clone(), stream));
+    }
+}
+
+#[cfg(windows)]
+async fn listen(app: tauri::AppHandle) -> std::io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(PIPE_NAME)?;
+
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = ServerOptions::new().reject_remote_clients(true).create(PIPE_NAME)?;
+        tokio::spawn(serve(app.clone(), connected));
+    }
+}
+
+async fn serve<S: AsyncRead + AsyncWrite>(app: tauri::AppHandle, stream: S) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match handle(&app, &line).await {
+            Ok(result) => ControlResponse {
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => ControlResponse {
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        };
+
+        let mut json = serde_json::to_string(&response).unwrap_or_default();
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn handle(app: &tauri::AppHandle, line: &str) -> Result<serde_json::Value, AppError> {
+    let request: ControlRequest = serde_json::from_str(line)
+        .map_err(|e| AppError::invalid_input(format!("Invalid request: {}", e)))?;
+
+    let result = match request {
+        ControlRequest::Scan { level } => {
+            if scanner::is_scan_running() {
+                return Err(AppError::new(ErrorKind::InvalidInput, "A scan is already running"));
+            }
+            let Some(level) = quiet::allowed_level(level) else {
+                return Err(AppError::new(ErrorKind::Cancelled, "Quiet hours are in effect")
+                    .with_hint("静かな時間帯の設定を確認してください"));
+            };
+            crate::require_scan_level(level)?;
+            serde_json::to_value(crate::run_scan(app.clone(), level, None, None, None, true).await?)
+        }
+        ControlRequest::Status => {
+            let last_scan = database::get_scan_history()?.into_iter().next();
+            serde_json::to_value(ControlStatus {
+                scanning: scanner::is_scan_running(),
+                progress: scanner::current_progress(),
+                last_scan,
+            })
+        }
+        ControlRequest::LastReport { format, mode } => {
+            let devices = database::get_latest_scan_devices()?;
+            serde_json::to_value(crate::generate_report(devices, format, mode, Some(scanner::network_issues())).await?)
+        }
+    };
+
+    result.map_err(|e| AppError::new(ErrorKind::Internal, e.to_string()))
+}
@@ -0,0 +1,37 @@
+//! Storage of alert rules
+
+use super::{DbError, DB};
+use crate::alerts::rules::AlertRule;
+
+/// All rules, in the order they were created
+pub fn list() -> Result<Vec<AlertRule>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT data FROM alert_rules ORDER BY rowid")?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.iter()
+        .map(|data| serde_json::from_str(data).map_err(DbError::from))
+        .collect()
+}
+
+/// Insert or replace a rule, assigning an ID to a new one
+pub fn save(mut rule: AlertRule) -> Result<AlertRule, DbError> {
+    if rule.id.is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute(
+        "INSERT INTO alert_rules (id, data) VALUES (?1, ?2)
+         ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+        (&rule.id, serde_json::to_string(&rule)?),
+    )?;
+    Ok(rule)
+}
+
+/// Delete a rule; unknown IDs are ignored
+pub fn delete(id: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute("DELETE FROM alert_rules WHERE id = ?1", [id])?;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Audit log
+//!
+//! Every change the app makes outside itself, such as removing a port
+//! mapping from the router, is recorded with who asked for it and whether it
+//! worked, so the user can later see what was done to their network.
+
+use super::{DbError, DB};
+use serde::{Deserialize, Serialize};
+
+/// A recorded action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Machine-readable action, e.g. `delete_port_mapping`
+    pub action: String,
+    /// Device or object the action was applied to
+    pub target: String,
+    pub detail: String,
+    /// Operator profile that requested the action
+    pub profile: Option<String>,
+    pub success: bool,
+}
+
+/// Record an action
+pub fn record(action: &str, target: &str, detail: &str, profile: Option<&str>, success: bool) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute(
+        "INSERT INTO audit_log (timestamp, action, target, detail, profile, success)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (chrono::Utc::now().to_rfc3339(), action, target, detail, profile, success),
+    )?;
+    Ok(())
+}
+
+/// Most recent entries, newest first
+pub fn list(limit: usize) -> Result<Vec<AuditEntry>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, action, target, detail, profile, success
+         FROM audit_log ORDER BY id DESC LIMIT ?1",
+    )?;
+    let entries = stmt.query_map([limit as i64], |row| {
+        let timestamp: String = row.get(1)?;
+        Ok(AuditEntry {
+            id: row.get(0)?,
+            timestamp: chrono::DateTime::parse_from_rfc3339(&timestamp)
+                .map(|dt| dt.with_timezone(&chrono::Utc))
+                .unwrap_or_else(|_| chrono::Utc::now()),
+            action: row.get(2)?,
+            target: row.get(3)?,
+            detail: row.get(4)?,
+            profile: row.get(5)?,
+            success: row.get(6)?,
+        })
+    })?;
+    entries.collect::<Result<Vec<_>, _>>().map_err(DbError::from)
+}
@@ -0,0 +1,274 @@
+//! Issue lifecycle across scans
+//!
+//! Findings are tracked per (MAC address, issue ID) instead of being treated
+//! as brand new on every scan. A finding is new the first time it shows up,
+//! recurring while it stays, resolved once a scan that runs its check no
+//! longer finds it, and regressed when it comes back after that. The user can
+//! acknowledge an open finding; it stays acknowledged until it is resolved.
+//! The state each scan left a finding in is kept as well, for comparisons.
+
+use super::{DbError, DB};
+use crate::scanner::{Device, IssueSeverity, ScanLevel};
+use crate::vulndb::catalog;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Where a finding is in its lifecycle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueState {
+    New,
+    Recurring,
+    Resolved,
+    Regressed,
+    Acknowledged,
+}
+
+impl IssueState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IssueState::New => "new",
+            IssueState::Recurring => "recurring",
+            IssueState::Resolved => "resolved",
+            IssueState::Regressed => "regressed",
+            IssueState::Acknowledged => "acknowledged",
+        }
+    }
+
+    fn parse(state: &str) -> Self {
+        match state {
+            "new" => IssueState::New,
+            "resolved" => IssueState::Resolved,
+            "regressed" => IssueState::Regressed,
+            "acknowledged" => IssueState::Acknowledged,
+            _ => IssueState::Recurring,
+        }
+    }
+}
+
+/// A finding of one device and where it stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueRecord {
+    pub mac: String,
+    pub issue_id: String,
+    /// Title as of the last scan that found it
+    pub title: String,
+    pub severity: IssueSeverity,
+    pub state: IssueState,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub acknowledged_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Number of findings in each state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueStateCounts {
+    pub new: usize,
+    pub recurring: usize,
+    pub resolved: usize,
+    pub regressed: usize,
+    pub acknowledged: usize,
+}
+
+impl IssueStateCounts {
+    fn add(&mut self, state: IssueState) {
+        match state {
+            IssueState::New => self.new += 1,
+            IssueState::Recurring => self.recurring += 1,
+            IssueState::Resolved => self.resolved += 1,
+            IssueState::Regressed => self.regressed += 1,
+            IssueState::Acknowledged => self.acknowledged += 1,
+        }
+    }
+}
+
+pub(super) fn create_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS issues (
+            mac TEXT NOT NULL,
+            issue_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            severity TEXT NOT NULL,
+            state TEXT NOT NULL,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            resolved_at TEXT,
+            acknowledged_at TEXT,
+            PRIMARY KEY (mac, issue_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS issue_history (
+            scan_id TEXT NOT NULL,
+            mac TEXT NOT NULL,
+            issue_id TEXT NOT NULL,
+            state TEXT NOT NULL,
+            PRIMARY KEY (scan_id, mac, issue_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Whether the scan that produced `device` ran the check behind `issue_id`
+fn was_checked(device: &Device, issue_id: &str) -> bool {
+    // Findings not in the catalog come from port scans and service probes
+    let required = catalog::check_level(issue_id).unwrap_or(ScanLevel::Level2);
+    // CVEs of the maker and model are unknown while their lookup is pending
+    let pending = device.enrichment_pending && issue_id.starts_with("CVE-");
+    device.reduced_coverage.is_empty() && !pending && device.scan_level.is_some_and(|level| level >= required)
+}
+
+/// Advance the lifecycle of every finding of the scanned devices
+pub(super) fn track(
+    conn: &Connection,
+    scan_id: &str,
+    devices: &[Device],
+    at: chrono::DateTime<chrono::Utc>,
+) -> Result<(), DbError> {
+    let at = at.to_rfc3339();
+    for device in devices {
+        let mac = device.mac.to_lowercase();
+        let previous: HashMap<String, IssueState> = conn
+            .prepare("SELECT issue_id, state FROM issues WHERE mac = ?1")?
+            .query_map([&mac], |row| {
+                Ok((row.get::<_, String>(0)?, IssueState::parse(&row.get::<_, String>(1)?)))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut present = HashSet::new();
+        for issue in device.issues.iter().filter(|i| present.insert(i.id.as_str())) {
+            let state = match previous.get(&issue.id) {
+                None => IssueState::New,
+                Some(IssueState::Resolved) => IssueState::Regressed,
+                Some(IssueState::Acknowledged) => IssueState::Acknowledged,
+                Some(_) => IssueState::Recurring,
+            };
+            conn.execute(
+                "INSERT INTO issues (mac, issue_id, title, severity, state, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT (mac, issue_id) DO UPDATE SET
+                    title = excluded.title,
+                    severity = excluded.severity,
+                    state = excluded.state,
+                    last_seen = excluded.last_seen,
+                    resolved_at = NULL",
+                (&mac, &issue.id, &issue.title, serde_json::to_string(&issue.severity)?, state.as_str(), &at),
+            )?;
+            record_history(conn, scan_id, &mac, &issue.id, state)?;
+        }
+
+        let resolved = previous
+            .iter()
+            .filter(|(id, state)| **state != IssueState::Resolved && !present.contains(id.as_str()))
+            .filter(|(id, _)| was_checked(device, id));
+        for (issue_id, _) in resolved {
+            conn.execute(
+                "UPDATE issues SET state = ?3, resolved_at = ?4, acknowledged_at = NULL
+                 WHERE mac = ?1 AND issue_id = ?2",
+                (&mac, issue_id, IssueState::Resolved.as_str(), &at),
+            )?;
+            record_history(conn, scan_id, &mac, issue_id, IssueState::Resolved)?;
+        }
+    }
+    Ok(())
+}
+
+fn record_history(
+    conn: &Connection,
+    scan_id: &str,
+    mac: &str,
+    issue_id: &str,
+    state: IssueState,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO issue_history (scan_id, mac, issue_id, state) VALUES (?1, ?2, ?3, ?4)",
+        (scan_id, mac, issue_id, state.as_str()),
+    )?;
+    Ok(())
+}
+
+fn parse_time(value: Option<String>) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(&value?).ok().map(|t| t.with_timezone(&chrono::Utc))
+}
+
+/// Every finding ever recorded for a device, open ones first
+pub fn for_device(mac: &str) -> Result<Vec<IssueRecord>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt = conn.prepare(
+        "SELECT mac, issue_id, title, severity, state, first_seen, last_seen, resolved_at, acknowledged_at
+         FROM issues WHERE mac = ?1 ORDER BY last_seen DESC",
+    )?;
+    let mut records = stmt
+        .query_map([mac.to_lowercase()], |row| {
+            Ok(IssueRecord {
+                mac: row.get(0)?,
+                issue_id: row.get(1)?,
+                title: row.get(2)?,
+                severity: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or(IssueSeverity::Info),
+                state: IssueState::parse(&row.get::<_, String>(4)?),
+                first_seen: parse_time(row.get(5)?).unwrap_or_default(),
+                last_seen: parse_time(row.get(6)?).unwrap_or_default(),
+                resolved_at: parse_time(row.get(7)?),
+                acknowledged_at: parse_time(row.get(8)?),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    records.sort_by_key(|r| r.state == IssueState::Resolved);
+    Ok(records)
+}
+
+/// Acknowledge an open finding, or withdraw the acknowledgement.
+/// Returns whether the finding was open.
+pub fn set_acknowledged(mac: &str, issue_id: &str, acknowledged: bool) -> Result<bool, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mac = mac.to_lowercase();
+    let current: Option<String> = conn
+        .query_row(
+            "SELECT state FROM issues WHERE mac = ?1 AND issue_id = ?2",
+            (&mac, issue_id),
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(current) = current.map(|s| IssueState::parse(&s)).filter(|s| *s != IssueState::Resolved) else {
+        return Ok(false);
+    };
+
+    let (state, acknowledged_at) = match (acknowledged, current) {
+        (true, _) => (IssueState::Acknowledged, Some(chrono::Utc::now().to_rfc3339())),
+        (false, IssueState::Acknowledged) => (IssueState::Recurring, None),
+        (false, state) => (state, None),
+    };
+    conn.execute(
+        "UPDATE issues SET state = ?3, acknowledged_at = ?4 WHERE mac = ?1 AND issue_id = ?2",
+        (&mac, issue_id, state.as_str(), acknowledged_at),
+    )?;
+    // The latest scan's count reflects the decision too
+    conn.execute(
+        "UPDATE issue_history SET state = ?3
+         WHERE mac = ?1 AND issue_id = ?2 AND scan_id = (
+            SELECT h.scan_id FROM issue_history h JOIN scans s ON s.id = h.scan_id
+            WHERE h.mac = ?1 AND h.issue_id = ?2 ORDER BY s.timestamp DESC LIMIT 1
+         )",
+        (&mac, issue_id, state.as_str()),
+    )?;
+    Ok(true)
+}
+
+/// Findings per state as a scan left them
+pub fn counts_for_scan(scan_id: &str) -> Result<IssueStateCounts, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT state FROM issue_history WHERE scan_id = ?1")?;
+    let states = stmt
+        .query_map([scan_id], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut counts = IssueStateCounts::default();
+    for state in states {
+        counts.add(IssueState::parse(&state));
+    }
+    Ok(counts)
+}
@@ -0,0 +1,144 @@
+//! Data directory resolution and relocation
+//!
+//! Data lives in the per-user app data directory resolved by Tauri unless the
+//! user points it elsewhere (e.g. a NAS share). The override is kept in
+//! `location.json` inside the default directory, since settings themselves
+//! live in the database being relocated.
+
+use super::{open, DbError, DB, DB_FILE};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Records a custom data directory
+const LOCATION_FILE: &str = "location.json";
+
+/// Directories copied along with the database when relocating
+const DATA_SUBDIRS: &[&str] = &["config", "datapacks"];
+
+/// Default directory and the active (possibly custom) one
+static DIRS: RwLock<Option<(PathBuf, PathBuf)>> = RwLock::new(None);
+
+/// Current data directory for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataLocation {
+    pub data_dir: PathBuf,
+    pub default_dir: PathBuf,
+    pub is_custom: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocationFile {
+    data_dir: Option<PathBuf>,
+}
+
+/// Directory used by versions that did not resolve the path through Tauri
+fn legacy_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("iot-doctor")
+}
+
+/// Resolve the data directory. `default_dir` is Tauri's per-user app data
+/// directory; data from the legacy location is moved there on first start.
+pub fn init(default_dir: Option<PathBuf>) {
+    let default_dir = default_dir.unwrap_or_else(legacy_dir);
+
+    let legacy = legacy_dir();
+    if legacy != default_dir && legacy.join(DB_FILE).exists() && !default_dir.join(DB_FILE).exists() {
+        if let Err(e) = copy_data(&legacy, &default_dir) {
+            eprintln!("Failed to migrate data from {}: {}", legacy.display(), e);
+        }
+    }
+
+    let custom = std::fs::read_to_string(default_dir.join(LOCATION_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str::<LocationFile>(&text).ok())
+        .and_then(|location| location.data_dir);
+
+    let active = custom.unwrap_or_else(|| default_dir.clone());
+    *DIRS.write().unwrap() = Some((default_dir, active));
+}
+
+/// Active data directory
+pub fn data_dir() -> PathBuf {
+    match DIRS.read().unwrap().as_ref() {
+        Some((_, active)) => active.clone(),
+        None => legacy_dir(),
+    }
+}
+
+/// Describe the active data directory
+pub fn current() -> DataLocation {
+    let (default_dir, data_dir) = DIRS
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| (legacy_dir(), legacy_dir()));
+    DataLocation {
+        is_custom: data_dir != default_dir,
+        data_dir,
+        default_dir,
+    }
+}
+
+/// Move to a new data directory (`None` restores the default).
+///
+/// If the target already holds a database it is used as-is, so several
+/// machines can share a directory; otherwise the current data is copied
+/// there. The old directory is left untouched.
+pub fn change(new_dir: Option<PathBuf>) -> Result<DataLocation, DbError> {
+    let location = current();
+    let target = new_dir.unwrap_or_else(|| location.default_dir.clone());
+    if target == location.data_dir {
+        return Ok(location);
+    }
+    std::fs::create_dir_all(&target)?;
+
+    // Hold the lock throughout so no writes land in the old database mid-move
+    let mut conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let target_db = target.join(DB_FILE);
+    if !target_db.exists() {
+        conn.execute("VACUUM INTO ?1", [target_db.to_string_lossy()])?;
+        copy_subdirs(&location.data_dir, &target)?;
+    }
+    *conn = open(&target_db)?;
+
+    let file = LocationFile {
+        data_dir: (target != location.default_dir).then(|| target.clone()),
+    };
+    std::fs::create_dir_all(&location.default_dir)?;
+    std::fs::write(
+        location.default_dir.join(LOCATION_FILE),
+        serde_json::to_string_pretty(&file)?,
+    )?;
+
+    *DIRS.write().unwrap() = Some((location.default_dir, target));
+    Ok(current())
+}
+
+/// Copy the database file and data subdirectories (used before the database is opened)
+fn copy_data(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    std::fs::copy(from.join(DB_FILE), to.join(DB_FILE))?;
+    copy_subdirs(from, to)
+}
+
+fn copy_subdirs(from: &Path, to: &Path) -> std::io::Result<()> {
+    for name in DATA_SUBDIRS {
+        let source = from.join(name);
+        let target = to.join(name);
+        if !source.is_dir() || target.exists() {
+            continue;
+        }
+        std::fs::create_dir_all(&target)?;
+        for entry in std::fs::read_dir(&source)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                std::fs::copy(entry.path(), target.join(entry.file_name()))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! User-assigned device metadata
+//!
+//! Tags, exclusion, criticality and the asset profile (room, purchase date,
+//! photo, ...) are kept per MAC address, separately from scan results, so
+//! they survive rescans. Bulk updates take lists of MACs and are written in a
+//! single transaction: either every device is updated or none.
+
+use super::{DbError, DB};
+use rusqlite::{OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How much it matters if a device is compromised
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Criticality {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Largest photo accepted, after base64 encoding
+const MAX_PHOTO_LEN: usize = 512 * 1024;
+
+/// What the household knows about a device, as in an asset register
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceProfile {
+    /// Name of one of the frontend's device icons
+    pub icon: Option<String>,
+    /// `data:image/...;base64,` URL
+    pub photo: Option<String>,
+    /// Room or location, e.g. "リビング"
+    pub room: Option<String>,
+    pub purchase_date: Option<chrono::NaiveDate>,
+    pub warranty_until: Option<chrono::NaiveDate>,
+}
+
+impl DeviceProfile {
+    /// Check that the photo is an inline image of acceptable size
+    pub fn validate(&self) -> Result<(), String> {
+        match self.photo.as_deref() {
+            Some(photo) if !photo.starts_with("data:image/") => {
+                Err("Photo must be a data:image URL".to_string())
+            }
+            Some(photo) if photo.len() > MAX_PHOTO_LEN => {
+                Err(format!("Photo is larger than {} KB", MAX_PHOTO_LEN / 1024))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Metadata of one device
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceMetadata {
+    pub mac: String,
+    pub tags: Vec<String>,
+    /// Left out of monitoring and active checks
+    pub excluded: bool,
+    pub criticality: Criticality,
+    #[serde(flatten)]
+    pub profile: DeviceProfile,
+}
+
+fn read(tx: &Transaction, mac: &str) -> Result<DeviceMetadata, DbError> {
+    let json: Option<String> = tx
+        .query_row("SELECT data FROM device_metadata WHERE mac = ?1", [mac], |row| row.get(0))
+        .optional()?;
+    let mut metadata: DeviceMetadata = match json {
+        Some(json) => serde_json::from_str(&json)?,
+        None => DeviceMetadata::default(),
+    };
+    metadata.mac = mac.to_string();
+    Ok(metadata)
+}
+
+/// Apply `update` to each device's metadata in one transaction.
+/// Returns the number of devices updated.
+fn update_all(macs: &[String], update: impl Fn(&mut DeviceMetadata)) -> Result<usize, DbError> {
+    let mut conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let tx = conn.transaction()?;
+    let mut macs: Vec<String> = macs.iter().map(|m| m.to_lowercase()).collect();
+    macs.sort();
+    macs.dedup();
+    for mac in &macs {
+        let mut metadata = read(&tx, mac)?;
+        update(&mut metadata);
+        tx.execute(
+            "INSERT OR REPLACE INTO device_metadata (mac, data) VALUES (?1, ?2)",
+            (mac, serde_json::to_string(&metadata)?),
+        )?;
+    }
+    tx.commit()?;
+    Ok(macs.len())
+}
+
+/// Add tags to devices; tags a device already has are not duplicated
+pub fn tag_devices(macs: &[String], tags: &[String]) -> Result<usize, DbError> {
+    update_all(macs, |metadata| {
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !metadata.tags.iter().any(|t| t == tag) {
+                metadata.tags.push(tag.to_string());
+            }
+        }
+    })
+}
+
+/// Exclude devices from (or return them to) monitoring and active checks
+pub fn exclude_devices(macs: &[String], excluded: bool) -> Result<usize, DbError> {
+    update_all(macs, |metadata| metadata.excluded = excluded)
+}
+
+/// Set the criticality of devices
+pub fn set_criticality(macs: &[String], criticality: Criticality) -> Result<usize, DbError> {
+    update_all(macs, |metadata| metadata.criticality = criticality)
+}
+
+/// Replace a device's profile
+pub fn update_profile(mac: &str, profile: DeviceProfile) -> Result<(), DbError> {
+    update_all(&[mac.to_string()], |metadata| metadata.profile = profile.clone())?;
+    Ok(())
+}
+
+/// Metadata of all devices that have any, keyed by lowercase MAC
+pub fn all() -> Result<HashMap<String, DeviceMetadata>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare("SELECT mac, data FROM device_metadata")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut metadata = HashMap::new();
+    for (mac, json) in rows {
+        let mut entry: DeviceMetadata = serde_json::from_str(&json)?;
+        entry.mac = mac.clone();
+        metadata.insert(mac, entry);
+    }
+    Ok(metadata)
+}
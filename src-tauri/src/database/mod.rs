@@ -1,12 +1,28 @@
 //! Database operations for storing scan history and device information
 
 use crate::scanner::Device;
+use crate::vulndb::content;
 use rusqlite::{Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use thiserror::Error;
 
+pub mod alert_rules;
+pub mod audit;
+pub mod issues;
+pub mod location;
+pub mod metadata;
+pub mod monitor;
+pub mod network;
+pub mod presence;
+pub mod schedules;
+pub mod sync;
+pub mod viewer;
+
+/// Database file name within the data directory
+const DB_FILE: &str = "data.db";
+
 static DB: std::sync::OnceLock<Mutex<Connection>> = std::sync::OnceLock::new();
 
 /// Database errors
@@ -20,6 +36,15 @@ pub enum DbError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unsupported bundle format version: {0}")]
+    UnsupportedFormat(u32),
+
+    #[error("No IoT Doctor report found in the file")]
+    NotAReport,
 }
 
 /// Scan history record
@@ -30,18 +55,37 @@ pub struct ScanRecord {
     pub device_count: usize,
     pub average_score: u8,
     pub issues_found: usize,
+    /// Installation that recorded the scan
+    #[serde(default)]
+    pub installation_id: Option<String>,
+    /// Network the scan was taken on (gateway MAC address)
+    #[serde(default)]
+    pub network_id: Option<String>,
+    /// Only some devices were scanned (a quick refresh or chosen ranges), so
+    /// the scan is no baseline for the next one
+    #[serde(default)]
+    pub partial: bool,
+    /// Timestamp formatted for display with the user's locale settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_timestamp: Option<String>,
 }
 
 /// Initialize database
 pub fn init() -> Result<(), DbError> {
-    let db_path = get_db_path();
+    let conn = open(&get_db_path())?;
+    DB.set(Mutex::new(conn)).ok();
+
+    Ok(())
+}
 
+/// Open the database at `db_path`, creating and migrating the schema as needed
+fn open(db_path: &Path) -> Result<Connection, DbError> {
     // Ensure parent directory exists
     if let Some(parent) = db_path.parent() {
         std::fs::create_dir_all(parent).ok();
     }
 
-    let conn = Connection::open(&db_path)?;
+    let conn = Connection::open(db_path)?;
 
     // Create tables
     conn.execute(
@@ -70,20 +114,221 @@ pub fn init() -> Result<(), DbError> {
         [],
     )?;
 
-    DB.set(Mutex::new(conn)).ok();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS http_cache (
+            url TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            fetched_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS vuln_matches (
+            fingerprint TEXT PRIMARY KEY,
+            data_version TEXT NOT NULL,
+            results TEXT NOT NULL,
+            checked_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS request_queue (
+            url TEXT PRIMARY KEY,
+            queued_at TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS presence (
+            mac TEXT NOT NULL,
+            hour INTEGER NOT NULL,
+            sightings INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (mac, hour)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS device_presence (
+            mac TEXT PRIMARY KEY,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            last_ip TEXT,
+            network_id TEXT,
+            online INTEGER NOT NULL DEFAULT 0,
+            sightings INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS presence_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            mac TEXT NOT NULL,
+            at TEXT NOT NULL,
+            online INTEGER NOT NULL,
+            ip TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_presence_events_mac ON presence_events(mac)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS networks (
+            network_id TEXT PRIMARY KEY,
+            isp TEXT,
+            public_ip TEXT,
+            router_model TEXT,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS device_metadata (
+            mac TEXT PRIMARY KEY,
+            data TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            action TEXT NOT NULL,
+            target TEXT NOT NULL,
+            detail TEXT NOT NULL,
+            profile TEXT,
+            success INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS alert_rules (
+            id TEXT PRIMARY KEY,
+            data TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS monitor_observations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            device_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            observed_at TEXT NOT NULL,
+            data TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_monitor_observations_device ON monitor_observations(device_id)",
+        [],
+    )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS scan_schedules (
+            id TEXT PRIMARY KEY,
+            data TEXT NOT NULL,
+            last_run TEXT
+        )",
+        [],
+    )?;
+
+    issues::create_tables(&conn)?;
+
+    // Migrations for databases created by earlier versions
+    add_column_if_missing(&conn, "scans", "installation_id", "TEXT")?;
+    add_column_if_missing(&conn, "scans", "network_id", "TEXT")?;
+    add_column_if_missing(&conn, "scans", "partial", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Devices of scans saved before presence tracking; their online state is unknown
+    let tracked: i64 = conn.query_row("SELECT COUNT(*) FROM device_presence", [], |row| row.get(0))?;
+    if tracked == 0 {
+        conn.execute(
+            "INSERT OR IGNORE INTO device_presence (mac, first_seen, last_seen, sightings)
+             SELECT lower(json_extract(d.data, '$.mac')), MIN(s.timestamp), MAX(s.timestamp), COUNT(*)
+             FROM devices d JOIN scans s ON s.id = d.scan_id
+             WHERE json_extract(d.data, '$.mac') IS NOT NULL
+             GROUP BY lower(json_extract(d.data, '$.mac'))",
+            [],
+        )?;
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO settings (key, value) VALUES ('installation_id', ?1)",
+        [uuid::Uuid::new_v4().to_string()],
+    )?;
+
+    Ok(conn)
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
     Ok(())
 }
 
+/// Persistent ID of this installation, generated on first start
+pub fn installation_id() -> Result<String, DbError> {
+    get_setting("installation_id")?.ok_or(DbError::NotInitialized)
+}
+
+/// Identify the network by its gateway's MAC address
+pub fn network_id(devices: &[Device]) -> Option<String> {
+    let gateway = crate::scanner::arp::get_default_gateway()?.to_string();
+    devices
+        .iter()
+        .find(|d| d.ip == gateway)
+        .map(|d| d.mac.to_lowercase())
+}
+
+/// Directory holding the database, configuration and data packs
+pub fn app_data_dir() -> PathBuf {
+    location::data_dir()
+}
+
 fn get_db_path() -> PathBuf {
-    dirs::data_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("iot-doctor")
-        .join("data.db")
+    app_data_dir().join(DB_FILE)
 }
 
-/// Save scan results
-pub fn save_scan(devices: &[Device]) -> Result<String, DbError> {
+/// Save scan results; `partial` when only some devices were scanned
+pub fn save_scan(devices: &[Device], partial: bool) -> Result<String, DbError> {
+    let installation_id = installation_id()?;
+    let network_id = network_id(devices);
     let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
 
     let scan_id = uuid::Uuid::new_v4().to_string();
@@ -97,8 +342,9 @@ pub fn save_scan(devices: &[Device]) -> Result<String, DbError> {
     let issues_found: usize = devices.iter().map(|d| d.issues.len()).sum();
 
     conn.execute(
-        "INSERT INTO scans (id, timestamp, device_count, average_score, issues_found) VALUES (?1, ?2, ?3, ?4, ?5)",
-        (&scan_id, timestamp.to_rfc3339(), device_count, average_score, issues_found),
+        "INSERT INTO scans (id, timestamp, device_count, average_score, issues_found, installation_id, network_id, partial)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        (&scan_id, timestamp.to_rfc3339(), device_count, average_score, issues_found, &installation_id, &network_id, partial),
     )?;
 
     for device in devices {
@@ -108,6 +354,7 @@ pub fn save_scan(devices: &[Device]) -> Result<String, DbError> {
             (&device.id, &scan_id, &device_json),
         )?;
     }
+    issues::track(&conn, &scan_id, devices, timestamp)?;
 
     Ok(scan_id)
 }
@@ -117,24 +364,68 @@ pub fn get_scan_history() -> Result<Vec<ScanRecord>, DbError> {
     let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
 
     let mut stmt = conn.prepare(
-        "SELECT id, timestamp, device_count, average_score, issues_found FROM scans ORDER BY timestamp DESC LIMIT 50"
-    )?;
-
-    let records = stmt.query_map([], |row| {
-        Ok(ScanRecord {
-            id: row.get(0)?,
-            timestamp: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .unwrap_or_else(|_| chrono::Utc::now()),
-            device_count: row.get(2)?,
-            average_score: row.get(3)?,
-            issues_found: row.get(4)?,
-        })
-    })?;
+        &format!("SELECT {} FROM scans ORDER BY timestamp DESC LIMIT 50", SCAN_COLUMNS),
+    )?;
+
+    let records = stmt.query_map([], row_to_scan_record)?;
 
     records.collect::<Result<Vec<_>, _>>().map_err(DbError::from)
 }
 
+/// Get a single scan record
+pub fn get_scan(scan_id: &str) -> Result<Option<ScanRecord>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scans WHERE id = ?1", SCAN_COLUMNS))?;
+    let mut rows = stmt.query_map([scan_id], row_to_scan_record)?;
+
+    rows.next().transpose().map_err(DbError::from)
+}
+
+/// Get the scans of the whole network recorded within a period, oldest
+/// first; partial scans would skew scores and device counts
+pub fn get_scans_between(
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<ScanRecord>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare(
+        &format!("SELECT {} FROM scans WHERE partial = 0 ORDER BY timestamp ASC", SCAN_COLUMNS),
+    )?;
+
+    let records = stmt
+        .query_map([], row_to_scan_record)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Timestamps are compared after parsing since RFC 3339 strings with
+    // varying fractional precision do not sort reliably as text
+    Ok(records
+        .into_iter()
+        .filter(|r| r.timestamp >= start && r.timestamp <= end)
+        .collect())
+}
+
+/// Columns read by `row_to_scan_record`, in order
+const SCAN_COLUMNS: &str =
+    "id, timestamp, device_count, average_score, issues_found, installation_id, network_id, partial";
+
+fn row_to_scan_record(row: &rusqlite::Row) -> SqliteResult<ScanRecord> {
+    Ok(ScanRecord {
+        id: row.get(0)?,
+        timestamp: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .unwrap_or_else(|_| chrono::Utc::now()),
+        device_count: row.get(2)?,
+        average_score: row.get(3)?,
+        issues_found: row.get(4)?,
+        installation_id: row.get(5)?,
+        network_id: row.get(6)?,
+        partial: row.get(7)?,
+        display_timestamp: None,
+    })
+}
+
 /// Get device by ID
 pub fn get_device(device_id: &str) -> Result<Option<Device>, DbError> {
     let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
@@ -144,7 +435,8 @@ pub fn get_device(device_id: &str) -> Result<Option<Device>, DbError> {
 
     if let Some(row) = rows.next()? {
         let data: String = row.get(0)?;
-        let device: Device = serde_json::from_str(&data)?;
+        let mut device: Device = serde_json::from_str(&data)?;
+        content::localize_devices(std::slice::from_mut(&mut device), content::language());
         Ok(Some(device))
     } else {
         Ok(None)
@@ -154,7 +446,25 @@ pub fn get_device(device_id: &str) -> Result<Option<Device>, DbError> {
 /// Get devices from a specific scan
 pub fn get_scan_devices(scan_id: &str) -> Result<Vec<Device>, DbError> {
     let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    query_scan_devices(&conn, scan_id)
+}
+
+/// Get devices from the most recent scan of the whole network
+pub fn get_latest_scan_devices() -> Result<Vec<Device>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare("SELECT id FROM scans WHERE partial = 0 ORDER BY timestamp DESC LIMIT 1")?;
+    let mut rows = stmt.query([])?;
 
+    if let Some(row) = rows.next()? {
+        let scan_id: String = row.get(0)?;
+        query_scan_devices(&conn, &scan_id)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn query_scan_devices(conn: &Connection, scan_id: &str) -> Result<Vec<Device>, DbError> {
     let mut stmt = conn.prepare("SELECT data FROM devices WHERE scan_id = ?1")?;
     let rows = stmt.query_map([scan_id], |row| {
         let data: String = row.get(0)?;
@@ -167,6 +477,161 @@ pub fn get_scan_devices(scan_id: &str) -> Result<Vec<Device>, DbError> {
         let device: Device = serde_json::from_str(&data)?;
         devices.push(device);
     }
+    // Scans taken with another language selected
+    content::localize_devices(&mut devices, content::language());
 
     Ok(devices)
 }
+
+/// Get a raw setting value
+pub fn get_setting(key: &str) -> Result<Option<String>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+    let mut rows = stmt.query([key])?;
+
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Insert or replace a raw setting value
+pub fn set_setting(key: &str, value: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        (key, value),
+    )?;
+
+    Ok(())
+}
+
+/// How long it takes to acquire the connection lock, for the watchdog
+pub fn lock_wait() -> Result<std::time::Duration, DbError> {
+    let started = std::time::Instant::now();
+    let _conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    Ok(started.elapsed())
+}
+
+/// Get a cached HTTP response body and when it was fetched
+pub fn get_cached_response(
+    url: &str,
+) -> Result<Option<(String, chrono::DateTime<chrono::Utc>)>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare("SELECT body, fetched_at FROM http_cache WHERE url = ?1")?;
+    let mut rows = stmt.query([url])?;
+
+    if let Some(row) = rows.next()? {
+        let body: String = row.get(0)?;
+        let fetched_at = chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(1)?)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .unwrap_or_default();
+        Ok(Some((body, fetched_at)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Store an HTTP response body in the cache
+pub fn put_cached_response(url: &str, body: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO http_cache (url, body, fetched_at) VALUES (?1, ?2, ?3)",
+        (url, body, chrono::Utc::now().to_rfc3339()),
+    )?;
+
+    Ok(())
+}
+
+/// When the most recent HTTP response was cached
+pub fn last_cached_response() -> Result<Option<chrono::DateTime<chrono::Utc>>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let fetched_at: Option<String> =
+        conn.query_row("SELECT MAX(fetched_at) FROM http_cache", [], |row| row.get(0))?;
+
+    Ok(fetched_at.and_then(|s| {
+        chrono::DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .ok()
+    }))
+}
+
+/// Get cached vulnerability matches for a device fingerprint, with the data
+/// version they were matched against and when
+pub fn get_vuln_matches(
+    fingerprint: &str,
+) -> Result<Option<(String, String, chrono::DateTime<chrono::Utc>)>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare(
+        "SELECT results, data_version, checked_at FROM vuln_matches WHERE fingerprint = ?1",
+    )?;
+    let mut rows = stmt.query([fingerprint])?;
+
+    if let Some(row) = rows.next()? {
+        let checked_at = chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .unwrap_or_default();
+        Ok(Some((row.get(0)?, row.get(1)?, checked_at)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Store vulnerability matches for a device fingerprint
+pub fn put_vuln_matches(fingerprint: &str, data_version: &str, results: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO vuln_matches (fingerprint, data_version, results, checked_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        (fingerprint, data_version, results, chrono::Utc::now().to_rfc3339()),
+    )?;
+
+    Ok(())
+}
+
+/// Drop all cached vulnerability matches
+pub fn clear_vuln_matches() -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute("DELETE FROM vuln_matches", [])?;
+    Ok(())
+}
+
+/// Queue a request that failed while offline
+pub fn enqueue_request(url: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    conn.execute(
+        "INSERT INTO request_queue (url, queued_at, attempts) VALUES (?1, ?2, 1)
+         ON CONFLICT(url) DO UPDATE SET attempts = attempts + 1",
+        (url, chrono::Utc::now().to_rfc3339()),
+    )?;
+
+    Ok(())
+}
+
+/// Get queued request URLs, oldest first
+pub fn get_queued_requests() -> Result<Vec<String>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare("SELECT url FROM request_queue ORDER BY queued_at ASC")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(DbError::from)
+}
+
+/// Remove a request from the queue once it has completed
+pub fn remove_queued_request(url: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    conn.execute("DELETE FROM request_queue WHERE url = ?1", [url])?;
+
+    Ok(())
+}
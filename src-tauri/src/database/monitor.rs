@@ -0,0 +1,75 @@
+//! Monitoring results between scans
+//!
+//! Monitoring rounds learn about the devices of the latest scan: ports that
+//! opened, SSDP descriptions, DHCP fingerprints. They are kept as
+//! observations of their own, so a saved scan keeps showing what that scan
+//! found and its summary stays right. The devices as monitoring last saw
+//! them are the latest scan's devices with their newest observation applied.
+
+use super::{DbError, DB};
+use crate::scanner::Device;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+/// Observations kept per device
+const MAX_OBSERVATIONS: usize = 20;
+
+/// What monitoring noticed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationKind {
+    PortsOpened,
+    Ssdp,
+    Dhcp,
+}
+
+impl ObservationKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ObservationKind::PortsOpened => "ports_opened",
+            ObservationKind::Ssdp => "ssdp",
+            ObservationKind::Dhcp => "dhcp",
+        }
+    }
+}
+
+/// Store a device as monitoring now sees it
+pub fn record(device: &Device, kind: ObservationKind) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute(
+        "INSERT INTO monitor_observations (device_id, kind, observed_at, data) VALUES (?1, ?2, ?3, ?4)",
+        (
+            &device.id,
+            kind.as_str(),
+            chrono::Utc::now().to_rfc3339(),
+            serde_json::to_string(device)?,
+        ),
+    )?;
+    // Devices of earlier scans are never looked at again
+    conn.execute(
+        "DELETE FROM monitor_observations WHERE device_id NOT IN
+         (SELECT id FROM devices WHERE scan_id = (SELECT id FROM scans WHERE partial = 0 ORDER BY timestamp DESC LIMIT 1))",
+        [],
+    )?;
+    conn.execute(
+        "DELETE FROM monitor_observations WHERE device_id = ?1 AND id NOT IN
+         (SELECT id FROM monitor_observations WHERE device_id = ?1 ORDER BY id DESC LIMIT ?2)",
+        (&device.id, MAX_OBSERVATIONS as i64),
+    )?;
+    Ok(())
+}
+
+/// Devices of the latest scan, each as monitoring last saw it
+pub fn current_devices() -> Result<Vec<Device>, DbError> {
+    let mut devices = super::get_latest_scan_devices()?;
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt =
+        conn.prepare("SELECT data FROM monitor_observations WHERE device_id = ?1 ORDER BY id DESC LIMIT 1")?;
+    for device in &mut devices {
+        let observed: Option<String> = stmt.query_row([&device.id], |row| row.get(0)).optional()?;
+        if let Some(data) = observed {
+            *device = serde_json::from_str(&data)?;
+        }
+    }
+    Ok(devices)
+}
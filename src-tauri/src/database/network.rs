@@ -0,0 +1,80 @@
+//! Network profiles
+//!
+//! A scan only knows its network by the gateway's MAC address. When the user
+//! opts in, the ISP, a coarse public IP and the router model are recorded per
+//! network so reports can say which network was assessed.
+
+use super::{DbError, DB};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+/// What is known about a network besides its gateway
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    /// Gateway MAC address, as in `ScanRecord::network_id`
+    pub network_id: String,
+    pub isp: Option<String>,
+    /// Public IP with the host part masked, e.g. `203.0.113.x`
+    pub public_ip: Option<String>,
+    pub router_model: Option<String>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl NetworkProfile {
+    /// One-line summary for report headers
+    pub fn summary(&self) -> String {
+        [&self.isp, &self.public_ip, &self.router_model]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// Store a profile, keeping previously known values the new one lacks
+pub fn save(profile: &NetworkProfile) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute(
+        "INSERT INTO networks (network_id, isp, public_ip, router_model, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(network_id) DO UPDATE SET
+            isp = COALESCE(excluded.isp, isp),
+            public_ip = COALESCE(excluded.public_ip, public_ip),
+            router_model = COALESCE(excluded.router_model, router_model),
+            updated_at = excluded.updated_at",
+        (
+            &profile.network_id,
+            &profile.isp,
+            &profile.public_ip,
+            &profile.router_model,
+            chrono::Utc::now().to_rfc3339(),
+        ),
+    )?;
+    Ok(())
+}
+
+/// Profile of a network, if one was recorded
+pub fn get(network_id: &str) -> Result<Option<NetworkProfile>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let profile = conn
+        .query_row(
+            "SELECT network_id, isp, public_ip, router_model, updated_at FROM networks WHERE network_id = ?1",
+            [network_id],
+            |row| {
+                Ok(NetworkProfile {
+                    network_id: row.get(0)?,
+                    isp: row.get(1)?,
+                    public_ip: row.get(2)?,
+                    router_model: row.get(3)?,
+                    updated_at: row
+                        .get::<_, String>(4)
+                        .ok()
+                        .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+                        .map(|t| t.with_timezone(&chrono::Utc)),
+                })
+            },
+        )
+        .optional()?;
+    Ok(profile)
+}
@@ -0,0 +1,319 @@
+//! Time-of-day device presence profiles
+//!
+//! Every sighting of a device increments a counter for the local hour it was
+//! seen in. Once a device has enough history, a sighting in hours where it has
+//! practically never been online is reported as unusual (e.g. a child's
+//! tablet at 3 AM). In away mode any device without history is reported.
+//!
+//! Apart from the hourly counters, each device's first and last sighting is
+//! kept along with the times it came online and went missing, so the user
+//! can see when a gadget shows up on the network.
+
+use super::{DbError, DB};
+use crate::scanner::Device;
+use chrono::{DateTime, Timelike, Utc};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Sightings needed before a profile is trusted
+pub const MIN_SIGHTINGS: u32 = 20;
+
+/// Share of sightings below which an hour (±1 hour) counts as unusual
+const UNUSUAL_SHARE: f64 = 0.02;
+
+/// Hours with at least this share of sightings are listed as typical
+const TYPICAL_SHARE: f64 = 0.05;
+
+/// Online/offline changes kept per device
+const MAX_EVENTS: usize = 500;
+
+/// Presence alert settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresenceSettings {
+    /// Household is away; any unfamiliar device is alerted
+    pub away_mode: bool,
+}
+
+/// When a device is usually online
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceProfile {
+    pub mac: String,
+    pub total_sightings: u32,
+    /// Sightings per local hour (index 0 = 00:00-00:59)
+    pub hourly: Vec<u32>,
+    /// Hours the device is typically online
+    pub typical_hours: Vec<u8>,
+}
+
+impl PresenceProfile {
+    fn from_hourly(mac: &str, hourly: [u32; 24]) -> Self {
+        let total_sightings: u32 = hourly.iter().sum();
+        let typical_hours = (0..24u8)
+            .filter(|&h| {
+                total_sightings > 0
+                    && hourly[h as usize] as f64 / total_sightings as f64 >= TYPICAL_SHARE
+            })
+            .collect();
+        Self {
+            mac: mac.to_string(),
+            total_sightings,
+            hourly: hourly.to_vec(),
+            typical_hours,
+        }
+    }
+
+    /// Whether a sighting at this hour is out of the ordinary
+    pub fn is_unusual_hour(&self, hour: u32) -> bool {
+        if self.total_sightings < MIN_SIGHTINGS {
+            return false;
+        }
+        // Include neighbouring hours so schedules that drift a little don't alert
+        let window: u32 = [hour + 23, hour, hour + 1]
+            .iter()
+            .map(|h| self.hourly[(h % 24) as usize])
+            .sum();
+        (window as f64 / self.total_sightings as f64) < UNUSUAL_SHARE
+    }
+}
+
+/// A device coming online or found missing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceEvent {
+    pub at: DateTime<Utc>,
+    pub online: bool,
+    /// Address it came online at
+    pub ip: Option<String>,
+}
+
+/// When a device was on the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTimeline {
+    pub mac: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub last_ip: Option<String>,
+    /// Scans the device was seen in
+    pub sightings: u32,
+    /// Answered a reachability probe just now; `None` when not probed
+    pub is_online: Option<bool>,
+    /// Oldest first
+    pub events: Vec<PresenceEvent>,
+}
+
+/// Reason for a presence alert
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceAlertKind {
+    UnusualTime,
+    UnknownWhileAway,
+}
+
+/// Device seen at an unexpected time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceAlert {
+    pub kind: PresenceAlertKind,
+    pub device_id: String,
+    pub mac: String,
+    pub ip: String,
+    pub name: Option<String>,
+    pub seen_at: chrono::DateTime<chrono::Utc>,
+    pub message: String,
+}
+
+/// Load a device's profile
+pub fn get_profile(mac: &str) -> Result<Option<PresenceProfile>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mac = mac.to_lowercase();
+
+    let mut stmt = conn.prepare("SELECT hour, sightings FROM presence WHERE mac = ?1")?;
+    let rows = stmt
+        .query_map([&mac], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut hourly = [0u32; 24];
+    for (hour, sightings) in rows {
+        if let Some(slot) = hourly.get_mut(hour as usize) {
+            *slot = sightings;
+        }
+    }
+    Ok(Some(PresenceProfile::from_hourly(&mac, hourly)))
+}
+
+/// Add a sighting of each device at the given time to its profile
+pub fn record(devices: &[Device], seen_at: chrono::DateTime<chrono::Local>) -> Result<(), DbError> {
+    let mut conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let hour = seen_at.hour();
+
+    let tx = conn.transaction()?;
+    for device in devices {
+        tx.execute(
+            "INSERT INTO presence (mac, hour, sightings) VALUES (?1, ?2, 1)
+             ON CONFLICT(mac, hour) DO UPDATE SET sightings = sightings + 1",
+            (device.mac.to_lowercase(), hour),
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Check devices against their profiles. Call before `record` so the
+/// current sighting does not count towards the profile.
+pub fn evaluate(
+    devices: &[Device],
+    seen_at: chrono::DateTime<chrono::Local>,
+    settings: &PresenceSettings,
+) -> Result<Vec<PresenceAlert>, DbError> {
+    let hour = seen_at.hour();
+    let mut alerts = Vec::new();
+
+    for device in devices {
+        let label = device
+            .name
+            .as_deref()
+            .or(device.hostname.as_deref())
+            .unwrap_or(&device.mac);
+
+        let alert = match get_profile(&device.mac)? {
+            Some(profile) if profile.is_unusual_hour(hour) => Some((
+                PresenceAlertKind::UnusualTime,
+                format!("{}が普段オンラインにならない時間帯（{}時台）に接続されています", label, hour),
+            )),
+            None if settings.away_mode => Some((
+                PresenceAlertKind::UnknownWhileAway,
+                format!("不在モード中に見慣れない機器（{}、{}）が接続されています", label, device.ip),
+            )),
+            _ => None,
+        };
+
+        if let Some((kind, message)) = alert {
+            alerts.push(PresenceAlert {
+                kind,
+                device_id: device.id.clone(),
+                mac: device.mac.clone(),
+                ip: device.ip.clone(),
+                name: device.name.clone(),
+                seen_at: seen_at.with_timezone(&chrono::Utc),
+                message,
+            });
+        }
+    }
+
+    Ok(alerts)
+}
+
+/// Mark the scan's devices as online, and the devices of the same network
+/// that were online but are missing as offline. A scan of chosen interfaces
+/// or ranges (`complete` false) sees part of the network only, so it marks
+/// nothing offline.
+pub fn track(
+    devices: &[Device],
+    network_id: Option<&str>,
+    seen_at: DateTime<Utc>,
+    complete: bool,
+) -> Result<(), DbError> {
+    let mut conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let seen = seen_at.to_rfc3339();
+    let tx = conn.transaction()?;
+    let mut changed = Vec::new();
+
+    let present: HashSet<String> = devices.iter().map(|d| d.mac.to_lowercase()).collect();
+    for device in devices {
+        let mac = device.mac.to_lowercase();
+        let online: Option<bool> = tx
+            .query_row("SELECT online FROM device_presence WHERE mac = ?1", [&mac], |row| row.get(0))
+            .optional()?;
+        if online != Some(true) {
+            tx.execute(
+                "INSERT INTO presence_events (mac, at, online, ip) VALUES (?1, ?2, 1, ?3)",
+                (&mac, &seen, &device.ip),
+            )?;
+            changed.push(mac.clone());
+        }
+        tx.execute(
+            "INSERT INTO device_presence (mac, first_seen, last_seen, last_ip, network_id, online, sightings)
+             VALUES (?1, ?2, ?2, ?3, ?4, 1, 1)
+             ON CONFLICT(mac) DO UPDATE SET last_seen = excluded.last_seen, last_ip = excluded.last_ip,
+                network_id = excluded.network_id, online = 1, sightings = sightings + 1",
+            (&mac, &seen, &device.ip, network_id),
+        )?;
+    }
+
+    if let Some(network_id) = network_id.filter(|_| complete) {
+        let online: Vec<String> = tx
+            .prepare("SELECT mac FROM device_presence WHERE online = 1 AND network_id = ?1")?
+            .query_map([network_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for mac in online.into_iter().filter(|mac| !present.contains(mac)) {
+            tx.execute("UPDATE device_presence SET online = 0 WHERE mac = ?1", [&mac])?;
+            tx.execute(
+                "INSERT INTO presence_events (mac, at, online) VALUES (?1, ?2, 0)",
+                (&mac, &seen),
+            )?;
+            changed.push(mac);
+        }
+    }
+
+    for mac in changed {
+        tx.execute(
+            "DELETE FROM presence_events WHERE mac = ?1 AND id NOT IN
+             (SELECT id FROM presence_events WHERE mac = ?1 ORDER BY id DESC LIMIT ?2)",
+            (&mac, MAX_EVENTS as i64),
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// A device's first and last sighting and its online/offline changes
+pub fn get_timeline(mac: &str) -> Result<Option<DeviceTimeline>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mac = mac.to_lowercase();
+
+    let summary = conn
+        .query_row(
+            "SELECT first_seen, last_seen, last_ip, sightings FROM device_presence WHERE mac = ?1",
+            [&mac],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, u32>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((first_seen, last_seen, last_ip, sightings)) = summary else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare("SELECT at, online, ip FROM presence_events WHERE mac = ?1 ORDER BY id")?;
+    let events = stmt
+        .query_map([&mac], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, Option<String>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|(at, online, ip)| Some(PresenceEvent { at: parse_time(&at)?, online, ip }))
+        .collect();
+
+    Ok(Some(DeviceTimeline {
+        first_seen: parse_time(&first_seen).unwrap_or_default(),
+        last_seen: parse_time(&last_seen).unwrap_or_default(),
+        mac,
+        last_ip,
+        sightings,
+        is_online: None,
+        events,
+    }))
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|t| t.with_timezone(&Utc))
+}
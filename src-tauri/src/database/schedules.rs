@@ -0,0 +1,69 @@
+//! Storage of scan schedules
+
+use super::{DbError, DB};
+use crate::scheduler::ScanSchedule;
+use chrono::{DateTime, Utc};
+
+/// All schedules, in the order they were created
+pub fn list() -> Result<Vec<ScanSchedule>, DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT data, last_run FROM scan_schedules ORDER BY rowid")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.iter()
+        .map(|(data, last_run)| {
+            let mut schedule: ScanSchedule = serde_json::from_str(data)?;
+            schedule.last_run = parse_time(last_run.as_deref());
+            Ok(schedule)
+        })
+        .collect()
+}
+
+/// Insert or replace a schedule, assigning an ID to a new one. When it last
+/// ran is kept.
+pub fn save(mut schedule: ScanSchedule) -> Result<ScanSchedule, DbError> {
+    if schedule.id.is_empty() {
+        schedule.id = uuid::Uuid::new_v4().to_string();
+    }
+    if schedule.created_at.is_none() {
+        schedule.created_at = Some(Utc::now());
+    }
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let last_run = conn
+        .query_row("SELECT last_run FROM scan_schedules WHERE id = ?1", [&schedule.id], |row| {
+            row.get::<_, Option<String>>(0)
+        })
+        .ok()
+        .flatten();
+    schedule.last_run = parse_time(last_run.as_deref());
+    conn.execute(
+        "INSERT INTO scan_schedules (id, data) VALUES (?1, ?2)
+         ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+        (&schedule.id, serde_json::to_string(&schedule)?),
+    )?;
+    Ok(schedule)
+}
+
+/// Record that a schedule ran
+pub fn mark_run(id: &str, at: DateTime<Utc>) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute(
+        "UPDATE scan_schedules SET last_run = ?2 WHERE id = ?1",
+        (id, at.to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+/// Delete a schedule; unknown IDs are ignored
+pub fn delete(id: &str) -> Result<(), DbError> {
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    conn.execute("DELETE FROM scan_schedules WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+fn parse_time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
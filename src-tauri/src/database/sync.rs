@@ -0,0 +1,194 @@
+//! Multi-machine history sync via export/import bundles
+//!
+//! A bundle carries scans together with the ID of the installation that
+//! recorded them. Scans are identified by UUID, so importing the same bundle
+//! twice, or bundles from several machines, merges without conflicts.
+
+use super::{query_scan_devices, row_to_scan_record, DbError, ScanRecord, DB, SCAN_COLUMNS};
+use crate::report::EMBEDDED_JSON_ID;
+use crate::scanner::Device;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Current bundle format version
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// A scan and its devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledScan {
+    pub record: ScanRecord,
+    pub devices: Vec<Device>,
+}
+
+/// Exported scan history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncBundle {
+    pub format_version: u32,
+    /// Installation that produced the bundle
+    pub installation_id: String,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub scans: Vec<BundledScan>,
+}
+
+/// Result of merging a bundle into the local history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Scans already present locally
+    pub skipped: usize,
+}
+
+/// Export the scan history, optionally limited to one network
+pub fn export_bundle(network_id: Option<&str>) -> Result<SyncBundle, DbError> {
+    let installation_id = super::installation_id()?;
+    let conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scans ORDER BY timestamp ASC", SCAN_COLUMNS))?;
+    let records = stmt
+        .query_map([], row_to_scan_record)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut scans = Vec::new();
+    for record in records {
+        if network_id.is_some() && record.network_id.as_deref() != network_id {
+            continue;
+        }
+        let devices = query_scan_devices(&conn, &record.id)?;
+        scans.push(BundledScan { record, devices });
+    }
+
+    Ok(SyncBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        installation_id,
+        exported_at: chrono::Utc::now(),
+        scans,
+    })
+}
+
+/// Merge a bundle into the local history, skipping scans that already exist
+pub fn import_bundle(bundle: &SyncBundle) -> Result<ImportSummary, DbError> {
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(DbError::UnsupportedFormat(bundle.format_version));
+    }
+
+    let mut conn = DB.get().ok_or(DbError::NotInitialized)?.lock().unwrap();
+    let tx = conn.transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for scan in &bundle.scans {
+        let r = &scan.record;
+        // Older scans carry no installation ID; attribute them to the bundle's origin if known
+        let installation_id = r
+            .installation_id
+            .as_ref()
+            .or(Some(&bundle.installation_id).filter(|id| !id.is_empty()));
+
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO scans (id, timestamp, device_count, average_score, issues_found, installation_id, network_id, partial)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (&r.id, r.timestamp.to_rfc3339(), r.device_count, r.average_score, r.issues_found, installation_id, &r.network_id, r.partial),
+        )?;
+
+        if inserted == 0 {
+            summary.skipped += 1;
+            continue;
+        }
+
+        for device in &scan.devices {
+            tx.execute(
+                "INSERT OR IGNORE INTO devices (id, scan_id, data) VALUES (?1, ?2, ?3)",
+                (&device.id, &r.id, serde_json::to_string(device)?),
+            )?;
+        }
+        summary.imported += 1;
+    }
+
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// Write a bundle to a JSON file
+pub fn write_bundle(path: &Path, bundle: &SyncBundle) -> Result<(), DbError> {
+    std::fs::write(path, serde_json::to_string_pretty(bundle)?)?;
+    Ok(())
+}
+
+/// Read a bundle from a JSON file
+pub fn read_bundle(path: &Path) -> Result<SyncBundle, DbError> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Shape of a JSON report produced by `report::generate`
+#[derive(Deserialize)]
+struct JsonReport {
+    generated_at: String,
+    devices: Vec<Device>,
+}
+
+/// JSON of a report file: the file itself, or the block embedded in an HTML report
+fn report_json(text: &str) -> Result<&str, DbError> {
+    if text.trim_start().starts_with('{') {
+        return Ok(text);
+    }
+    let marker = format!("id=\"{}\"", EMBEDDED_JSON_ID);
+    let start = text.find(&marker).ok_or(DbError::NotAReport)?;
+    let body = &text[start..];
+    let open = body.find('>').ok_or(DbError::NotAReport)? + 1;
+    let close = body.find("</script>").ok_or(DbError::NotAReport)?;
+    // "</" is written as "<\/", which JSON reads back unchanged
+    body.get(open..close).ok_or(DbError::NotAReport)
+}
+
+/// Wrap a JSON or HTML report as a one-scan bundle
+pub fn bundle_from_report(text: &str) -> Result<SyncBundle, DbError> {
+    let report: JsonReport = serde_json::from_str(report_json(text)?)?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&report.generated_at)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now());
+    let average_score = if report.devices.is_empty() {
+        0
+    } else {
+        report.devices.iter().map(|d| d.security_score as u32).sum::<u32>() / report.devices.len() as u32
+    } as u8;
+
+    Ok(SyncBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        installation_id: String::new(),
+        exported_at: timestamp,
+        scans: vec![BundledScan {
+            record: ScanRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp,
+                device_count: report.devices.len(),
+                average_score,
+                issues_found: report.devices.iter().map(|d| d.issues.len()).sum(),
+                installation_id: None,
+                network_id: super::network_id(&report.devices),
+                partial: false,
+                display_timestamp: None,
+            },
+            devices: report.devices,
+        }],
+    })
+}
+
+/// Store an exported JSON or HTML report as a historical scan. A report whose
+/// timestamp is already in the history counts as skipped, so importing the
+/// same file twice does not duplicate it.
+pub fn import_report(path: &Path) -> Result<ImportSummary, DbError> {
+    let bundle = bundle_from_report(&std::fs::read_to_string(path)?)?;
+    let timestamp = bundle.scans[0].record.timestamp.to_rfc3339();
+
+    let exists: bool = DB
+        .get()
+        .ok_or(DbError::NotInitialized)?
+        .lock()
+        .unwrap()
+        .query_row("SELECT EXISTS(SELECT 1 FROM scans WHERE timestamp = ?1)", [&timestamp], |row| row.get(0))?;
+    if exists {
+        return Ok(ImportSummary { imported: 0, skipped: 1 });
+    }
+
+    import_bundle(&bundle)
+}
@@ -0,0 +1,58 @@
+//! Read-only viewer for exported results
+//!
+//! Opened bundles are held in memory only; nothing is written to the local
+//! history, so a consultant can review a client's export without mixing it
+//! into their own scans.
+
+use super::sync::{self, SyncBundle};
+use super::{DbError, ScanRecord};
+use crate::scanner::Device;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+static OPEN_BUNDLE: Mutex<Option<SyncBundle>> = Mutex::new(None);
+
+/// Overview of an opened bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedBundle {
+    pub installation_id: String,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub scans: Vec<ScanRecord>,
+}
+
+/// Load a sync bundle or JSON/HTML report file into the viewer, replacing any open one
+pub fn open(path: &Path) -> Result<OpenedBundle, DbError> {
+    let text = std::fs::read_to_string(path)?;
+
+    let bundle = match serde_json::from_str::<SyncBundle>(&text) {
+        Ok(bundle) => bundle,
+        Err(_) => sync::bundle_from_report(&text)?,
+    };
+
+    let opened = OpenedBundle {
+        installation_id: bundle.installation_id.clone(),
+        exported_at: bundle.exported_at,
+        scans: bundle.scans.iter().map(|s| s.record.clone()).collect(),
+    };
+    *OPEN_BUNDLE.lock().unwrap() = Some(bundle);
+
+    Ok(opened)
+}
+
+/// Devices of a scan in the opened bundle
+pub fn get_scan_devices(scan_id: &str) -> Option<Vec<Device>> {
+    OPEN_BUNDLE
+        .lock()
+        .unwrap()
+        .as_ref()?
+        .scans
+        .iter()
+        .find(|s| s.record.id == scan_id)
+        .map(|s| s.devices.clone())
+}
+
+/// Discard the opened bundle
+pub fn close() {
+    *OPEN_BUNDLE.lock().unwrap() = None;
+}
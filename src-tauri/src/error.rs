@@ -0,0 +1,265 @@
+//! Error type returned by Tauri commands
+//!
+//! Module errors are mapped to an `AppError` carrying a machine-readable kind
+//! and a remediation hint, so the UI can tell "run elevated" apart from
+//! "no network" instead of showing a bare message.
+
+use crate::config::ConfigError;
+use crate::database::DbError;
+use crate::probe::ProbeError;
+use crate::report::ReportError;
+use crate::scanner::ble::BleError;
+use crate::scanner::datapack::DataPackError;
+use crate::scanner::igd::IgdError;
+use crate::scanner::router::RouterError;
+use crate::scanner::ScanError;
+use crate::secrets::SecretsError;
+use crate::settings::profiles::ProfileError;
+use crate::vulndb::VulnDbError;
+use serde::{Deserialize, Serialize};
+
+/// Error category the UI can branch on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    PermissionDenied,
+    Network,
+    Timeout,
+    Cancelled,
+    Database,
+    Io,
+    InvalidData,
+    InvalidInput,
+    Keychain,
+    Internal,
+}
+
+/// Error returned by every command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// What the user can do about it
+    pub hint: Option<String>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            Self::new(ErrorKind::PermissionDenied, e.to_string())
+                .with_hint("ファイルへのアクセス権限を確認してください")
+        } else {
+            Self::new(ErrorKind::Io, e.to_string())
+        }
+    }
+}
+
+impl From<ScanError> for AppError {
+    fn from(e: ScanError) -> Self {
+        let message = e.to_string();
+        match e {
+            ScanError::NetworkError(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("Wi-Fiまたは有線LANに接続されているか確認してください"),
+            ScanError::PermissionDenied(_) => Self::new(ErrorKind::PermissionDenied, message)
+                .with_hint("管理者権限（sudo / 管理者として実行）でアプリを起動してください"),
+            ScanError::Timeout => Self::new(ErrorKind::Timeout, message)
+                .with_hint("しばらくしてから再度スキャンしてください"),
+            ScanError::Cancelled => Self::new(ErrorKind::Cancelled, message),
+            ScanError::StrictlyPassive => Self::new(ErrorKind::PermissionDenied, message)
+                .with_hint("厳格なパッシブモードでは機器に通信を送れません。設定でオフにしてください"),
+            ScanError::InvalidTarget(_) => Self::new(ErrorKind::InvalidInput, message)
+                .with_hint("このパソコンが接続しているネットワークのインターフェースか範囲（CIDR）を選んでください"),
+            ScanError::AlreadyRunning => Self::new(ErrorKind::InvalidInput, message)
+                .with_hint("実行中のスキャンが終わってから、もう一度お試しください"),
+            ScanError::Internal(_) => Self::new(ErrorKind::Internal, message),
+        }
+    }
+}
+
+impl From<DbError> for AppError {
+    fn from(e: DbError) -> Self {
+        let message = e.to_string();
+        match e {
+            DbError::Sqlite(_) => Self::new(ErrorKind::Database, message),
+            DbError::NotInitialized => Self::new(ErrorKind::Database, message)
+                .with_hint("アプリを再起動してください"),
+            DbError::Serialization(_) => Self::new(ErrorKind::InvalidData, message),
+            DbError::Io(io) => io.into(),
+            DbError::UnsupportedFormat(_) => Self::new(ErrorKind::InvalidData, message)
+                .with_hint("アプリを最新版に更新してから読み込んでください"),
+            DbError::NotAReport => Self::new(ErrorKind::InvalidData, message)
+                .with_hint("IoT Doctorで書き出したJSONまたはHTMLのレポートを選んでください"),
+        }
+    }
+}
+
+impl From<ReportError> for AppError {
+    fn from(e: ReportError) -> Self {
+        match e {
+            ReportError::IoError(io) => io.into(),
+            ReportError::Database(db) => db.into(),
+            other @ ReportError::DifferentNetworks => Self::new(ErrorKind::InvalidInput, other.to_string())
+                .with_hint("同じネットワークで実施したスキャン同士を選んでください"),
+            other @ ReportError::ScanNotFound(_) => Self::new(ErrorKind::InvalidInput, other.to_string())
+                .with_hint("スキャン履歴を更新してから選び直してください"),
+            other => Self::new(ErrorKind::Internal, other.to_string()),
+        }
+    }
+}
+
+impl From<VulnDbError> for AppError {
+    fn from(e: VulnDbError) -> Self {
+        let message = e.to_string();
+        match e {
+            VulnDbError::LookupFailed(_) => Self::new(ErrorKind::Network, message),
+            VulnDbError::NetworkError(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("インターネットに接続されているか確認してください"),
+            VulnDbError::Deferred(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("オンラインに戻ると自動的に再確認します"),
+            VulnDbError::Database(db) => db.into(),
+        }
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Io(io) => io.into(),
+            ConfigError::Parse { .. } => Self::new(ErrorKind::InvalidData, e.to_string())
+                .with_hint("設定ファイルの書式を確認してください"),
+            ConfigError::Watch(_) => Self::new(ErrorKind::Internal, e.to_string()),
+        }
+    }
+}
+
+impl From<DataPackError> for AppError {
+    fn from(e: DataPackError) -> Self {
+        let message = e.to_string();
+        match e {
+            DataPackError::Invalid(_) | DataPackError::BadSignature => {
+                Self::new(ErrorKind::InvalidData, message)
+                    .with_hint("データの配布元が正しくない可能性があります。時間をおいて再度更新してください")
+            }
+            DataPackError::Io(io) => io.into(),
+            DataPackError::Download(vuln) => vuln.into(),
+        }
+    }
+}
+
+impl From<RouterError> for AppError {
+    fn from(e: RouterError) -> Self {
+        let message = e.to_string();
+        match e {
+            RouterError::NotConfigured => Self::new(ErrorKind::InvalidInput, message)
+                .with_hint("設定画面でルーターの種類とアドレスを指定してください"),
+            RouterError::AuthenticationFailed => Self::new(ErrorKind::PermissionDenied, message)
+                .with_hint("ルーターのユーザー名とパスワードを確認してください"),
+            RouterError::Http(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("ルーターのアドレスと、APIが有効になっているかを確認してください"),
+            RouterError::Protocol(_) => Self::new(ErrorKind::InvalidData, message)
+                .with_hint("ルーターの種類の設定が正しいか確認してください"),
+            RouterError::Secrets(secrets) => secrets.into(),
+        }
+    }
+}
+
+impl From<IgdError> for AppError {
+    fn from(e: IgdError) -> Self {
+        let message = e.to_string();
+        match e {
+            IgdError::NotFound => Self::new(ErrorKind::Network, message)
+                .with_hint("ルーターのUPnP機能が無効になっているか、応答しませんでした。ルーターの管理画面から削除してください"),
+            IgdError::Unsupported => Self::new(ErrorKind::InvalidInput, message)
+                .with_hint("NAT-PMP・PCPの設定はルーターの管理画面から削除してください"),
+            IgdError::Http(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("ルーターに接続できるか確認してください"),
+            IgdError::Rejected(_) => Self::new(ErrorKind::PermissionDenied, message)
+                .with_hint("ルーターが削除を許可しませんでした。ルーターの管理画面から削除してください"),
+        }
+    }
+}
+
+impl From<ProbeError> for AppError {
+    fn from(e: ProbeError) -> Self {
+        let message = e.to_string();
+        match e {
+            ProbeError::Io(io) if io.kind() == std::io::ErrorKind::TimedOut => {
+                Self::new(ErrorKind::Timeout, message)
+                    .with_hint("プローブの電源とネットワーク接続を確認してください")
+            }
+            ProbeError::Io(_) => Self::new(ErrorKind::Network, message)
+                .with_hint("プローブのアドレスとポート（既定: 7734）に接続できるか確認してください"),
+            ProbeError::AuthenticationFailed | ProbeError::InvalidPairingCode => {
+                Self::new(ErrorKind::PermissionDenied, message)
+                    .with_hint("プローブに表示されたペアリングコードで、もう一度ペアリングしてください")
+            }
+            ProbeError::NotFound(_) => Self::new(ErrorKind::InvalidInput, message),
+            ProbeError::Protocol(_) | ProbeError::Serialization(_) => Self::new(ErrorKind::InvalidData, message)
+                .with_hint("アプリとプローブを同じバージョンに更新してください"),
+            ProbeError::Remote(_) => Self::new(ErrorKind::Internal, message),
+            ProbeError::Secrets(secrets) => secrets.into(),
+        }
+    }
+}
+
+impl From<BleError> for AppError {
+    fn from(e: BleError) -> Self {
+        let message = e.to_string();
+        match e {
+            BleError::NotBuilt => Self::new(ErrorKind::InvalidInput, message)
+                .with_hint("Bluetoothに対応したビルド（ble 機能を有効にしたもの）を使ってください"),
+            BleError::NoAdapter | BleError::Bluetooth(_) => Self::new(ErrorKind::Io, message)
+                .with_hint("Bluetoothがオンになっているか、アプリにBluetoothの使用が許可されているか確認してください"),
+        }
+    }
+}
+
+impl From<ProfileError> for AppError {
+    fn from(e: ProfileError) -> Self {
+        let message = e.to_string();
+        match e {
+            ProfileError::NotPermitted(_) => Self::new(ErrorKind::PermissionDenied, message)
+                .with_hint("管理者のプロファイルに切り替えてください"),
+            ProfileError::WrongPin => Self::new(ErrorKind::PermissionDenied, message),
+            ProfileError::UnknownProfile(_) | ProfileError::PinRequired | ProfileError::AdminRequired => {
+                Self::new(ErrorKind::InvalidInput, message)
+            }
+            ProfileError::Random => Self::new(ErrorKind::Internal, message),
+            ProfileError::Database(db) => db.into(),
+        }
+    }
+}
+
+impl From<SecretsError> for AppError {
+    fn from(e: SecretsError) -> Self {
+        Self::new(ErrorKind::Keychain, e.to_string())
+            .with_hint("OSのキーチェーン（資格情報マネージャー）が利用できるか確認してください")
+    }
+}
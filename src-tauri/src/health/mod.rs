@@ -0,0 +1,291 @@
+//! Watchdog for the background subsystems
+//!
+//! When the app runs unattended (background monitoring, the control
+//! interface) nobody notices a loop that died or hangs. Supervised
+//! subsystems report a heartbeat; the watchdog restarts those that stopped
+//! or went silent, checks the database and mDNS on the side, and shows the
+//! overall state in the tray.
+
+use crate::database;
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::tray::TrayIconBuilder;
+use tauri::{Emitter, Manager};
+
+/// How often the watchdog looks at the subsystems
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Waiting longer than this for the database lock counts as contention
+const DB_SLOW: Duration = Duration::from_secs(2);
+
+/// Giving up on the database lock after this long
+const DB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Restarts before the watchdog leaves a subsystem stopped
+const MAX_RESTARTS: u32 = 5;
+
+const TRAY_ID: &str = "health";
+
+/// A watched subsystem
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Monitor,
+    Control,
+    Alerts,
+    NetworkWatch,
+    Scheduler,
+    Database,
+    Mdns,
+}
+
+impl Subsystem {
+    pub fn label(&self) -> String {
+        let key = match self {
+            Subsystem::Monitor => "health.monitor",
+            Subsystem::Control => "health.control",
+            Subsystem::Alerts => "health.alerts",
+            Subsystem::NetworkWatch => "health.network-watch",
+            Subsystem::Scheduler => "health.scheduler",
+            Subsystem::Database => "health.database",
+            Subsystem::Mdns => "health.mdns",
+        };
+        content::label(key, content::language())
+    }
+}
+
+/// How a subsystem is doing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Healthy,
+    /// Working, but slow or partly unavailable
+    Degraded,
+    /// Running without a heartbeat for too long
+    Stuck,
+    /// The task ended
+    Stopped,
+}
+
+/// Health of one subsystem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsystemHealth {
+    pub subsystem: Subsystem,
+    pub label: String,
+    pub state: HealthState,
+    pub last_heartbeat: Option<chrono::DateTime<chrono::Utc>>,
+    pub restarts: u32,
+    /// Scheduled rounds that did not happen
+    pub missed_runs: u32,
+    pub detail: Option<String>,
+}
+
+type Starter = Box<dyn Fn() -> JoinHandle<()> + Send + Sync>;
+
+/// A supervised background task
+struct Task {
+    handle: JoinHandle<()>,
+    start: Starter,
+    /// Expected time between heartbeats; `None` for tasks that only wait for work
+    period: Option<Duration>,
+    last_beat: chrono::DateTime<chrono::Utc>,
+    restarts: u32,
+}
+
+/// Result of a check the watchdog runs itself
+#[derive(Debug, Clone)]
+struct Probe {
+    state: HealthState,
+    checked_at: chrono::DateTime<chrono::Utc>,
+    detail: Option<String>,
+}
+
+#[derive(Default)]
+struct Registry {
+    tasks: BTreeMap<Subsystem, Task>,
+    probes: BTreeMap<Subsystem, Probe>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Mutex::default)
+}
+
+/// Start a background task under the watchdog. `start` is called again to
+/// restart it; tasks with a `period` must call [`beat`] at least that often.
+pub fn supervise(
+    subsystem: Subsystem,
+    period: Option<Duration>,
+    start: impl Fn() -> JoinHandle<()> + Send + Sync + 'static,
+) {
+    let task = Task {
+        handle: start(),
+        start: Box::new(start),
+        period,
+        last_beat: chrono::Utc::now(),
+        restarts: 0,
+    };
+    registry().lock().unwrap().tasks.insert(subsystem, task);
+}
+
+/// Report that a supervised task is alive
+pub fn beat(subsystem: Subsystem) {
+    if let Some(task) = registry().lock().unwrap().tasks.get_mut(&subsystem) {
+        task.last_beat = chrono::Utc::now();
+    }
+}
+
+/// Current health of every watched subsystem
+pub fn status() -> Vec<SubsystemHealth> {
+    let registry = registry().lock().unwrap();
+    let now = chrono::Utc::now();
+
+    let tasks = registry.tasks.iter().map(|(&subsystem, task)| {
+        let silent = (now - task.last_beat).to_std().unwrap_or_default();
+        let missed_runs = task
+            .period
+            .map_or(0, |period| (silent.as_secs() / period.as_secs().max(1)).saturating_sub(1) as u32);
+        let (state, detail) = if task.handle.inner().is_finished() {
+            let detail = if task.restarts >= MAX_RESTARTS {
+                "再起動を繰り返したため停止しています。アプリを再起動してください"
+            } else {
+                "停止しました。自動的に再起動します"
+            };
+            (HealthState::Stopped, Some(detail.to_string()))
+        } else if missed_runs >= 2 {
+            (HealthState::Stuck, Some(format!("{}回分の定期実行が行われていません", missed_runs)))
+        } else if missed_runs == 1 {
+            (HealthState::Degraded, Some("定期実行が遅れています".to_string()))
+        } else {
+            (HealthState::Healthy, None)
+        };
+        SubsystemHealth {
+            subsystem,
+            label: subsystem.label(),
+            state,
+            last_heartbeat: task.period.map(|_| task.last_beat),
+            restarts: task.restarts,
+            missed_runs,
+            detail,
+        }
+    });
+    let probes = registry.probes.iter().map(|(&subsystem, probe)| SubsystemHealth {
+        subsystem,
+        label: subsystem.label(),
+        state: probe.state,
+        last_heartbeat: Some(probe.checked_at),
+        restarts: 0,
+        missed_runs: 0,
+        detail: probe.detail.clone(),
+    });
+
+    tasks.chain(probes).collect()
+}
+
+/// Start the watchdog and the tray indicator
+pub fn start(app: tauri::AppHandle) {
+    if let Some(icon) = app.default_window_icon().cloned() {
+        if let Err(e) = TrayIconBuilder::with_id(TRAY_ID).icon(icon).tooltip("IoT Doctor").build(&app) {
+            eprintln!("Failed to create tray icon: {}", e);
+        }
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+            let database = check_database().await;
+            let mdns = check_mdns().await;
+            {
+                let mut registry = registry().lock().unwrap();
+                registry.probes.insert(Subsystem::Database, database);
+                registry.probes.insert(Subsystem::Mdns, mdns);
+            }
+
+            let health = status();
+            restart_failed(&health);
+            update_tray(&app, &health);
+            let _ = app.emit("subsystem-health", &health);
+        }
+    });
+}
+
+/// Restart tasks that stopped or hang
+fn restart_failed(health: &[SubsystemHealth]) {
+    let mut registry = registry().lock().unwrap();
+    for entry in health.iter().filter(|h| matches!(h.state, HealthState::Stopped | HealthState::Stuck)) {
+        let Some(task) = registry.tasks.get_mut(&entry.subsystem) else {
+            continue;
+        };
+        if task.restarts >= MAX_RESTARTS {
+            continue;
+        }
+        eprintln!("Restarting {:?} ({:?})", entry.subsystem, entry.state);
+        task.handle.abort();
+        task.handle = (task.start)();
+        task.last_beat = chrono::Utc::now();
+        task.restarts += 1;
+    }
+}
+
+fn update_tray(app: &tauri::AppHandle, health: &[SubsystemHealth]) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let failing: Vec<&str> = health
+        .iter()
+        .filter(|h| h.state != HealthState::Healthy)
+        .map(|h| h.label.as_str())
+        .collect();
+    let (tooltip, title) = if failing.is_empty() {
+        ("IoT Doctor: 正常に動作しています".to_string(), None)
+    } else {
+        (format!("IoT Doctor: 問題があります（{}）", failing.join("、")), Some("⚠"))
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+    let _ = tray.set_title(title);
+}
+
+/// How long it takes to get the database lock
+async fn check_database() -> Probe {
+    let wait = tokio::time::timeout(DB_TIMEOUT, tokio::task::spawn_blocking(database::lock_wait)).await;
+    let (state, detail) = match wait {
+        Ok(Ok(Ok(wait))) if wait > DB_SLOW => (
+            HealthState::Degraded,
+            Some(format!("応答に{}秒かかっています", wait.as_secs())),
+        ),
+        Ok(Ok(Ok(_))) => (HealthState::Healthy, None),
+        Ok(Ok(Err(e))) => (HealthState::Stopped, Some(e.to_string())),
+        Ok(Err(_)) => (HealthState::Stopped, None),
+        Err(_) => (
+            HealthState::Stuck,
+            Some("応答がありません。アプリを再起動してください".to_string()),
+        ),
+    };
+    Probe {
+        state,
+        checked_at: chrono::Utc::now(),
+        detail,
+    }
+}
+
+/// Whether an mDNS daemon can be started; scans start their own
+async fn check_mdns() -> Probe {
+    let (state, detail) = match tokio::task::spawn_blocking(crate::scanner::mdns::daemon_available).await {
+        Ok(Ok(())) => (HealthState::Healthy, None),
+        Ok(Err(e)) => (
+            HealthState::Degraded,
+            Some(format!("機器名の取得（mDNS）が利用できません: {}", e)),
+        ),
+        Err(_) => (HealthState::Stopped, None),
+    };
+    Probe {
+        state,
+        checked_at: chrono::Utc::now(),
+        detail,
+    }
+}
@@ -1,33 +1,429 @@
+pub mod alerts;
+pub mod config;
+pub mod control;
 pub mod database;
+pub mod error;
+pub mod health;
+pub mod monitor;
+pub mod probe;
 pub mod report;
 pub mod scanner;
+pub mod scheduler;
+pub mod secrets;
+pub mod settings;
+#[cfg(all(feature = "netns-tests", target_os = "linux"))]
+pub mod testnet;
 pub mod vulndb;
 
+use error::AppError;
+use health::Subsystem;
 use scanner::{Device, ScanLevel, ScanProgress};
+use settings::profiles::{self, Permission};
 use serde::{Deserialize, Serialize};
+use tauri::Emitter;
 use tauri::Manager;
 
-/// Start network scan
+/// Level 2/3 scans probe devices, which viewer profiles may not do
+pub(crate) fn require_scan_level(level: ScanLevel) -> Result<(), AppError> {
+    if level >= ScanLevel::Level2 {
+        profiles::require(Permission::ActiveScan)?;
+    }
+    Ok(())
+}
+
+/// Interface names and CIDR ranges chosen for a scan
+fn parse_targets(targets: Option<Vec<String>>) -> Result<Vec<scanner::interfaces::ScanTarget>, AppError> {
+    let targets = targets.unwrap_or_default().iter().map(|t| t.parse()).collect::<Result<_, scanner::ScanError>>()?;
+    Ok(targets)
+}
+
+/// A data pack port profile name, rejected when the active pack has no such profile
+fn check_port_profile(profile: Option<String>) -> Result<Option<String>, AppError> {
+    match profile {
+        Some(name) if scanner::datapack::port_profile(&name).is_none() => {
+            Err(AppError::invalid_input(format!("Unknown port profile: {}", name))
+                .with_hint("データパックにあるポートプロファイルを選んでください"))
+        }
+        profile => Ok(profile),
+    }
+}
+
+/// Start network scan. `credential_consent` lists the MACs of the devices the
+/// user allowed default password logins on; they are only tried at Level 3.
+/// `targets` are interface names and CIDR ranges to sweep, the default
+/// interface when omitted. `profile` names the data pack port profile whose
+/// ports are added at Level 2 and 3; every profile's when omitted. Every scan is saved to the history, and the
+/// devices of the latest saved scan of the whole network are what the next
+/// one re-verifies and compares with; scans of chosen targets are kept as
+/// partial.
 #[tauri::command]
 async fn start_scan(
     app: tauri::AppHandle,
     level: ScanLevel,
-) -> Result<Vec<Device>, String> {
-    scanner::scan_network(&app, level)
-        .await
-        .map_err(|e| e.to_string())
+    credential_consent: Option<Vec<String>>,
+    targets: Option<Vec<String>>,
+    profile: Option<String>,
+) -> Result<Vec<Device>, AppError> {
+    require_scan_level(level)?;
+    run_scan(app, level, credential_consent, targets, profile, false).await
+}
+
+/// [`start_scan`] without the permission check, for scans authorized
+/// beforehand such as schedules. Quiet hours may slow `unattended` scans.
+pub(crate) async fn run_scan(
+    app: tauri::AppHandle,
+    level: ScanLevel,
+    credential_consent: Option<Vec<String>>,
+    targets: Option<Vec<String>>,
+    profile: Option<String>,
+    unattended: bool,
+) -> Result<Vec<Device>, AppError> {
+    // Fail before any setup; `scan_network` claims the running flag atomically
+    if scanner::is_scan_running() {
+        return Err(scanner::ScanError::AlreadyRunning.into());
+    }
+    let mut options = config::scan_options();
+    if unattended {
+        monitor::quiet::throttle(&mut options.port_config);
+    }
+    options.targets = parse_targets(targets)?;
+    options.port_profile = check_port_profile(profile)?;
+    options.credential_consent = credential_consent.unwrap_or_default().iter().map(|m| m.to_lowercase()).collect();
+    options.known_devices = database::get_latest_scan_devices().unwrap_or_default();
+    let devices = scanner::scan_network(&app, level, &options).await?;
+
+    // Presence tracking must not fail the scan itself
+    let seen_at = chrono::Local::now();
+    let settings = settings::load().unwrap_or_default();
+    if let Ok(alerts) = database::presence::evaluate(&devices, seen_at, &settings.presence) {
+        alerts::publish(alerts.iter().filter_map(|alert| {
+            let device = devices.iter().find(|d| d.mac == alert.mac)?;
+            Some(alerts::AlertEvent::new(
+                alerts::EventKind::UnusualPresence,
+                device,
+                None,
+                alert.message.clone(),
+            ))
+        }));
+        if !alerts.is_empty() {
+            let _ = app.emit("presence-alerts", alerts);
+        }
+    }
+    let _ = database::presence::record(&devices, seen_at);
+    let network_id = database::network_id(&devices);
+    let complete = options.targets.is_empty();
+    let _ = database::presence::track(&devices, network_id.as_deref(), seen_at.with_timezone(&chrono::Utc), complete);
+    // After moving to another network the last scan is no baseline
+    let same_network = network_id == database::network_id(&options.known_devices);
+    let previous: &[Device] = if same_network { &options.known_devices } else { &[] };
+    alerts::publish(alerts::scan_events(previous, &devices));
+
+    if let Err(e) = database::save_scan(&devices, !complete) {
+        eprintln!("Failed to save scan: {}", e);
+    }
+    // Public IP lookups are traffic too
+    let profile = if options.strictly_passive {
+        None
+    } else {
+        scanner::wan::profile(&devices, &settings.wan).await
+    };
+    if let Some(profile) = profile {
+        if let Err(e) = database::network::save(&profile) {
+            eprintln!("Failed to save network profile: {}", e);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// IPv4 networks of this machine that a scan can sweep
+#[tauri::command]
+async fn list_interfaces() -> Result<Vec<scanner::interfaces::ScanInterface>, AppError> {
+    Ok(scanner::interfaces::list_interfaces())
+}
+
+/// Abort the running scan; returns whether one was running. The scan may be
+/// a schedule's or another profile's, so aborting takes an active scan permission.
+#[tauri::command]
+async fn cancel_scan() -> Result<bool, AppError> {
+    profiles::require(Permission::ActiveScan)?;
+    Ok(scanner::cancel_scan())
+}
+
+/// Re-check the devices of the last scan without discovery ("did my fix work?")
+#[tauri::command]
+async fn quick_refresh(app: tauri::AppHandle) -> Result<Vec<Device>, AppError> {
+    profiles::require(Permission::ActiveScan)?;
+    let mut options = config::scan_options();
+    options.known_devices = database::get_latest_scan_devices()?;
+    if options.known_devices.is_empty() {
+        return Err(AppError::invalid_input("No previous scan to refresh")
+            .with_hint("先に通常のスキャンを実行してください"));
+    }
+    let devices = scanner::quick::refresh(&app, &options).await?;
+
+    // Devices that did not answer are missing, so the last full scan stays the baseline
+    if let Err(e) = database::save_scan(&devices, true) {
+        eprintln!("Failed to save scan: {}", e);
+    }
+
+    Ok(devices)
+}
+
+/// List the active and intrusive checks a scan at `level` would run against each device
+#[tauri::command]
+async fn preview_checks(
+    devices: Vec<Device>,
+    level: ScanLevel,
+    credential_consent: Option<Vec<String>>,
+) -> Result<scanner::preview::CheckPreview, AppError> {
+    let mut options = config::scan_options();
+    options.credential_consent = credential_consent.unwrap_or_default().iter().map(|m| m.to_lowercase()).collect();
+    Ok(scanner::preview::preview(&devices, level, &options))
+}
+
+/// Estimate how many probes a scan of `targets` with the ports of `profile`
+/// (as for `start_scan`) will send and how long it will take
+#[tauri::command]
+async fn estimate_scan(
+    level: ScanLevel,
+    targets: Option<Vec<String>>,
+    profile: Option<String>,
+) -> Result<scanner::estimate::ScanEstimate, AppError> {
+    let mut options = config::scan_options();
+    options.targets = parse_targets(targets)?;
+    options.port_profile = check_port_profile(profile)?;
+    options.known_devices = database::get_latest_scan_devices().unwrap_or_default();
+    Ok(scanner::estimate::estimate(level, &options))
 }
 
 /// Get scan history
 #[tauri::command]
-async fn get_scan_history() -> Result<Vec<database::ScanRecord>, String> {
-    database::get_scan_history().map_err(|e| e.to_string())
+async fn get_scan_history() -> Result<Vec<database::ScanRecord>, AppError> {
+    let dates = settings::load().unwrap_or_default().locale.formatter();
+    let mut records = database::get_scan_history()?;
+    for record in &mut records {
+        record.display_timestamp = Some(dates.datetime(record.timestamp));
+    }
+    Ok(records)
 }
 
 /// Get device details
 #[tauri::command]
-async fn get_device_details(device_id: String) -> Result<Option<Device>, String> {
-    database::get_device(&device_id).map_err(|e| e.to_string())
+async fn get_device_details(device_id: String) -> Result<Option<Device>, AppError> {
+    database::get_device(&device_id).map_err(AppError::from)
+}
+
+/// Explain how a device's security score was computed
+#[tauri::command]
+async fn explain_score(device_id: String) -> Result<Option<scanner::scoring::ScoreExplanation>, AppError> {
+    let device = database::get_device(&device_id)?;
+    let policy = config::scoring_policy();
+    Ok(device.map(|d| policy.explain(&d)))
+}
+
+/// Get when a device is usually online
+#[tauri::command]
+async fn get_presence_profile(mac: String) -> Result<Option<database::presence::PresenceProfile>, AppError> {
+    database::presence::get_profile(&mac).map_err(AppError::from)
+}
+
+/// When a device was on the network, and whether it answers right now
+#[tauri::command]
+async fn get_device_timeline(mac: String) -> Result<Option<database::presence::DeviceTimeline>, AppError> {
+    let Some(mut timeline) = database::presence::get_timeline(&mac)? else {
+        return Ok(None);
+    };
+    if let Some(ip) = timeline.last_ip.clone() {
+        // The probe is a single datagram, but traffic all the same
+        let active = !scanner::net::is_strictly_passive() && profiles::require(Permission::ActiveScan).is_ok();
+        timeline.is_online = Some(scanner::arp::is_online(&ip, &timeline.mac, active).await);
+    }
+    Ok(Some(timeline))
+}
+
+/// Export scan history as a sync bundle for merging on another machine
+#[tauri::command]
+async fn export_sync_bundle(path: String, network_id: Option<String>) -> Result<usize, AppError> {
+    let bundle = database::sync::export_bundle(network_id.as_deref())?;
+    database::sync::write_bundle(std::path::Path::new(&path), &bundle)?;
+    Ok(bundle.scans.len())
+}
+
+/// Merge a sync bundle exported by another installation into the local history
+#[tauri::command]
+async fn import_sync_bundle(path: String) -> Result<database::sync::ImportSummary, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let bundle = database::sync::read_bundle(std::path::Path::new(&path))?;
+    database::sync::import_bundle(&bundle).map_err(AppError::from)
+}
+
+/// Store a previously exported JSON or HTML report in the scan history
+#[tauri::command]
+async fn import_report(path: String) -> Result<database::sync::ImportSummary, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::sync::import_report(std::path::Path::new(&path)).map_err(AppError::from)
+}
+
+/// Open an exported bundle or JSON/HTML report for read-only viewing
+#[tauri::command]
+async fn open_scan_bundle(path: String) -> Result<database::viewer::OpenedBundle, AppError> {
+    database::viewer::open(std::path::Path::new(&path)).map_err(AppError::from)
+}
+
+/// Get devices of a scan in the opened bundle
+#[tauri::command]
+async fn get_bundle_scan_devices(scan_id: String) -> Result<Option<Vec<Device>>, AppError> {
+    Ok(database::viewer::get_scan_devices(&scan_id))
+}
+
+/// Close the opened bundle
+#[tauri::command]
+async fn close_scan_bundle() -> Result<(), AppError> {
+    database::viewer::close();
+    Ok(())
+}
+
+/// Get the persistent ID of this installation
+#[tauri::command]
+async fn get_installation_id() -> Result<String, AppError> {
+    database::installation_id().map_err(AppError::from)
+}
+
+/// Get the directory history and configuration are stored in
+#[tauri::command]
+async fn get_data_location() -> Result<database::location::DataLocation, AppError> {
+    Ok(database::location::current())
+}
+
+/// Move history and configuration to another directory (`None` restores the default)
+#[tauri::command]
+async fn set_data_directory(
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> Result<database::location::DataLocation, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let location = database::location::change(path.map(std::path::PathBuf::from))?;
+
+    // Configuration and data packs now come from the new directory
+    config::reload()?;
+    config::watch(app)?;
+    scanner::datapack::load_installed()?;
+    scanner::oui::load()?;
+    Ok(location)
+}
+
+/// Tags, exclusion and criticality of all devices, keyed by MAC address
+#[tauri::command]
+async fn get_device_metadata(
+) -> Result<std::collections::HashMap<String, database::metadata::DeviceMetadata>, AppError> {
+    database::metadata::all().map_err(AppError::from)
+}
+
+/// Every finding recorded for a device and where it stands
+#[tauri::command]
+async fn get_issue_lifecycle(mac: String) -> Result<Vec<database::issues::IssueRecord>, AppError> {
+    database::issues::for_device(&mac).map_err(AppError::from)
+}
+
+/// Acknowledge an open finding, or withdraw the acknowledgement
+#[tauri::command]
+async fn acknowledge_issue(mac: String, issue_id: String, acknowledged: Option<bool>) -> Result<bool, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::issues::set_acknowledged(&mac, &issue_id, acknowledged.unwrap_or(true)).map_err(AppError::from)
+}
+
+/// Add tags to several devices at once
+#[tauri::command]
+async fn tag_devices(macs: Vec<String>, tags: Vec<String>) -> Result<usize, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::metadata::tag_devices(&macs, &tags).map_err(AppError::from)
+}
+
+/// Exclude several devices from monitoring and active checks, or include them again
+#[tauri::command]
+async fn exclude_devices(macs: Vec<String>, excluded: Option<bool>) -> Result<usize, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::metadata::exclude_devices(&macs, excluded.unwrap_or(true)).map_err(AppError::from)
+}
+
+/// Set the criticality of several devices at once
+#[tauri::command]
+async fn set_criticality_bulk(
+    macs: Vec<String>,
+    criticality: database::metadata::Criticality,
+) -> Result<usize, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::metadata::set_criticality(&macs, criticality).map_err(AppError::from)
+}
+
+/// Set a device's icon, photo, room, purchase date and warranty
+#[tauri::command]
+async fn update_device_profile(
+    mac: String,
+    profile: database::metadata::DeviceProfile,
+) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    profile.validate().map_err(AppError::invalid_input)?;
+    database::metadata::update_profile(&mac, profile).map_err(AppError::from)
+}
+
+/// Write the latest scan's devices, joined with their profiles, as an inventory
+#[tauri::command]
+async fn export_inventory(
+    path: String,
+    format: Option<report::inventory::InventoryFormat>,
+) -> Result<usize, AppError> {
+    let devices = database::get_latest_scan_devices()?;
+    let metadata = database::metadata::all()?;
+    let inventory = report::inventory::generate(&devices, &metadata, format.unwrap_or_default())?;
+    std::fs::write(&path, inventory)?;
+    Ok(devices.len())
+}
+
+/// Write one scan's reports, inventory, raw data, audit log and diagnostics
+/// to a ZIP file for handing over to a client
+#[tauri::command]
+async fn export_assessment_bundle(scan_id: String, path: String) -> Result<Vec<String>, AppError> {
+    let settings = settings::load().unwrap_or_default();
+    let network_id = database::get_scan(&scan_id)?.and_then(|scan| scan.network_id);
+    let options = report::ReportOptions {
+        branding: settings.branding,
+        mode: report::ReportMode::Standard,
+        context: settings.context,
+        locale: settings.locale,
+        network: network_id
+            .filter(|_| settings.wan.enabled)
+            .and_then(|id| database::network::get(&id).ok().flatten()),
+        network_issues: Vec::new(),
+    };
+    report::bundle::export(&scan_id, &options, std::path::Path::new(&path)).map_err(AppError::from)
+}
+
+/// ISP, public IP and router model recorded for a network
+#[tauri::command]
+async fn get_network_profile(
+    network_id: String,
+) -> Result<Option<database::network::NetworkProfile>, AppError> {
+    database::network::get(&network_id).map_err(AppError::from)
+}
+
+/// Findings of the last scan about the network as a whole, such as its DNS
+#[tauri::command]
+async fn get_network_issues() -> Result<Vec<scanner::NetworkIssue>, AppError> {
+    Ok(scanner::network_issues())
+}
+
+/// Encryption and WPS of the Wi-Fi network this machine is connected to
+#[tauri::command]
+async fn get_wifi_security() -> Result<Option<scanner::wifi::WifiConnection>, AppError> {
+    Ok(scanner::wifi::current_connection().await)
+}
+
+/// Compare two scans from history, matching devices by MAC address
+#[tauri::command]
+async fn get_scan_diff(scan_a: String, scan_b: String) -> Result<report::diff::ScanDiff, AppError> {
+    report::diff::diff_scans(&scan_a, &scan_b).map_err(AppError::from)
 }
 
 /// Generate security report
@@ -35,35 +431,550 @@ async fn get_device_details(device_id: String) -> Result<Option<Device>, String>
 async fn generate_report(
     devices: Vec<Device>,
     format: report::ReportFormat,
-) -> Result<String, String> {
-    report::generate(&devices, format).map_err(|e| e.to_string())
+    mode: Option<report::ReportMode>,
+    network_issues: Option<Vec<scanner::NetworkIssue>>,
+) -> Result<String, AppError> {
+    let settings = settings::load().unwrap_or_default();
+    let options = report::ReportOptions {
+        branding: settings.branding,
+        mode: mode.unwrap_or_default(),
+        context: settings.context,
+        locale: settings.locale,
+        network: settings
+            .wan
+            .enabled
+            .then(|| database::network_id(&devices))
+            .flatten()
+            .and_then(|id| database::network::get(&id).ok().flatten()),
+        network_issues: network_issues.unwrap_or_default(),
+    };
+    report::generate(&devices, format, &options).map_err(AppError::from)
+}
+
+/// Get application settings
+#[tauri::command]
+async fn get_settings() -> Result<settings::Settings, AppError> {
+    settings::load().map_err(AppError::from)
+}
+
+/// Update application settings
+#[tauri::command]
+async fn update_settings(mut settings: settings::Settings) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    settings.locale.validate().map_err(AppError::invalid_input)?;
+    settings.scan_budget.clamp();
+    settings::save(&settings)?;
+    vulndb::content::set_language(settings.locale.language);
+    scanner::net::set_strictly_passive(settings.safe_mode.strictly_passive);
+    Ok(())
+}
+
+/// Get the ports scans probe
+#[tauri::command]
+async fn get_scan_config() -> Result<scanner::ports::ScanConfig, AppError> {
+    scanner::ports::load_config().map_err(AppError::from)
+}
+
+/// Choose the ports scans probe: a preset or a custom list of ports and ranges
+#[tauri::command]
+async fn set_scan_config(config: scanner::ports::ScanConfig) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    config.validate().map_err(|e| {
+        AppError::invalid_input(e)
+            .with_hint("ポートは「22, 80, 8000-8100」のように 1〜65535 の番号で、同時接続数は 1 以上、1台あたりの接続速度は毎秒 100000 回以下で指定してください")
+    })?;
+    scanner::ports::save_config(&config)?;
+    Ok(())
+}
+
+/// Generate a digest of all scans in the last `days` days
+#[tauri::command]
+async fn generate_digest(
+    days: Option<i64>,
+    format: report::ReportFormat,
+) -> Result<String, AppError> {
+    let locale = settings::load().unwrap_or_default().locale;
+    let end = chrono::Utc::now();
+    let start = end - chrono::Duration::days(days.unwrap_or(report::digest::DEFAULT_PERIOD_DAYS));
+    report::digest::build(start, end)
+        .and_then(|digest| report::digest::render(&digest, format, &locale))
+        .map_err(AppError::from)
+}
+
+/// Everything the home screen shows, in one call
+#[tauri::command]
+async fn get_dashboard_data() -> Result<report::dashboard::DashboardData, AppError> {
+    report::dashboard::build().map_err(AppError::from)
 }
 
 /// Check for vulnerabilities
 #[tauri::command]
-async fn check_vulnerabilities(device: Device) -> Result<Vec<vulndb::Vulnerability>, String> {
+async fn check_vulnerabilities(device: Device) -> Result<Vec<vulndb::Vulnerability>, AppError> {
     vulndb::check_device(&device)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
+}
+
+/// Search the vulnerability database by keyword or CVE ID
+#[tauri::command]
+async fn search_vulnerabilities(query: String) -> Result<Vec<vulndb::Vulnerability>, AppError> {
+    vulndb::catalog::search(&query).await.map_err(AppError::from)
+}
+
+/// Look up a single vulnerability by ID or CVE ID
+#[tauri::command]
+async fn get_vulnerability(id: String) -> Result<Option<vulndb::Vulnerability>, AppError> {
+    vulndb::catalog::get(&id).await.map_err(AppError::from)
+}
+
+/// Everything a scan checks for
+#[tauri::command]
+async fn list_checks() -> Result<Vec<vulndb::catalog::CheckInfo>, AppError> {
+    Ok(vulndb::catalog::list_checks())
+}
+
+/// Run every check against bundled fixtures to confirm it still works
+#[tauri::command]
+async fn selftest_checks() -> Result<scanner::selftest::SelfTestReport, AppError> {
+    Ok(scanner::selftest::run().await)
+}
+
+/// Retry vulnerability lookups that were queued while offline
+#[tauri::command]
+async fn process_enrichment_queue() -> Result<usize, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let completed = vulndb::client::shared().process_queue().await?;
+    // Findings checked while lookups were missing are checked again
+    if completed > 0 {
+        vulndb::cache::clear()?;
+    }
+    Ok(completed)
+}
+
+/// Reload user-editable check configuration files
+#[tauri::command]
+async fn reload_configuration() -> Result<config::CheckConfig, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    config::reload().map_err(AppError::from)
+}
+
+/// Download and install the latest signed fingerprint data pack
+#[tauri::command]
+async fn update_datapacks() -> Result<scanner::datapack::DataPackInfo, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let info = scanner::datapack::update().await?;
+    vulndb::cache::clear()?;
+    Ok(info)
+}
+
+/// Download the current IEEE OUI registry and use it for vendor lookups
+#[tauri::command]
+async fn update_oui_database() -> Result<scanner::oui::OuiInfo, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    Ok(scanner::oui::update().await?)
+}
+
+/// List the Bluetooth LE devices advertising nearby. From Level 2 the
+/// connectable ones are also checked for data readable without pairing.
+#[tauri::command]
+async fn scan_ble(level: ScanLevel) -> Result<Vec<scanner::ble::BleDevice>, AppError> {
+    require_scan_level(level)?;
+    let probe = level >= ScanLevel::Level2 && !config::scan_options().strictly_passive;
+    Ok(scanner::ble::scan(scanner::ble::SCAN_WINDOW, probe).await?)
+}
+
+/// Source and size of the OUI registry in use
+#[tauri::command]
+async fn get_oui_info() -> Result<Option<scanner::oui::OuiInfo>, AppError> {
+    Ok(scanner::oui::info())
+}
+
+/// Version of the installed fingerprint data pack, if any
+#[tauri::command]
+async fn get_datapack_info() -> Result<Option<scanner::datapack::DataPackInfo>, AppError> {
+    Ok(scanner::datapack::info())
+}
+
+/// Fetch the client list from the configured router to verify the integration
+#[tauri::command]
+async fn test_router_integration() -> Result<Vec<scanner::router::DhcpLease>, AppError> {
+    profiles::require(Permission::ActiveScan)?;
+    let settings = settings::load()?.router;
+    scanner::router::fetch_leases(&settings)
+        .await?
+        .ok_or_else(|| scanner::router::RouterError::NotConfigured.into())
+}
+
+/// Remove a UPnP port mapping from the gateway `device`. The UI asks the
+/// user to confirm first; the attempt is recorded in the audit log either way.
+#[tauri::command]
+async fn delete_port_mapping(
+    device: Device,
+    mapping: scanner::portmap::PortMapping,
+) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let gateway: std::net::Ipv4Addr = device
+        .ip
+        .parse()
+        .map_err(|_| AppError::invalid_input(format!("Not an IPv4 gateway: {}", device.ip)))?;
+
+    let result = scanner::igd::delete_mapping(gateway, &mapping, scanner::GATEWAY_PROBE_TIMEOUT).await;
+
+    let target = match &device.name {
+        Some(name) => format!("{} ({})", name, device.ip),
+        None => device.ip.clone(),
+    };
+    let detail = format!(
+        "{} {} → {}:{}{}",
+        mapping.transport,
+        mapping.external_port,
+        mapping.internal_client,
+        mapping.internal_port,
+        mapping.description.as_ref().map(|d| format!(" ({})", d)).unwrap_or_default(),
+    );
+    let profile = profiles::active()?.map(|p| p.name);
+    database::audit::record("delete_port_mapping", &target, &detail, profile.as_deref(), result.is_ok())?;
+
+    result.map_err(AppError::from)
+}
+
+/// Actions the app performed on the network, newest first
+#[tauri::command]
+async fn get_audit_log(limit: Option<usize>) -> Result<Vec<database::audit::AuditEntry>, AppError> {
+    database::audit::list(limit.unwrap_or(100)).map_err(AppError::from)
+}
+
+/// Saved alert rules
+#[tauri::command]
+async fn list_rules() -> Result<Vec<alerts::rules::AlertRule>, AppError> {
+    alerts::rules::list().map_err(AppError::from)
+}
+
+/// Create or update an alert rule
+#[tauri::command]
+async fn save_rule(rule: alerts::rules::AlertRule) -> Result<alerts::rules::AlertRule, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    if rule.name.trim().is_empty() {
+        return Err(AppError::invalid_input("Rule name is empty").with_hint("ルールの名前を入力してください"));
+    }
+    database::alert_rules::save(rule).map_err(AppError::from)
+}
+
+/// Delete an alert rule
+#[tauri::command]
+async fn delete_rule(rule_id: String) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::alert_rules::delete(&rule_id).map_err(AppError::from)
+}
+
+/// What a rule would have notified about for the latest scan
+#[tauri::command]
+async fn test_rule(rule: alerts::rules::AlertRule) -> Result<Vec<alerts::AlertEvent>, AppError> {
+    alerts::rules::test(&rule).map_err(AppError::from)
+}
+
+/// Saved scan schedules
+#[tauri::command]
+async fn get_schedules() -> Result<Vec<scheduler::ScanSchedule>, AppError> {
+    scheduler::list().map_err(AppError::from)
+}
+
+/// Create or update a scan schedule
+#[tauri::command]
+async fn set_schedule(schedule: scheduler::ScanSchedule) -> Result<scheduler::ScanSchedule, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    if schedule.level > ScanLevel::Level2 {
+        return Err(AppError::invalid_input("Scheduled scans are limited to Level 1 and 2")
+            .with_hint("定期スキャンにはレベル1またはレベル2を選んでください"));
+    }
+    require_scan_level(schedule.level)?;
+    database::schedules::save(schedule).map_err(AppError::from)
+}
+
+/// Delete a scan schedule
+#[tauri::command]
+async fn delete_schedule(schedule_id: String) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    database::schedules::delete(&schedule_id).map_err(AppError::from)
+}
+
+/// Pair with a remote probe using the code it printed on first start
+#[tauri::command]
+async fn pair_probe(name: String, address: String, pairing_code: String) -> Result<probe::RemoteProbe, AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let remote = probe::pair(name, address, &pairing_code).await?;
+    let mut settings = settings::load()?;
+    settings.probes.push(remote.clone());
+    settings::save(&settings)?;
+    Ok(remote)
+}
+
+/// Paired remote probes
+#[tauri::command]
+async fn list_probes() -> Result<Vec<probe::RemoteProbe>, AppError> {
+    Ok(settings::load()?.probes)
+}
+
+/// Forget a paired remote probe
+#[tauri::command]
+async fn unpair_probe(probe_id: String) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    let mut settings = settings::load()?;
+    if let Some(pos) = settings.probes.iter().position(|p| p.id == probe_id) {
+        let remote = settings.probes.remove(pos);
+        probe::unpair(&remote)?;
+        settings::save(&settings)?;
+    }
+    Ok(())
+}
+
+/// Scan the network a remote probe is attached to
+#[tauri::command]
+async fn start_remote_scan(
+    app: tauri::AppHandle,
+    probe_id: String,
+    level: ScanLevel,
+) -> Result<Vec<Device>, AppError> {
+    require_scan_level(level)?;
+    let remote = settings::load()?
+        .probes
+        .into_iter()
+        .find(|p| p.id == probe_id)
+        .ok_or_else(|| probe::ProbeError::NotFound(probe_id.clone()))?;
+
+    let mut devices = probe::scan(&remote, level, &app).await?;
+
+    // Score with this app's policy and show findings in this app's language,
+    // rather than the probe's defaults
+    let policy = config::scoring_policy();
+    for device in &mut devices {
+        policy.apply(device);
+    }
+    vulndb::content::localize_devices(&mut devices, vulndb::content::language());
+
+    if let Err(e) = database::save_scan(&devices, false) {
+        eprintln!("Failed to save scan: {}", e);
+    }
+
+    Ok(devices)
+}
+
+/// All operator profiles
+#[tauri::command]
+async fn list_profiles() -> Result<Vec<profiles::OperatorProfile>, AppError> {
+    profiles::list().map_err(AppError::from)
+}
+
+/// The profile the app is used as; `None` when no profiles are set up
+#[tauri::command]
+async fn get_active_profile() -> Result<Option<profiles::OperatorProfile>, AppError> {
+    profiles::active().map_err(AppError::from)
+}
+
+/// Add an operator profile; administrators need a PIN
+#[tauri::command]
+async fn create_profile(
+    name: String,
+    role: profiles::Role,
+    pin: Option<String>,
+) -> Result<profiles::OperatorProfile, AppError> {
+    profiles::create(&name, role, pin.as_deref()).map_err(AppError::from)
+}
+
+/// Remove an operator profile
+#[tauri::command]
+async fn delete_profile(profile_id: String) -> Result<(), AppError> {
+    profiles::delete(&profile_id).map_err(AppError::from)
+}
+
+/// Switch to another profile, checking its PIN
+#[tauri::command]
+async fn switch_profile(profile_id: String, pin: Option<String>) -> Result<profiles::OperatorProfile, AppError> {
+    profiles::switch(&profile_id, pin.as_deref()).map_err(AppError::from)
+}
+
+/// Store an integration credential in the OS keychain
+#[tauri::command]
+async fn set_secret(kind: secrets::SecretKind, value: String) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    secrets::set_secret(kind, &value).map_err(AppError::from)
+}
+
+/// Remove an integration credential from the OS keychain
+#[tauri::command]
+async fn delete_secret(kind: secrets::SecretKind) -> Result<(), AppError> {
+    profiles::require(Permission::ChangeSettings)?;
+    secrets::delete_secret(kind).map_err(AppError::from)
+}
+
+/// Health of the background subsystems watched by the watchdog
+#[tauri::command]
+async fn get_subsystem_health() -> Result<Vec<health::SubsystemHealth>, AppError> {
+    Ok(health::status())
+}
+
+/// Check whether an integration credential is configured
+#[tauri::command]
+async fn has_secret(kind: secrets::SecretKind) -> Result<bool, AppError> {
+    secrets::has_secret(kind).map_err(AppError::from)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .setup(|_app| {
+        .setup(|app| {
+            // Resolve the per-user data directory before anything touches it
+            database::location::init(app.path().app_data_dir().ok());
+
             // Initialize database
             if let Err(e) = database::init() {
                 eprintln!("Failed to initialize database: {}", e);
             }
+
+            // Start in the least privileged operator profile
+            if let Err(e) = profiles::init() {
+                eprintln!("Failed to load operator profiles: {}", e);
+            }
+
+            let app_settings = settings::load().unwrap_or_default();
+            vulndb::content::set_language(app_settings.locale.language);
+            scanner::net::set_strictly_passive(app_settings.safe_mode.strictly_passive);
+
+            // Local automation interface for scripts and cron jobs
+            if app_settings.control.enabled {
+                let handle = app.handle().clone();
+                health::supervise(Subsystem::Control, None, move || control::start(handle.clone()));
+            }
+
+            // Notify about changes that match the user's alert rules
+            let handle = app.handle().clone();
+            health::supervise(Subsystem::Alerts, None, move || alerts::start(handle.clone()));
+
+            // Watch for risky ports opening between scans
+            if app_settings.monitor.enabled {
+                let handle = app.handle().clone();
+                let monitor = app_settings.monitor.clone();
+                health::supervise(Subsystem::Monitor, Some(monitor.interval()), move || {
+                    monitor::start(handle.clone(), monitor.clone())
+                });
+            }
+
+            // Keep the device list current after sleep or moving between networks
+            if app_settings.monitor.refresh_on_network_change {
+                let handle = app.handle().clone();
+                health::supervise(Subsystem::NetworkWatch, Some(monitor::wake::HEARTBEAT_PERIOD), move || {
+                    monitor::wake::start(handle.clone())
+                });
+            }
+
+            // Scans the user scheduled; schedules can be added at any time
+            let handle = app.handle().clone();
+            health::supervise(Subsystem::Scheduler, Some(scheduler::HEARTBEAT_PERIOD), move || {
+                scheduler::start(handle.clone())
+            });
+
+            // Restart background subsystems that die while the app runs unattended
+            let scheduled = scheduler::list().is_ok_and(|s| s.iter().any(|s| s.enabled));
+            if app_settings.control.enabled || app_settings.monitor.enabled || scheduled {
+                health::start(app.handle().clone());
+            }
+
+            // Pick up configuration edits without a restart
+            if let Err(e) = config::watch(app.handle().clone()) {
+                eprintln!("Failed to watch configuration directory: {}", e);
+            }
+
+            // Fingerprint data installed since the last app release
+            if let Err(e) = scanner::datapack::load_installed() {
+                eprintln!("Failed to load fingerprint data pack: {}", e);
+            }
+            if let Err(e) = scanner::oui::load() {
+                eprintln!("Failed to load OUI registry: {}", e);
+            }
+
+            // Complete lookups that were queued during a previous offline session
+            tauri::async_runtime::spawn(async {
+                let _ = vulndb::client::shared().process_queue().await;
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             start_scan,
+            list_interfaces,
+            cancel_scan,
+            quick_refresh,
+            estimate_scan,
+            preview_checks,
+            delete_port_mapping,
+            get_audit_log,
+            list_rules,
+            save_rule,
+            delete_rule,
+            test_rule,
+            get_schedules,
+            set_schedule,
+            delete_schedule,
             get_scan_history,
             get_device_details,
+            explain_score,
+            get_scan_diff,
+            get_network_profile,
+            get_network_issues,
+            get_wifi_security,
+            get_device_metadata,
+            tag_devices,
+            exclude_devices,
+            set_criticality_bulk,
+            update_device_profile,
+            export_inventory,
+            get_presence_profile,
+            get_device_timeline,
+            export_sync_bundle,
+            import_sync_bundle,
+            import_report,
+            get_installation_id,
+            get_data_location,
+            set_data_directory,
+            open_scan_bundle,
+            get_bundle_scan_devices,
+            close_scan_bundle,
             generate_report,
+            generate_digest,
+            export_assessment_bundle,
+            get_dashboard_data,
             check_vulnerabilities,
+            process_enrichment_queue,
+            search_vulnerabilities,
+            get_vulnerability,
+            list_checks,
+            selftest_checks,
+            get_settings,
+            update_settings,
+            get_scan_config,
+            set_scan_config,
+            reload_configuration,
+            update_datapacks,
+            update_oui_database,
+            get_oui_info,
+            scan_ble,
+            get_datapack_info,
+            test_router_integration,
+            pair_probe,
+            list_probes,
+            unpair_probe,
+            start_remote_scan,
+            list_profiles,
+            get_active_profile,
+            create_profile,
+            delete_profile,
+            switch_profile,
+            set_secret,
+            delete_secret,
+            has_secret,
+            get_subsystem_health,
+            get_issue_lifecycle,
+            acknowledge_issue,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
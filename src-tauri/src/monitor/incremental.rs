@@ -0,0 +1,72 @@
+//! Incremental checks for changes seen while monitoring
+//!
+//! A camera that starts listening on Telnet after a firmware update should
+//! not wait for the next full scan. Only the risky ports are probed, and only
+//! the checks that depend on a newly opened port are re-run.
+
+use crate::scanner::scoring::ScoringPolicy;
+use crate::scanner::{self, ports, Device, PortVerification, SecurityIssue};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Ports whose appearance is worth an immediate alert
+const WATCHED_PORTS: &[u16] = &[21, 23, 1900, 2323, 5555, 7547];
+
+/// A device that opened a watched port since the last round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortAlert {
+    pub device_id: String,
+    pub mac: String,
+    pub ip: String,
+    pub name: Option<String>,
+    pub new_ports: Vec<u16>,
+    /// Findings raised for the new ports
+    pub issues: Vec<SecurityIssue>,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Open watched ports per MAC address, as of the previous round
+#[derive(Debug, Default)]
+pub struct PortWatcher {
+    known: HashMap<String, HashSet<u16>>,
+}
+
+impl PortWatcher {
+    /// Probe a device's watched ports. On a newly opened port the port's
+    /// checks run, the device is rescored and an alert is returned.
+    pub async fn check(&mut self, device: &mut Device, policy: &ScoringPolicy) -> Option<PortAlert> {
+        let open: HashSet<u16> = ports::probe_open(&device.ip, WATCHED_PORTS).await.into_iter().collect();
+        let mac = device.mac.to_lowercase();
+
+        // The first round only learns what is already open
+        let previous = self.known.insert(mac, open.clone())?;
+        // Ports the last full scan already reported are not news either
+        let mut new_ports: Vec<u16> = open
+            .iter()
+            .copied()
+            .filter(|port| !previous.contains(port) && !device.open_ports.iter().any(|p| p.number == *port))
+            .collect();
+        if new_ports.is_empty() {
+            return None;
+        }
+        new_ports.sort_unstable();
+
+        let mut issues = Vec::new();
+        for &port in &new_ports {
+            device.open_ports.push(ports::tcp_port(port, PortVerification::SingleAttempt));
+            issues.extend(scanner::port_issues(port));
+        }
+        device.issues.extend(issues.iter().cloned());
+        policy.apply(device);
+
+        Some(PortAlert {
+            device_id: device.id.clone(),
+            mac: device.mac.clone(),
+            ip: device.ip.clone(),
+            name: device.name.clone(),
+            new_ports,
+            issues,
+            detected_at: chrono::Utc::now(),
+        })
+    }
+}
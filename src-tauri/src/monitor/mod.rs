@@ -0,0 +1,212 @@
+//! Background monitoring between full scans
+//!
+//! While enabled, the devices of the latest scan are watched with cheap
+//! probes on a fixed interval. Changes that matter (see [`incremental`]) are
+//! checked right away and reported as `monitor-alerts` events instead of
+//! waiting for the next full scan. What rounds learn is stored as
+//! observations (see [`database::monitor`]), never into the saved scan. Each round also sweeps the network for
+//! devices that joined since (see [`newcomers`]).
+
+use crate::scanner::fingerprint::TypeSource;
+use crate::scanner::ssdp::Announcement;
+use crate::scanner::{self, ScanLevel};
+use crate::alerts::{self, AlertEvent, EventKind};
+use crate::health::{self, Subsystem};
+use crate::database::monitor::ObservationKind;
+use crate::{config, database};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::Emitter;
+
+pub mod incremental;
+pub mod newcomers;
+pub mod quiet;
+pub mod wake;
+
+/// Monitoring settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorSettings {
+    /// Watch devices in the background (takes effect on restart)
+    pub enabled: bool,
+    /// Minutes between monitoring rounds
+    pub interval_minutes: u32,
+    /// Run a passive scan after waking from sleep or moving to another
+    /// network (takes effect on restart)
+    pub refresh_on_network_change: bool,
+    /// Sweep for devices that joined since the last scan
+    pub watch_new_devices: bool,
+    /// Show an OS notification when one joins
+    pub notify_new_devices: bool,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 10,
+            refresh_on_network_change: true,
+            watch_new_devices: true,
+            notify_new_devices: true,
+        }
+    }
+}
+
+impl MonitorSettings {
+    /// Time between monitoring rounds
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_minutes.max(1)) * 60)
+    }
+}
+
+/// Start monitoring in the background
+pub fn start(app: tauri::AppHandle, settings: MonitorSettings) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let interval = settings.interval();
+        let mut watcher = incremental::PortWatcher::default();
+        let mut newcomers = newcomers::NewcomerWatcher::default();
+        loop {
+            health::beat(Subsystem::Monitor);
+            // The sweep sends ARP requests like a scan's discovery
+            let sweep_allowed = settings.watch_new_devices
+                && quiet::allowed_level(ScanLevel::Level1).is_some()
+                && !scanner::net::is_strictly_passive();
+            if sweep_allowed && !scanner::is_scan_running() {
+                let sweep = newcomers.check(&config::scan_options()).await;
+                newcomers::report(&app, &sweep.joined, settings.notify_new_devices).await;
+                newcomers::report_conflicts(&sweep.conflicts);
+            }
+
+            // A full scan is about to replace what we are watching. The port
+            // probes are active, so passive-only quiet hours skip them too.
+            let allowed = quiet::allowed_level(ScanLevel::Level2) == Some(ScanLevel::Level2)
+                && !scanner::net::is_strictly_passive();
+            if allowed && !scanner::is_scan_running() {
+                run_round(&app, &mut watcher).await;
+            }
+
+            // Devices announce themselves between rounds; listen instead of sleeping
+            let (announcements, dhcp) =
+                tokio::join!(scanner::ssdp::listen_notify(interval), scanner::dhcp::listen(interval));
+            let passive_allowed = quiet::allowed_level(ScanLevel::Level1).is_some();
+            if passive_allowed && !scanner::is_scan_running() {
+                if !announcements.is_empty() {
+                    fold_announcements(announcements).await;
+                }
+                if !dhcp.is_empty() {
+                    fold_dhcp(&dhcp);
+                }
+            }
+        }
+    })
+}
+
+/// Merge what devices announced over SSDP into the devices of the latest scan
+async fn fold_announcements(announcements: HashMap<String, Announcement>) {
+    let Ok(devices) = database::monitor::current_devices() else {
+        return;
+    };
+    // Only devices already known; new ones are left to the next scan
+    let announcements: HashMap<String, Announcement> = announcements
+        .into_iter()
+        .filter(|(ip, _)| devices.iter().any(|d| &d.ip == ip))
+        .collect();
+    // Fetching descriptions connects to the devices, which passive-only
+    // quiet hours rule out like the port probes
+    let fetch_allowed = quiet::allowed_level(ScanLevel::Level2) == Some(ScanLevel::Level2)
+        && !scanner::net::is_strictly_passive();
+    let descriptions = if fetch_allowed {
+        scanner::ssdp::describe(announcements).await
+    } else {
+        scanner::ssdp::from_headers(&announcements)
+    };
+
+    for mut device in devices {
+        let Some(description) = descriptions.get(&device.ip) else {
+            continue;
+        };
+        let before = serde_json::to_string(&device).ok();
+        let mut merged = device.ssdp.clone().unwrap_or_default();
+        merged.merge(description.clone());
+        if let Some(device_type) = scanner::ssdp::classify(&merged) {
+            let evidence = format!("SSDP: {}", merged.device_types.join(", "));
+            scanner::fingerprint::record_type(&mut device, device_type, TypeSource::Ssdp, evidence);
+        }
+        if device.model.is_none() {
+            device.model = merged.model_name.clone();
+        }
+        device.ssdp = Some(merged);
+        // Devices repeat their announcements every few minutes
+        if serde_json::to_string(&device).ok() == before {
+            continue;
+        }
+        if let Err(e) = database::monitor::record(&device, ObservationKind::Ssdp) {
+            eprintln!("Failed to store SSDP announcement: {}", e);
+        }
+    }
+}
+
+/// Record the DHCP fingerprints of devices of the latest scan that asked for an address
+fn fold_dhcp(fingerprints: &HashMap<String, scanner::dhcp::DhcpFingerprint>) {
+    let Ok(devices) = database::monitor::current_devices() else {
+        return;
+    };
+    for mut device in devices {
+        let Some(fingerprint) = fingerprints.get(&device.mac.to_lowercase()) else {
+            continue;
+        };
+        if device.dhcp.as_ref() == Some(fingerprint) {
+            continue;
+        }
+        scanner::dhcp::apply(&mut device, fingerprint.clone());
+        if let Err(e) = database::monitor::record(&device, ObservationKind::Dhcp) {
+            eprintln!("Failed to store DHCP fingerprint: {}", e);
+        }
+    }
+}
+
+async fn run_round(app: &tauri::AppHandle, watcher: &mut incremental::PortWatcher) {
+    let devices = match database::monitor::current_devices() {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprintln!("Monitoring could not load devices: {}", e);
+            return;
+        }
+    };
+
+    let metadata = database::metadata::all().unwrap_or_default();
+    let policy = config::scoring_policy();
+    let mut alerts = Vec::new();
+    for mut device in devices {
+        if metadata.get(&device.mac.to_lowercase()).is_some_and(|m| m.excluded) {
+            continue;
+        }
+        let Some(alert) = watcher.check(&mut device, &policy).await else {
+            continue;
+        };
+        if let Err(e) = database::monitor::record(&device, ObservationKind::PortsOpened) {
+            eprintln!("Failed to store monitoring result: {}", e);
+        }
+        let ports: Vec<String> = alert.new_ports.iter().map(u16::to_string).collect();
+        let worst = alert.issues.iter().max_by_key(|i| i.severity).cloned();
+        let label = device.name.clone().unwrap_or_else(|| device.ip.clone());
+        alerts::publish(
+            std::iter::once(AlertEvent::new(
+                EventKind::PortOpened,
+                &device,
+                worst,
+                format!("{} でポート {} が開きました", label, ports.join(", ")),
+            ))
+            .chain(alert.issues.iter().map(|issue| {
+                AlertEvent::new(EventKind::NewIssue, &device, Some(issue.clone()), format!("{}: {}", label, issue.title))
+            })),
+        );
+        alerts.push(alert);
+    }
+
+    if !alerts.is_empty() {
+        let _ = app.emit("monitor-alerts", alerts);
+    }
+}
@@ -0,0 +1,177 @@
+//! Devices joining between full scans
+//!
+//! Each monitoring round sweeps the network the way a scan's discovery does,
+//! ARP only, and compares the MAC addresses with what the database knows:
+//! the devices of the latest scan, those presence tracking has ever seen and
+//! those the user added notes to. A MAC never seen before is reported once as
+//! a `new-devices` event, on the alert bus, and as an OS notification.
+//! Addresses two machines answered the sweep for are reported once as an
+//! `ip-conflict` finding on the alert bus.
+
+use crate::alerts::{self, AlertEvent, EventKind};
+use crate::database;
+use crate::scanner::conflict::{self, IpConflict};
+use crate::scanner::{arp, fingerprint, Device, DiscoverySource, ProgressSink, ScanOptions, ScanProgress};
+use std::collections::HashSet;
+use std::process::Stdio;
+use tauri::Emitter;
+use tokio::process::Command;
+
+/// Title of the OS notification
+const NOTIFICATION_TITLE: &str = "IoT Doctor: 新しい機器";
+
+/// Sweep progress is not shown outside a scan
+struct Silent;
+
+impl ProgressSink for Silent {
+    fn report(&self, _update: &ScanProgress) {}
+}
+
+/// Remembers what was reported, so a device is announced once until the next
+/// full scan records it
+#[derive(Default)]
+pub struct NewcomerWatcher {
+    reported: HashSet<String>,
+    conflicts: HashSet<String>,
+}
+
+/// What a monitoring sweep found
+#[derive(Default)]
+pub struct Sweep {
+    /// Devices with a MAC the database has never seen
+    pub joined: Vec<Device>,
+    /// Address conflicts not reported before
+    pub conflicts: Vec<IpConflict>,
+}
+
+impl NewcomerWatcher {
+    /// Sweep the network for devices the database has never seen and for address conflicts
+    pub async fn check(&mut self, options: &ScanOptions) -> Sweep {
+        let latest = database::get_latest_scan_devices().unwrap_or_default();
+        let known_ips: Vec<std::net::Ipv4Addr> = latest.iter().filter_map(|d| d.ip.parse().ok()).collect();
+        let discovery = match arp::discover_devices(&options.targets, &options.sweep, &known_ips, &Silent).await {
+            Ok(discovery) => discovery,
+            Err(e) => {
+                eprintln!("Monitoring sweep failed: {}", e);
+                return Sweep::default();
+            }
+        };
+
+        let conflicts: Vec<IpConflict> = conflict::find_conflicts(&discovery.observed, &[])
+            .into_iter()
+            .filter(|c| self.conflicts.insert(format!("{}|{}", c.ip, c.macs.join(","))))
+            .collect();
+
+        let metadata = database::metadata::all().unwrap_or_default();
+        let mut newcomers = Vec::new();
+        for (ip, mac) in discovery.hosts {
+            let mac = mac.to_lowercase();
+            let known = latest.iter().any(|d| d.mac.eq_ignore_ascii_case(&mac))
+                || metadata.contains_key(&mac)
+                || database::presence::get_profile(&mac).ok().flatten().is_some();
+            if known || !self.reported.insert(mac.clone()) {
+                continue;
+            }
+            let vendor = fingerprint::lookup_vendor(&mac);
+            newcomers.push(Device {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: vendor.as_ref().map(|v| format!("{} デバイス", v)),
+                ip,
+                mac_randomized: fingerprint::is_randomized_mac(&mac),
+                mac,
+                vendor,
+                last_seen: chrono::Utc::now(),
+                discovery_sources: vec![DiscoverySource::Arp],
+                ..Device::default()
+            });
+        }
+        Sweep { joined: newcomers, conflicts }
+    }
+}
+
+/// Put conflicts on the alert bus as findings of the latest scan's device at the address
+pub fn report_conflicts(conflicts: &[IpConflict]) {
+    if conflicts.is_empty() {
+        return;
+    }
+    let latest = database::get_latest_scan_devices().unwrap_or_default();
+    alerts::publish(conflicts.iter().map(|c| {
+        let device = latest.iter().find(|d| d.ip == c.ip).cloned().unwrap_or_else(|| Device {
+            ip: c.ip.clone(),
+            mac: c.macs[0].clone(),
+            ..Device::default()
+        });
+        let issue = conflict::to_issue(c);
+        let message = format!("{}: {}", c.ip, issue.title);
+        AlertEvent::new(EventKind::NewIssue, &device, Some(issue), message)
+    }));
+}
+
+/// Tell the UI, the alert rules and, if enabled, the OS about the newcomers
+pub async fn report(app: &tauri::AppHandle, newcomers: &[Device], notify: bool) {
+    if newcomers.is_empty() {
+        return;
+    }
+    let _ = app.emit("new-devices", newcomers);
+    alerts::publish(newcomers.iter().map(|device| {
+        AlertEvent::new(EventKind::NewDevice, device, None, message(device))
+    }));
+    if notify {
+        let body = newcomers.iter().map(message).collect::<Vec<_>>().join("\n");
+        if let Err(e) = show_notification(NOTIFICATION_TITLE, &body).await {
+            eprintln!("Failed to show notification: {}", e);
+        }
+    }
+}
+
+fn message(device: &Device) -> String {
+    let label = device.name.clone().unwrap_or_else(|| "不明な機器".to_string());
+    let note = if device.mac_randomized { "（ランダム化されたMACアドレス）" } else { "" };
+    format!("{} がネットワークに接続しました: {} / {}{}", label, device.ip, device.mac, note)
+}
+
+/// Show a desktop notification through the OS's own tool, without holding up
+/// the runtime while it runs. Title and body are passed as arguments or
+/// environment, never spliced into a script.
+pub(crate) async fn show_notification(title: &str, body: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-Command", WINDOWS_TOAST])
+            .env("IOT_DOCTOR_TITLE", title)
+            .env("IOT_DOCTOR_BODY", body);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            body,
+        ]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=IoT Doctor", title, body]);
+        command
+    };
+    let status = command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status().await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(std::io::ErrorKind::Other, format!("notifier exited with {}", status)))
+    }
+}
+
+/// Toast through the WinRT API, which PowerShell reaches without extra modules
+const WINDOWS_TOAST: &str = "\
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null; \
+$xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+$text = $xml.GetElementsByTagName('text'); \
+$text.Item(0).AppendChild($xml.CreateTextNode($env:IOT_DOCTOR_TITLE)) | Out-Null; \
+$text.Item(1).AppendChild($xml.CreateTextNode($env:IOT_DOCTOR_BODY)) | Out-Null; \
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('IoT Doctor').Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
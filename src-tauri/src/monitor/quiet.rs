@@ -0,0 +1,116 @@
+//! Quiet hours
+//!
+//! Probing every device on the network during a video call is a good way to
+//! make the app unpopular. During the configured hours, unattended activity
+//! (monitoring rounds, scheduled scans and scans requested through the
+//! control interface) is suppressed, limited to passive discovery, or slowed
+//! down. Scans the user starts from the UI are not affected.
+//!
+//! Every unattended entry point asks [`allowed_level`], and unattended scans
+//! pass their connection limits through [`throttle`], so the policy is
+//! enforced in one place.
+
+use crate::scanner::ports::ScanConfig;
+use crate::scanner::ScanLevel;
+use chrono::{Datelike, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Connection attempts in flight while quiet hours throttle scans
+const THROTTLED_MAX_CONNECTIONS: usize = 16;
+
+/// Connection attempts per second to one device while quiet hours throttle scans
+const THROTTLED_PER_HOST_RATE: u32 = 10;
+
+/// What happens during quiet hours
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuietMode {
+    /// Nothing runs
+    #[default]
+    Suppress,
+    /// Scans run as Level 1 (passive discovery); monitoring probes are skipped
+    PassiveOnly,
+    /// Everything runs, with the port scan of unattended scans slowed down
+    Throttle,
+}
+
+/// Quiet hours settings, in local time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    /// Start of the quiet period, e.g. `09:00:00`
+    pub start: NaiveTime,
+    /// End of the quiet period; before `start` for periods spanning midnight
+    pub end: NaiveTime,
+    /// Only Monday to Friday
+    pub weekdays_only: bool,
+    pub mode: QuietMode,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            end: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            weekdays_only: true,
+            mode: QuietMode::Suppress,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether `now` falls into the quiet period
+    pub fn is_quiet(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        // A period spanning midnight belongs to the day it started
+        let time = now.time().with_nanosecond(0).unwrap_or(now.time());
+        let spans_midnight = self.end < self.start;
+        let started_yesterday = spans_midnight && time < self.end;
+        let day = if started_yesterday {
+            now.date_naive().pred_opt().unwrap_or(now.date_naive())
+        } else {
+            now.date_naive()
+        };
+        if self.weekdays_only && day.weekday().number_from_monday() > 5 {
+            return false;
+        }
+
+        if spans_midnight {
+            time >= self.start || time < self.end
+        } else {
+            time >= self.start && time < self.end
+        }
+    }
+}
+
+/// Highest scan level unattended activity may use right now, or `None` when
+/// it should not run at all
+pub fn allowed_level(requested: ScanLevel) -> Option<ScanLevel> {
+    let quiet = crate::settings::load().unwrap_or_default().quiet_hours;
+    if !quiet.is_quiet(chrono::Local::now()) {
+        return Some(requested);
+    }
+    match quiet.mode {
+        QuietMode::Suppress => None,
+        QuietMode::PassiveOnly => Some(requested.min(ScanLevel::Level1)),
+        QuietMode::Throttle => Some(requested),
+    }
+}
+
+/// Lower the connection limits of an unattended scan while quiet hours
+/// throttle scans
+pub fn throttle(config: &mut ScanConfig) {
+    let quiet = crate::settings::load().unwrap_or_default().quiet_hours;
+    if quiet.mode != QuietMode::Throttle || !quiet.is_quiet(chrono::Local::now()) {
+        return;
+    }
+    config.max_connections = config.max_connections.min(THROTTLED_MAX_CONNECTIONS);
+    config.per_host_rate = match config.per_host_rate {
+        0 => THROTTLED_PER_HOST_RATE,
+        rate => rate.min(THROTTLED_PER_HOST_RATE),
+    };
+}
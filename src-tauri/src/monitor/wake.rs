@@ -0,0 +1,106 @@
+//! Refresh after sleep and network changes
+//!
+//! A laptop that was closed at home and opened at the office still shows the
+//! home network's devices. The network the machine is on (interface, address,
+//! gateway, Wi-Fi SSID) is polled, and resuming from sleep is noticed by the
+//! wall clock jumping ahead of the monotonic clock, which stops while the
+//! machine sleeps. Either triggers a passive (Level 1) scan once the network
+//! has settled.
+
+use super::quiet;
+use crate::health::{self, Subsystem};
+use crate::scanner::{self, arp, wifi, ScanLevel};
+use serde::Serialize;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::async_runtime::JoinHandle;
+use tauri::Emitter;
+
+/// How often the network state is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Longest expected time between heartbeats, including a refresh scan
+pub const HEARTBEAT_PERIOD: Duration = Duration::from_secs(120);
+
+/// Wall clock running ahead of the monotonic clock by more than this means the machine slept
+const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+/// Time for DHCP and Wi-Fi to come up before scanning
+const SETTLE_DELAY: Duration = Duration::from_secs(10);
+
+/// Why a refresh was started
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshReason {
+    Resumed,
+    InterfaceUp,
+    NetworkChanged,
+}
+
+/// The network this machine is on
+#[derive(Debug, Clone, Default, PartialEq)]
+struct NetworkState {
+    interface: Option<String>,
+    address: Option<String>,
+    gateway: Option<Ipv4Addr>,
+    ssid: Option<String>,
+}
+
+impl NetworkState {
+    async fn current() -> Self {
+        let local = arp::get_local_interface();
+        Self {
+            interface: local.as_ref().map(|(name, ..)| name.clone()),
+            address: local.map(|(_, ip, _)| ip),
+            gateway: arp::get_default_gateway(),
+            ssid: wifi::connected_ssid().await,
+        }
+    }
+}
+
+/// Watch for sleep and network changes in the background
+pub fn start(app: tauri::AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut state = NetworkState::current().await;
+        let mut wall = SystemTime::now();
+        let mut monotonic = Instant::now();
+        loop {
+            health::beat(Subsystem::NetworkWatch);
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let slept = wall.elapsed().unwrap_or_default().saturating_sub(monotonic.elapsed());
+            let current = NetworkState::current().await;
+            let reason = if slept > SLEEP_GAP {
+                Some(RefreshReason::Resumed)
+            } else if current == state {
+                None
+            } else if state.interface.is_none() {
+                Some(RefreshReason::InterfaceUp)
+            } else {
+                Some(RefreshReason::NetworkChanged)
+            };
+            state = current;
+
+            if let Some(reason) = reason {
+                tokio::time::sleep(SETTLE_DELAY).await;
+                state = NetworkState::current().await;
+                // Still offline; coming back up is a change of its own
+                if state.interface.is_some() {
+                    refresh(&app, reason).await;
+                }
+            }
+            wall = SystemTime::now();
+            monotonic = Instant::now();
+        }
+    })
+}
+
+async fn refresh(app: &tauri::AppHandle, reason: RefreshReason) {
+    if scanner::is_scan_running() || quiet::allowed_level(ScanLevel::Level1).is_none() {
+        return;
+    }
+    let _ = app.emit("auto-refresh", reason);
+    if let Err(e) = crate::start_scan(app.clone(), ScanLevel::Level1, None, None, None).await {
+        eprintln!("Automatic refresh failed: {}", e);
+    }
+}
@@ -0,0 +1,139 @@
+//! Probe side: accept paired apps and run scans for them
+
+use super::channel::{generate_key, SecureChannel, KEY_LEN};
+use super::{pairing_code, ProbeError, ProbeMessage, PROTOCOL_VERSION};
+use crate::database;
+use crate::scanner::{self, ProgressSink, ScanLevel, ScanOptions, ScanProgress};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+fn key_path() -> PathBuf {
+    database::app_data_dir().join("probe.key")
+}
+
+/// Load the pairing key, creating one on first start
+pub fn load_or_create_key() -> Result<[u8; KEY_LEN], ProbeError> {
+    let path = key_path();
+    if let Ok(code) = std::fs::read_to_string(&path) {
+        return super::parse_key(&code);
+    }
+
+    let key = generate_key()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Owner-only from the moment it exists, never readable in between
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = match options.open(&path) {
+        Ok(file) => file,
+        // Another process created it first
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return super::parse_key(&std::fs::read_to_string(&path)?);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    file.write_all(pairing_code(&key).as_bytes())?;
+    Ok(key)
+}
+
+/// Discard the current key so previously paired apps can no longer connect
+pub fn reset_key() -> Result<[u8; KEY_LEN], ProbeError> {
+    match std::fs::remove_file(key_path()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    load_or_create_key()
+}
+
+/// Forwards scan progress to the connection task
+struct ChannelSink(mpsc::UnboundedSender<ScanProgress>);
+
+impl ProgressSink for ChannelSink {
+    fn report(&self, update: &ScanProgress) {
+        let _ = self.0.send(update.clone());
+    }
+}
+
+/// Serve paired apps until the listener fails
+pub async fn run(listen: SocketAddr, key: [u8; KEY_LEN]) -> Result<(), ProbeError> {
+    let listener = TcpListener::bind(listen).await?;
+    println!("Listening on {}", listen);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = serve(stream, &key).await {
+                eprintln!("Connection from {} ended: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn serve(stream: TcpStream, key: &[u8; KEY_LEN]) -> Result<(), ProbeError> {
+    let mut channel = SecureChannel::accept(stream, key).await?;
+
+    match channel.recv().await? {
+        ProbeMessage::Hello { .. } => {}
+        _ => return Err(ProbeError::Protocol("Expected hello".to_string())),
+    }
+    channel
+        .send(&ProbeMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: dns_lookup::get_hostname().ok(),
+        })
+        .await?;
+
+    loop {
+        let level = match channel.recv().await {
+            Ok(ProbeMessage::Scan { level }) => level,
+            Ok(_) => return Err(ProbeError::Protocol("Unexpected message".to_string())),
+            // The app closes the connection once it has what it needs
+            Err(ProbeError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let reply = match run_scan(&mut channel, level).await? {
+            Ok(devices) => ProbeMessage::Result { devices },
+            Err(message) => ProbeMessage::Error { message },
+        };
+        channel.send(&reply).await?;
+    }
+}
+
+/// Run a scan, streaming progress; the outer error is a broken connection,
+/// the inner one a failed scan to report to the app
+async fn run_scan(
+    channel: &mut SecureChannel<TcpStream>,
+    level: ScanLevel,
+) -> Result<Result<Vec<scanner::Device>, String>, ProbeError> {
+    if scanner::is_scan_running() {
+        return Ok(Err("A scan is already running on this probe".to_string()));
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let scan = async move {
+        let sink = ChannelSink(tx);
+        // The app applies its own scoring policy and known devices
+        scanner::scan_network(&sink, level, &ScanOptions::default()).await
+    };
+    let forward = async {
+        while let Some(progress) = rx.recv().await {
+            channel.send(&ProbeMessage::Progress { progress }).await?;
+        }
+        Ok::<(), ProbeError>(())
+    };
+
+    let (result, forwarded) = tokio::join!(scan, forward);
+    forwarded?;
+    Ok(result.map_err(|e| e.to_string()))
+}
@@ -0,0 +1,171 @@
+//! Encrypted, authenticated message channel between the app and a probe
+//!
+//! Both sides hold the same 32-byte pairing key. The handshake exchanges a
+//! magic string and a random nonce in the clear; per-direction
+//! ChaCha20-Poly1305 keys are then derived with HKDF-SHA256 from the pairing
+//! key, salted with both nonces. A peer without the pairing key cannot
+//! produce a frame that decrypts, so the first message doubles as
+//! authentication.
+//!
+//! Frames are a big-endian `u32` length followed by the sealed JSON message.
+//! Until a frame from the peer has opened, frames are capped at a few KiB,
+//! so a stranger on the LAN cannot make the agent allocate large buffers.
+
+use super::ProbeError;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Length of the pairing key
+pub const KEY_LEN: usize = 32;
+
+/// Identifies the protocol before any key material is used
+const MAGIC: &[u8; 8] = b"IOTDPRB1";
+
+const HANDSHAKE_NONCE_LEN: usize = 32;
+
+/// Scan results of a large network fit comfortably below this
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Largest frame accepted before the peer proved it holds the key; the
+/// hello message is far smaller
+const MAX_UNAUTHENTICATED_FRAME_LEN: usize = 4 * 1024;
+
+/// Generate a new random pairing key
+pub fn generate_key() -> Result<[u8; KEY_LEN], ProbeError> {
+    let mut key = [0u8; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| ProbeError::Protocol("No secure random source".to_string()))?;
+    Ok(key)
+}
+
+/// Sealing state for one direction
+struct Direction {
+    key: LessSafeKey,
+    seq: u64,
+}
+
+impl Direction {
+    fn derive(prk: &hkdf::Prk, label: &[u8]) -> Result<Self, ProbeError> {
+        let info = [label];
+        let okm = prk
+            .expand(&info, &CHACHA20_POLY1305)
+            .map_err(|_| ProbeError::Protocol("Key derivation failed".to_string()))?;
+        Ok(Self {
+            key: LessSafeKey::new(UnboundKey::from(okm)),
+            seq: 0,
+        })
+    }
+
+    /// Nonces are a message counter, never reused within a session
+    fn next_nonce(&mut self) -> Nonce {
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        nonce[4..].copy_from_slice(&self.seq.to_be_bytes());
+        self.seq += 1;
+        Nonce::assume_unique_for_key(nonce)
+    }
+}
+
+/// An established channel
+pub struct SecureChannel<S> {
+    stream: S,
+    send: Direction,
+    recv: Direction,
+    /// A frame from the peer opened with the shared key
+    authenticated: bool,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SecureChannel<S> {
+    /// Handshake as the connecting side (the desktop app)
+    pub async fn connect(stream: S, key: &[u8; KEY_LEN]) -> Result<Self, ProbeError> {
+        Self::handshake(stream, key, true).await
+    }
+
+    /// Handshake as the listening side (the probe agent)
+    pub async fn accept(stream: S, key: &[u8; KEY_LEN]) -> Result<Self, ProbeError> {
+        Self::handshake(stream, key, false).await
+    }
+
+    async fn handshake(mut stream: S, key: &[u8; KEY_LEN], is_client: bool) -> Result<Self, ProbeError> {
+        let mut own_nonce = [0u8; HANDSHAKE_NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut own_nonce)
+            .map_err(|_| ProbeError::Protocol("No secure random source".to_string()))?;
+
+        stream.write_all(MAGIC).await?;
+        stream.write_all(&own_nonce).await?;
+
+        let mut magic = [0u8; 8];
+        stream.read_exact(&mut magic).await?;
+        if &magic != MAGIC {
+            return Err(ProbeError::Protocol("Not an IoT Doctor probe".to_string()));
+        }
+        let mut peer_nonce = [0u8; HANDSHAKE_NONCE_LEN];
+        stream.read_exact(&mut peer_nonce).await?;
+
+        let (client_nonce, server_nonce) = if is_client {
+            (own_nonce, peer_nonce)
+        } else {
+            (peer_nonce, own_nonce)
+        };
+        let salt = [client_nonce, server_nonce].concat();
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &salt).extract(key);
+
+        let to_server = Direction::derive(&prk, b"client to probe")?;
+        let to_client = Direction::derive(&prk, b"probe to client")?;
+        let (send, recv) = if is_client {
+            (to_server, to_client)
+        } else {
+            (to_client, to_server)
+        };
+
+        Ok(Self {
+            stream,
+            send,
+            recv,
+            authenticated: false,
+        })
+    }
+
+    /// Seal and send one message
+    pub async fn send<T: Serialize>(&mut self, message: &T) -> Result<(), ProbeError> {
+        let mut data = serde_json::to_vec(message)?;
+        let nonce = self.send.next_nonce();
+        self.send
+            .key
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut data)
+            .map_err(|_| ProbeError::Protocol("Encryption failed".to_string()))?;
+
+        self.stream.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        self.stream.write_all(&data).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    /// Receive and open one message
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<T, ProbeError> {
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len).await?;
+        let len = u32::from_be_bytes(len) as usize;
+        let max_len = if self.authenticated { MAX_FRAME_LEN } else { MAX_UNAUTHENTICATED_FRAME_LEN };
+        if len > max_len {
+            return Err(ProbeError::Protocol(format!("Frame too large: {} bytes", len)));
+        }
+
+        let mut data = vec![0u8; len];
+        self.stream.read_exact(&mut data).await?;
+        let nonce = self.recv.next_nonce();
+        let plain = self
+            .recv
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| ProbeError::AuthenticationFailed)?;
+        self.authenticated = true;
+
+        Ok(serde_json::from_slice(plain)?)
+    }
+}
@@ -0,0 +1,197 @@
+//! Remote probe agents
+//!
+//! A probe is the headless `iot-doctor-probe` binary running on a small
+//! machine (e.g. a Raspberry Pi) inside another network. It performs the
+//! same discovery as a local scan and streams progress and results back to
+//! the desktop app, so a consultant can assess a customer's network without
+//! travelling.
+//!
+//! On first start the probe prints a pairing code. Entering it together with
+//! the probe's address pairs the app with that probe; the key is kept in the
+//! OS keychain and every connection is encrypted and authenticated with it
+//! (see [`channel`]).
+
+use crate::scanner::{Device, ProgressSink, ScanLevel, ScanProgress};
+use crate::secrets::{self, SecretsError};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod agent;
+pub mod channel;
+
+use channel::{SecureChannel, KEY_LEN};
+
+/// Port the probe listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 7734;
+
+/// Bumped on incompatible message changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Prefix of pairing codes, so a pasted code is recognisable
+const PAIRING_PREFIX: &str = "iotdoc-probe:";
+
+/// Probe errors
+#[derive(Error, Debug)]
+pub enum ProbeError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Probe authentication failed")]
+    AuthenticationFailed,
+
+    #[error("Invalid pairing code")]
+    InvalidPairingCode,
+
+    #[error("Unknown probe: {0}")]
+    NotFound(String),
+
+    #[error("Probe protocol error: {0}")]
+    Protocol(String),
+
+    #[error("Probe error: {0}")]
+    Remote(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Secrets(#[from] SecretsError),
+}
+
+/// Message exchanged over the channel
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProbeMessage {
+    /// Sent by both sides right after the handshake
+    Hello {
+        version: u32,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    Scan {
+        level: ScanLevel,
+    },
+    Progress {
+        progress: ScanProgress,
+    },
+    Result {
+        devices: Vec<Device>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A paired probe (the key lives in the keychain)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteProbe {
+    pub id: String,
+    pub name: String,
+    /// "host" or "host:port"
+    pub address: String,
+    /// Host name the probe reported when paired
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+/// Pairing code shown by the probe
+pub fn pairing_code(key: &[u8; KEY_LEN]) -> String {
+    format!(
+        "{}{}",
+        PAIRING_PREFIX,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key)
+    )
+}
+
+fn parse_key(encoded: &str) -> Result<[u8; KEY_LEN], ProbeError> {
+    let encoded = encoded.trim();
+    let encoded = encoded.strip_prefix(PAIRING_PREFIX).unwrap_or(encoded);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ProbeError::InvalidPairingCode)
+}
+
+/// Add the default port unless the address already has one
+fn socket_address(address: &str) -> String {
+    let address = address.trim();
+    if address.parse::<std::net::SocketAddr>().is_ok() {
+        return address.to_string();
+    }
+    if let Ok(ip) = address.trim_matches(|c| c == '[' || c == ']').parse::<std::net::IpAddr>() {
+        return std::net::SocketAddr::new(ip, DEFAULT_PORT).to_string();
+    }
+    if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+/// Connect, authenticate and exchange hellos; returns the probe's name
+async fn open(
+    address: &str,
+    key: &[u8; KEY_LEN],
+) -> Result<(SecureChannel<tokio::net::TcpStream>, Option<String>), ProbeError> {
+    let stream = tokio::net::TcpStream::connect(socket_address(address)).await?;
+    let mut channel = SecureChannel::connect(stream, key).await?;
+
+    channel
+        .send(&ProbeMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: None,
+        })
+        .await?;
+    match channel.recv().await? {
+        ProbeMessage::Hello { version, name } if version == PROTOCOL_VERSION => Ok((channel, name)),
+        ProbeMessage::Hello { version, .. } => Err(ProbeError::Protocol(format!(
+            "Probe speaks protocol version {}, expected {}",
+            version, PROTOCOL_VERSION
+        ))),
+        ProbeMessage::Error { message } => Err(ProbeError::Remote(message)),
+        _ => Err(ProbeError::Protocol("Unexpected message".to_string())),
+    }
+}
+
+/// Verify the pairing code against the probe and store its key
+pub async fn pair(name: String, address: String, code: &str) -> Result<RemoteProbe, ProbeError> {
+    let key = parse_key(code)?;
+    let (_, hostname) = open(&address, &key).await?;
+
+    let probe = RemoteProbe {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        address,
+        hostname,
+    };
+    secrets::set_probe_key(&probe.id, code.trim())?;
+    Ok(probe)
+}
+
+/// Forget a probe's key
+pub fn unpair(probe: &RemoteProbe) -> Result<(), ProbeError> {
+    Ok(secrets::delete_probe_key(&probe.id)?)
+}
+
+/// Run a scan on the probe, forwarding its progress to `progress`
+pub async fn scan(
+    probe: &RemoteProbe,
+    level: ScanLevel,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<Device>, ProbeError> {
+    let code = secrets::get_probe_key(&probe.id)?.ok_or_else(|| ProbeError::NotFound(probe.name.clone()))?;
+    let key = parse_key(&code)?;
+    let (mut channel, _) = open(&probe.address, &key).await?;
+
+    channel.send(&ProbeMessage::Scan { level }).await?;
+    loop {
+        match channel.recv().await? {
+            ProbeMessage::Progress { progress: update } => progress.report(&update),
+            ProbeMessage::Result { devices } => return Ok(devices),
+            ProbeMessage::Error { message } => return Err(ProbeError::Remote(message)),
+            _ => return Err(ProbeError::Protocol("Unexpected message".to_string())),
+        }
+    }
+}
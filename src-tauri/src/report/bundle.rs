@@ -0,0 +1,83 @@
+//! Assessment bundle
+//!
+//! Everything about one scan in a single ZIP file: the reports, the device
+//! inventory, the raw scan data, what the app changed on the network and how
+//! it was run. This is what a consultant hands to the client at the end of an
+//! assessment.
+
+use super::{inventory, ReportError, ReportFormat, ReportOptions};
+use crate::database::{self, audit::AuditEntry, ScanRecord};
+use crate::scanner::datapack::{self, DataPackInfo};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+
+/// Audit entries recorded this long before the scan are included as well
+const AUDIT_LOOKBACK_DAYS: i64 = 30;
+
+/// Most audit entries searched for the excerpt
+const AUDIT_LIMIT: usize = 1000;
+
+/// How the scan and the bundle were produced, for support
+#[derive(Debug, Clone, Serialize)]
+struct Diagnostics {
+    generated_at: chrono::DateTime<chrono::Utc>,
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    scan: ScanRecord,
+    datapack: Option<DataPackInfo>,
+}
+
+/// Write the bundle for `scan_id` to `path`; returns the names of the files in it
+pub fn export(scan_id: &str, options: &ReportOptions, path: &Path) -> Result<Vec<String>, ReportError> {
+    let scan = database::get_scan(scan_id)?.ok_or_else(|| ReportError::ScanNotFound(scan_id.to_string()))?;
+    let devices = database::get_scan_devices(scan_id)?;
+    let metadata = database::metadata::all()?;
+
+    let since = scan.timestamp - chrono::Duration::days(AUDIT_LOOKBACK_DAYS);
+    let audit: Vec<AuditEntry> = database::audit::list(AUDIT_LIMIT)?
+        .into_iter()
+        .filter(|entry| entry.timestamp >= since)
+        .collect();
+
+    let diagnostics = Diagnostics {
+        generated_at: chrono::Utc::now(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        scan,
+        datapack: datapack::info(),
+    };
+
+    let files = [
+        ("report.html", super::generate(&devices, ReportFormat::Html, options)?),
+        ("report.txt", super::generate(&devices, ReportFormat::Text, options)?),
+        ("scan.json", super::generate(&devices, ReportFormat::Json, options)?),
+        (
+            "inventory.csv",
+            inventory::generate(&devices, &metadata, inventory::InventoryFormat::Csv)?,
+        ),
+        ("audit-log.json", to_json(&audit)?),
+        ("diagnostics.json", to_json(&diagnostics)?),
+    ];
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let file_options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in &files {
+        zip.start_file(*name, file_options).map_err(archive_error)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish().map_err(archive_error)?;
+
+    Ok(files.iter().map(|(name, _)| name.to_string()).collect())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, ReportError> {
+    serde_json::to_string_pretty(value).map_err(|e| ReportError::GenerationFailed(e.to_string()))
+}
+
+fn archive_error(e: zip::result::ZipError) -> ReportError {
+    ReportError::GenerationFailed(e.to_string())
+}
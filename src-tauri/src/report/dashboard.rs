@@ -0,0 +1,148 @@
+//! Home screen data
+//!
+//! Everything the dashboard shows, computed from the database in one call
+//! instead of one IPC round trip per widget.
+
+use super::digest::{DeviceChange, ScorePoint};
+use crate::database::issues::IssueStateCounts;
+use crate::database::{self, DbError, ScanRecord};
+use crate::scanner::{datapack, Device, IssueSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Days of scores in the trend
+const TREND_DAYS: i64 = 30;
+
+/// Days a device counts as new
+const NEW_DEVICE_DAYS: i64 = 7;
+
+/// Issues listed on the dashboard
+const TOP_ISSUES: usize = 5;
+
+/// An issue of the latest scan and how many devices have it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopIssue {
+    pub issue_id: String,
+    pub title: String,
+    pub severity: IssueSeverity,
+    pub device_count: usize,
+}
+
+/// How current the data behind the checks is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataAge {
+    /// The built-in checks ship with the app
+    pub app_version: String,
+    pub datapack_version: Option<u32>,
+    pub datapack_generated_at: Option<String>,
+    /// Most recent successful online lookup (NVD, EPSS, OUI)
+    pub last_online_lookup: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Everything the home screen shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardData {
+    pub latest_scan: Option<ScanRecord>,
+    /// Average score per scan, oldest first
+    pub score_trend: Vec<ScorePoint>,
+    /// Most severe issues of the latest scan
+    pub top_issues: Vec<TopIssue>,
+    /// Devices of the latest scan not seen before this week
+    pub new_devices: Vec<DeviceChange>,
+    /// Findings per lifecycle state after the latest scan
+    pub issue_states: IssueStateCounts,
+    pub data_age: DataAge,
+}
+
+/// Collect the dashboard data
+pub fn build() -> Result<DashboardData, DbError> {
+    let now = chrono::Utc::now();
+    let history = database::get_scan_history()?;
+    let latest_scan = history.first().cloned();
+    let latest_devices = match &latest_scan {
+        Some(scan) => database::get_scan_devices(&scan.id)?,
+        None => Vec::new(),
+    };
+
+    let score_trend = database::get_scans_between(now - chrono::Duration::days(TREND_DAYS), now)?
+        .into_iter()
+        .map(|s| ScorePoint {
+            scan_id: s.id,
+            timestamp: s.timestamp,
+            average_score: s.average_score,
+        })
+        .collect();
+
+    // Compared with the last scan before the week, or the week's first scan
+    let week_start = now - chrono::Duration::days(NEW_DEVICE_DAYS);
+    let baseline = history
+        .iter()
+        .find(|s| s.timestamp < week_start)
+        .or_else(|| history.last())
+        .filter(|s| latest_scan.as_ref().is_some_and(|latest| latest.id != s.id));
+    let new_devices = match baseline {
+        Some(scan) => {
+            let known: HashSet<String> = database::get_scan_devices(&scan.id)?
+                .into_iter()
+                .map(|d| d.mac.to_lowercase())
+                .collect();
+            latest_devices
+                .iter()
+                .filter(|d| !known.contains(&d.mac.to_lowercase()))
+                .map(|d| DeviceChange {
+                    mac: d.mac.clone(),
+                    ip: d.ip.clone(),
+                    name: d.name.clone(),
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let issue_states = match &latest_scan {
+        Some(scan) => database::issues::counts_for_scan(&scan.id)?,
+        None => IssueStateCounts::default(),
+    };
+
+    let datapack = datapack::info();
+    Ok(DashboardData {
+        latest_scan,
+        score_trend,
+        top_issues: top_issues(&latest_devices),
+        new_devices,
+        issue_states,
+        data_age: DataAge {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            datapack_version: datapack.as_ref().map(|d| d.version),
+            datapack_generated_at: datapack.and_then(|d| d.generated_at),
+            last_online_lookup: database::last_cached_response()?,
+        },
+    })
+}
+
+/// Issues by severity, then by how many devices have them
+fn top_issues(devices: &[Device]) -> Vec<TopIssue> {
+    let mut issues: HashMap<&str, TopIssue> = HashMap::new();
+    for device in devices {
+        for issue in &device.issues {
+            let entry = issues.entry(&issue.id).or_insert_with(|| TopIssue {
+                issue_id: issue.id.clone(),
+                title: issue.title.clone(),
+                severity: issue.severity,
+                device_count: 0,
+            });
+            entry.severity = entry.severity.max(issue.severity);
+            entry.device_count += 1;
+        }
+    }
+
+    let mut issues: Vec<TopIssue> = issues.into_values().collect();
+    issues.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then(b.device_count.cmp(&a.device_count))
+            .then(a.issue_id.cmp(&b.issue_id))
+    });
+    issues.truncate(TOP_ISSUES);
+    issues
+}
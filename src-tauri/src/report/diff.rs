@@ -0,0 +1,195 @@
+//! Structured comparison of two scans
+//!
+//! Devices are matched by MAC address so the frontend's diff view does not
+//! have to reimplement the matching logic.
+
+use super::ReportError;
+use crate::database;
+use crate::database::issues::IssueStateCounts;
+use crate::scanner::{Device, IssueSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A single field that differs between the two scans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+/// How an issue changed between the two scans
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueTransitionKind {
+    Opened,
+    Closed,
+    SeverityChanged,
+}
+
+/// Issue state change on a matched device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueTransition {
+    pub issue_id: String,
+    pub title: String,
+    pub kind: IssueTransitionKind,
+    pub severity_before: Option<IssueSeverity>,
+    pub severity_after: Option<IssueSeverity>,
+}
+
+/// Differences for a device present in both scans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceDiff {
+    pub mac: String,
+    pub name: Option<String>,
+    pub field_changes: Vec<FieldChange>,
+    pub issue_transitions: Vec<IssueTransition>,
+}
+
+/// Full diff between scan A (before) and scan B (after)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanDiff {
+    pub scan_a: String,
+    pub scan_b: String,
+    pub added: Vec<Device>,
+    pub removed: Vec<Device>,
+    pub changed: Vec<DeviceDiff>,
+    pub unchanged_count: usize,
+    /// Findings per lifecycle state as scan B left them
+    #[serde(default)]
+    pub issue_states: IssueStateCounts,
+}
+
+/// Load two scans from the database and diff them.
+/// Scans of different networks are refused; their devices have nothing in common.
+pub fn diff_scans(scan_a: &str, scan_b: &str) -> Result<ScanDiff, ReportError> {
+    let network = |id| database::get_scan(id).map(|r| r.and_then(|r| r.network_id));
+    if let (Some(a), Some(b)) = (network(scan_a)?, network(scan_b)?) {
+        if a != b {
+            return Err(ReportError::DifferentNetworks);
+        }
+    }
+    let before = database::get_scan_devices(scan_a)?;
+    let after = database::get_scan_devices(scan_b)?;
+    Ok(ScanDiff {
+        issue_states: database::issues::counts_for_scan(scan_b)?,
+        ..diff(scan_a, scan_b, &before, &after)
+    })
+}
+
+/// Diff two device lists, matching devices by MAC address
+pub fn diff(scan_a: &str, scan_b: &str, before: &[Device], after: &[Device]) -> ScanDiff {
+    let before_map: HashMap<&str, &Device> = before.iter().map(|d| (d.mac.as_str(), d)).collect();
+    let after_map: HashMap<&str, &Device> = after.iter().map(|d| (d.mac.as_str(), d)).collect();
+
+    let added = after
+        .iter()
+        .filter(|d| !before_map.contains_key(d.mac.as_str()))
+        .cloned()
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|d| !after_map.contains_key(d.mac.as_str()))
+        .cloned()
+        .collect();
+
+    let mut changed = Vec::new();
+    let mut unchanged_count = 0;
+
+    for new in after {
+        let Some(old) = before_map.get(new.mac.as_str()) else {
+            continue;
+        };
+
+        let device_diff = diff_device(old, new);
+        if device_diff.field_changes.is_empty() && device_diff.issue_transitions.is_empty() {
+            unchanged_count += 1;
+        } else {
+            changed.push(device_diff);
+        }
+    }
+
+    ScanDiff {
+        scan_a: scan_a.to_string(),
+        scan_b: scan_b.to_string(),
+        added,
+        removed,
+        changed,
+        unchanged_count,
+        issue_states: IssueStateCounts::default(),
+    }
+}
+
+fn diff_device(old: &Device, new: &Device) -> DeviceDiff {
+    let mut field_changes = Vec::new();
+
+    let mut compare = |field: &str, before: serde_json::Value, after: serde_json::Value| {
+        if before != after {
+            field_changes.push(FieldChange {
+                field: field.to_string(),
+                before,
+                after,
+            });
+        }
+    };
+
+    let ports = |d: &Device| {
+        let mut numbers: Vec<u16> = d.open_ports.iter().map(|p| p.number).collect();
+        numbers.sort_unstable();
+        numbers
+    };
+
+    compare("name", serde_json::json!(old.name), serde_json::json!(new.name));
+    compare("ip", serde_json::json!(old.ip), serde_json::json!(new.ip));
+    compare("hostname", serde_json::json!(old.hostname), serde_json::json!(new.hostname));
+    compare("vendor", serde_json::json!(old.vendor), serde_json::json!(new.vendor));
+    compare("os_guess", serde_json::json!(old.os_guess), serde_json::json!(new.os_guess));
+    compare("device_type", serde_json::json!(old.device_type), serde_json::json!(new.device_type));
+    compare("security_level", serde_json::json!(old.security_level), serde_json::json!(new.security_level));
+    compare("security_score", serde_json::json!(old.security_score), serde_json::json!(new.security_score));
+    compare("open_ports", serde_json::json!(ports(old)), serde_json::json!(ports(new)));
+
+    let old_issues: HashMap<&str, _> = old.issues.iter().map(|i| (i.id.as_str(), i)).collect();
+    let new_issues: HashMap<&str, _> = new.issues.iter().map(|i| (i.id.as_str(), i)).collect();
+
+    let mut issue_transitions = Vec::new();
+    for issue in &new.issues {
+        match old_issues.get(issue.id.as_str()) {
+            None => issue_transitions.push(IssueTransition {
+                issue_id: issue.id.clone(),
+                title: issue.title.clone(),
+                kind: IssueTransitionKind::Opened,
+                severity_before: None,
+                severity_after: Some(issue.severity),
+            }),
+            Some(previous) if previous.severity != issue.severity => {
+                issue_transitions.push(IssueTransition {
+                    issue_id: issue.id.clone(),
+                    title: issue.title.clone(),
+                    kind: IssueTransitionKind::SeverityChanged,
+                    severity_before: Some(previous.severity),
+                    severity_after: Some(issue.severity),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for issue in &old.issues {
+        if !new_issues.contains_key(issue.id.as_str()) {
+            issue_transitions.push(IssueTransition {
+                issue_id: issue.id.clone(),
+                title: issue.title.clone(),
+                kind: IssueTransitionKind::Closed,
+                severity_before: Some(issue.severity),
+                severity_after: None,
+            });
+        }
+    }
+
+    DeviceDiff {
+        mac: new.mac.clone(),
+        name: new.name.clone().or_else(|| old.name.clone()),
+        field_changes,
+        issue_transitions,
+    }
+}
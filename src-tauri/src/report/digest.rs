@@ -0,0 +1,398 @@
+//! Periodic digest reports
+//!
+//! Summarizes every scan recorded in a period into a single report:
+//! score trajectory, devices added/removed and issues opened/closed.
+//! Digests are generated on demand, and when enabled the scheduler writes
+//! one as HTML to the `digests` folder of the data directory every period
+//! and announces it (see [`deliver_due`]).
+
+use super::{escape_html, ReportError, ReportFormat};
+use crate::database::{self, ScanRecord};
+use crate::scanner::{Device, IssueSeverity};
+use crate::settings::locale::{Language, LocaleSettings};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Default digest period in days
+pub const DEFAULT_PERIOD_DAYS: i64 = 7;
+
+/// Folder of the data directory delivered digests are written to
+const DIGEST_DIR: &str = "digests";
+
+/// Setting holding when the last delivered digest's period ended
+const LAST_DELIVERED_KEY: &str = "digest_last_delivered";
+
+/// Automatic digest delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    /// Write a digest at the end of every period
+    pub enabled: bool,
+    /// Days each delivered digest covers
+    pub period_days: u32,
+    /// Show an OS notification when one is written
+    pub notify: bool,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            period_days: DEFAULT_PERIOD_DAYS as u32,
+            notify: true,
+        }
+    }
+}
+
+/// A digest written by scheduled delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveredDigest {
+    pub path: std::path::PathBuf,
+    pub digest: Digest,
+}
+
+/// Average score of a single scan within the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScorePoint {
+    pub scan_id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub average_score: u8,
+}
+
+/// Device that appeared or disappeared during the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceChange {
+    pub mac: String,
+    pub ip: String,
+    pub name: Option<String>,
+}
+
+/// Issue that was opened or closed during the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueChange {
+    pub mac: String,
+    pub device_name: Option<String>,
+    pub issue_id: String,
+    pub title: String,
+    pub severity: IssueSeverity,
+}
+
+/// Digest of all scans in a period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Digest {
+    pub period_start: chrono::DateTime<chrono::Utc>,
+    pub period_end: chrono::DateTime<chrono::Utc>,
+    pub scan_count: usize,
+    pub score_trajectory: Vec<ScorePoint>,
+    pub devices_added: Vec<DeviceChange>,
+    pub devices_removed: Vec<DeviceChange>,
+    pub issues_opened: Vec<IssueChange>,
+    pub issues_closed: Vec<IssueChange>,
+}
+
+/// Build a digest from the scan history stored in the database
+pub fn build(
+    period_start: chrono::DateTime<chrono::Utc>,
+    period_end: chrono::DateTime<chrono::Utc>,
+) -> Result<Digest, ReportError> {
+    let scans = database::get_scans_between(period_start, period_end)?;
+
+    let (first_devices, last_devices) = match (scans.first(), scans.last()) {
+        (Some(first), Some(last)) => (
+            database::get_scan_devices(&first.id)?,
+            database::get_scan_devices(&last.id)?,
+        ),
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    Ok(compare(period_start, period_end, &scans, &first_devices, &last_devices))
+}
+
+/// Write the digest of the period since the last delivery once a full
+/// period has passed. The first call only starts the period.
+pub fn deliver_due(settings: &DigestSettings, locale: &LocaleSettings) -> Result<Option<DeliveredDigest>, ReportError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let now = chrono::Utc::now();
+    let last = database::get_setting(LAST_DELIVERED_KEY)?
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&chrono::Utc));
+    let Some(start) = last else {
+        database::set_setting(LAST_DELIVERED_KEY, &now.to_rfc3339())?;
+        return Ok(None);
+    };
+    if now - start < chrono::Duration::days(i64::from(settings.period_days.max(1))) {
+        return Ok(None);
+    }
+
+    let digest = build(start, now)?;
+    let dir = database::app_data_dir().join(DIGEST_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("digest-{}.html", now.format("%Y-%m-%d")));
+    std::fs::write(&path, render(&digest, ReportFormat::Html, locale)?)?;
+    database::set_setting(LAST_DELIVERED_KEY, &now.to_rfc3339())?;
+    Ok(Some(DeliveredDigest { path, digest }))
+}
+
+/// One-line summary for a notification
+pub fn summary(digest: &Digest, locale: &LocaleSettings) -> String {
+    let dates = locale.formatter();
+    content::label("digest.summary", locale.language)
+        .replace("{start}", &dates.date(digest.period_start))
+        .replace("{end}", &dates.date(digest.period_end))
+        .replace("{scans}", &digest.scan_count.to_string())
+        .replace("{opened}", &digest.issues_opened.len().to_string())
+        .replace("{closed}", &digest.issues_closed.len().to_string())
+}
+
+/// "Period: start – end" line
+fn period(digest: &Digest, locale: &LocaleSettings) -> String {
+    let dates = locale.formatter();
+    content::label("digest.period", locale.language)
+        .replace("{start}", &dates.date(digest.period_start))
+        .replace("{end}", &dates.date(digest.period_end))
+}
+
+/// "Scans: N" line
+fn scan_count(digest: &Digest, language: Language) -> String {
+    content::label("digest.scan-count", language).replace("{count}", &digest.scan_count.to_string())
+}
+
+fn score(score: u8, language: Language) -> String {
+    content::label("digest.score-value", language).replace("{score}", &score.to_string())
+}
+
+fn device_name(name: Option<&str>, language: Language) -> String {
+    name.map_or_else(|| content::label("report.unknown-device", language), str::to_string)
+}
+
+/// Compare the first and last scan of a period
+pub fn compare(
+    period_start: chrono::DateTime<chrono::Utc>,
+    period_end: chrono::DateTime<chrono::Utc>,
+    scans: &[ScanRecord],
+    first_devices: &[Device],
+    last_devices: &[Device],
+) -> Digest {
+    let score_trajectory = scans
+        .iter()
+        .map(|s| ScorePoint {
+            scan_id: s.id.clone(),
+            timestamp: s.timestamp,
+            average_score: s.average_score,
+        })
+        .collect();
+
+    let first_macs: HashSet<&str> = first_devices.iter().map(|d| d.mac.as_str()).collect();
+    let last_macs: HashSet<&str> = last_devices.iter().map(|d| d.mac.as_str()).collect();
+
+    let devices_added = last_devices
+        .iter()
+        .filter(|d| !first_macs.contains(d.mac.as_str()))
+        .map(device_change)
+        .collect();
+    let devices_removed = first_devices
+        .iter()
+        .filter(|d| !last_macs.contains(d.mac.as_str()))
+        .map(device_change)
+        .collect();
+
+    let first_issues = issue_map(first_devices);
+    let last_issues = issue_map(last_devices);
+
+    let mut issues_opened: Vec<IssueChange> = last_issues
+        .iter()
+        .filter(|(key, _)| !first_issues.contains_key(*key))
+        .map(|(_, change)| change.clone())
+        .collect();
+    let mut issues_closed: Vec<IssueChange> = first_issues
+        .iter()
+        .filter(|(key, _)| !last_issues.contains_key(*key))
+        .map(|(_, change)| change.clone())
+        .collect();
+
+    // Most severe first
+    issues_opened.sort_by_key(|i| std::cmp::Reverse(i.severity));
+    issues_closed.sort_by_key(|i| std::cmp::Reverse(i.severity));
+
+    Digest {
+        period_start,
+        period_end,
+        scan_count: scans.len(),
+        score_trajectory,
+        devices_added,
+        devices_removed,
+        issues_opened,
+        issues_closed,
+    }
+}
+
+fn device_change(device: &Device) -> DeviceChange {
+    DeviceChange {
+        mac: device.mac.clone(),
+        ip: device.ip.clone(),
+        name: device.name.clone(),
+    }
+}
+
+/// Map (MAC, issue ID) -> issue, so the same issue on the same device matches across scans
+fn issue_map(devices: &[Device]) -> HashMap<(String, String), IssueChange> {
+    let mut map = HashMap::new();
+    for device in devices {
+        for issue in &device.issues {
+            map.insert(
+                (device.mac.clone(), issue.id.clone()),
+                IssueChange {
+                    mac: device.mac.clone(),
+                    device_name: device.name.clone(),
+                    issue_id: issue.id.clone(),
+                    title: issue.title.clone(),
+                    severity: issue.severity,
+                },
+            );
+        }
+    }
+    map
+}
+
+/// Render a digest in the requested format
+pub fn render(digest: &Digest, format: ReportFormat, locale: &LocaleSettings) -> Result<String, ReportError> {
+    match format {
+        ReportFormat::Text => Ok(render_text(digest, locale)),
+        ReportFormat::Html => Ok(render_html(digest, locale)),
+        ReportFormat::Json => serde_json::to_string_pretty(digest)
+            .map_err(|e| ReportError::GenerationFailed(e.to_string())),
+    }
+}
+
+fn render_text(digest: &Digest, locale: &LocaleSettings) -> String {
+    let language = locale.language;
+    let dates = locale.formatter();
+    let mut report = String::new();
+
+    report.push_str("═════════════════════════════════════════════════════════════\n");
+    report.push_str(&format!("  {}\n", content::label("digest.title", language)));
+    report.push_str("═════════════════════════════════════════════════════════════\n\n");
+
+    report.push_str(&format!("{}\n", period(digest, locale)));
+    report.push_str(&format!("{}\n\n", scan_count(digest, language)));
+
+    report.push_str(&format!("【{}】\n", content::label("digest.score-trajectory", language)));
+    if digest.score_trajectory.is_empty() {
+        report.push_str(&format!("  {}\n", content::label("digest.no-scans", language)));
+    }
+    for point in &digest.score_trajectory {
+        report.push_str(&format!(
+            "  {}  {}\n",
+            dates.short(point.timestamp),
+            score(point.average_score, language)
+        ));
+    }
+
+    report.push_str(&format!("\n【{}】\n", content::label("digest.devices-added", language)));
+    push_device_lines(&mut report, &digest.devices_added, language);
+    report.push_str(&format!("\n【{}】\n", content::label("digest.devices-removed", language)));
+    push_device_lines(&mut report, &digest.devices_removed, language);
+
+    report.push_str(&format!("\n【{}】\n", content::label("digest.issues-opened", language)));
+    push_issue_lines(&mut report, &digest.issues_opened, language);
+    report.push_str(&format!("\n【{}】\n", content::label("digest.issues-closed", language)));
+    push_issue_lines(&mut report, &digest.issues_closed, language);
+
+    report.push_str("\n─────────────────────────────────────────────────────────────\n");
+    report.push_str(&format!("{}\n", content::label("report.generated-by", language)));
+
+    report
+}
+
+fn push_device_lines(report: &mut String, devices: &[DeviceChange], language: Language) {
+    if devices.is_empty() {
+        report.push_str(&format!("  {}\n", content::label("digest.none", language)));
+    }
+    for device in devices {
+        report.push_str(&format!(
+            "  • {} (IP: {} | MAC: {})\n",
+            device_name(device.name.as_deref(), language),
+            device.ip,
+            device.mac
+        ));
+    }
+}
+
+fn push_issue_lines(report: &mut String, issues: &[IssueChange], language: Language) {
+    if issues.is_empty() {
+        report.push_str(&format!("  {}\n", content::label("digest.none", language)));
+    }
+    for issue in issues {
+        report.push_str(&format!(
+            "  • {} — {}\n",
+            device_name(issue.device_name.as_deref(), language),
+            issue.title
+        ));
+    }
+}
+
+fn render_html(digest: &Digest, locale: &LocaleSettings) -> String {
+    let language = locale.language;
+    let dates = locale.formatter();
+    let title = content::label("digest.title", language);
+    let mut html = String::new();
+
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", super::html_lang(language)));
+    html.push_str("<meta charset=\"UTF-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", title));
+    html.push_str("<style>\n");
+    html.push_str("body { font-family: 'Noto Sans JP', sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }\n");
+    html.push_str("table { border-collapse: collapse; } td, th { padding: 4px 12px; border-bottom: 1px solid #e2e8f0; }\n");
+    html.push_str("</style>\n</head>\n<body>\n");
+
+    html.push_str(&format!("<h1>{}</h1>\n", title));
+    html.push_str(&format!("<p>{} / {}</p>\n", period(digest, locale), scan_count(digest, language)));
+
+    html.push_str(&format!("<h2>{}</h2>\n<table>\n", content::label("digest.score-trajectory", language)));
+    for point in &digest.score_trajectory {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            dates.short(point.timestamp),
+            score(point.average_score, language)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    for (heading, devices) in [
+        ("digest.devices-added", &digest.devices_added),
+        ("digest.devices-removed", &digest.devices_removed),
+    ] {
+        html.push_str(&format!("<h2>{}</h2>\n<ul>\n", content::label(heading, language)));
+        for device in devices {
+            html.push_str(&format!(
+                "<li>{} (IP: {} | MAC: {})</li>\n",
+                escape_html(&device_name(device.name.as_deref(), language)),
+                escape_html(&device.ip),
+                escape_html(&device.mac)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    for (heading, issues) in [
+        ("digest.issues-opened", &digest.issues_opened),
+        ("digest.issues-closed", &digest.issues_closed),
+    ] {
+        html.push_str(&format!("<h2>{}</h2>\n<ul>\n", content::label(heading, language)));
+        for issue in issues {
+            html.push_str(&format!(
+                "<li><strong>{}</strong> — {}</li>\n",
+                escape_html(&device_name(issue.device_name.as_deref(), language)),
+                escape_html(&issue.title)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
@@ -0,0 +1,125 @@
+//! Device inventory export
+//!
+//! One row per device of a scan, joined with what the user recorded about it
+//! (room, purchase date, warranty, tags, ...), so the household's IoT devices
+//! can be kept in a spreadsheet or another asset register.
+
+use super::ReportError;
+use crate::database::metadata::{DeviceMetadata, DeviceProfile};
+use crate::scanner::{Device, DeviceType, SecurityLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Inventory export format
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventoryFormat {
+    /// Spreadsheet-friendly; photos are left out
+    #[default]
+    Csv,
+    Json,
+}
+
+/// One inventory row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryEntry {
+    pub name: Option<String>,
+    pub mac: String,
+    pub ip: String,
+    pub vendor: Option<String>,
+    pub device_type: DeviceType,
+    pub security_level: SecurityLevel,
+    pub security_score: u8,
+    pub metadata: DeviceMetadata,
+}
+
+/// Join devices with their metadata
+pub fn entries(devices: &[Device], metadata: &HashMap<String, DeviceMetadata>) -> Vec<InventoryEntry> {
+    devices
+        .iter()
+        .map(|device| {
+            let mac = device.mac.to_lowercase();
+            InventoryEntry {
+                name: device.name.clone(),
+                mac: device.mac.clone(),
+                ip: device.ip.clone(),
+                vendor: device.vendor.clone(),
+                device_type: device.device_type,
+                security_level: device.security_level,
+                security_score: device.security_score,
+                metadata: metadata.get(&mac).cloned().unwrap_or(DeviceMetadata {
+                    mac,
+                    ..DeviceMetadata::default()
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Render the inventory of `devices`
+pub fn generate(
+    devices: &[Device],
+    metadata: &HashMap<String, DeviceMetadata>,
+    format: InventoryFormat,
+) -> Result<String, ReportError> {
+    let entries = entries(devices, metadata);
+    match format {
+        InventoryFormat::Json => serde_json::to_string_pretty(&entries)
+            .map_err(|e| ReportError::GenerationFailed(e.to_string())),
+        InventoryFormat::Csv => Ok(render_csv(&entries)),
+    }
+}
+
+/// Quote a CSV field when needed (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// serde name of a unit enum variant, e.g. `smart_tv`
+fn variant_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn render_csv(entries: &[InventoryEntry]) -> String {
+    // BOM so spreadsheet apps open Japanese names as UTF-8
+    let mut csv = String::from("\u{feff}");
+    csv.push_str("名前,MACアドレス,IPアドレス,メーカー,種類,設置場所,購入日,保証期限,重要度,タグ,監視対象外,スコア,判定,アイコン\r\n");
+
+    for entry in entries {
+        let DeviceProfile {
+            ref icon,
+            ref room,
+            purchase_date,
+            warranty_until,
+            ..
+        } = entry.metadata.profile;
+        let date = |d: Option<chrono::NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+        let row = [
+            entry.name.clone().unwrap_or_default(),
+            entry.mac.clone(),
+            entry.ip.clone(),
+            entry.vendor.clone().unwrap_or_default(),
+            variant_name(&entry.device_type),
+            room.clone().unwrap_or_default(),
+            date(purchase_date),
+            date(warranty_until),
+            variant_name(&entry.metadata.criticality),
+            entry.metadata.tags.join(";"),
+            if entry.metadata.excluded { "yes" } else { "" }.to_string(),
+            entry.security_score.to_string(),
+            variant_name(&entry.security_level),
+            icon.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
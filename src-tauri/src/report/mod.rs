@@ -1,9 +1,23 @@
 //! Report generation module
 
-use crate::scanner::{Device, SecurityLevel, IssueSeverity};
+use crate::database::network::NetworkProfile;
+use crate::scanner::dependency;
+use crate::scanner::exposure::ProbeMethod;
+use crate::scanner::firmware::{CheckStatus, HardeningCheck};
+use crate::scanner::{DeploymentContext, Device, SecurityLevel, IssueSeverity, NetworkIssue};
+use crate::settings::locale::{Language, LocaleSettings};
+use crate::vulndb::content;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub mod bundle;
+pub mod dashboard;
+pub mod diff;
+pub mod digest;
+pub mod inventory;
+pub mod qr;
+pub mod simple;
+
 /// Report generation errors
 #[derive(Error, Debug)]
 pub enum ReportError {
@@ -15,6 +29,32 @@ pub enum ReportError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Database error: {0}")]
+    Database(#[from] crate::database::DbError),
+
+    #[error("Scans were taken on different networks")]
+    DifferentNetworks,
+
+    #[error("Scan not found: {0}")]
+    ScanNotFound(String),
+}
+
+/// `id` of the `<script>` block carrying the machine-readable report in HTML reports
+pub const EMBEDDED_JSON_ID: &str = "iot-doctor-report";
+
+/// Label of the note shown for devices rated `InsufficientData`: the scan's
+/// time limit cut checks short, known vulnerabilities could not be looked
+/// up, or the device type needs a deeper scan
+fn insufficient_data_note(device: &Device, language: Language) -> String {
+    let key = if !device.reduced_coverage.is_empty() {
+        "report.note.reduced-coverage"
+    } else if device.enrichment_pending {
+        "report.note.enrichment-pending"
+    } else {
+        "report.note.insufficient-data"
+    };
+    content::label(key, language)
 }
 
 /// Report format
@@ -26,24 +66,90 @@ pub enum ReportFormat {
     Json,
 }
 
+/// Branding shown on client-deliverable reports
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Branding {
+    /// Company name shown in the report header
+    pub company_name: Option<String>,
+    /// Logo image URL or data URI (HTML reports only)
+    pub logo_url: Option<String>,
+    /// Text appended to the report footer
+    pub footer_text: Option<String>,
+}
+
+/// Report wording style
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportMode {
+    /// Full technical report
+    #[default]
+    Standard,
+    /// Plain-language report without jargon
+    Simple,
+}
+
+/// Options controlling report rendering
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportOptions {
+    pub branding: Branding,
+    pub mode: ReportMode,
+    pub context: DeploymentContext,
+    pub locale: LocaleSettings,
+    /// Network the devices were found on (opt-in network profile)
+    pub network: Option<NetworkProfile>,
+    /// Findings about the network as a whole, e.g. its DNS
+    pub network_issues: Vec<NetworkIssue>,
+}
+
 /// Generate security report
-pub fn generate(devices: &[Device], format: ReportFormat) -> Result<String, ReportError> {
-    match format {
-        ReportFormat::Text => generate_text_report(devices),
-        ReportFormat::Html => generate_html_report(devices),
-        ReportFormat::Json => generate_json_report(devices),
+pub fn generate(
+    devices: &[Device],
+    format: ReportFormat,
+    options: &ReportOptions,
+) -> Result<String, ReportError> {
+    // Findings in the report's language, whatever the scan was taken with
+    let mut devices = devices.to_vec();
+    content::localize_devices(&mut devices, options.locale.language);
+    let devices = devices.as_slice();
+    let mut options = options.clone();
+    for network_issue in &mut options.network_issues {
+        content::localize(&mut network_issue.issue, options.locale.language);
+    }
+    let options = &options;
+
+    match (format, options.mode) {
+        (ReportFormat::Text, ReportMode::Simple) => Ok(simple::render_text(devices, options)),
+        (ReportFormat::Html, ReportMode::Simple) => Ok(simple::render_html(devices, options)),
+        (ReportFormat::Text, ReportMode::Standard) => generate_text_report(devices, options),
+        (ReportFormat::Html, ReportMode::Standard) => generate_html_report(devices, options),
+        (ReportFormat::Json, _) => generate_json_report(devices),
     }
 }
 
-fn generate_text_report(devices: &[Device]) -> Result<String, ReportError> {
+fn generate_text_report(devices: &[Device], options: &ReportOptions) -> Result<String, ReportError> {
+    let language = options.locale.language;
     let mut report = String::new();
 
-    report.push_str("╔═══════════════════════════════════════════════════════════╗\n");
-    report.push_str("║           IoT Doctor セキュリティ診断レポート             ║\n");
-    report.push_str("╚═══════════════════════════════════════════════════════════╝\n\n");
+    report.push_str("═════════════════════════════════════════════════════════════\n");
+    report.push_str(&format!("  {}\n", content::label("report.title", language)));
+    report.push_str("═════════════════════════════════════════════════════════════\n\n");
 
-    report.push_str(&format!("診断日時: {}\n", chrono::Local::now().format("%Y年%m月%d日 %H:%M:%S")));
-    report.push_str(&format!("検出デバイス数: {}台\n\n", devices.len()));
+    if let Some(ref company) = options.branding.company_name {
+        report.push_str(&format!("{}: {}\n", content::label("report.created-by", language), company));
+    }
+
+    report.push_str(&format!(
+        "{}: {}\n",
+        content::label("report.scanned-at", language),
+        options.locale.formatter().datetime(chrono::Utc::now())
+    ));
+    report.push_str(&format!("{}: {}\n", content::label("report.context", language), options.context.label(language)));
+    if let Some(ref network) = options.network {
+        report.push_str(&format!("{}: {}\n", content::label("report.network", language), network.summary()));
+    }
+    report.push_str(&format!("{}\n\n", device_count(devices, language)));
 
     // Overall score
     let avg_score = if devices.is_empty() {
@@ -52,131 +158,608 @@ fn generate_text_report(devices: &[Device]) -> Result<String, ReportError> {
         devices.iter().map(|d| d.security_score as u32).sum::<u32>() / devices.len() as u32
     };
 
-    report.push_str("【総合セキュリティスコア】\n");
-    report.push_str(&format!("  {} / 100 点\n\n", avg_score));
+    report.push_str(&format!("【{}】\n", content::label("report.score", language)));
+    report.push_str(&format!(
+        "  {}\n\n",
+        content::label("report.score-value", language).replace("{score}", &avg_score.to_string())
+    ));
+
+    push_network_issues_text(&mut report, &options.network_issues, language);
 
     // Device details
-    report.push_str("【検出されたデバイス】\n");
+    report.push_str(&format!("【{}】\n", content::label("report.devices", language)));
     report.push_str("─────────────────────────────────────────────────────────────\n");
 
     for (i, device) in devices.iter().enumerate() {
-        let status = match device.security_level {
-            SecurityLevel::Safe => "✓ 安全",
-            SecurityLevel::Warning => "△ 注意",
-            SecurityLevel::Danger => "✗ 危険",
-            SecurityLevel::Unknown => "? 不明",
+        let (mark, key) = match device.security_level {
+            SecurityLevel::Safe => ("✓", "report.level.safe"),
+            SecurityLevel::Warning => ("△", "report.level.warning"),
+            SecurityLevel::Danger => ("✗", "report.level.danger"),
+            SecurityLevel::InsufficientData => ("…", "report.level.insufficient-data"),
+            SecurityLevel::Unknown => ("?", "report.level.unknown"),
         };
 
         report.push_str(&format!(
-            "\n{}. {} [{}]\n",
+            "\n{}. {} [{} {}]\n",
             i + 1,
-            device.name.as_deref().unwrap_or("不明なデバイス"),
-            status
+            device_name(device, language),
+            mark,
+            content::label(key, language)
         ));
         report.push_str(&format!("   IP: {} | MAC: {}\n", device.ip, device.mac));
+        if !device.discovery_sources.is_empty() {
+            report.push_str(&format!(
+                "   {}: {}\n",
+                content::label("report.discovery", language),
+                discovery_sources(device, language)
+            ));
+        }
 
-        if let Some(ref vendor) = device.vendor {
-            report.push_str(&format!("   メーカー: {}\n", vendor));
+        let vendor = match device.vendor {
+            Some(ref vendor) => Some(vendor.clone()),
+            None if device.mac_randomized => Some(content::label("report.vendor-randomized", language)),
+            None => None,
+        };
+        if let Some(vendor) = vendor {
+            report.push_str(&format!("   {}: {}\n", content::label("report.vendor", language), vendor));
+        }
+        if !device.identification.evidence.is_empty() {
+            let identification = content::label("report.identification", language)
+                .replace("{confidence}", &device.identification.confidence.to_string())
+                .replace(
+                    "{evidence}",
+                    &device.identification.evidence.join(&content::label("report.list-separator", language)),
+                );
+            report.push_str(&format!("   {}\n", identification));
+        }
+        if let Some(version) = device.onvif.as_ref().and_then(|o| o.firmware_version.as_ref()) {
+            report.push_str(&format!("   {}: {}\n", content::label("report.onvif-firmware", language), version));
+        }
+        if let Some(ref os) = device.os_guess {
+            report.push_str(&format!("   {}: {}\n", content::label("report.os", language), os));
         }
 
-        report.push_str(&format!("   セキュリティスコア: {} 点\n", device.security_score));
+        report.push_str(&format!(
+            "   {}\n",
+            content::label("report.device-score", language).replace("{score}", &device.security_score.to_string())
+        ));
+        if !device.open_ports.is_empty() {
+            report.push_str(&format!("   {}:\n", content::label("report.open-ports", language)));
+            for port in &device.open_ports {
+                report.push_str(&format!("     {}/{}", port.number, port.protocol));
+                if let Some(ref version) = port.version {
+                    report.push_str(&format!(" ({})", version));
+                }
+                if let Some(ref note) = port.risk_note {
+                    report.push_str(&format!(" - {}", note));
+                }
+                report.push('\n');
+            }
+        }
+        if !device.forwarded_ports.is_empty() {
+            report.push_str(&format!("   {}:\n", content::label("report.forwarded-ports", language)));
+            for port in &device.forwarded_ports {
+                let status = match (port.reachable, port.method) {
+                    (Some(true), Some(ProbeMethod::Loopback)) => "report.forward.loopback",
+                    (Some(true), _) => "report.forward.reachable",
+                    (Some(false), _) => "report.forward.unreachable",
+                    (None, _) => "report.forward.unknown",
+                };
+                report.push_str(&format!(
+                    "     {}/{} → {} - {}\n",
+                    port.address, port.transport, port.internal_port, content::label(status, language)
+                ));
+            }
+        }
+        if device.security_level == SecurityLevel::InsufficientData {
+            report.push_str(&format!("   ※ {}\n", insufficient_data_note(device, language)));
+        }
 
         if !device.issues.is_empty() {
-            report.push_str("   問題点:\n");
+            report.push_str(&format!("   {}:\n", content::label("report.issues", language)));
             for issue in &device.issues {
-                let severity_icon = match issue.severity {
-                    IssueSeverity::Critical => "🔴",
-                    IssueSeverity::High => "🟠",
-                    IssueSeverity::Medium => "🟡",
-                    IssueSeverity::Low => "🟢",
-                    IssueSeverity::Info => "🔵",
-                };
-                report.push_str(&format!("     {} {}\n", severity_icon, issue.title));
+                report.push_str(&format!("     {} {}\n", severity_icon(issue.severity), issue.title));
+            }
+        }
+
+        if let Some(ref note) = device.privacy_note {
+            report.push_str(&format!("   {}: {}\n", content::label("report.privacy", language), note.summary));
+            for incident in &note.incidents {
+                report.push_str(&format!("     - {}\n", incident));
             }
         }
+
+        if let Some(note) = dependency::unplug_note(device) {
+            report.push_str(&format!("   {}: {}\n", content::label("report.dependency", language), note));
+        }
+
+        for warning in &device.warnings {
+            report.push_str(&format!("   ※ {}\n", warning));
+        }
     }
 
+    push_router_audit_text(&mut report, devices, language);
+    push_firmware_text(&mut report, devices, language);
+    push_dependencies_text(&mut report, devices, language);
+
     // Remediation summary
-    report.push_str("\n\n【推奨される対策】\n");
+    report.push_str(&format!("\n\n【{}】\n", content::label("report.remediation", language)));
     report.push_str("─────────────────────────────────────────────────────────────\n");
 
     let mut remediation_map: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
 
     for device in devices {
         for issue in &device.issues {
-            let device_name = device.name.as_deref().unwrap_or("不明なデバイス");
             remediation_map
                 .entry(issue.remediation.clone())
                 .or_default()
-                .push(device_name.to_string());
+                .push(device_name(device, language));
         }
     }
 
     for (remediation, affected_devices) in remediation_map {
         report.push_str(&format!(
-            "\n• {} (対象: {})\n",
+            "\n• {} ({}: {})\n",
             remediation,
+            content::label("report.target", language),
             affected_devices.join(", ")
         ));
     }
 
     report.push_str("\n\n─────────────────────────────────────────────────────────────\n");
-    report.push_str("このレポートはIoT Doctorによって自動生成されました。\n");
+    if let Some(ref footer) = options.branding.footer_text {
+        report.push_str(&format!("{}\n", footer));
+    }
+    report.push_str(&format!("{}\n", content::label("report.generated-by", language)));
 
     Ok(report)
 }
 
-fn generate_html_report(devices: &[Device]) -> Result<String, ReportError> {
+fn generate_html_report(devices: &[Device], options: &ReportOptions) -> Result<String, ReportError> {
+    let language = options.locale.language;
+    let title = content::label("report.title", language);
     let mut html = String::new();
 
-    html.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n");
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", html_lang(language)));
     html.push_str("<meta charset=\"UTF-8\">\n");
     html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
-    html.push_str("<title>IoT Doctor セキュリティ診断レポート</title>\n");
+    html.push_str("<meta name=\"color-scheme\" content=\"light dark\">\n");
+    html.push_str(&format!("<title>{}</title>\n", title));
     html.push_str("<style>\n");
     html.push_str("body { font-family: 'Noto Sans JP', sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }\n");
     html.push_str(".safe { color: #22c55e; } .warning { color: #f59e0b; } .danger { color: #ef4444; }\n");
     html.push_str(".device { border: 1px solid #e2e8f0; border-radius: 8px; padding: 16px; margin: 16px 0; }\n");
     html.push_str(".issue { padding: 8px; margin: 4px 0; background: #fef2f2; border-radius: 4px; }\n");
+    html.push_str(".brand { display: flex; align-items: center; gap: 12px; } .brand img { max-height: 48px; }\n");
+    html.push_str("footer { margin-top: 32px; color: #64748b; font-size: 0.9em; }\n");
+    html.push_str(".qr { display: flex; align-items: center; gap: 16px; margin-top: 12px; }\n");
+    html.push_str("@media (prefers-color-scheme: dark) {\n");
+    html.push_str("  body { background: #0f172a; color: #e2e8f0; }\n");
+    html.push_str("  .device { border-color: #334155; }\n");
+    html.push_str("  .issue { background: #3b1d1d; }\n");
+    html.push_str("  a { color: #93c5fd; }\n");
+    html.push_str("  footer { color: #94a3b8; }\n");
+    html.push_str("  .qr svg { background: #fff; padding: 4px; }\n");
+    html.push_str("}\n");
     html.push_str("</style>\n</head>\n<body>\n");
 
-    html.push_str("<h1>IoT Doctor セキュリティ診断レポート</h1>\n");
-    html.push_str(&format!("<p>診断日時: {}</p>\n", chrono::Local::now().format("%Y年%m月%d日 %H:%M:%S")));
-    html.push_str(&format!("<p>検出デバイス数: {}台</p>\n", devices.len()));
+    let branding = &options.branding;
+    if branding.company_name.is_some() || branding.logo_url.is_some() {
+        html.push_str("<div class=\"brand\">\n");
+        if let Some(ref logo) = branding.logo_url {
+            html.push_str(&format!("<img src=\"{}\" alt=\"logo\">\n", escape_html(logo)));
+        }
+        if let Some(ref company) = branding.company_name {
+            html.push_str(&format!("<strong>{}</strong>\n", escape_html(company)));
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str(&format!("<h1>{}</h1>\n", title));
+    html.push_str(&format!(
+        "<p>{}: {}</p>\n",
+        content::label("report.scanned-at", language),
+        options.locale.formatter().datetime(chrono::Utc::now())
+    ));
+    html.push_str(&format!("<p>{}: {}</p>\n", content::label("report.context", language), options.context.label(language)));
+    if let Some(ref network) = options.network {
+        html.push_str(&format!(
+            "<p>{}: {}</p>\n",
+            content::label("report.network", language),
+            escape_html(&network.summary())
+        ));
+    }
+    html.push_str(&format!("<p>{}</p>\n", device_count(devices, language)));
+    push_network_issues_html(&mut html, &options.network_issues, language);
 
     for device in devices {
         let class = match device.security_level {
             SecurityLevel::Safe => "safe",
             SecurityLevel::Warning => "warning",
             SecurityLevel::Danger => "danger",
-            SecurityLevel::Unknown => "",
+            SecurityLevel::InsufficientData | SecurityLevel::Unknown => "",
         };
 
         html.push_str("<div class=\"device\">\n");
         html.push_str(&format!(
-            "<h3 class=\"{}\">{}（スコア: {}）</h3>\n",
+            "<h3 class=\"{}\">{}{}</h3>\n",
             class,
-            device.name.as_deref().unwrap_or("不明なデバイス"),
-            device.security_score
+            escape_html(&device_name(device, language)),
+            content::label("report.score-inline", language).replace("{score}", &device.security_score.to_string())
         ));
         html.push_str(&format!("<p>IP: {} | MAC: {}</p>\n", device.ip, device.mac));
+        if !device.discovery_sources.is_empty() {
+            html.push_str(&format!(
+                "<p>{}: {}</p>\n",
+                content::label("report.discovery", language),
+                discovery_sources(device, language)
+            ));
+        }
+        if device.security_level == SecurityLevel::InsufficientData {
+            html.push_str(&format!("<p>※ {}</p>\n", insufficient_data_note(device, language)));
+        }
+
+        if !device.open_ports.is_empty() {
+            html.push_str(&format!("<h4>{}:</h4>\n<ul>\n", content::label("report.open-ports", language)));
+            for port in &device.open_ports {
+                html.push_str(&format!("<li>{}/{}", port.number, escape_html(&port.protocol)));
+                if let Some(ref version) = port.version {
+                    html.push_str(&format!(" ({})", escape_html(version)));
+                }
+                if let Some(ref note) = port.risk_note {
+                    html.push_str(&format!(" - {}", escape_html(note)));
+                }
+                html.push_str("</li>\n");
+            }
+            html.push_str("</ul>\n");
+        }
 
         if !device.issues.is_empty() {
-            html.push_str("<h4>検出された問題:</h4>\n");
+            html.push_str(&format!("<h4>{}:</h4>\n", content::label("report.issues-found", language)));
             for issue in &device.issues {
-                html.push_str(&format!("<div class=\"issue\"><strong>{}</strong><br>{}</div>\n",
-                    issue.title, issue.description));
+                html.push_str(&format!("<div class=\"issue\"><strong>{}</strong><br>{}",
+                    escape_html(&issue.title), escape_html(&issue.description)));
+                for reference in &issue.references {
+                    html.push_str(&format!(
+                        "<br><a href=\"{}\" target=\"_blank\" rel=\"noopener\">{}</a>",
+                        escape_html(&reference.url),
+                        escape_html(&reference.title)
+                    ));
+                }
+                html.push_str("</div>\n");
+            }
+        }
+
+        if let Some(ref note) = device.privacy_note {
+            html.push_str(&format!("<p>{}: {}", content::label("report.privacy", language), escape_html(&note.summary)));
+            if let Some(ref source) = note.source {
+                html.push_str(&format!(
+                    " (<a href=\"{}\" target=\"_blank\" rel=\"noopener\">{}</a>)",
+                    escape_html(source),
+                    content::label("report.source", language)
+                ));
+            }
+            html.push_str("</p>\n");
+            if !note.incidents.is_empty() {
+                html.push_str("<ul>\n");
+                for incident in &note.incidents {
+                    html.push_str(&format!("<li>{}</li>\n", escape_html(incident)));
+                }
+                html.push_str("</ul>\n");
+            }
+        }
+
+        if let Some(note) = dependency::unplug_note(device) {
+            html.push_str(&format!("<p>{}: {}</p>\n", content::label("report.dependency", language), escape_html(&note)));
+        }
+
+        for warning in &device.warnings {
+            html.push_str(&format!("<p class=\"warning\">※ {}</p>\n", escape_html(warning)));
+        }
+
+        // Printed reports: let the reader jump to the admin page of critical devices
+        if device.security_level == SecurityLevel::Danger {
+            let url = qr::admin_url(device);
+            if let Some(svg) = qr::svg(&url) {
+                html.push_str(&format!(
+                    "<div class=\"qr\">{}<p>{}<br>{}</p></div>\n",
+                    svg,
+                    content::label("report.qr", language),
+                    escape_html(&url)
+                ));
             }
         }
 
         html.push_str("</div>\n");
     }
 
+    push_router_audit_html(&mut html, devices, language);
+    push_firmware_html(&mut html, devices, language);
+    push_dependencies_html(&mut html, devices, language);
+
+    if let Some(ref footer) = branding.footer_text {
+        html.push_str(&format!("<footer>{}</footer>\n", escape_html(footer)));
+    }
+
+    html.push_str(&embedded_json(devices));
     html.push_str("</body>\n</html>\n");
 
     Ok(html)
 }
 
+/// How the device was found, e.g. "ARP、mDNS"
+fn discovery_sources(device: &Device, language: Language) -> String {
+    device
+        .discovery_sources
+        .iter()
+        .map(|s| s.label(language))
+        .collect::<Vec<_>>()
+        .join(&content::label("report.list-separator", language))
+}
+
+/// Name of the device, or the report's word for an unknown one
+fn device_name(device: &Device, language: Language) -> String {
+    device.name.clone().unwrap_or_else(|| content::label("report.unknown-device", language))
+}
+
+/// "Devices found: N" line of the report header
+fn device_count(devices: &[Device], language: Language) -> String {
+    content::label("report.device-count", language).replace("{count}", &devices.len().to_string())
+}
+
+/// `lang` attribute of HTML reports
+fn html_lang(language: Language) -> &'static str {
+    match language {
+        Language::Ja => "ja",
+        Language::En => "en",
+    }
+}
+
+fn severity_icon(severity: IssueSeverity) -> &'static str {
+    match severity {
+        IssueSeverity::Critical => "🔴",
+        IssueSeverity::High => "🟠",
+        IssueSeverity::Medium => "🟡",
+        IssueSeverity::Low => "🟢",
+        IssueSeverity::Info => "🔵",
+    }
+}
+
+/// Findings that concern the network rather than one device
+fn push_network_issues_text(report: &mut String, issues: &[NetworkIssue], language: Language) {
+    if issues.is_empty() {
+        return;
+    }
+
+    report.push_str(&format!("【{}】\n", content::label("report.network-issues", language)));
+    report.push_str("─────────────────────────────────────────────────────────────\n");
+    for network_issue in issues {
+        let issue = &network_issue.issue;
+        report.push_str(&format!("\n{} {}\n", severity_icon(issue.severity), issue.title));
+        report.push_str(&format!("   {}\n", issue.description));
+        if !network_issue.devices.is_empty() {
+            report.push_str(&format!(
+                "   {}: {}\n",
+                content::label("report.target", language),
+                network_issue.devices.join(", ")
+            ));
+        }
+        report.push_str(&format!("   {}: {}\n", content::label("report.fix", language), issue.remediation));
+    }
+    report.push('\n');
+}
+
+fn push_network_issues_html(html: &mut String, issues: &[NetworkIssue], language: Language) {
+    if issues.is_empty() {
+        return;
+    }
+
+    html.push_str(&format!("<h2>{}</h2>\n", content::label("report.network-issues", language)));
+    for network_issue in issues {
+        let issue = &network_issue.issue;
+        html.push_str(&format!(
+            "<div class=\"issue\"><strong>{}</strong><br>{}",
+            escape_html(&issue.title),
+            escape_html(&issue.description)
+        ));
+        if !network_issue.devices.is_empty() {
+            html.push_str(&format!(
+                "<br>{}: {}",
+                content::label("report.target", language),
+                escape_html(&network_issue.devices.join(", "))
+            ));
+        }
+        html.push_str(&format!("<br>{}: {}", content::label("report.fix", language), escape_html(&issue.remediation)));
+        for reference in &issue.references {
+            html.push_str(&format!(
+                "<br><a href=\"{}\" target=\"_blank\" rel=\"noopener\">{}</a>",
+                escape_html(&reference.url),
+                escape_html(&reference.title)
+            ));
+        }
+        html.push_str("</div>\n");
+    }
+}
+
+fn check_mark(status: CheckStatus) -> &'static str {
+    match status {
+        CheckStatus::Pass => "✓",
+        CheckStatus::Fail => "✗",
+        CheckStatus::Unknown => "?",
+    }
+}
+
+/// Hardening checklist for routers running open firmware
+fn push_firmware_text(report: &mut String, devices: &[Device], language: Language) {
+    let routers: Vec<&Device> = devices.iter().filter(|d| d.firmware.is_some()).collect();
+    if routers.is_empty() {
+        return;
+    }
+
+    report.push_str(&format!("\n\n【{}】\n", content::label("report.firmware", language)));
+    report.push_str("─────────────────────────────────────────────────────────────\n");
+
+    for device in routers {
+        let Some(ref firmware) = device.firmware else {
+            continue;
+        };
+        report.push_str(&format!(
+            "\n{} ({}) - {} {}\n",
+            device_name(device, language),
+            device.ip,
+            firmware.kind.label(),
+            firmware.version.as_deref().unwrap_or("")
+        ));
+        push_checklist_text(report, &firmware.checks);
+    }
+}
+
+fn push_checklist_text(report: &mut String, checks: &[HardeningCheck]) {
+    for check in checks {
+        report.push_str(&format!("   [{}] {}\n", check_mark(check.status), check.title));
+        if let Some(ref detail) = check.detail {
+            report.push_str(&format!("       {}\n", detail));
+        }
+    }
+}
+
+/// Checklist of the default gateway, whatever its firmware
+fn push_router_audit_text(report: &mut String, devices: &[Device], language: Language) {
+    let Some((device, audit)) = devices.iter().find_map(|d| Some((d, d.router_audit.as_ref()?))) else {
+        return;
+    };
+
+    report.push_str(&format!("\n\n【{}】\n", content::label("report.router-audit", language)));
+    report.push_str("─────────────────────────────────────────────────────────────\n");
+    report.push_str(&format!(
+        "\n{} ({})\n",
+        device_name(device, language),
+        device.ip
+    ));
+    push_checklist_text(report, &audit.checks);
+}
+
+fn push_firmware_html(html: &mut String, devices: &[Device], language: Language) {
+    let routers: Vec<&Device> = devices.iter().filter(|d| d.firmware.is_some()).collect();
+    if routers.is_empty() {
+        return;
+    }
+
+    html.push_str(&format!("<h2>{}</h2>\n", content::label("report.firmware", language)));
+    for device in routers {
+        let Some(ref firmware) = device.firmware else {
+            continue;
+        };
+        html.push_str("<div class=\"device\">\n");
+        html.push_str(&format!(
+            "<h3>{} ({}) - {} {}</h3>\n<ul>\n",
+            escape_html(&device_name(device, language)),
+            device.ip,
+            firmware.kind.label(),
+            escape_html(firmware.version.as_deref().unwrap_or(""))
+        ));
+        push_checklist_html(html, &firmware.checks);
+        html.push_str("</ul>\n</div>\n");
+    }
+}
+
+fn push_checklist_html(html: &mut String, checks: &[HardeningCheck]) {
+    for check in checks {
+        let class = match check.status {
+            CheckStatus::Pass => "safe",
+            CheckStatus::Fail => "danger",
+            CheckStatus::Unknown => "",
+        };
+        html.push_str(&format!(
+            "<li class=\"{}\">{} {}",
+            class,
+            check_mark(check.status),
+            escape_html(&check.title)
+        ));
+        if let Some(ref detail) = check.detail {
+            html.push_str(&format!("<br><small>{}</small>", escape_html(detail)));
+        }
+        html.push_str("</li>\n");
+    }
+}
+
+fn push_router_audit_html(html: &mut String, devices: &[Device], language: Language) {
+    let Some((device, audit)) = devices.iter().find_map(|d| Some((d, d.router_audit.as_ref()?))) else {
+        return;
+    };
+
+    html.push_str(&format!("<h2>{}</h2>\n<div class=\"device\">\n", content::label("report.router-audit", language)));
+    html.push_str(&format!(
+        "<h3>{} ({})</h3>\n<ul>\n",
+        escape_html(&device_name(device, language)),
+        device.ip
+    ));
+    push_checklist_html(html, &audit.checks);
+    html.push_str("</ul>\n</div>\n");
+}
+
+fn push_dependencies_text(report: &mut String, devices: &[Device], language: Language) {
+    let edges: Vec<String> = dependency::edges(devices)
+        .map(|(provider, dependency)| {
+            format!("   {} → {}（{}）\n", device_label(provider), dependency.dependent, dependency.kind.label())
+        })
+        .collect();
+    if edges.is_empty() {
+        return;
+    }
+
+    report.push_str(&format!("\n\n【{}】\n", content::label("report.dependencies", language)));
+    report.push_str("─────────────────────────────────────────────────────────────\n");
+    report.push_str(&edges.concat());
+}
+
+fn push_dependencies_html(html: &mut String, devices: &[Device], language: Language) {
+    let edges: Vec<String> = dependency::edges(devices)
+        .map(|(provider, dependency)| {
+            format!(
+                "<li>{} → {}（{}）</li>\n",
+                escape_html(device_label(provider)),
+                escape_html(&dependency.dependent),
+                dependency.kind.label()
+            )
+        })
+        .collect();
+    if edges.is_empty() {
+        return;
+    }
+
+    html.push_str(&format!("<h2>{}</h2>\n<ul>\n", content::label("report.dependencies", language)));
+    html.push_str(&edges.concat());
+    html.push_str("</ul>\n");
+}
+
+fn device_label(device: &Device) -> &str {
+    device.name.as_deref().unwrap_or(&device.ip)
+}
+
+/// The JSON report as a `<script type="application/json">` block, so an HTML
+/// report can be re-imported later
+fn embedded_json(devices: &[Device]) -> String {
+    let Ok(json) = generate_json_report(devices) else {
+        return String::new();
+    };
+    // "</script>" inside a string value would otherwise end the block early
+    format!(
+        "<script type=\"application/json\" id=\"{}\">\n{}\n</script>\n",
+        EMBEDDED_JSON_ID,
+        json.replace("</", "<\\/")
+    )
+}
+
+/// Escape user-supplied text for inclusion in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 fn generate_json_report(devices: &[Device]) -> Result<String, ReportError> {
     #[derive(Serialize)]
     struct Report {
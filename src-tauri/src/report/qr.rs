@@ -0,0 +1,36 @@
+//! QR code generation for printed reports
+
+use crate::scanner::Device;
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+/// Build the URL of a device's web admin page from its open ports
+pub fn admin_url(device: &Device) -> String {
+    let has_port = |n: u16| device.open_ports.iter().any(|p| p.number == n);
+
+    if has_port(443) {
+        format!("https://{}", device.ip)
+    } else if has_port(80) {
+        format!("http://{}", device.ip)
+    } else if has_port(8443) {
+        format!("https://{}:8443", device.ip)
+    } else if has_port(8080) {
+        format!("http://{}:8080", device.ip)
+    } else {
+        // Ports are unknown at scan level 1; most admin pages live on plain HTTP
+        format!("http://{}", device.ip)
+    }
+}
+
+/// Render data as an SVG QR code suitable for inlining into HTML
+pub fn svg(data: &str) -> Option<String> {
+    let code = QrCode::new(data.as_bytes()).ok()?;
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(120, 120)
+        .quiet_zone(true)
+        .build();
+
+    // Drop the XML prolog so the SVG can be embedded directly
+    image.find("<svg").map(|start| image[start..].to_string())
+}
@@ -0,0 +1,170 @@
+//! Plain-language "simple mode" reports
+//!
+//! Renders the same scan data without technical jargon: each device gets a
+//! one-sentence verdict and at most three concrete steps to fix it.
+
+use super::{embedded_json, escape_html, html_lang, ReportOptions};
+use crate::scanner::{Device, IssueSeverity, SecurityLevel};
+use crate::settings::locale::Language;
+use crate::vulndb::content;
+
+/// Label of the one-sentence verdict per security level
+const PLAIN_VERDICTS: &[(SecurityLevel, &str)] = &[
+    (SecurityLevel::Safe, "simple.verdict.safe"),
+    (SecurityLevel::Warning, "simple.verdict.warning"),
+    (SecurityLevel::Danger, "simple.verdict.danger"),
+    (SecurityLevel::InsufficientData, "simple.verdict.insufficient-data"),
+    (SecurityLevel::Unknown, "simple.verdict.unknown"),
+];
+
+/// Label of the plain-language wording of each issue severity
+const PLAIN_SEVERITY: &[(IssueSeverity, &str)] = &[
+    (IssueSeverity::Critical, "simple.severity.critical"),
+    (IssueSeverity::High, "simple.severity.high"),
+    (IssueSeverity::Medium, "simple.severity.medium"),
+    (IssueSeverity::Low, "simple.severity.low"),
+    (IssueSeverity::Info, "simple.severity.info"),
+];
+
+/// Issue IDs with their own three-step fix, labelled `simple.fix.<id>.1` to `.3`
+const PLAIN_FIXES: &[&str] = &["default-password", "telnet-open", "upnp-enabled"];
+
+/// Fix used when no specific one is known
+const GENERIC_FIX: &str = "generic";
+
+fn verdict(level: SecurityLevel, language: Language) -> String {
+    PLAIN_VERDICTS
+        .iter()
+        .find(|(l, _)| *l == level)
+        .map(|(_, key)| content::label(key, language))
+        .unwrap_or_default()
+}
+
+fn severity_text(severity: IssueSeverity, language: Language) -> String {
+    PLAIN_SEVERITY
+        .iter()
+        .find(|(s, _)| *s == severity)
+        .map(|(_, key)| content::label(key, language))
+        .unwrap_or_default()
+}
+
+/// Fix for the most severe issue on the device, if any
+fn fix_steps(device: &Device, language: Language) -> Option<(IssueSeverity, [String; 3])> {
+    let worst = device.issues.iter().max_by_key(|i| i.severity)?;
+    let fix = PLAIN_FIXES.iter().find(|id| **id == worst.id).copied().unwrap_or(GENERIC_FIX);
+    let steps = [1, 2, 3].map(|n| content::label(&format!("simple.fix.{}.{}", fix, n), language));
+    Some((worst.severity, steps))
+}
+
+fn device_label(device: &Device, language: Language) -> String {
+    device.name.clone().unwrap_or_else(|| content::label("simple.unknown-device", language))
+}
+
+/// Why a device shows no maker, when its MAC address is randomized
+fn randomized_note(device: &Device, language: Language) -> Option<String> {
+    (device.mac_randomized && device.vendor.is_none()).then(|| content::label("simple.randomized", language))
+}
+
+/// "Devices found: N" line
+fn device_count(devices: &[Device], language: Language) -> String {
+    content::label("simple.device-count", language).replace("{count}", &devices.len().to_string())
+}
+
+/// Render a plain-language text report
+pub fn render_text(devices: &[Device], options: &ReportOptions) -> String {
+    let language = options.locale.language;
+    let mut report = String::new();
+
+    report.push_str(&format!("{}\n", content::label("report.simple-title", language)));
+    report.push_str("==============================\n\n");
+    if let Some(ref company) = options.branding.company_name {
+        report.push_str(&format!("{}: {}\n", content::label("report.created-by", language), company));
+    }
+    report.push_str(&format!(
+        "{}: {}\n",
+        content::label("simple.scanned-on", language),
+        options.locale.formatter().date(chrono::Utc::now())
+    ));
+    report.push_str(&format!("{}\n\n", device_count(devices, language)));
+
+    for (i, device) in devices.iter().enumerate() {
+        report.push_str(&format!("{}. {}\n", i + 1, device_label(device, language)));
+        report.push_str(&format!("   {}\n", verdict(device.security_level, language)));
+        if let Some(note) = randomized_note(device, language) {
+            report.push_str(&format!("   ※ {}\n", note));
+        }
+
+        if let Some((severity, steps)) = fix_steps(device, language) {
+            report.push_str(&format!("   ({})\n", severity_text(severity, language)));
+            for (n, step) in steps.iter().enumerate() {
+                let label = content::label("simple.step", language).replace("{n}", &(n + 1).to_string());
+                report.push_str(&format!("   {}: {}\n", label, step));
+            }
+        }
+        report.push('\n');
+    }
+
+    if let Some(ref footer) = options.branding.footer_text {
+        report.push_str(&format!("{}\n", footer));
+    }
+
+    report
+}
+
+/// Render a plain-language HTML report with large, high-contrast text
+pub fn render_html(devices: &[Device], options: &ReportOptions) -> String {
+    let language = options.locale.language;
+    let title = content::label("report.simple-title", language);
+    let mut html = String::new();
+
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", html_lang(language)));
+    html.push_str("<meta charset=\"UTF-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str("<meta name=\"color-scheme\" content=\"light dark\">\n");
+    html.push_str(&format!("<title>{}</title>\n", title));
+    html.push_str("<style>\n");
+    html.push_str("body { font-family: 'Noto Sans JP', sans-serif; font-size: 20px; line-height: 1.8; color: #000; max-width: 760px; margin: 0 auto; padding: 24px; }\n");
+    html.push_str(".device { border: 3px solid #334155; border-radius: 8px; padding: 16px; margin: 24px 0; }\n");
+    html.push_str("ol { padding-left: 1.5em; }\n");
+    html.push_str("@media (prefers-color-scheme: dark) { body { background: #000; color: #fff; } .device { border-color: #cbd5e1; } }\n");
+    html.push_str("</style>\n</head>\n<body>\n");
+
+    html.push_str(&format!("<h1>{}</h1>\n", title));
+    if let Some(ref company) = options.branding.company_name {
+        html.push_str(&format!("<p>{}: {}</p>\n", content::label("report.created-by", language), escape_html(company)));
+    }
+    html.push_str(&format!(
+        "<p>{}: {}<br>{}</p>\n",
+        content::label("simple.scanned-on", language),
+        options.locale.formatter().date(chrono::Utc::now()),
+        device_count(devices, language)
+    ));
+
+    for device in devices {
+        html.push_str("<section class=\"device\">\n");
+        html.push_str(&format!("<h2>{}</h2>\n", escape_html(&device_label(device, language))));
+        html.push_str(&format!("<p>{}</p>\n", verdict(device.security_level, language)));
+        if let Some(note) = randomized_note(device, language) {
+            html.push_str(&format!("<p>※ {}</p>\n", note));
+        }
+
+        if let Some((severity, steps)) = fix_steps(device, language) {
+            html.push_str(&format!("<p><strong>{}</strong></p>\n<ol>\n", severity_text(severity, language)));
+            for step in steps {
+                html.push_str(&format!("<li>{}</li>\n", step));
+            }
+            html.push_str("</ol>\n");
+        }
+
+        html.push_str("</section>\n");
+    }
+
+    if let Some(ref footer) = options.branding.footer_text {
+        html.push_str(&format!("<footer>{}</footer>\n", escape_html(footer)));
+    }
+
+    html.push_str(&embedded_json(devices));
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
@@ -0,0 +1,109 @@
+//! Apple model identifiers
+//!
+//! Apple devices announce their hardware model over mDNS (`model=` in
+//! `_device-info._tcp` and `_airplay._tcp`, `am=` in `_raop._tcp`, `rpMd=`
+//! in `_companion-link._tcp`) as identifiers such as `MacBookPro18,3` or
+//! `AudioAccessory5,1`. Mapping them tells a Mac from an Apple TV or a
+//! HomePod, which the vendor alone cannot. Data pack entries take precedence
+//! over the built-in table; unknown identifiers fall back to their family.
+
+use super::{datapack, DeviceType};
+use std::collections::HashMap;
+
+/// TXT keys carrying the model identifier
+const MODEL_KEYS: &[&str] = &["model", "am", "rpMd"];
+
+/// Built-in identifiers → marketing name and device type
+const MODELS: &[(&str, &str, DeviceType)] = &[
+    ("MacBookPro18,1", "MacBook Pro (16-inch, 2021)", DeviceType::Computer),
+    ("MacBookPro18,2", "MacBook Pro (16-inch, 2021)", DeviceType::Computer),
+    ("MacBookPro18,3", "MacBook Pro (14-inch, 2021)", DeviceType::Computer),
+    ("MacBookPro18,4", "MacBook Pro (14-inch, 2021)", DeviceType::Computer),
+    ("MacBookPro17,1", "MacBook Pro (13-inch, M1, 2020)", DeviceType::Computer),
+    ("MacBookAir10,1", "MacBook Air (M1, 2020)", DeviceType::Computer),
+    ("Mac14,2", "MacBook Air (M2, 2022)", DeviceType::Computer),
+    ("Mac14,15", "MacBook Air (15-inch, M2, 2023)", DeviceType::Computer),
+    ("Mac14,3", "Mac mini (2023)", DeviceType::Computer),
+    ("Mac14,13", "Mac Studio (2023)", DeviceType::Computer),
+    ("Mac15,3", "MacBook Pro (14-inch, M3, 2023)", DeviceType::Computer),
+    ("Mac15,12", "MacBook Air (13-inch, M3, 2024)", DeviceType::Computer),
+    ("Macmini9,1", "Mac mini (M1, 2020)", DeviceType::Computer),
+    ("iMac21,1", "iMac (24-inch, M1, 2021)", DeviceType::Computer),
+    ("iMac21,2", "iMac (24-inch, M1, 2021)", DeviceType::Computer),
+    ("AppleTV5,3", "Apple TV HD", DeviceType::SmartTv),
+    ("AppleTV6,2", "Apple TV 4K", DeviceType::SmartTv),
+    ("AppleTV11,1", "Apple TV 4K (第2世代)", DeviceType::SmartTv),
+    ("AppleTV14,1", "Apple TV 4K (第3世代)", DeviceType::SmartTv),
+    ("AudioAccessory1,1", "HomePod", DeviceType::SmartSpeaker),
+    ("AudioAccessory1,2", "HomePod", DeviceType::SmartSpeaker),
+    ("AudioAccessory5,1", "HomePod mini", DeviceType::SmartSpeaker),
+    ("AudioAccessory6,1", "HomePod (第2世代)", DeviceType::SmartSpeaker),
+    ("AirPort6,1", "AirPort Extreme", DeviceType::Router),
+    ("AirPort7,120", "AirPort Extreme (802.11ac)", DeviceType::Router),
+    ("iPhone14,5", "iPhone 13", DeviceType::Smartphone),
+    ("iPhone15,2", "iPhone 14 Pro", DeviceType::Smartphone),
+    ("iPhone16,1", "iPhone 15 Pro", DeviceType::Smartphone),
+    ("iPad13,18", "iPad (第10世代)", DeviceType::Smartphone),
+];
+
+/// Identifier families, longest prefix first where they overlap
+const FAMILIES: &[(&str, &str, DeviceType)] = &[
+    ("MacBookPro", "MacBook Pro", DeviceType::Computer),
+    ("MacBookAir", "MacBook Air", DeviceType::Computer),
+    ("MacBook", "MacBook", DeviceType::Computer),
+    ("Macmini", "Mac mini", DeviceType::Computer),
+    ("MacPro", "Mac Pro", DeviceType::Computer),
+    ("iMacPro", "iMac Pro", DeviceType::Computer),
+    ("iMac", "iMac", DeviceType::Computer),
+    ("Mac", "Mac", DeviceType::Computer),
+    ("AppleTV", "Apple TV", DeviceType::SmartTv),
+    ("AudioAccessory", "HomePod", DeviceType::SmartSpeaker),
+    ("AirPort", "AirPort", DeviceType::Router),
+    ("iPhone", "iPhone", DeviceType::Smartphone),
+    ("iPad", "iPad", DeviceType::Smartphone),
+    ("iPod", "iPod touch", DeviceType::Smartphone),
+];
+
+/// A recognised Apple model
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppleModel {
+    pub identifier: String,
+    pub name: String,
+    pub device_type: DeviceType,
+}
+
+/// Look up a model identifier such as `MacBookPro18,3`
+pub fn lookup(identifier: &str) -> Option<AppleModel> {
+    let identifier = identifier.trim();
+    let model = |name: &str, device_type: DeviceType| AppleModel {
+        identifier: identifier.to_string(),
+        name: name.to_string(),
+        device_type,
+    };
+
+    if let Some(entry) = datapack::apple_model(identifier) {
+        return Some(model(&entry.name, entry.device_type));
+    }
+    if let Some((_, name, device_type)) = MODELS.iter().find(|(id, _, _)| id.eq_ignore_ascii_case(identifier)) {
+        return Some(model(name, *device_type));
+    }
+
+    // "MacBookPro99,1" → family "MacBookPro"; the version keeps "Mac" from matching "Macmini"
+    let family = identifier.trim_end_matches(|c: char| c.is_ascii_digit() || c == ',');
+    if family.len() == identifier.len() {
+        return None;
+    }
+    FAMILIES
+        .iter()
+        .find(|(prefix, _, _)| *prefix == family)
+        .map(|(_, name, device_type)| model(name, *device_type))
+}
+
+/// Model announced by any of a device's mDNS service instances
+pub fn from_txt(instances: &[String], txt: &HashMap<String, HashMap<String, String>>) -> Option<AppleModel> {
+    instances
+        .iter()
+        .filter_map(|instance| txt.get(instance))
+        .flat_map(|record| MODEL_KEYS.iter().filter_map(|key| record.get(*key)))
+        .find_map(|identifier| lookup(identifier))
+}
@@ -1,122 +1,394 @@
 //! ARP-based device discovery
+//!
+//! The subnet is swept with ARP requests sent on a raw socket, which every
+//! IPv4 host has to answer whatever its firewall does with ICMP. Without
+//! raw socket access (no CAP_NET_RAW, or Windows) it falls back to pinging
+//! every address and reading what the OS learned into its ARP table, through
+//! the backend in [`super::platform`] for the running OS.
+//!
+//! IPv6 hosts are found by pinging the all-nodes multicast group and reading
+//! the IPv6 neighbour cache the replies leave behind.
+//!
+//! Which networks are swept is decided in [`super::interfaces`].
 
-use super::ScanError;
-use network_interface::{NetworkInterface, NetworkInterfaceConfig, V4IfAddr};
+use super::interfaces::{self, ScanTarget, SweepSettings};
+use super::{emit_progress_message, net, platform, ProgressSink, ScanError};
+use crate::vulndb::content;
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use std::collections::HashSet;
+use std::io;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use std::net::Ipv4Addr;
 use std::str::FromStr;
+use std::time::Duration;
 
-/// Discover devices on the local network using ARP
-pub async fn discover_devices() -> Result<Vec<(String, String)>, ScanError> {
-    // 1. Get local network interface
-    let (interface_name, ip_addr, subnet_mask) = get_local_interface()
-        .ok_or_else(|| ScanError::NetworkError("Could not find suitable network interface".to_string()))?;
-
-    println!("Using interface: {} ({}) mask: {}", interface_name, ip_addr, subnet_mask);
-
-    // 2. Perform Ping Sweep (ARP scan)
-    // Calculate IP range and ping all hosts
-    if let (Ok(ip), Ok(mask)) = (Ipv4Addr::from_str(&ip_addr), Ipv4Addr::from_str(&subnet_mask)) {
-         let ips = get_ips_in_subnet(ip, mask);
-         
-         // Ping in batches to avoid too many open files/processes
-         for chunk in ips.chunks(50) {
-             let mut tasks = Vec::new();
-             for target_ip in chunk {
-                 let target_ip = *target_ip;
-                 tasks.push(tokio::spawn(async move {
-                     let _ = Command::new("ping")
-                         .arg("-c")
-                         .arg("1")
-                         .arg("-W")
-                         .arg("1") // 1 second timeout
-                         .arg(target_ip.to_string())
-                         .stdout(Stdio::null())
-                         .stderr(Stdio::null())
-                         .status()
-                         .await;
-                 }));
-             }
-             
-             // Wait for batch to complete
-             for task in tasks {
-                 let _ = task.await;
-             }
-         }
-    }
+/// Time to wait for ARP replies after the last request
+pub const ARP_REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Gap between ARP requests, so a /24 goes out in about a quarter second
+#[cfg(unix)]
+const ARP_REQUEST_GAP: Duration = Duration::from_millis(1);
+
+/// Addresses swept between progress updates
+pub const SWEEP_CHUNK_SIZE: usize = 256;
+
+/// Hosts pinged concurrently during the fallback sweep
+pub const SWEEP_BATCH_SIZE: usize = 50;
 
-    // 3. Read ARP table
-    parse_arp_table().await
+/// Ping timeout during the fallback sweep
+pub const SWEEP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a device gets to answer an online check
+const ONLINE_PROBE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Result of ARP discovery
+#[derive(Debug, Default)]
+pub struct Discovery {
+    /// (IP, MAC) pairs that answered the sweep or are in the ARP table
+    pub hosts: Vec<(String, String)>,
+    /// Every (IP, MAC) reply to the sweep itself: raw ARP replies, or the
+    /// neighbour cache entries of addresses that answered a ping. Entries the
+    /// cache held from before are left out. An IP with more than one owner
+    /// has more than one machine answering.
+    pub observed: Vec<(String, String)>,
 }
 
-/// Get the local network interface information
-pub fn get_local_interface() -> Option<(String, String, String)> {
-    let interfaces = NetworkInterface::show().ok()?;
+/// Discover devices on the chosen networks using ARP; the default
+/// interface's network when `targets` is empty. `known` are the addresses of
+/// the last scan's devices, kept when a large network is narrowed.
+pub async fn discover_devices(
+    targets: &[ScanTarget],
+    settings: &SweepSettings,
+    known: &[Ipv4Addr],
+    progress: &dyn ProgressSink,
+) -> Result<Discovery, ScanError> {
+    let cached = parse_arp_table().await.unwrap_or_default();
+    let mut observed = Vec::new();
 
-    for iface in interfaces {
-        // Skip loopback and down interfaces
-        if iface.name == "lo" || iface.name.starts_with("docker") || iface.name.starts_with("br-") {
-            continue;
+    // 1. Networks to sweep, narrowed around the hosts already known
+    let seeds: Vec<Ipv4Addr> =
+        cached.iter().filter_map(|(ip, _)| ip.parse().ok()).chain(known.iter().copied()).collect();
+    let sweeps = interfaces::resolve(targets, settings, &seeds)?;
+    let skipped: usize = sweeps.iter().map(|s| s.skipped).sum();
+    if skipped > 0 {
+        let message = content::label("progress.narrowed", content::language()).replace("{skipped}", &skipped.to_string());
+        emit_progress_message(progress, message, 10);
+    }
+
+    // 2. Sweep each network in chunks: raw ARP, or a ping sweep without raw sockets
+    let total: usize = sweeps.iter().map(|s| s.addresses.len()).sum();
+    let mut swept = 0;
+    let mut answered = Vec::new();
+    for sweep in &sweeps {
+        eprintln!("Using interface: {} ({}) hosts: {}", sweep.interface, sweep.local_ip, sweep.addresses.len());
+        let mut raw_available = true;
+        for chunk in sweep.addresses.chunks(SWEEP_CHUNK_SIZE) {
+            if raw_available {
+                let (interface, local_ip, targets) = (sweep.interface.clone(), sweep.local_ip, chunk.to_vec());
+                let raw = tokio::task::spawn_blocking(move || raw_sweep(&interface, local_ip, &targets))
+                    .await
+                    .map_err(|e| ScanError::Internal(e.to_string()))?;
+                match raw {
+                    Ok(replies) => answered.extend(replies),
+                    Err(e) => {
+                        eprintln!("Raw ARP unavailable ({}), falling back to ping sweep", e);
+                        raw_available = false;
+                    }
+                }
+            }
+            if !raw_available {
+                observed.extend(ping_replies(chunk).await);
+            }
+            swept += chunk.len();
+            let message = content::label("progress.sweep", content::language())
+                .replace("{swept}", &swept.to_string())
+                .replace("{total}", &total.to_string());
+            emit_progress_message(progress, message, 10 + (swept * 14 / total) as u8);
         }
+    }
+
+    // 3. Read ARP table; raw replies never reach it, so add them. Entries of
+    // networks that were not chosen are left out.
+    let swept: HashSet<String> = sweeps.iter().flat_map(|s| &s.addresses).map(Ipv4Addr::to_string).collect();
+    let mut hosts = parse_arp_table().await?;
+    hosts.retain(|(ip, _)| swept.contains(ip));
+    for (ip, mac) in &answered {
+        if !hosts.iter().any(|(known, _)| known == ip) {
+            hosts.push((ip.clone(), mac.clone()));
+        }
+    }
+    observed.extend(answered);
+    Ok(Discovery { hosts, observed })
+}
+
+/// Send an ARP request to every target and collect the (IP, MAC) replies.
+/// Every reply is kept, so an address answered by two machines shows up twice.
+#[cfg(unix)]
+fn raw_sweep(interface: &str, local_ip: Ipv4Addr, targets: &[Ipv4Addr]) -> io::Result<Vec<(String, String)>> {
+    use pnet_datalink::Channel;
+    use std::time::Instant;
+
+    net::check_active()?;
+    let iface = pnet_datalink::interfaces()
+        .into_iter()
+        .find(|i| i.name == interface)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("interface {} not found", interface)))?;
+    let local_mac = iface
+        .mac
+        .filter(|mac| !mac.is_zero())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "interface has no MAC address"))?
+        .octets();
+
+    let config = pnet_datalink::Config {
+        read_timeout: Some(Duration::from_millis(100)),
+        promiscuous: false,
+        ..Default::default()
+    };
+    let (mut tx, mut rx) = match pnet_datalink::channel(&iface, config)? {
+        Channel::Ethernet(tx, rx) => (tx, rx),
+        _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "not an Ethernet interface")),
+    };
 
-        // Find IPv4 address
-        for addr in iface.addr {
-            if let network_interface::Addr::V4(V4IfAddr { ip, netmask: Some(netmask), .. }) = addr {
-                 // Check if it's a private IP (simple check)
-                 if !ip.is_loopback() && !ip.is_unspecified() {
-                     return Some((iface.name, ip.to_string(), netmask.to_string()));
-                 }
+    let wanted: HashSet<Ipv4Addr> = targets.iter().copied().collect();
+    let mut replies = Vec::new();
+    std::thread::scope(|scope| {
+        // Send from another thread so replies are read while requests go out
+        let sender = scope.spawn(move || -> io::Result<()> {
+            for target in targets {
+                if let Some(sent) = tx.send_to(&arp_request(local_mac, local_ip, *target), None) {
+                    sent?;
+                }
+                std::thread::sleep(ARP_REQUEST_GAP);
+            }
+            Ok(())
+        });
+
+        let mut deadline = None;
+        while deadline.map_or(true, |d| Instant::now() < d) {
+            if deadline.is_none() && sender.is_finished() {
+                deadline = Some(Instant::now() + ARP_REPLY_TIMEOUT);
+            }
+            match rx.next() {
+                Ok(frame) => {
+                    if let Some((ip, mac)) = parse_arp_reply(frame).filter(|(ip, _)| wanted.contains(ip)) {
+                        replies.push((ip.to_string(), mac));
+                    }
+                }
+                Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
             }
         }
+
+        sender
+            .join()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "ARP sender panicked"))??;
+        Ok(replies)
+    })
+}
+
+#[cfg(not(unix))]
+fn raw_sweep(_interface: &str, _local_ip: Ipv4Addr, _targets: &[Ipv4Addr]) -> io::Result<Vec<(String, String)>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "raw sockets are not supported on this platform"))
+}
+
+/// Broadcast Ethernet frame asking who has `target`
+#[cfg(unix)]
+fn arp_request(local_mac: [u8; 6], local_ip: Ipv4Addr, target: Ipv4Addr) -> [u8; 42] {
+    let mut frame = [0u8; 42];
+    frame[0..6].copy_from_slice(&[0xff; 6]);
+    frame[6..12].copy_from_slice(&local_mac);
+    frame[12..14].copy_from_slice(&[0x08, 0x06]);
+    // Ethernet / IPv4, address lengths 6 and 4, request
+    frame[14..22].copy_from_slice(&[0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01]);
+    frame[22..28].copy_from_slice(&local_mac);
+    frame[28..32].copy_from_slice(&local_ip.octets());
+    // Target hardware address stays zero
+    frame[38..42].copy_from_slice(&target.octets());
+    frame
+}
+
+/// Sender (IP, MAC) of an ARP reply frame
+#[cfg(unix)]
+fn parse_arp_reply(frame: &[u8]) -> Option<(Ipv4Addr, String)> {
+    if frame.len() < 42 || frame[12..14] != [0x08, 0x06] || frame[20..22] != [0x00, 0x02] {
+        return None;
     }
-    None
+    let mac = frame[22..28].iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":");
+    let ip = Ipv4Addr::new(frame[28], frame[29], frame[30], frame[31]);
+    Some((ip, mac))
 }
 
-fn get_ips_in_subnet(ip: Ipv4Addr, mask: Ipv4Addr) -> Vec<Ipv4Addr> {
-    let ip_u32 = u32::from(ip);
-    let mask_u32 = u32::from(mask);
-    let network_u32 = ip_u32 & mask_u32;
-    let broadcast_u32 = network_u32 | !mask_u32;
+/// (name, IPv4 address, netmask) of the interface swept by default
+pub fn get_local_interface() -> Option<(String, String, String)> {
+    interfaces::default_interface().map(|i| (i.name, i.ip, i.netmask))
+}
 
-    let mut ips = Vec::new();
-    // Start from network+1 to broadcast-1
-    for i in (network_u32 + 1)..broadcast_u32 {
-        ips.push(Ipv4Addr::from(i));
+/// Ping the IPv6 all-nodes group on the scan interface. Every IPv6 host on
+/// the link answers, resolving this machine first with a neighbour
+/// solicitation that puts the host into the OS neighbour cache.
+pub async fn solicit_ipv6_neighbors() {
+    if net::check_active().is_err() {
+        return;
     }
-    ips
+    let Some((name, ..)) = get_local_interface() else {
+        return;
+    };
+    let Some(index) = NetworkInterface::show()
+        .ok()
+        .and_then(|interfaces| interfaces.into_iter().find(|i| i.name == name))
+        .map(|i| i.index)
+    else {
+        return;
+    };
+
+    let (program, args) = platform::current().ping_all_nodes_command(&name, index);
+    let _ = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
 }
 
-async fn parse_arp_table() -> Result<Vec<(String, String)>, ScanError> {
-    let file = tokio::fs::File::open("/proc/net/arp")
+/// Read IPv6 neighbors (address, MAC) from the OS neighbor cache
+pub async fn get_ipv6_neighbors() -> Vec<(String, String)> {
+    tokio::task::spawn_blocking(|| platform::current().ipv6_neighbors())
         .await
-        .map_err(|e| ScanError::Internal(format!("Failed to open /proc/net/arp: {}", e)))?;
-    
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
-    let mut devices = Vec::new();
-
-    // Skip header
-    let _ = lines.next_line().await;
-
-    while let Ok(Some(line)) = lines.next_line().await {
-        // Format: IP address       HW type     Flags       HW address            Mask     Device
-        // 192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:ff     *        eth0
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 6 {
-            continue;
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+}
+
+/// Get the default gateway from the OS routing table
+pub fn get_default_gateway() -> Option<Ipv4Addr> {
+    platform::current().default_gateway()
+}
+
+/// Number of addresses swept for the chosen targets
+pub fn subnet_host_count(targets: &[ScanTarget], settings: &SweepSettings) -> Option<usize> {
+    let sweeps = interfaces::resolve(targets, settings, &[]).ok()?;
+    Some(sweeps.iter().map(|s| s.addresses.len()).sum())
+}
+
+/// Gently re-probe known devices that did not answer the sweep.
+///
+/// Each round sends one ARP request per device, or without raw sockets one
+/// ping. Only a reply received during the round counts: the neighbour cache
+/// keeps entries of devices that left for minutes, so an entry alone proves
+/// nothing. Rounds back off until `window` has elapsed. Returns the (IP, MAC)
+/// pairs that answered with the expected MAC.
+pub async fn reverify(targets: &[(String, String)], window: Duration) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    if net::check_active().is_err() {
+        return found;
+    }
+    let mut pending: Vec<(Ipv4Addr, &str)> = targets
+        .iter()
+        .filter_map(|(ip, mac)| Some((Ipv4Addr::from_str(ip).ok()?, mac.as_str())))
+        .collect();
+    let deadline = tokio::time::Instant::now() + window;
+    let mut delay = Duration::from_secs(1);
+    let mut raw_available = true;
+
+    while !pending.is_empty() && tokio::time::Instant::now() < deadline {
+        let round = tokio::time::Instant::now();
+        let addresses: Vec<Ipv4Addr> = pending.iter().map(|(ip, _)| *ip).collect();
+        let mut replies = Vec::new();
+        if raw_available {
+            match raw_replies(&addresses).await {
+                Ok(answered) => replies = answered,
+                Err(_) => raw_available = false,
+            }
+        }
+        if !raw_available {
+            replies = ping_replies(&addresses).await;
         }
 
-        let ip = parts[0].to_string();
-        let mac = parts[3].to_string();
-        let _device = parts[5];
+        pending.retain(|(ip, mac)| {
+            let answered = replies.iter().any(|(i, m)| *i == ip.to_string() && m.eq_ignore_ascii_case(mac));
+            if answered {
+                found.push((ip.to_string(), mac.to_lowercase()));
+            }
+            !answered
+        });
+
+        let next = (round + delay).min(deadline);
+        tokio::time::sleep_until(next).await;
+        delay *= 2;
+    }
+
+    found
+}
+
+/// ARP replies to requests sent now, through the interface on each address' link
+async fn raw_replies(addresses: &[Ipv4Addr]) -> io::Result<Vec<(String, String)>> {
+    let mut replies = Vec::new();
+    let mut by_interface: Vec<(interfaces::ScanInterface, Vec<Ipv4Addr>)> = Vec::new();
+    for address in addresses {
+        let Some(interface) = interfaces::interface_for(*address) else {
+            continue;
+        };
+        match by_interface.iter_mut().find(|(i, _)| i.name == interface.name && i.ip == interface.ip) {
+            Some((_, targets)) => targets.push(*address),
+            None => by_interface.push((interface, vec![*address])),
+        }
+    }
+    for (interface, targets) in by_interface {
+        let local_ip: Ipv4Addr = interface.ip.parse().map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+        let answered = tokio::task::spawn_blocking(move || raw_sweep(&interface.name, local_ip, &targets))
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))??;
+        replies.extend(answered);
+    }
+    Ok(replies)
+}
 
-        // Filter out incomplete entries and match interface if needed (implied by ping sweep)
-        if mac != "00:00:00:00:00:00" {
-            devices.push((ip, mac));
+/// Addresses that answered a ping now, with the MAC the exchange left in the
+/// neighbour cache
+async fn ping_replies(addresses: &[Ipv4Addr]) -> Vec<(String, String)> {
+    let mut answered = Vec::new();
+    for chunk in addresses.chunks(SWEEP_BATCH_SIZE) {
+        let mut tasks = Vec::new();
+        for target in chunk {
+            let (program, args) = platform::current().ping_command(*target, SWEEP_TIMEOUT);
+            let target = *target;
+            tasks.push(tokio::spawn(async move {
+                let status = Command::new(program).args(args).stdout(Stdio::null()).stderr(Stdio::null()).status().await;
+                status.is_ok_and(|s| s.success()).then_some(target)
+            }));
+        }
+        for task in tasks {
+            if let Ok(Some(target)) = task.await {
+                answered.push(target.to_string());
+            }
         }
     }
+    if answered.is_empty() {
+        return Vec::new();
+    }
+    let table = parse_arp_table().await.unwrap_or_default();
+    table.into_iter().filter(|(ip, _)| answered.contains(ip)).collect()
+}
+
+/// Whether a known device is on the network right now: a short
+/// [`reverify`], or with `active` false a look at the neighbour cache, which
+/// sends nothing but may hold entries of devices that just left
+pub async fn is_online(ip: &str, mac: &str, active: bool) -> bool {
+    if active {
+        let target = [(ip.to_string(), mac.to_string())];
+        return !reverify(&target, ONLINE_PROBE_WINDOW).await.is_empty();
+    }
+    parse_arp_table()
+        .await
+        .is_ok_and(|table| table.iter().any(|(_, m)| m.eq_ignore_ascii_case(mac)))
+}
 
-    Ok(devices)
+/// (IP, MAC) pairs in the OS neighbour cache; reading it sends nothing
+pub(super) async fn parse_arp_table() -> Result<Vec<(String, String)>, ScanError> {
+    tokio::task::spawn_blocking(|| platform::current().arp_table())
+        .await
+        .map_err(|e| ScanError::Internal(e.to_string()))?
+        .map_err(|e| ScanError::Internal(format!("Failed to read the ARP table: {}", e)))
 }
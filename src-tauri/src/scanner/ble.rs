@@ -0,0 +1,213 @@
+//! Bluetooth Low Energy discovery
+//!
+//! Smart locks, trackers, bulbs and many sensors never join Wi-Fi, so the
+//! network scan cannot see them. Their advertisements are collected instead:
+//! the name, the advertised services and the Bluetooth SIG company ID of the
+//! manufacturer data, which names the maker. From Level 2 each connectable
+//! device is asked for its characteristics; data that reads without pairing
+//! is open to anyone within radio range.
+//!
+//! Bluetooth needs the `ble` cargo feature (btleplug); builds without it
+//! report BLE as not built in.
+
+use super::{IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long advertisements are collected
+pub const SCAN_WINDOW: Duration = Duration::from_secs(10);
+
+/// Time for connecting to a device and reading its characteristics
+#[cfg(feature = "ble")]
+const PROBE_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Bluetooth SIG company identifiers of manufacturer data
+const COMPANY_IDS: &[(u16, &str)] = &[
+    (0x0006, "Microsoft"),
+    (0x000D, "Texas Instruments"),
+    (0x000F, "Broadcom"),
+    (0x004C, "Apple"),
+    (0x0059, "Nordic Semiconductor"),
+    (0x0075, "Samsung Electronics"),
+    (0x0087, "Garmin"),
+    (0x00E0, "Google"),
+    (0x0171, "Amazon"),
+    (0x02E5, "Espressif"),
+    (0x038F, "Xiaomi"),
+];
+
+/// Makers of phones and computers, which are not probed: their adverts are
+/// proximity beacons and a connection may show a pairing prompt to the owner
+#[cfg(feature = "ble")]
+const PERSONAL_DEVICE_VENDORS: &[&str] = &["Apple", "Microsoft", "Samsung Electronics", "Google"];
+
+/// Standard services whose data is public by design: GAP, GATT, Device
+/// Information, Battery
+const PUBLIC_SERVICES: &[u16] = &[0x1800, 0x1801, 0x180A, 0x180F];
+
+/// Suffix of 16-bit UUIDs expanded onto the Bluetooth base UUID
+const BASE_UUID_SUFFIX: &str = "-0000-1000-8000-00805f9b34fb";
+
+/// BLE discovery errors
+#[derive(Error, Debug)]
+pub enum BleError {
+    #[error("Bluetooth support is not included in this build")]
+    NotBuilt,
+
+    #[error("No Bluetooth adapter found")]
+    NoAdapter,
+
+    #[error("Bluetooth error: {0}")]
+    Bluetooth(String),
+}
+
+/// A device advertising nearby
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BleDevice {
+    /// Bluetooth address; phones and trackers rotate random ones
+    pub address: String,
+    pub name: Option<String>,
+    /// Maker named by the manufacturer data
+    pub vendor: Option<String>,
+    /// Signal strength in dBm, for telling near from far
+    pub rssi: Option<i16>,
+    /// Advertised service UUIDs
+    pub services: Vec<String>,
+    /// Characteristics that could be read without pairing (Level 2)
+    pub open_characteristics: Vec<String>,
+    pub issues: Vec<SecurityIssue>,
+}
+
+/// Maker of a manufacturer data company ID
+pub fn vendor_for(company_id: u16) -> Option<&'static str> {
+    COMPANY_IDS.iter().find(|(id, _)| *id == company_id).map(|(_, vendor)| *vendor)
+}
+
+/// What an advertisement tells about a device
+pub(super) fn from_advertisement(
+    address: String,
+    name: Option<String>,
+    company_ids: &[u16],
+    services: Vec<String>,
+    rssi: Option<i16>,
+) -> BleDevice {
+    BleDevice {
+        address,
+        name: name.filter(|n| !n.trim().is_empty()),
+        vendor: company_ids.iter().find_map(|id| vendor_for(*id)).map(str::to_string),
+        rssi,
+        services,
+        ..BleDevice::default()
+    }
+}
+
+/// Whether the device is worth connecting to
+#[cfg(feature = "ble")]
+fn probed(device: &BleDevice) -> bool {
+    !device.vendor.as_deref().is_some_and(|v| PERSONAL_DEVICE_VENDORS.contains(&v))
+}
+
+/// 16-bit form of a UUID on the Bluetooth base UUID
+fn short_uuid(uuid: &str) -> Option<u16> {
+    let lower = uuid.to_ascii_lowercase();
+    let head = lower.strip_suffix(BASE_UUID_SUFFIX)?;
+    u16::from_str_radix(head.strip_prefix("0000")?, 16).ok()
+}
+
+/// Whether the service is public by design, so reading it shows nothing
+pub(super) fn is_public_service(service_uuid: &str) -> bool {
+    short_uuid(service_uuid).is_some_and(|uuid| PUBLIC_SERVICES.contains(&uuid))
+}
+
+/// Finding for a device whose data reads without pairing
+pub(super) fn to_issue(device: &BleDevice) -> SecurityIssue {
+    let label = device.name.clone().unwrap_or_else(|| device.address.clone());
+    content::issue(
+        "ble-no-pairing",
+        IssueSeverity::Medium,
+        &[
+            ("device", label),
+            ("count", device.open_characteristics.len().to_string()),
+        ],
+    )
+}
+
+/// Collect the advertisements sent during `window`; with `probe`, connect to
+/// the connectable devices and try reading their characteristics
+#[cfg(feature = "ble")]
+pub async fn scan(window: Duration, probe: bool) -> Result<Vec<BleDevice>, BleError> {
+    use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter};
+    use btleplug::platform::Manager;
+
+    let manager = Manager::new().await?;
+    let adapter = manager.adapters().await?.into_iter().next().ok_or(BleError::NoAdapter)?;
+    adapter.start_scan(ScanFilter::default()).await?;
+    tokio::time::sleep(window).await;
+    let _ = adapter.stop_scan().await;
+
+    let mut devices = Vec::new();
+    for peripheral in adapter.peripherals().await? {
+        let Ok(Some(properties)) = peripheral.properties().await else {
+            continue;
+        };
+        let company_ids: Vec<u16> = properties.manufacturer_data.keys().copied().collect();
+        let services = properties.services.iter().map(|uuid| uuid.to_string()).collect();
+        let mut device = from_advertisement(
+            properties.address.to_string(),
+            properties.local_name,
+            &company_ids,
+            services,
+            properties.rssi,
+        );
+
+        if probe && probed(&device) {
+            // Devices that do not accept connections time out here
+            if let Ok(Ok(open)) = tokio::time::timeout(PROBE_TIMEOUT, read_open(&peripheral)).await {
+                device.open_characteristics = open;
+            }
+            let _ = peripheral.disconnect().await;
+            if !device.open_characteristics.is_empty() {
+                device.issues.push(to_issue(&device));
+            }
+        }
+        devices.push(device);
+    }
+    Ok(devices)
+}
+
+/// Without the `ble` feature there is no Bluetooth stack to ask
+#[cfg(not(feature = "ble"))]
+pub async fn scan(_window: Duration, _probe: bool) -> Result<Vec<BleDevice>, BleError> {
+    Err(BleError::NotBuilt)
+}
+
+/// UUIDs of the characteristics outside the public services that read
+/// without pairing
+#[cfg(feature = "ble")]
+async fn read_open(peripheral: &btleplug::platform::Peripheral) -> Result<Vec<String>, BleError> {
+    use btleplug::api::{CharPropFlags, Peripheral as _};
+
+    peripheral.connect().await?;
+    peripheral.discover_services().await?;
+    let mut open = Vec::new();
+    for characteristic in peripheral.characteristics() {
+        let readable = characteristic.properties.contains(CharPropFlags::READ);
+        if !readable || is_public_service(&characteristic.service_uuid.to_string()) {
+            continue;
+        }
+        // A protected characteristic answers with an authentication error
+        if peripheral.read(&characteristic).await.is_ok() {
+            open.push(characteristic.uuid.to_string());
+        }
+    }
+    Ok(open)
+}
+
+#[cfg(feature = "ble")]
+impl From<btleplug::Error> for BleError {
+    fn from(e: btleplug::Error) -> Self {
+        BleError::Bluetooth(e.to_string())
+    }
+}
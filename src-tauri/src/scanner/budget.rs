@@ -0,0 +1,182 @@
+//! Time-boxed scans
+//!
+//! With a maximum scan duration set, the time left after discovery is split
+//! across the active phases by weight; time a phase does not use carries
+//! over to the next. Within a phase devices are checked in priority order
+//! (the gateway, cameras, devices with findings last time, then the rest), so
+//! running out of time costs coverage where it matters least. Devices that
+//! were skipped or cut short are marked, and never rated safe.
+
+use super::{arp, Device, DeviceType, SecurityLevel};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Longest limit that can be set; no scan needs a day, and the planner's
+/// deadline arithmetic stays far from overflowing
+const MAX_DURATION_SECS: u64 = 24 * 60 * 60;
+
+/// Scan duration limit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    /// Maximum duration of a full scan in seconds; `None` for no limit
+    pub max_duration_secs: Option<u64>,
+}
+
+impl BudgetSettings {
+    /// Bound the limit before it is saved
+    pub fn clamp(&mut self) {
+        self.max_duration_secs = self.max_duration_secs.map(|s| s.min(MAX_DURATION_SECS));
+    }
+
+    pub fn max_duration(&self) -> Option<Duration> {
+        // Settings saved before the bound are clamped here
+        self.max_duration_secs.filter(|&s| s > 0).map(|s| Duration::from_secs(s.min(MAX_DURATION_SECS)))
+    }
+}
+
+/// Budgeted phase of a scan
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanPhase {
+    Ports,
+    SilentDevices,
+    Services,
+    Gateway,
+    Dns,
+    Firmware,
+    Exposure,
+    Vulnerabilities,
+    Shares,
+    Reverify,
+}
+
+impl ScanPhase {
+    pub fn label(&self) -> String {
+        let key = match self {
+            ScanPhase::Ports => "phase.ports",
+            ScanPhase::SilentDevices => "phase.silent-devices",
+            ScanPhase::Services => "phase.services",
+            ScanPhase::Gateway => "phase.gateway",
+            ScanPhase::Dns => "phase.dns",
+            ScanPhase::Firmware => "phase.firmware",
+            ScanPhase::Exposure => "phase.exposure",
+            ScanPhase::Vulnerabilities => "phase.vulnerabilities",
+            ScanPhase::Shares => "phase.shares",
+            ScanPhase::Reverify => "phase.reverify",
+        };
+        content::label(key, content::language())
+    }
+
+    /// Share of the remaining time the phase gets
+    fn weight(&self) -> u32 {
+        match self {
+            ScanPhase::Ports => 50,
+            ScanPhase::Services | ScanPhase::Firmware => 10,
+            _ => 5,
+        }
+    }
+}
+
+/// Splits the time left across the phases a scan will run
+pub struct Planner {
+    deadline: Option<Instant>,
+    /// Phases still to run, in order
+    pending: Vec<ScanPhase>,
+}
+
+/// Why a device was not fully checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortfall {
+    /// The phase ran out before the device's turn
+    Skipped,
+    /// The check was still running when the phase ran out
+    Cut,
+}
+
+/// Time allotted to the running phase
+#[derive(Clone)]
+pub struct PhaseBudget {
+    phase: ScanPhase,
+    deadline: Option<Instant>,
+}
+
+impl Planner {
+    /// Plan `phases` to finish `max_duration` after `started`
+    pub fn new(started: Instant, max_duration: Option<Duration>, phases: &[ScanPhase]) -> Self {
+        Self {
+            deadline: max_duration.map(|d| started + d),
+            pending: phases.to_vec(),
+        }
+    }
+
+    /// Start `phase`, giving it its share of the time left
+    pub fn start(&mut self, phase: ScanPhase) -> PhaseBudget {
+        let weights: u32 = self.pending.iter().map(ScanPhase::weight).sum();
+        self.pending.retain(|&p| p != phase);
+        let deadline = self.deadline.map(|deadline| {
+            let now = Instant::now();
+            let left = deadline.saturating_duration_since(now);
+            now + left * phase.weight() / weights.max(1)
+        });
+        PhaseBudget { phase, deadline }
+    }
+}
+
+impl PhaseBudget {
+    /// Time left in the phase; `None` once it ran out
+    pub fn time_left(&self) -> Option<Duration> {
+        match self.deadline {
+            None => Some(Duration::MAX),
+            Some(deadline) => Some(deadline.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero()),
+        }
+    }
+
+    /// Run a check within the phase
+    pub async fn run<F: Future>(&self, check: F) -> Result<F::Output, Shortfall> {
+        let left = self.time_left().ok_or(Shortfall::Skipped)?;
+        match self.deadline {
+            Some(_) => tokio::time::timeout(left, check).await.map_err(|_| Shortfall::Cut),
+            None => Ok(check.await),
+        }
+    }
+
+    /// Record that the phase did not fully cover `device`
+    pub fn mark(&self, device: &mut Device, shortfall: Shortfall) {
+        if device.reduced_coverage.contains(&self.phase) {
+            return;
+        }
+        device.reduced_coverage.push(self.phase);
+        device.warnings.push(match shortfall {
+            Shortfall::Skipped => format!("時間制限のため「{}」を省略しました", self.phase.label()),
+            Shortfall::Cut => format!("時間制限のため「{}」を途中で打ち切りました", self.phase.label()),
+        });
+    }
+}
+
+/// Indices of `devices` in the order they should be checked
+pub fn priority_order(devices: &[Device], known_devices: &[Device]) -> Vec<usize> {
+    let gateway = arp::get_default_gateway().map(|ip| ip.to_string());
+    let rank = |device: &Device| {
+        let had_findings = known_devices.iter().any(|k| {
+            k.mac.eq_ignore_ascii_case(&device.mac)
+                && (!k.issues.is_empty() || matches!(k.security_level, SecurityLevel::Warning | SecurityLevel::Danger))
+        });
+        if gateway.as_deref() == Some(device.ip.as_str()) {
+            0
+        } else if device.device_type == DeviceType::Camera {
+            1
+        } else if had_findings {
+            2
+        } else {
+            3
+        }
+    };
+
+    let mut order: Vec<usize> = (0..devices.len()).collect();
+    order.sort_by_key(|&i| rank(&devices[i]));
+    order
+}
@@ -0,0 +1,65 @@
+//! Duplicate IP address detection
+//!
+//! When two machines use the same IPv4 address, whichever answered ARP last
+//! wins, and connections to both break intermittently. Users usually blame
+//! Wi-Fi for this. We detect it from two machines answering the sweep for
+//! one address, and from the router's DHCP leases disagreeing with who
+//! actually answers. Monitoring rounds sweep too and report conflicts that
+//! appear between scans.
+//!
+//! A single MAC answering for several addresses is left alone: that is the
+//! pattern of ARP spoofing or proxy ARP, not of two devices colliding.
+
+use super::router::DhcpLease;
+use super::{IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An address claimed by more than one MAC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpConflict {
+    pub ip: String,
+    pub macs: Vec<String>,
+}
+
+/// Find addresses claimed by more than one MAC in the (IP, MAC) replies to
+/// a sweep and the router's active leases
+pub fn find_conflicts(observed: &[(String, String)], leases: &[DhcpLease]) -> Vec<IpConflict> {
+    let active_leases = leases
+        .iter()
+        .filter(|l| l.active == Some(true) && !l.ip.is_empty())
+        .map(|l| (l.ip.clone(), l.mac.clone()));
+
+    let mut macs_by_ip: HashMap<String, Vec<String>> = HashMap::new();
+    let mut ips_by_mac: HashMap<String, Vec<String>> = HashMap::new();
+    for (ip, mac) in observed.iter().cloned().chain(active_leases) {
+        let mac = mac.to_lowercase();
+        let macs = macs_by_ip.entry(ip.clone()).or_default();
+        if !macs.contains(&mac) {
+            macs.push(mac.clone());
+        }
+        let ips = ips_by_mac.entry(mac).or_default();
+        if !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+
+    let mut conflicts: Vec<IpConflict> = macs_by_ip
+        .into_iter()
+        .filter(|(_, macs)| macs.len() > 1)
+        .filter(|(_, macs)| macs.iter().all(|mac| ips_by_mac.get(mac).map_or(0, Vec::len) == 1))
+        .map(|(ip, macs)| IpConflict { ip, macs })
+        .collect();
+    conflicts.sort_by(|a, b| a.ip.cmp(&b.ip));
+    conflicts
+}
+
+/// Finding for an address conflict
+pub fn to_issue(conflict: &IpConflict) -> SecurityIssue {
+    content::issue(
+        "ip-conflict",
+        IssueSeverity::Medium,
+        &[("ip", conflict.ip.clone()), ("macs", conflict.macs.join(", "))],
+    )
+}
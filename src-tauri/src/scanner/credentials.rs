@@ -0,0 +1,266 @@
+//! Default credential verification (Level 3, per-device consent)
+//!
+//! Tries the factory credentials the built-in database lists for the
+//! device's maker and type against its HTTP Basic auth, its HTML login form
+//! and its Telnet login. A login attempt can lock an account or leave an
+//! entry in the device's log, so it only runs for devices the user
+//! explicitly consented to. Every service gets a deliberately wrong password
+//! first: a login that "succeeds" with it cannot be judged and is skipped.
+
+use super::{firmware, login_page, net, ports, Device, DeviceType, IssueSeverity, SecurityIssue};
+use crate::vulndb::{self, content, DefaultCredential};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Pause between login attempts, so devices that throttle logins are not tripped
+const ATTEMPT_GAP: Duration = Duration::from_secs(1);
+
+/// Login attempts per service
+const MAX_ATTEMPTS: usize = 6;
+
+/// Timeout for a Telnet prompt or the answer to a login
+const TELNET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Password no device ships with, for the baseline attempt
+const WRONG_PASSWORD: &str = "iot-doctor-invalid-3f9c";
+
+/// Page text of a rejected login
+const FAILURE_HINTS: &[&str] = &["incorrect", "invalid", "failed", "wrong", "denied", "error", "誤り", "違います", "失敗"];
+
+/// A factory login the device accepted
+#[derive(Debug, Clone)]
+pub struct AcceptedLogin {
+    /// "HTTP" or "Telnet"
+    pub service: &'static str,
+    pub target: String,
+    pub username: String,
+}
+
+/// Product name the credential database uses for a device type
+fn product(device_type: DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Router => "Router",
+        DeviceType::Camera => "Camera",
+        _ => "",
+    }
+}
+
+/// Try the device's factory credentials; the first accepted login, if any
+pub async fn check_device(device: &Device) -> Option<AcceptedLogin> {
+    let mut candidates =
+        vulndb::get_default_credentials(device.vendor.as_deref().unwrap_or_default(), product(device.device_type));
+    candidates.truncate(MAX_ATTEMPTS);
+    if candidates.is_empty() {
+        return None;
+    }
+
+    if let Some(login) = check_web(device, &candidates).await {
+        return Some(login);
+    }
+    let telnet = device.open_ports.iter().any(|p| p.protocol == "tcp" && matches!(p.number, 23 | 2323));
+    if telnet {
+        let port = if device.open_ports.iter().any(|p| p.number == 23) { 23 } else { 2323 };
+        return check_telnet(&device.ip, port, &candidates).await;
+    }
+    None
+}
+
+/// Basic auth and login forms of the web interfaces found at Level 2
+async fn check_web(device: &Device, candidates: &[DefaultCredential]) -> Option<AcceptedLogin> {
+    let client = firmware::client()?;
+    for interface in &device.web_interfaces {
+        let accepted = if interface.status == 401 && interface.basic_realm.is_some() {
+            try_each(candidates, |c| basic_login(&client, &interface.url, c)).await
+        } else {
+            let page = firmware::fetch(&client, &interface.url).await;
+            let Some(form) = page.and_then(|page| login_form(&interface.url, &page.body)) else {
+                continue;
+            };
+            try_each(candidates, |c| form_login(&client, &form, c)).await
+        };
+        if let Some(credential) = accepted {
+            return Some(AcceptedLogin {
+                service: "HTTP",
+                target: interface.url.clone(),
+                username: credential.username,
+            });
+        }
+    }
+    None
+}
+
+async fn check_telnet(ip: &str, port: u16, candidates: &[DefaultCredential]) -> Option<AcceptedLogin> {
+    let credential = try_each(candidates, |c| telnet_login(ip, port, c)).await?;
+    Some(AcceptedLogin {
+        service: "Telnet",
+        target: format!("{}:{}", ip, port),
+        username: credential.username,
+    })
+}
+
+/// First candidate `attempt` reports accepted. The wrong-password baseline
+/// goes first; a service that accepts it, or cannot be asked, is skipped.
+async fn try_each<F, Fut>(candidates: &[DefaultCredential], attempt: F) -> Option<DefaultCredential>
+where
+    F: Fn(DefaultCredential) -> Fut,
+    Fut: std::future::Future<Output = Option<bool>>,
+{
+    let baseline = DefaultCredential {
+        password: WRONG_PASSWORD.to_string(),
+        ..candidates.first()?.clone()
+    };
+    if attempt(baseline).await != Some(false) {
+        return None;
+    }
+
+    for candidate in candidates {
+        tokio::time::sleep(ATTEMPT_GAP).await;
+        match attempt(candidate.clone()).await {
+            Some(true) => return Some(candidate.clone()),
+            Some(false) => {}
+            // The service stopped answering, e.g. after locking the account
+            None => return None,
+        }
+    }
+    None
+}
+
+async fn basic_login(client: &reqwest::Client, url: &str, credential: DefaultCredential) -> Option<bool> {
+    let response = client.get(url).basic_auth(credential.username, Some(credential.password)).send().await.ok()?;
+    match response.status().as_u16() {
+        401 | 403 => Some(false),
+        status => Some((200..400).contains(&status)),
+    }
+}
+
+/// An HTML login form and where it posts to
+#[derive(Debug, Clone)]
+pub(super) struct LoginForm {
+    pub action: String,
+    pub user_field: Option<String>,
+    pub password_field: String,
+    /// Hidden inputs sent along, e.g. CSRF tokens
+    pub hidden: Vec<(String, String)>,
+}
+
+/// The form holding the page's password field
+pub(super) fn login_form(url: &str, body: &str) -> Option<LoginForm> {
+    let lower = body.to_ascii_lowercase();
+    let password_at = login_page::input_tags(&lower)
+        .zip(lower.match_indices("<input").map(|(i, _)| i))
+        .find(|(tag, _)| login_page::attribute(tag, "type") == Some("password"))
+        .map(|(_, at)| at)?;
+    let start = lower[..password_at].rfind("<form").unwrap_or(0);
+    let end = lower[password_at..].find("</form").map_or(body.len(), |i| password_at + i);
+    // Lowercased tags locate the fields; values come from the original text
+    let form = &body[start..end];
+
+    let form_tag = &form[..form.find('>').unwrap_or(form.len())];
+    let action = login_page::attribute(form_tag, "action").unwrap_or_default();
+    let action = reqwest::Url::parse(url).ok()?.join(action).ok()?.to_string();
+
+    let mut user_field = None;
+    let mut password_field = None;
+    let mut hidden = Vec::new();
+    for tag in login_page::input_tags(form) {
+        let Some(name) = login_page::attribute(tag, "name") else {
+            continue;
+        };
+        match login_page::attribute(tag, "type").map(str::to_ascii_lowercase).as_deref() {
+            Some("password") => password_field = password_field.or(Some(name.to_string())),
+            Some("hidden") => {
+                hidden.push((name.to_string(), login_page::attribute(tag, "value").unwrap_or_default().to_string()))
+            }
+            None | Some("text" | "email") if password_field.is_none() => user_field = Some(name.to_string()),
+            _ => {}
+        }
+    }
+
+    Some(LoginForm {
+        action,
+        user_field,
+        password_field: password_field?,
+        hidden,
+    })
+}
+
+async fn form_login(client: &reqwest::Client, form: &LoginForm, credential: DefaultCredential) -> Option<bool> {
+    let mut fields = form.hidden.clone();
+    if let Some(user_field) = &form.user_field {
+        fields.push((user_field.clone(), credential.username));
+    }
+    fields.push((form.password_field.clone(), credential.password));
+
+    let response = client.post(&form.action).form(&fields).send().await.ok()?;
+    let status = response.status().as_u16();
+    let sets_cookie = response.headers().contains_key(reqwest::header::SET_COOKIE);
+    let body = response.text().await.unwrap_or_default();
+    Some(form_accepted(status, sets_cookie, &body))
+}
+
+/// Whether the answer to a form login looks like a session instead of the
+/// login page again
+pub(super) fn form_accepted(status: u16, sets_cookie: bool, body: &str) -> bool {
+    let lower = body.to_lowercase();
+    let password_field = login_page::input_tags(&lower).any(|tag| login_page::attribute(tag, "type") == Some("password"));
+    match status {
+        301 | 302 | 303 | 307 => sets_cookie,
+        200 => !password_field && !FAILURE_HINTS.iter().any(|hint| lower.contains(hint)),
+        _ => false,
+    }
+}
+
+async fn telnet_login(ip: &str, port: u16, credential: DefaultCredential) -> Option<bool> {
+    let addr = format!("{}:{}", ip, port).parse().ok()?;
+    let mut stream = net::tcp_connect_async(addr, TELNET_TIMEOUT).await.ok()?;
+
+    read_until(&mut stream, &["login:", "username:", "user name:"]).await?;
+    stream.write_all(format!("{}\r\n", credential.username).as_bytes()).await.ok()?;
+    read_until(&mut stream, &["password:"]).await?;
+    stream.write_all(format!("{}\r\n", credential.password).as_bytes()).await.ok()?;
+
+    // Whatever arrives before the prompt settles
+    let reply = read_until(&mut stream, &["#", "$", ">", "login:", "incorrect", "failed"]).await.unwrap_or_default();
+    Some(telnet_accepted(&reply))
+}
+
+/// Whether the answer to a Telnet login is a shell prompt
+pub(super) fn telnet_accepted(reply: &str) -> bool {
+    let last = reply.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or_default();
+    // "Last login: ..." greets a successful login, so only a trailing prompt counts
+    let asks_again = ["login:", "username:", "password:"].iter().any(|prompt| last.ends_with(prompt));
+    let rejected = ["incorrect", "failed", "denied", "invalid"].iter().any(|hint| reply.contains(hint));
+    !asks_again && !rejected && ["#", "$", ">"].iter().any(|prompt| last.ends_with(prompt))
+}
+
+/// Lowercased text received until one of `markers` shows up; `None` on timeout
+async fn read_until(stream: &mut tokio::net::TcpStream, markers: &[&str]) -> Option<String> {
+    let deadline = tokio::time::Instant::now() + TELNET_TIMEOUT;
+    let mut received = Vec::new();
+    let mut buf = [0u8; 512];
+    while received.len() < 8192 {
+        let n = tokio::time::timeout_at(deadline, stream.read(&mut buf)).await.ok()?.ok()?;
+        if n == 0 {
+            return None;
+        }
+        received.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&ports::strip_telnet_commands(&received)).to_lowercase();
+        if markers.iter().any(|marker| text.trim_end().ends_with(marker) || text.contains(&format!("{}\n", marker))) {
+            return Some(text);
+        }
+    }
+    None
+}
+
+/// Finding for an accepted factory login
+pub(super) fn to_issue(login: &AcceptedLogin) -> SecurityIssue {
+    content::issue(
+        "default-password",
+        IssueSeverity::Critical,
+        &[
+            ("service", login.service.to_string()),
+            ("target", login.target.clone()),
+            ("user", login.username.clone()),
+        ],
+    )
+}
@@ -0,0 +1,344 @@
+//! Fingerprint data packs
+//!
+//! OUI additions, name patterns, model tables, Apple model identifiers, port profiles and the optional
+//! vendor privacy ratings can be updated independently of app releases. A pack is a JSON payload signed
+//! with the project's Ed25519 key:
+//!
+//! `{"payload": "<DataPack as JSON>", "signature": "<hex Ed25519 signature of payload>"}`
+//!
+//! Verified packs are stored in the app data directory and loaded at startup;
+//! their entries take precedence over the built-in tables.
+//!
+//! Packs are signed with `iot-doctor-data sign-pack <pack.json> <key.pk8> <out.json>`
+//! and published at [`DATAPACK_URL`]. `iot-doctor-data keygen <key.pk8>` creates
+//! a key pair and prints the public key to put into `DATAPACK_PUBLIC_KEY`; the
+//! private key is kept by the maintainers, never in the repository.
+
+use super::DeviceType;
+use crate::database;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use thiserror::Error;
+
+/// Where `update_datapacks` downloads the latest signed pack from
+pub const DATAPACK_URL: &str =
+    "https://raw.githubusercontent.com/Saku0512/lot-doctor/main/datapacks/fingerprint.json";
+
+/// Ed25519 public key that data packs must be signed with
+const DATAPACK_PUBLIC_KEY: [u8; 32] = [
+    0x3d, 0x67, 0xd5, 0xe6, 0x44, 0x6a, 0xd0, 0x1d, 0xc0, 0x8b, 0xef, 0xf6, 0x09, 0xe4, 0x73, 0xcb,
+    0xb3, 0x77, 0x0f, 0x08, 0xbc, 0x2e, 0xa8, 0xdb, 0xd5, 0x38, 0xb2, 0x80, 0x78, 0xf9, 0x91, 0x57,
+];
+
+static ACTIVE: RwLock<Option<DataPack>> = RwLock::new(None);
+
+/// Data pack errors
+#[derive(Error, Debug)]
+pub enum DataPackError {
+    #[error("Invalid data pack: {0}")]
+    Invalid(String),
+
+    #[error("Data pack signature verification failed")]
+    BadSignature,
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Download failed: {0}")]
+    Download(#[from] crate::vulndb::VulnDbError),
+}
+
+/// OUI prefix → vendor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OuiEntry {
+    /// "AA:BB:CC"
+    pub prefix: String,
+    pub vendor: String,
+}
+
+/// Case-insensitive name substring → device type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamePattern {
+    pub pattern: String,
+    pub device_type: DeviceType,
+}
+
+/// Model string pattern → model name and device type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub pattern: String,
+    pub model: String,
+    pub device_type: DeviceType,
+}
+
+/// Apple model identifier (e.g. `AudioAccessory5,1`) → marketing name and device type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppleModelEntry {
+    pub identifier: String,
+    pub name: String,
+    pub device_type: DeviceType,
+}
+
+/// Named list of ports to scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortProfile {
+    pub name: String,
+    pub ports: Vec<u16>,
+}
+
+/// Overall privacy rating, in the spirit of Mozilla's *Privacy Not Included
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyRating {
+    Good,
+    Acceptable,
+    Concerning,
+    /// Would carry a "*Privacy Not Included" warning
+    Poor,
+}
+
+/// Privacy characteristics of a vendor or one of its products
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyEntry {
+    /// Case-insensitive vendor name substring
+    pub vendor: String,
+    /// Case-insensitive product/name substring; vendor-wide when absent
+    #[serde(default)]
+    pub product: Option<String>,
+    pub rating: PrivacyRating,
+    /// Works only through the vendor's cloud service
+    #[serde(default)]
+    pub cloud_required: bool,
+    /// Known data-sharing incidents, one short line each
+    #[serde(default)]
+    pub incidents: Vec<String>,
+    /// Where the rating comes from
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Fingerprint data pack contents
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataPack {
+    pub version: u32,
+    pub generated_at: Option<String>,
+    pub oui: Vec<OuiEntry>,
+    pub name_patterns: Vec<NamePattern>,
+    pub models: Vec<ModelEntry>,
+    pub apple_models: Vec<AppleModelEntry>,
+    pub port_profiles: Vec<PortProfile>,
+    pub privacy: Vec<PrivacyEntry>,
+}
+
+/// Signed wrapper as distributed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedDataPack {
+    pub payload: String,
+    pub signature: String,
+}
+
+/// Version information for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataPackInfo {
+    pub version: u32,
+    pub generated_at: Option<String>,
+    pub oui_entries: usize,
+    pub name_patterns: usize,
+    pub models: usize,
+    pub apple_models: usize,
+    pub port_profiles: usize,
+    pub privacy_entries: usize,
+}
+
+fn installed_path() -> PathBuf {
+    database::app_data_dir().join("datapacks").join("fingerprint.json")
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Sign a pack's JSON with a PKCS#8 Ed25519 key, checking that it parses first
+pub fn sign(payload: String, pkcs8: &[u8]) -> Result<SignedDataPack, DataPackError> {
+    serde_json::from_str::<DataPack>(&payload).map_err(|e| DataPackError::Invalid(e.to_string()))?;
+    let key = ring::signature::Ed25519KeyPair::from_pkcs8(pkcs8)
+        .map_err(|e| DataPackError::Invalid(format!("signing key: {}", e)))?;
+    let signature = key.sign(payload.as_bytes()).as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(SignedDataPack { payload, signature })
+}
+
+/// Verify the signature and parse the payload
+pub fn verify(signed: &SignedDataPack) -> Result<DataPack, DataPackError> {
+    let signature = decode_hex(signed.signature.trim()).ok_or(DataPackError::BadSignature)?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, &DATAPACK_PUBLIC_KEY)
+        .verify(signed.payload.as_bytes(), &signature)
+        .map_err(|_| DataPackError::BadSignature)?;
+
+    serde_json::from_str(&signed.payload).map_err(|e| DataPackError::Invalid(e.to_string()))
+}
+
+/// Load the installed pack, if any, into memory
+pub fn load_installed() -> Result<Option<DataPackInfo>, DataPackError> {
+    let text = match std::fs::read_to_string(installed_path()) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let signed: SignedDataPack =
+        serde_json::from_str(&text).map_err(|e| DataPackError::Invalid(e.to_string()))?;
+
+    // Re-verify on load so a tampered file on disk is never trusted
+    let pack = verify(&signed)?;
+    let info = info_of(&pack);
+    *ACTIVE.write().unwrap() = Some(pack);
+    Ok(Some(info))
+}
+
+/// Download, verify and install the latest pack. When the installed pack is
+/// as new, it stays and its summary is returned.
+pub async fn update() -> Result<DataPackInfo, DataPackError> {
+    // A pack published minutes ago must not be hidden by the lookup cache
+    let text = crate::vulndb::client::shared().get_fresh(DATAPACK_URL).await?;
+    let signed: SignedDataPack =
+        serde_json::from_str(&text).map_err(|e| DataPackError::Invalid(e.to_string()))?;
+    let pack = verify(&signed)?;
+
+    if let Some(current) = info() {
+        if pack.version <= current.version {
+            return Ok(current);
+        }
+    }
+
+    let path = installed_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, text)?;
+
+    let info = info_of(&pack);
+    *ACTIVE.write().unwrap() = Some(pack);
+    Ok(info)
+}
+
+fn info_of(pack: &DataPack) -> DataPackInfo {
+    DataPackInfo {
+        version: pack.version,
+        generated_at: pack.generated_at.clone(),
+        oui_entries: pack.oui.len(),
+        name_patterns: pack.name_patterns.len(),
+        models: pack.models.len(),
+        apple_models: pack.apple_models.len(),
+        port_profiles: pack.port_profiles.len(),
+        privacy_entries: pack.privacy.len(),
+    }
+}
+
+/// Version of the active pack, if one is loaded
+pub fn info() -> Option<DataPackInfo> {
+    ACTIVE.read().unwrap().as_ref().map(info_of)
+}
+
+/// Vendor for an OUI prefix ("AA:BB:CC") from the active pack
+pub fn lookup_oui(prefix: &str) -> Option<String> {
+    let active = ACTIVE.read().unwrap();
+    active
+        .as_ref()?
+        .oui
+        .iter()
+        .find(|e| e.prefix.eq_ignore_ascii_case(prefix))
+        .map(|e| e.vendor.clone())
+}
+
+/// Device type for a (lowercased) device name from the active pack's name and model tables
+pub fn match_name(lower_name: &str) -> Option<DeviceType> {
+    let active = ACTIVE.read().unwrap();
+    let pack = active.as_ref()?;
+
+    pack.models
+        .iter()
+        .find(|m| lower_name.contains(&m.pattern.to_lowercase()))
+        .map(|m| m.device_type)
+        .or_else(|| {
+            pack.name_patterns
+                .iter()
+                .find(|p| lower_name.contains(&p.pattern.to_lowercase()))
+                .map(|p| p.device_type)
+        })
+}
+
+/// Model name for a (lowercased) device name from the active pack's model table
+pub fn match_model(lower_name: &str) -> Option<String> {
+    let active = ACTIVE.read().unwrap();
+    active
+        .as_ref()?
+        .models
+        .iter()
+        .find(|m| lower_name.contains(&m.pattern.to_lowercase()))
+        .map(|m| m.model.clone())
+}
+
+/// Apple model identifier entry from the active pack
+pub fn apple_model(identifier: &str) -> Option<AppleModelEntry> {
+    let active = ACTIVE.read().unwrap();
+    active
+        .as_ref()?
+        .apple_models
+        .iter()
+        .find(|m| m.identifier.eq_ignore_ascii_case(identifier))
+        .cloned()
+}
+
+/// Ports of a named profile from the active pack
+pub fn port_profile(name: &str) -> Option<Vec<u16>> {
+    let active = ACTIVE.read().unwrap();
+    active
+        .as_ref()?
+        .port_profiles
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.ports.clone())
+}
+
+/// Additional ports to scan from all of the active pack's port profiles
+pub fn profile_ports() -> Vec<u16> {
+    let active = ACTIVE.read().unwrap();
+    let mut ports: Vec<u16> = active
+        .iter()
+        .flat_map(|pack| pack.port_profiles.iter())
+        .flat_map(|profile| profile.ports.iter().copied())
+        .collect();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+/// Privacy entry for a vendor, preferring a product-specific entry matching
+/// one of the device's (lowercased) names
+pub fn privacy_entry(vendor: &str, lower_names: &[String]) -> Option<PrivacyEntry> {
+    let active = ACTIVE.read().unwrap();
+    let vendor = vendor.to_lowercase();
+    let entries: Vec<&PrivacyEntry> = active
+        .as_ref()?
+        .privacy
+        .iter()
+        .filter(|e| vendor.contains(&e.vendor.to_lowercase()))
+        .collect();
+
+    let product_match = entries.iter().find(|e| {
+        e.product.as_ref().is_some_and(|product| {
+            let product = product.to_lowercase();
+            lower_names.iter().any(|name| name.contains(&product))
+        })
+    });
+    product_match
+        .or_else(|| entries.iter().find(|e| e.product.is_none()))
+        .map(|e| (*e).clone())
+}
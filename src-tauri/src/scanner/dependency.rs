@@ -0,0 +1,200 @@
+//! Service dependency hints between devices
+//!
+//! Nothing on the wire says "this camera records to that NAS", but the
+//! services devices announce make some relationships likely: cameras record
+//! to the NVR/NAS on the network, lights and sensors are paired to a hub, and
+//! a printer advertised by a PC is shared from it. The edges are stored on the
+//! device others depend on, so its details can say what stops working if it
+//! is unplugged. They are hints, not observations.
+
+use super::{Device, DeviceType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Ports of NVR software (Synology Surveillance Station, QNAP QVR, RTSP)
+const RECORDER_PORTS: &[u16] = &[554, 9900, 38080];
+
+/// mDNS services announced by hubs that pair non-IP devices
+const HUB_SERVICES: &[(&str, &str)] = &[
+    ("_hue._tcp", "Zigbee照明"),
+    ("_meshcop._udp", "Thread機器"),
+];
+
+/// HomeKit accessory category of bridges (TXT `ci`)
+const HOMEKIT_BRIDGE_CATEGORY: &str = "2";
+
+/// How the dependent relies on the provider
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    /// Camera footage is recorded on the provider
+    Recording,
+    /// Device is paired to the provider hub
+    Hub,
+    /// Printer is shared from the provider
+    SharedPrinter,
+}
+
+/// Edge from the device holding it (the provider) to a dependent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    /// MAC of the dependent; `None` when it is not on the IP network
+    /// (USB printers, Zigbee / Thread devices)
+    pub dependent_mac: Option<String>,
+    /// Display name of the dependent
+    pub dependent: String,
+}
+
+impl DependencyKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Recording => "録画先",
+            Self::Hub => "ハブ",
+            Self::SharedPrinter => "プリンター共有",
+        }
+    }
+}
+
+impl Dependency {
+    /// What stops working without the provider
+    fn consequence(&self) -> String {
+        match self.kind {
+            DependencyKind::Recording => format!("{}の録画", self.dependent),
+            DependencyKind::Hub => format!("{}の操作", self.dependent),
+            DependencyKind::SharedPrinter => format!("{}での印刷", self.dependent),
+        }
+    }
+}
+
+fn display_name(device: &Device) -> String {
+    device.name.clone().unwrap_or_else(|| device.ip.clone())
+}
+
+fn announces(device: &Device, service: &str) -> bool {
+    device.mdns_instances.iter().any(|i| i.contains(&format!(".{}.", service)))
+}
+
+/// TXT value of any of the device's service instances
+fn txt_value<'a>(
+    device: &Device,
+    txt: &'a HashMap<String, HashMap<String, String>>,
+    service: &str,
+    key: &str,
+) -> Option<&'a str> {
+    device
+        .mdns_instances
+        .iter()
+        .filter(|i| i.contains(&format!(".{}.", service)))
+        .find_map(|i| txt.get(i)?.get(key).map(String::as_str))
+}
+
+fn edge(kind: DependencyKind, dependent: &Device) -> Dependency {
+    Dependency {
+        kind,
+        dependent_mac: Some(dependent.mac.clone()),
+        dependent: display_name(dependent),
+    }
+}
+
+/// Infer edges and attach them to the devices they point from.
+/// `txt` maps mDNS service instances to their TXT records.
+pub fn infer(devices: &mut [Device], txt: &HashMap<String, HashMap<String, String>>) {
+    let mut edges: Vec<(usize, Dependency)> = Vec::new();
+
+    // Cameras → NAS running recorder software, or the only NAS there is
+    let nas: Vec<usize> = (0..devices.len())
+        .filter(|&i| matches!(devices[i].device_type, DeviceType::Nas))
+        .collect();
+    let mut recorders: Vec<usize> = nas
+        .iter()
+        .copied()
+        .filter(|&i| devices[i].open_ports.iter().any(|p| RECORDER_PORTS.contains(&p.number)))
+        .collect();
+    if recorders.is_empty() && nas.len() == 1 {
+        recorders = nas;
+    }
+    for camera in devices.iter().filter(|d| matches!(d.device_type, DeviceType::Camera)) {
+        edges.extend(recorders.iter().map(|&i| (i, edge(DependencyKind::Recording, camera))));
+    }
+
+    for (i, device) in devices.iter().enumerate() {
+        // Hubs: paired devices that are not on the IP network, plus smart
+        // plugs from the hub's maker
+        let mut hub_of: Vec<&str> = HUB_SERVICES
+            .iter()
+            .filter(|(service, _)| announces(device, service))
+            .map(|(_, paired)| *paired)
+            .collect();
+        if txt_value(device, txt, "_hap._tcp", "ci") == Some(HOMEKIT_BRIDGE_CATEGORY) {
+            hub_of.push("HomeKitアクセサリ");
+        }
+        if !hub_of.is_empty() {
+            for paired in hub_of {
+                edges.push((
+                    i,
+                    Dependency {
+                        kind: DependencyKind::Hub,
+                        dependent_mac: None,
+                        dependent: format!("ペアリングされた{}", paired),
+                    },
+                ));
+            }
+            let same_maker = devices.iter().filter(|d| {
+                d.mac != device.mac
+                    && matches!(d.device_type, DeviceType::SmartPlug)
+                    && d.vendor.is_some()
+                    && d.vendor == device.vendor
+            });
+            edges.extend(same_maker.map(|d| (i, edge(DependencyKind::Hub, d))));
+        }
+
+        // Printers announced by a computer are shared from it
+        if matches!(device.device_type, DeviceType::Computer) {
+            for service in ["_ipp._tcp", "_printer._tcp"] {
+                let Some(instance) = device.mdns_instances.iter().find(|i| i.contains(&format!(".{}.", service))) else {
+                    continue;
+                };
+                let model = txt_value(device, txt, service, "ty");
+                let label = model.map(str::to_string).unwrap_or_else(|| {
+                    instance.split('.').next().unwrap_or(instance).to_string()
+                });
+                // A network printer the computer re-shares
+                let printer = devices.iter().find(|d| {
+                    matches!(d.device_type, DeviceType::Printer)
+                        && model.is_some_and(|m| d.name.as_deref().is_some_and(|n| n.contains(m)))
+                });
+                edges.push((
+                    i,
+                    Dependency {
+                        kind: DependencyKind::SharedPrinter,
+                        dependent_mac: printer.map(|p| p.mac.clone()),
+                        dependent: format!("プリンター「{}」", label),
+                    },
+                ));
+                break;
+            }
+        }
+    }
+
+    for (i, dependency) in edges {
+        devices[i].dependents.push(dependency);
+    }
+}
+
+/// All edges as (provider, dependency) pairs, for topology output
+pub fn edges(devices: &[Device]) -> impl Iterator<Item = (&Device, &Dependency)> {
+    devices.iter().flat_map(|d| d.dependents.iter().map(move |dep| (d, dep)))
+}
+
+/// "What breaks if I unplug this" for a device's details
+pub fn unplug_note(device: &Device) -> Option<String> {
+    if device.dependents.is_empty() {
+        return None;
+    }
+    let consequences: Vec<String> = device.dependents.iter().map(Dependency::consequence).collect();
+    Some(format!(
+        "この機器を外すと、{}ができなくなる可能性があります",
+        consequences.join("、")
+    ))
+}
@@ -0,0 +1,192 @@
+//! DHCP fingerprinting
+//!
+//! A device joining the network broadcasts a DHCP DISCOVER or REQUEST. The
+//! options it asks for (option 55, the parameter request list) and their
+//! order are fixed by its DHCP client, so the list tells Windows from an
+//! iPhone or a BusyBox camera even when the device opens no port and
+//! announces nothing. The hostname (option 12) and the vendor class
+//! (option 60) often come along.
+//!
+//! Only broadcasts are seen, and clients renewing a lease send them rarely,
+//! so every listen feeds a cache kept for the life of the app: monitoring
+//! listens between its rounds, and scans use what it caught.
+
+use super::fingerprint::{self, TypeSource};
+use super::{Device, DeviceType};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DHCP_SERVER_PORT: u16 = 67;
+
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// DHCP message types sent by clients looking for an address: DISCOVER, REQUEST, INFORM
+const CLIENT_MESSAGE_TYPES: [u8; 3] = [1, 3, 8];
+
+const OPTION_HOSTNAME: u8 = 12;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_VENDOR_CLASS: u8 = 60;
+const OPTION_END: u8 = 255;
+
+pub const OS_MACOS: &str = "macOS";
+pub const OS_IOS: &str = "iOS";
+pub const OS_ANDROID: &str = "Android";
+
+/// Parameter request lists of common DHCP clients: (list, OS, device type)
+const FINGERPRINTS: &[(&str, &str, Option<DeviceType>)] = &[
+    ("1,3,6,15,31,33,43,44,46,47,119,121,249,252", fingerprint::OS_WINDOWS, Some(DeviceType::Computer)),
+    ("1,15,3,6,44,46,47,31,33,121,249,43", fingerprint::OS_WINDOWS, Some(DeviceType::Computer)),
+    ("1,15,3,6,44,46,47,31,33,121,249,43,252", fingerprint::OS_WINDOWS, Some(DeviceType::Computer)),
+    ("1,121,3,6,15,119,252,95,44,46", OS_MACOS, Some(DeviceType::Computer)),
+    ("1,121,3,6,15,108,114,119,162,252,95,44,46", OS_MACOS, Some(DeviceType::Computer)),
+    ("1,121,3,6,15,119,252", OS_IOS, Some(DeviceType::Smartphone)),
+    ("1,121,3,6,15,108,114,119,252", OS_IOS, Some(DeviceType::Smartphone)),
+    ("1,3,6,15,26,28,51,58,59,43", OS_ANDROID, Some(DeviceType::Smartphone)),
+    ("1,3,6,15,26,28,51,58,59,43,114", OS_ANDROID, Some(DeviceType::Smartphone)),
+    ("1,3,6,15,26,28,51,58,59,43,114,108", OS_ANDROID, Some(DeviceType::Smartphone)),
+    ("1,28,2,3,15,6,119,12,44,47,26,121,42", fingerprint::OS_LINUX, None),
+    // BusyBox udhcpc: cameras, NAS, routers, set-top boxes
+    ("1,3,6,12,15,28,42", fingerprint::OS_EMBEDDED_LINUX, None),
+    ("1,3,6,12,15,28,40,41,42", fingerprint::OS_EMBEDDED_LINUX, None),
+    // lwIP: ESP8266/ESP32 plugs, bulbs and sensors
+    ("1,3,28,6", fingerprint::OS_NETWORK, None),
+];
+
+/// Vendor class prefixes, for clients whose list is not in [`FINGERPRINTS`]
+const VENDOR_CLASSES: &[(&str, &str, Option<DeviceType>)] = &[
+    ("MSFT", fingerprint::OS_WINDOWS, Some(DeviceType::Computer)),
+    ("android-dhcp", OS_ANDROID, Some(DeviceType::Smartphone)),
+    ("udhcp", fingerprint::OS_EMBEDDED_LINUX, None),
+    ("dhcpcd", fingerprint::OS_LINUX, None),
+];
+
+/// Fingerprints caught so far, by lowercase MAC address
+static SEEN: Mutex<Option<HashMap<String, DhcpFingerprint>>> = Mutex::new(None);
+
+/// What a device's DHCP client sent
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DhcpFingerprint {
+    /// Option 55, in the order asked
+    pub parameter_list: Vec<u8>,
+    /// Option 12
+    pub hostname: Option<String>,
+    /// Option 60, e.g. "MSFT 5.0", "android-dhcp-13"
+    pub vendor_class: Option<String>,
+}
+
+impl DhcpFingerprint {
+    /// Parameter request list as the fingerprint databases write it, e.g. "1,3,6,15"
+    pub fn list(&self) -> String {
+        self.parameter_list.iter().map(u8::to_string).collect::<Vec<_>>().join(",")
+    }
+
+    /// OS and device type of the DHCP client
+    pub fn classify(&self) -> Option<(&'static str, Option<DeviceType>)> {
+        let list = self.list();
+        let known = FINGERPRINTS.iter().find(|(known_list, _, _)| *known_list == list);
+        let class = self.vendor_class.as_deref().unwrap_or_default();
+        known
+            .or_else(|| VENDOR_CLASSES.iter().find(|(prefix, _, _)| class.starts_with(prefix)))
+            .map(|(_, os, device_type)| (*os, *device_type))
+    }
+}
+
+/// Collect the DHCP requests broadcast during `duration`, by lowercase MAC
+/// address, and add them to the cache
+pub async fn listen(duration: Duration) -> HashMap<String, DhcpFingerprint> {
+    let mut fingerprints = HashMap::new();
+    let socket = match listen_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to listen for DHCP broadcasts: {}", e);
+            return fingerprints;
+        }
+    };
+
+    let deadline = tokio::time::Instant::now() + duration;
+    let mut buf = [0u8; 1500];
+    while let Ok(Ok((len, _))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        if let Some((mac, fingerprint)) = parse_request(&buf[..len]) {
+            fingerprints.insert(mac, fingerprint);
+        }
+    }
+
+    SEEN.lock().unwrap().get_or_insert_with(HashMap::new).extend(fingerprints.clone());
+    fingerprints
+}
+
+/// Fingerprint caught earlier for the MAC address
+pub fn seen(mac: &str) -> Option<DhcpFingerprint> {
+    SEEN.lock().unwrap().as_ref()?.get(&mac.to_lowercase()).cloned()
+}
+
+/// The server port; the port is shared with a DHCP server on this machine
+fn listen_socket() -> std::io::Result<tokio::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_broadcast(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DHCP_SERVER_PORT).into())?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket.into())
+}
+
+/// (client MAC address, fingerprint) of a client's DHCP request
+pub(super) fn parse_request(packet: &[u8]) -> Option<(String, DhcpFingerprint)> {
+    // op 1 (BOOTREQUEST), htype 1 (Ethernet), hlen 6
+    if packet.get(..3)? != [1, 1, 6] || packet.get(236..240)? != MAGIC_COOKIE {
+        return None;
+    }
+    let mac = packet[28..34].iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":");
+
+    let mut fingerprint = DhcpFingerprint::default();
+    let mut message_type = None;
+    let mut options = &packet[240..];
+    while let [code, rest @ ..] = options {
+        match *code {
+            OPTION_END => break,
+            // Pad
+            0 => {
+                options = rest;
+                continue;
+            }
+            _ => {}
+        }
+        let (&len, rest) = rest.split_first()?;
+        let value = rest.get(..len as usize)?;
+        match *code {
+            OPTION_MESSAGE_TYPE => message_type = value.first().copied(),
+            OPTION_PARAMETER_LIST => fingerprint.parameter_list = value.to_vec(),
+            OPTION_HOSTNAME => fingerprint.hostname = text(value),
+            OPTION_VENDOR_CLASS => fingerprint.vendor_class = text(value),
+            _ => {}
+        }
+        options = &rest[len as usize..];
+    }
+
+    let from_client = message_type.is_some_and(|t| CLIENT_MESSAGE_TYPES.contains(&t));
+    (from_client && !fingerprint.parameter_list.is_empty()).then_some((mac, fingerprint))
+}
+
+/// Printable text of a string option
+fn text(value: &[u8]) -> Option<String> {
+    let text: String = String::from_utf8_lossy(value).chars().filter(|c| !c.is_control()).collect();
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Record the device's DHCP fingerprint, with the OS and type it points to
+pub fn apply(device: &mut Device, dhcp: DhcpFingerprint) {
+    if let Some((os, device_type)) = dhcp.classify() {
+        device.os_guess = Some(os.to_string());
+        if let Some(device_type) = device_type {
+            let evidence = format!("DHCP: {}", dhcp.list());
+            fingerprint::record_type(device, device_type, TypeSource::Dhcp, evidence);
+        }
+    }
+    device.dhcp = Some(dhcp);
+}
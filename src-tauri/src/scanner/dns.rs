@@ -0,0 +1,265 @@
+//! DNS resolver audit (Level 2)
+//!
+//! Every device on the network resolves names through the resolver DHCP
+//! hands out, usually the router. The resolvers this machine was given are
+//! checked against the ranges DNS-hijacking malware pointed victims to, and
+//! against the providers a home network normally uses. The LAN resolver is
+//! then asked for a public name that points at itself: a resolver without
+//! rebinding protection passes the answer on, which lets a web page reach
+//! the router's admin UI through the browser of anyone on the network.
+//! Whether it offers encrypted DNS (DoT or DoH) is checked last. The results
+//! concern the whole network, so they come out as [`NetworkIssue`]s.
+
+use super::{arp, firmware, net, platform, topology, IssueSeverity, NetworkIssue, SecurityIssue};
+use crate::vulndb::content;
+use base64::Engine;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+const DNS_PORT: u16 = 53;
+
+/// DNS over TLS
+const DOT_PORT: u16 = 853;
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Wildcard DNS service: a name with an address in it resolves to that
+/// address, e.g. "192-168-1-1.sslip.io" → 192.168.1.1
+const REBIND_DOMAIN: &str = "sslip.io";
+
+/// Public address of the control query, which any recursive resolver answers
+const CONTROL_ADDRESS: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 1);
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+/// Public resolvers of well-known providers
+const KNOWN_RESOLVERS: &[(&str, &str)] = &[
+    ("8.8.8.8", "Google Public DNS"),
+    ("8.8.4.4", "Google Public DNS"),
+    ("1.1.1.1", "Cloudflare"),
+    ("1.0.0.1", "Cloudflare"),
+    ("1.1.1.2", "Cloudflare"),
+    ("1.1.1.3", "Cloudflare"),
+    ("9.9.9.9", "Quad9"),
+    ("149.112.112.112", "Quad9"),
+    ("208.67.222.222", "OpenDNS"),
+    ("208.67.220.220", "OpenDNS"),
+    ("94.140.14.14", "AdGuard DNS"),
+    ("94.140.15.15", "AdGuard DNS"),
+    ("185.228.168.9", "CleanBrowsing"),
+];
+
+/// Networks of the rogue resolvers DNSChanger malware set on infected
+/// computers and routers: (network, prefix length)
+const ROGUE_RANGES: &[(Ipv4Addr, u8)] = &[
+    (Ipv4Addr::new(85, 255, 112, 0), 20),
+    (Ipv4Addr::new(67, 210, 0, 0), 20),
+    (Ipv4Addr::new(93, 188, 160, 0), 21),
+    (Ipv4Addr::new(77, 67, 83, 0), 24),
+    (Ipv4Addr::new(213, 109, 64, 0), 20),
+    (Ipv4Addr::new(64, 28, 176, 0), 20),
+];
+
+/// Check the resolvers this machine uses and the LAN resolver among them,
+/// or `gateway` when none is on the LAN. Resolvers at `excluded` addresses
+/// are not queried.
+pub async fn audit(gateway: Option<Ipv4Addr>, excluded: &[String]) -> Vec<NetworkIssue> {
+    let mut resolvers: Vec<IpAddr> = Vec::new();
+    let configured = tokio::task::spawn_blocking(|| platform::current().dns_servers()).await;
+    for resolver in configured.ok().and_then(Result::ok).unwrap_or_default() {
+        if !resolvers.contains(&resolver) {
+            resolvers.push(resolver);
+        }
+    }
+
+    // The resolvers are this machine's settings
+    let local_ip: Vec<String> = arp::get_local_interface().map(|(_, ip, _)| ip).into_iter().collect();
+    let mut issues: Vec<NetworkIssue> = resolvers
+        .iter()
+        .filter_map(|resolver| match resolver {
+            IpAddr::V4(ip) => resolver_issue(*ip),
+            IpAddr::V6(_) => None,
+        })
+        .map(|issue| NetworkIssue {
+            issue,
+            devices: local_ip.clone(),
+        })
+        .collect();
+
+    let lan = resolvers
+        .iter()
+        .find_map(|resolver| match resolver {
+            IpAddr::V4(ip) if ip.is_private() => Some(*ip),
+            _ => None,
+        })
+        .or(gateway)
+        .filter(|ip| !excluded.contains(&ip.to_string()));
+    if let Some(lan) = lan {
+        issues.extend(check_lan_resolver(lan).await.into_iter().map(|issue| NetworkIssue {
+            issue,
+            devices: vec![lan.to_string()],
+        }));
+    }
+    issues
+}
+
+/// Finding for a public resolver in a rogue range or of an unknown provider
+pub(super) fn resolver_issue(resolver: Ipv4Addr) -> Option<SecurityIssue> {
+    let params = [("resolver", resolver.to_string())];
+    if is_rogue(resolver) {
+        return Some(content::issue("dns-rogue-resolver", IssueSeverity::High, &params));
+    }
+    let public = !(resolver.is_private()
+        || resolver.is_loopback()
+        || resolver.is_link_local()
+        || resolver.is_unspecified()
+        || topology::is_cgnat(resolver));
+    (public && provider(resolver).is_none()).then(|| content::issue("dns-unknown-resolver", IssueSeverity::Low, &params))
+}
+
+/// Provider of a well-known public resolver
+fn provider(resolver: Ipv4Addr) -> Option<&'static str> {
+    let address = resolver.to_string();
+    KNOWN_RESOLVERS.iter().find(|(ip, _)| *ip == address).map(|(_, provider)| *provider)
+}
+
+/// Whether the resolver is in a range DNSChanger malware used
+pub(super) fn is_rogue(resolver: Ipv4Addr) -> bool {
+    ROGUE_RANGES.iter().any(|(network, prefix)| {
+        let mask = u32::MAX << (32 - prefix);
+        u32::from(resolver) & mask == u32::from(*network)
+    })
+}
+
+/// Rebinding protection and encrypted DNS of a resolver on the LAN
+async fn check_lan_resolver(resolver: Ipv4Addr) -> Vec<SecurityIssue> {
+    // Nothing to check on a gateway that runs no resolver
+    let Some(control) = query_a(resolver, &rebind_name(CONTROL_ADDRESS)).await else {
+        return Vec::new();
+    };
+    // A resolver that cannot look up public names (no internet, a filter) tells nothing
+    let rebinds = control.contains(&CONTROL_ADDRESS)
+        && query_a(resolver, &rebind_name(resolver)).await.is_some_and(|answers| answers.contains(&resolver));
+    let encrypted = offers_encrypted_dns(resolver).await;
+    lan_resolver_issues(resolver, rebinds, encrypted)
+}
+
+/// Findings for a LAN resolver that passes on answers pointing into the LAN
+/// (`rebinds`) or offers no encrypted DNS
+pub(super) fn lan_resolver_issues(resolver: Ipv4Addr, rebinds: bool, encrypted: bool) -> Vec<SecurityIssue> {
+    let params = [("resolver", resolver.to_string())];
+    let mut issues = Vec::new();
+    if rebinds {
+        issues.push(content::issue("dns-rebinding", IssueSeverity::Medium, &params));
+    }
+    if !encrypted {
+        issues.push(content::issue("dns-unencrypted", IssueSeverity::Info, &params));
+    }
+    issues
+}
+
+/// Public name that resolves to `address`
+pub(super) fn rebind_name(address: Ipv4Addr) -> String {
+    format!("{}.{}", address.to_string().replace('.', "-"), REBIND_DOMAIN)
+}
+
+/// Whether the resolver answers DNS over TLS or DNS over HTTPS
+async fn offers_encrypted_dns(resolver: Ipv4Addr) -> bool {
+    let dot = SocketAddr::new(IpAddr::V4(resolver), DOT_PORT);
+    if net::tcp_connect_async(dot, QUERY_TIMEOUT).await.is_ok() {
+        return true;
+    }
+
+    let Some(client) = firmware::client() else {
+        return false;
+    };
+    let query = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(build_query(0, &rebind_name(CONTROL_ADDRESS)));
+    let url = format!("https://{}/dns-query?dns={}", resolver, query);
+    let Ok(response) = client.get(url).header(reqwest::header::ACCEPT, "application/dns-message").send().await else {
+        return false;
+    };
+    // Admin pages answer any path; only a DNS message counts
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    response.status().is_success() && content_type.is_some_and(|t| t.starts_with("application/dns-message"))
+}
+
+/// A records the resolver answers for `name`; empty for NXDOMAIN or a
+/// refusal, `None` when it does not answer
+async fn query_a(resolver: Ipv4Addr, name: &str) -> Option<Vec<Ipv4Addr>> {
+    let socket = net::udp_bind(net::ANY).await.ok()?;
+    let random = uuid::Uuid::new_v4();
+    let id = u16::from_be_bytes([random.as_bytes()[0], random.as_bytes()[1]]);
+    let target = SocketAddr::new(IpAddr::V4(resolver), DNS_PORT);
+    socket.send_to(&build_query(id, name), target).await.ok()?;
+
+    let deadline = tokio::time::Instant::now() + QUERY_TIMEOUT;
+    let mut buf = [0u8; 1500];
+    while let Ok(Ok((len, from))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        if from != target {
+            continue;
+        }
+        if let Some(answers) = parse_response(&buf[..len], id) {
+            return Some(answers);
+        }
+    }
+    None
+}
+
+/// Recursive query for the A records of `name`
+pub(super) fn build_query(id: u16, name: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + name.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    // Flags: recursion desired; one question
+    packet.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_A.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet
+}
+
+/// A records of the response to query `id`; `None` if it is not one
+pub(super) fn parse_response(packet: &[u8], id: u16) -> Option<Vec<Ipv4Addr>> {
+    let header = packet.get(..12)?;
+    let is_response = header[2] & 0x80 != 0;
+    if u16::from_be_bytes([header[0], header[1]]) != id || !is_response {
+        return None;
+    }
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(packet, offset)? + 4;
+    }
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        offset = skip_name(packet, offset)?;
+        let record = packet.get(offset..offset + 10)?;
+        let record_type = u16::from_be_bytes([record[0], record[1]]);
+        let length = u16::from_be_bytes([record[8], record[9]]) as usize;
+        let data = packet.get(offset + 10..offset + 10 + length)?;
+        // CNAMEs come before the addresses they lead to
+        if record_type == TYPE_A && length == 4 {
+            addresses.push(Ipv4Addr::new(data[0], data[1], data[2], data[3]));
+        }
+        offset += 10 + length;
+    }
+    Some(addresses)
+}
+
+/// Offset past the name at `offset`, which may end in a compression pointer
+fn skip_name(packet: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(offset)?;
+        match len {
+            0 => return Some(offset + 1),
+            _ if len & 0xC0 == 0xC0 => return Some(offset + 2),
+            _ => offset += 1 + len as usize,
+        }
+    }
+}
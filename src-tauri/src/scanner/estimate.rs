@@ -0,0 +1,150 @@
+//! Scan duration estimation
+//!
+//! Computes how many hosts and probes a scan will touch and roughly how long
+//! it will take from the same timeouts and concurrency the scan uses, so the
+//! UI can warn before a long scan starts.
+
+use super::{arp, ports, ScanLevel, ScanOptions};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Assumed device count when there is no previous scan to go by
+const DEFAULT_EXPECTED_DEVICES: usize = 15;
+
+/// Typical round trip of an external exposure probe
+const EXPOSURE_PROBE_TIME: Duration = Duration::from_secs(1);
+
+/// Time spent in one phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseEstimate {
+    pub phase: String,
+    pub seconds: u64,
+}
+
+/// Expected cost of a scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanEstimate {
+    pub level: ScanLevel,
+    /// Addresses swept during discovery
+    pub host_count: usize,
+    /// Devices expected to answer
+    pub expected_devices: usize,
+    pub ports_per_device: usize,
+    /// Total packets/connections sent
+    pub probe_count: usize,
+    pub estimated_seconds: u64,
+    pub phases: Vec<PhaseEstimate>,
+    /// Scan time limit, when the estimate exceeds it and lower-priority
+    /// devices will get reduced coverage
+    pub budget_seconds: Option<u64>,
+}
+
+fn batches(items: usize, batch_size: usize) -> u32 {
+    let batch_size = batch_size.max(1);
+    ((items + batch_size - 1) / batch_size) as u32
+}
+
+/// Estimate a scan at `level` of `options.targets` (the default interface's
+/// network when empty) using the ports of `options.port_profile`
+pub fn estimate(level: ScanLevel, options: &ScanOptions) -> ScanEstimate {
+    let host_count = arp::subnet_host_count(&options.targets, &options.sweep).unwrap_or(254);
+    let expected_devices = if options.known_devices.is_empty() {
+        DEFAULT_EXPECTED_DEVICES
+    } else {
+        options.known_devices.len()
+    }
+    .min(host_count);
+
+    // Nothing is sent; discovery only listens
+    if options.strictly_passive {
+        let listen = super::passive::LISTEN_WINDOW.as_secs();
+        return ScanEstimate {
+            level: ScanLevel::Level1,
+            host_count: 0,
+            expected_devices,
+            ports_per_device: 0,
+            probe_count: 0,
+            estimated_seconds: listen,
+            phases: vec![PhaseEstimate {
+                phase: "機器からの発信の待ち受け".to_string(),
+                seconds: listen,
+            }],
+            budget_seconds: None,
+        };
+    }
+
+    let mut phases = Vec::new();
+    let mut probe_count = host_count;
+
+    // Ping sweep runs alongside the mDNS/SSDP listening window
+    let sweep = arp::SWEEP_TIMEOUT * batches(host_count, arp::SWEEP_BATCH_SIZE);
+    phases.push(PhaseEstimate {
+        phase: "ネットワーク検索".to_string(),
+        seconds: sweep.max(super::DISCOVERY_WINDOW).as_secs(),
+    });
+    phases.push(PhaseEstimate {
+        phase: "デバイス名の解決".to_string(),
+        seconds: super::NBNS_TIMEOUT.as_secs(),
+    });
+    probe_count += expected_devices;
+
+    let port_list = ports::ports_to_scan(&options.port_config, options.port_profile.as_deref());
+    let ports_per_device = if matches!(level, ScanLevel::Level1) { 0 } else { port_list.len() };
+
+    if matches!(level, ScanLevel::Level2 | ScanLevel::Level3) {
+        // Devices are scanned at once: bounded by the pacing of each device, or
+        // by the shared connection slots when every probe times out
+        let config = &options.port_config;
+        let pacing = match config.per_host_rate {
+            0 => Duration::ZERO,
+            rate => Duration::from_secs(1) * ports_per_device as u32 / rate,
+        };
+        let slots = ports::CONNECT_TIMEOUT * batches(expected_devices * ports_per_device, config.max_connections);
+        phases.push(PhaseEstimate {
+            phase: "ポートスキャン".to_string(),
+            // UDP probes follow the TCP ones and wait out their retries
+            seconds: (pacing.max(slots) + ports::CONNECT_TIMEOUT + ports::UDP_TIMEOUT * ports::UDP_ATTEMPTS).as_secs(),
+        });
+        probe_count += expected_devices * (ports_per_device + ports::udp_ports().len());
+
+        phases.push(PhaseEstimate {
+            phase: "ルーター設定の確認".to_string(),
+            seconds: (super::GATEWAY_PROBE_TIMEOUT + arp::SWEEP_TIMEOUT).as_secs(),
+        });
+        probe_count += 3;
+
+        if options.exposure.endpoint().is_some() {
+            let probes = expected_devices * ports_per_device;
+            phases.push(PhaseEstimate {
+                phase: "インターネット側からの到達性確認".to_string(),
+                seconds: (EXPOSURE_PROBE_TIME * probes as u32).as_secs(),
+            });
+            probe_count += probes;
+        }
+    }
+
+    // Only runs when devices from the previous scan are missing; worst case shown
+    if !options.known_devices.is_empty() {
+        phases.push(PhaseEstimate {
+            phase: "応答のない機器の再確認（最大）".to_string(),
+            seconds: super::REVERIFY_WINDOW.as_secs(),
+        });
+    }
+
+    let estimated_seconds = phases.iter().map(|p| p.seconds).sum();
+    let budget_seconds = options
+        .max_duration
+        .map(|d| d.as_secs())
+        .filter(|&budget| budget < estimated_seconds);
+
+    ScanEstimate {
+        level,
+        host_count,
+        expected_devices,
+        ports_per_device,
+        probe_count,
+        estimated_seconds: budget_seconds.unwrap_or(estimated_seconds),
+        phases,
+        budget_seconds,
+    }
+}
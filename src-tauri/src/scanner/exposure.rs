@@ -0,0 +1,264 @@
+//! External exposure checks (opt-in)
+//!
+//! A scan running inside the LAN cannot tell whether a device is reachable
+//! from the internet. When enabled, we ask a user-configured probe service
+//! outside the network to attempt connections on our behalf:
+//!
+//! `GET {probe_url}?host=<address>&port=<port>` → `{"reachable": true|false}`
+//!
+//! With IPv6 there is usually no NAT, so a device's global address may be
+//! directly reachable unless the router's IPv6 firewall blocks inbound traffic.
+//!
+//! Over IPv4, the ports the router forwards (UPnP IGD mappings) are probed on
+//! the network's public address. A forward can still be dead: the provider may
+//! block the port or put the router behind its own NAT. When the probe
+//! service cannot answer, a connection from inside to the public address
+//! (NAT loopback) at least shows the router forwards it.
+
+use super::portmap::PortMapping;
+use super::{net, topology, wan, Device, DeviceType, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Ports probed when a device's open ports are unknown
+const DEFAULT_PROBE_PORTS: &[u16] = &[22, 23, 80, 443, 554, 8080];
+
+/// Most forwarded ports probed in one scan
+const MAX_FORWARD_PROBES: usize = 32;
+
+/// Pause between probes of forwarded ports, so the probe service is not flooded
+const PROBE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Connect timeout of a NAT loopback connection
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Exposure check settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExposureSettings {
+    /// Explicit opt-in; probes are sent from outside the network, and the
+    /// public IP is looked up when the router does not report it
+    pub enabled: bool,
+    /// Probe service endpoint
+    pub probe_url: Option<String>,
+}
+
+impl ExposureSettings {
+    /// Probe endpoint, if the check is enabled and configured
+    pub fn endpoint(&self) -> Option<&str> {
+        if self.enabled {
+            self.probe_url.as_deref().filter(|u| !u.is_empty())
+        } else {
+            None
+        }
+    }
+}
+
+/// How a forwarded port was found reachable
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeMethod {
+    /// The probe service connected from outside the network
+    ProbeService,
+    /// A connection from inside to the public address was forwarded
+    Loopback,
+}
+
+/// A port the router forwards to a device, and whether the internet reaches it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardedPort {
+    /// Public address and port, e.g. "203.0.113.5:8080"
+    pub address: String,
+    /// "TCP" or "UDP"
+    pub transport: String,
+    pub internal_port: u16,
+    /// `None` when it could not be told (UDP, or neither probe answered)
+    pub reachable: Option<bool>,
+    pub method: Option<ProbeMethod>,
+}
+
+#[derive(Deserialize)]
+struct ProbeResponse {
+    reachable: bool,
+}
+
+/// Whether an IPv6 address is globally routable (not link-local, ULA, etc.)
+pub fn is_global_ipv6(addr: &Ipv6Addr) -> bool {
+    let first = addr.segments()[0];
+    !addr.is_loopback()
+        && !addr.is_unspecified()
+        && !addr.is_multicast()
+        && (first & 0xffc0) != 0xfe80 // link-local
+        && (first & 0xfe00) != 0xfc00 // unique local
+}
+
+/// Ask the probe service whether host:port accepts inbound connections
+async fn probe(client: &reqwest::Client, endpoint: &str, host: &str, port: u16) -> Option<bool> {
+    let response = client
+        .get(endpoint)
+        .query(&[("host", host), ("port", &port.to_string())])
+        .send()
+        .await
+        .ok()?;
+    let body: ProbeResponse = serde_json::from_str(&response.text().await.ok()?).ok()?;
+    Some(body.reachable)
+}
+
+/// Ask the probe service whether a single host:port is reachable from outside,
+/// or `None` when the check is disabled or the probe failed
+pub async fn is_reachable(settings: &ExposureSettings, host: &str, port: u16) -> Option<bool> {
+    let endpoint = settings.endpoint()?;
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().ok()?;
+    probe(&client, endpoint, host, port).await
+}
+
+/// Probe devices' global IPv6 addresses from the external vantage point
+/// (skipping the `excluded` MACs)
+pub async fn check_ipv6_exposure(devices: &mut [Device], settings: &ExposureSettings, excluded: &[String]) {
+    let Some(endpoint) = settings.endpoint() else {
+        return;
+    };
+    let Ok(client) = reqwest::Client::builder().timeout(Duration::from_secs(10)).build() else {
+        return;
+    };
+
+    let devices = devices.iter_mut().filter(|d| !excluded.iter().any(|m| m.eq_ignore_ascii_case(&d.mac)));
+    for device in devices {
+        let ports: Vec<u16> = if device.open_ports.is_empty() {
+            DEFAULT_PROBE_PORTS.to_vec()
+        } else {
+            device.open_ports.iter().map(|p| p.number).collect()
+        };
+
+        let mut reachable = Vec::new();
+        for addr in &device.ipv6_addresses {
+            let is_global = addr.parse::<Ipv6Addr>().is_ok_and(|a| is_global_ipv6(&a));
+            if !is_global {
+                continue;
+            }
+            for &port in &ports {
+                if probe(&client, endpoint, addr, port).await == Some(true) {
+                    reachable.push(format!("[{}]:{}", addr, port));
+                }
+            }
+        }
+
+        if !reachable.is_empty() {
+            device.issues.push(ipv6_exposure_issue(device.device_type, &reachable));
+        }
+    }
+}
+
+pub(super) fn ipv6_exposure_issue(device_type: DeviceType, reachable: &[String]) -> SecurityIssue {
+    content::issue("ipv6-exposed", severity(device_type), &[("addresses", reachable.join(", "))])
+}
+
+/// PCs and phones run their own firewalls; IoT devices usually do not
+fn severity(device_type: DeviceType) -> IssueSeverity {
+    match device_type {
+        DeviceType::Computer | DeviceType::Smartphone => IssueSeverity::High,
+        _ => IssueSeverity::Critical,
+    }
+}
+
+/// Test whether the ports the router forwards are reachable on the network's
+/// public address, and record the result on the devices they lead to
+/// (skipping the `excluded` MACs). `wan_address` is what the router reported.
+pub async fn check_external_exposure(
+    devices: &mut [Device],
+    mappings: &[PortMapping],
+    wan_address: Option<&str>,
+    settings: &ExposureSettings,
+    excluded: &[String],
+) {
+    let Some(endpoint) = settings.endpoint() else {
+        return;
+    };
+    if mappings.is_empty() {
+        return;
+    }
+    let Some(public) = public_address(wan_address).await else {
+        return;
+    };
+    let Ok(client) = reqwest::Client::builder().timeout(Duration::from_secs(10)).build() else {
+        return;
+    };
+
+    for mapping in mappings.iter().take(MAX_FORWARD_PROBES) {
+        let Some(device) = devices
+            .iter_mut()
+            .find(|d| d.ip == mapping.internal_client && !excluded.iter().any(|m| m.eq_ignore_ascii_case(&d.mac)))
+        else {
+            continue;
+        };
+
+        // The probe service and a loopback connection only speak TCP
+        let (reachable, method) = if mapping.transport.eq_ignore_ascii_case("TCP") {
+            let probed = probe(&client, endpoint, &public, mapping.external_port).await;
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            match probed {
+                Some(reachable) => (Some(reachable), Some(ProbeMethod::ProbeService)),
+                None if loopback_connects(&public, mapping.external_port).await => {
+                    (Some(true), Some(ProbeMethod::Loopback))
+                }
+                None => (None, None),
+            }
+        } else {
+            (None, None)
+        };
+        device.forwarded_ports.push(ForwardedPort {
+            address: format!("{}:{}", public, mapping.external_port),
+            transport: mapping.transport.clone(),
+            internal_port: mapping.internal_port,
+            reachable,
+            method,
+        });
+    }
+
+    for device in devices.iter_mut() {
+        if let Some(issue) = forward_exposure_issue(device) {
+            device.issues.push(issue);
+        }
+    }
+}
+
+/// The router's WAN address when it is public; behind another NAT, the
+/// address the outside sees
+async fn public_address(wan_address: Option<&str>) -> Option<String> {
+    let reported = wan_address
+        .and_then(|a| a.parse::<Ipv4Addr>().ok())
+        .filter(|ip| !ip.is_private() && !ip.is_loopback() && !ip.is_unspecified() && !topology::is_cgnat(*ip));
+    match reported {
+        Some(ip) => Some(ip.to_string()),
+        None => wan::public_ip().await,
+    }
+}
+
+/// Whether a connection to our own public address goes through. Routers that
+/// do not support NAT loopback refuse it even for live forwards.
+async fn loopback_connects(public: &str, port: u16) -> bool {
+    let Ok(ip) = public.parse::<IpAddr>() else {
+        return false;
+    };
+    net::tcp_connect_async(SocketAddr::new(ip, port), LOOPBACK_TIMEOUT).await.is_ok()
+}
+
+/// Finding for the device's forwarded ports that the internet reaches
+pub(super) fn forward_exposure_issue(device: &Device) -> Option<SecurityIssue> {
+    let reachable: Vec<String> = device
+        .forwarded_ports
+        .iter()
+        .filter(|p| p.reachable == Some(true))
+        .map(|p| format!("{} → {}:{}", p.address, device.ip, p.internal_port))
+        .collect();
+    if reachable.is_empty() {
+        return None;
+    }
+    Some(content::issue(
+        "port-forward-reachable",
+        severity(device.device_type),
+        &[("addresses", reachable.join(", "))],
+    ))
+}
@@ -1,254 +1,11 @@
 //! Device fingerprinting and identification
 
-use super::{Device, DeviceType};
-
-/// OUI (Organizationally Unique Identifier) database for vendor lookup
-/// Format: First 3 bytes of MAC address -> Vendor name
-const OUI_DATABASE: &[(&str, &str)] = &[
-    // Buffalo
-    ("00:1A:2B", "Buffalo Inc."),
-    ("00:26:AB", "Buffalo Inc."),
-    ("AC:22:0B", "Buffalo Inc."),
-    ("10:6F:3F", "Buffalo Inc."),
-    // I-O DATA
-    ("18:C2:BF", "I-O DATA DEVICE, INC."),
-    ("00:A0:B0", "I-O DATA DEVICE, INC."),
-    // TP-LINK
-    ("40:8D:5C", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    ("50:C7:BF", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    ("FC:EC:DA", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    ("30:B5:C2", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    ("60:E3:27", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    ("B0:4E:26", "TP-LINK TECHNOLOGIES CO.,LTD."),
-    // Google
-    ("A4:77:33", "Google, Inc."),
-    ("D4:F5:47", "Google, Inc."),
-    ("F4:F5:D8", "Google, Inc."),
-    ("54:60:09", "Google, Inc."),
-    ("30:FD:38", "Google, Inc."),
-    ("48:D6:D5", "Google, Inc."),
-    ("E4:F0:42", "Google, Inc."),
-    ("6C:AD:F8", "Google, Inc."),
-    // Amazon
-    ("44:65:0D", "Amazon Technologies Inc."),
-    ("68:54:FD", "Amazon Technologies Inc."),
-    ("A0:02:DC", "Amazon Technologies Inc."),
-    ("FC:65:DE", "Amazon Technologies Inc."),
-    ("40:B4:CD", "Amazon Technologies Inc."),
-    ("74:C2:46", "Amazon Technologies Inc."),
-    ("84:D6:D0", "Amazon Technologies Inc."),
-    ("F0:F0:A4", "Amazon Technologies Inc."),
-    // Philips
-    ("00:17:88", "Philips Lighting BV"),
-    // Arcadyan
-    ("BC:30:D9", "Arcadyan Technology Corporation"),
-    // Planex
-    ("00:90:CC", "Planex Communications Inc."),
-    ("00:22:CF", "PLANEX COMMUNICATIONS INC."),
-    // Apple
-    ("00:1C:B3", "Apple, Inc."),
-    ("28:CF:DA", "Apple, Inc."),
-    ("3C:06:30", "Apple, Inc."),
-    ("D0:03:4B", "Apple, Inc."),
-    ("F0:18:98", "Apple, Inc."),
-    ("A4:83:E7", "Apple, Inc."),
-    ("DC:A4:CA", "Apple, Inc."),
-    ("F0:D4:F6", "Apple, Inc."),
-    ("A8:66:7F", "Apple, Inc."),
-    ("14:7D:DA", "Apple, Inc."),
-    ("88:66:A5", "Apple, Inc."),
-    ("8C:85:90", "Apple, Inc."),
-    ("AC:BC:32", "Apple, Inc."),
-    ("C8:69:CD", "Apple, Inc."),
-    ("E0:B5:2D", "Apple, Inc."),
-    ("F4:5C:89", "Apple, Inc."),
-    ("10:DD:B1", "Apple, Inc."),
-    ("1C:91:48", "Apple, Inc."),
-    ("34:C0:59", "Apple, Inc."),
-    ("40:A6:D9", "Apple, Inc."),
-    ("48:A1:95", "Apple, Inc."),
-    ("54:4E:90", "Apple, Inc."),
-    ("60:03:08", "Apple, Inc."),
-    ("6C:96:CF", "Apple, Inc."),
-    ("70:DE:E2", "Apple, Inc."),
-    ("78:7B:8A", "Apple, Inc."),
-    ("7C:D1:C3", "Apple, Inc."),
-    ("84:FC:FE", "Apple, Inc."),
-    ("90:27:E4", "Apple, Inc."),
-    ("98:01:A7", "Apple, Inc."),
-    ("A0:99:9B", "Apple, Inc."),
-    ("A4:B1:97", "Apple, Inc."),
-    ("B0:34:95", "Apple, Inc."),
-    ("BC:52:B7", "Apple, Inc."),
-    ("C0:A5:3E", "Apple, Inc."),
-    // Samsung
-    ("00:1A:8A", "Samsung Electronics Co.,Ltd"),
-    ("00:21:19", "Samsung Electronics Co.,Ltd"),
-    ("8C:F5:A3", "Samsung Electronics Co.,Ltd"),
-    ("AC:5F:3E", "Samsung Electronics Co.,Ltd"),
-    ("C0:97:27", "Samsung Electronics Co.,Ltd"),
-    ("5C:3A:45", "Samsung Electronics Co.,Ltd"),
-    ("10:D5:42", "Samsung Electronics Co.,Ltd"),
-    ("78:47:1D", "Samsung Electronics Co.,Ltd"),
-    ("D0:22:BE", "Samsung Electronics Co.,Ltd"),
-    ("E4:7C:F9", "Samsung Electronics Co.,Ltd"),
-    ("34:14:5F", "Samsung Electronics Co.,Ltd"),
-    ("90:18:7C", "Samsung Electronics Co.,Ltd"),
-    ("A8:9F:BA", "Samsung Electronics Co.,Ltd"),
-    ("B4:3A:28", "Samsung Electronics Co.,Ltd"),
-    ("C4:73:1E", "Samsung Electronics Co.,Ltd"),
-    ("FC:A8:9A", "Samsung Electronics Co.,Ltd"),
-    ("14:49:E0", "Samsung Electronics Co.,Ltd"),
-    ("1C:AF:05", "Samsung Electronics Co.,Ltd"),
-    ("30:07:4D", "Samsung Electronics Co.,Ltd"),
-    ("40:4E:36", "Samsung Electronics Co.,Ltd"),
-    ("58:C3:8B", "Samsung Electronics Co.,Ltd"),
-    ("6C:C7:EC", "Samsung Electronics Co.,Ltd"),
-    ("84:25:DB", "Samsung Electronics Co.,Ltd"),
-    ("98:83:89", "Samsung Electronics Co.,Ltd"),
-    ("BC:14:EF", "Samsung Electronics Co.,Ltd"),
-    ("CC:07:AB", "Samsung Electronics Co.,Ltd"),
-    ("E8:E5:D6", "Samsung Electronics Co.,Ltd"),
-    ("F8:04:2E", "Samsung Electronics Co.,Ltd"),
-    // Xiaomi
-    ("28:6C:07", "Xiaomi Communications Co Ltd"),
-    ("64:CC:2E", "Xiaomi Communications Co Ltd"),
-    ("7C:1D:D9", "Xiaomi Communications Co Ltd"),
-    ("0C:1D:AF", "Xiaomi Communications Co Ltd"),
-    ("34:80:B3", "Xiaomi Communications Co Ltd"),
-    ("50:64:2B", "Xiaomi Communications Co Ltd"),
-    ("78:11:DC", "Xiaomi Communications Co Ltd"),
-    ("8C:DE:F9", "Xiaomi Communications Co Ltd"),
-    ("A4:77:58", "Xiaomi Communications Co Ltd"),
-    ("B0:E2:35", "Xiaomi Communications Co Ltd"),
-    ("C4:0B:CB", "Xiaomi Communications Co Ltd"),
-    ("DC:D8:7D", "Xiaomi Communications Co Ltd"),
-    ("F0:B4:29", "Xiaomi Communications Co Ltd"),
-    ("18:59:36", "Xiaomi Communications Co Ltd"),
-    ("58:44:98", "Xiaomi Communications Co Ltd"),
-    // Huawei
-    ("00:E0:FC", "Huawei Technologies Co.,Ltd"),
-    ("04:F9:38", "Huawei Technologies Co.,Ltd"),
-    ("20:F3:A3", "Huawei Technologies Co.,Ltd"),
-    ("48:46:FB", "Huawei Technologies Co.,Ltd"),
-    ("70:72:3C", "Huawei Technologies Co.,Ltd"),
-    ("88:28:B3", "Huawei Technologies Co.,Ltd"),
-    ("AC:61:75", "Huawei Technologies Co.,Ltd"),
-    ("C8:D1:5E", "Huawei Technologies Co.,Ltd"),
-    ("E0:24:7F", "Huawei Technologies Co.,Ltd"),
-    ("EC:CB:30", "Huawei Technologies Co.,Ltd"),
-    ("14:30:04", "Huawei Technologies Co.,Ltd"),
-    ("24:44:27", "Huawei Technologies Co.,Ltd"),
-    ("3C:47:11", "Huawei Technologies Co.,Ltd"),
-    ("54:A5:1B", "Huawei Technologies Co.,Ltd"),
-    ("78:F5:57", "Huawei Technologies Co.,Ltd"),
-    // Sony
-    ("00:1F:E4", "Sony Corporation"),
-    ("00:13:A9", "Sony Corporation"),
-    ("00:24:BE", "Sony Corporation"),
-    ("04:5D:4B", "Sony Corporation"),
-    ("28:3F:69", "Sony Corporation"),
-    ("40:B8:37", "Sony Corporation"),
-    ("78:84:3C", "Sony Corporation"),
-    ("AC:9B:0A", "Sony Corporation"),
-    ("B4:52:7E", "Sony Corporation"),
-    ("FC:0F:E6", "Sony Corporation"),
-    // OPPO
-    ("3C:77:E6", "OPPO Digital, Inc."),
-    ("54:A0:50", "OPPO Digital, Inc."),
-    ("A4:3D:78", "OPPO Digital, Inc."),
-    ("CC:2D:83", "OPPO Digital, Inc."),
-    ("E8:BB:A8", "OPPO Digital, Inc."),
-    ("74:A5:28", "OPPO Digital, Inc."),
-    ("90:6C:AC", "OPPO Digital, Inc."),
-    ("18:D7:17", "OPPO Digital, Inc."),
-    // ASUS
-    ("00:1A:92", "ASUSTek COMPUTER INC."),
-    ("1C:87:2C", "ASUSTek COMPUTER INC."),
-    ("2C:56:DC", "ASUSTek COMPUTER INC."),
-    ("54:04:A6", "ASUSTek COMPUTER INC."),
-    ("AC:9E:17", "ASUSTek COMPUTER INC."),
-    ("D8:50:E6", "ASUSTek COMPUTER INC."),
-    // NEC
-    ("00:0B:A2", "NEC Corporation"),
-    ("00:30:13", "NEC Corporation"),
-    ("00:70:4C", "NEC Corporation"),
-    // Netgear
-    ("00:14:6C", "NETGEAR"),
-    ("00:1F:33", "NETGEAR"),
-    ("20:E5:2A", "NETGEAR"),
-    ("44:94:FC", "NETGEAR"),
-    ("6C:B0:CE", "NETGEAR"),
-    ("B0:7F:B9", "NETGEAR"),
-    ("C4:04:15", "NETGEAR"),
-    // Raspberry Pi
-    ("B8:27:EB", "Raspberry Pi Foundation"),
-    ("DC:A6:32", "Raspberry Pi Foundation"),
-    ("E4:5F:01", "Raspberry Pi Foundation"),
-    // Espressif (ESP32/ESP8266)
-    ("24:0A:C4", "Espressif Inc."),
-    ("30:AE:A4", "Espressif Inc."),
-    ("A4:CF:12", "Espressif Inc."),
-    ("CC:50:E3", "Espressif Inc."),
-    ("84:CC:A8", "Espressif Inc."),
-    // LG Electronics
-    ("00:1C:62", "LG Electronics"),
-    ("10:68:3F", "LG Electronics"),
-    ("2C:54:CF", "LG Electronics"),
-    ("58:A2:B5", "LG Electronics"),
-    ("A8:23:FE", "LG Electronics"),
-    // Panasonic
-    ("00:0E:6B", "Panasonic Corporation"),
-    ("00:1B:52", "Panasonic Corporation"),
-    ("10:5F:06", "Panasonic Corporation"),
-    ("34:FC:EF", "Panasonic Corporation"),
-    // Nintendo
-    ("00:09:BF", "Nintendo Co.,Ltd"),
-    ("00:17:AB", "Nintendo Co.,Ltd"),
-    ("00:1E:35", "Nintendo Co.,Ltd"),
-    ("00:24:1E", "Nintendo Co.,Ltd"),
-    ("34:AF:2C", "Nintendo Co.,Ltd"),
-    ("58:BD:A3", "Nintendo Co.,Ltd"),
-    ("7C:BB:8A", "Nintendo Co.,Ltd"),
-    ("98:B6:E9", "Nintendo Co.,Ltd"),
-    ("E8:4E:CE", "Nintendo Co.,Ltd"),
-    // Microsoft (Xbox, Surface)
-    ("28:18:78", "Microsoft Corporation"),
-    ("60:45:BD", "Microsoft Corporation"),
-    ("7C:1E:52", "Microsoft Corporation"),
-    ("C8:3F:26", "Microsoft Corporation"),
-    // Intel (PCs, laptops)
-    ("00:1E:64", "Intel Corporate"),
-    ("3C:97:0E", "Intel Corporate"),
-    ("68:05:CA", "Intel Corporate"),
-    ("8C:EC:4B", "Intel Corporate"),
-    // Hikvision
-    ("C0:56:E3", "Hangzhou Hikvision Digital Technology"),
-    ("44:19:B6", "Hangzhou Hikvision Digital Technology"),
-    ("54:C4:15", "Hangzhou Hikvision Digital Technology"),
-    // Dahua
-    ("3C:EF:8C", "Zhejiang Dahua Technology Co., Ltd."),
-    ("A0:BD:CD", "Zhejiang Dahua Technology Co., Ltd."),
-    // Sonos
-    ("00:0E:58", "Sonos, Inc."),
-    ("34:7E:5C", "Sonos, Inc."),
-    ("48:A6:B8", "Sonos, Inc."),
-    ("78:28:CA", "Sonos, Inc."),
-    // Bose
-    ("04:52:C7", "Bose Corporation"),
-    ("08:DF:1F", "Bose Corporation"),
-    // Elecom
-    ("00:1D:62", "ELECOM CO.,LTD."),
-    ("74:03:BD", "ELECOM CO.,LTD."),
-    ("C8:2E:47", "ELECOM CO.,LTD."),
-    // OnePlus
-    ("94:65:2D", "OnePlus Technology (Shenzhen) Co., Ltd"),
-    ("C0:EE:40", "OnePlus Technology (Shenzhen) Co., Ltd"),
-    // Motorola
-    ("00:04:56", "Motorola Mobility LLC"),
-    ("68:C4:4D", "Motorola Mobility LLC"),
-    ("E8:65:D4", "Motorola Mobility LLC"),
-];
+use super::{datapack, net, oui, ports, ssdp, stealth, wsd, Device, DeviceType, Port};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{Duration, Instant};
 
 /// Router vendor patterns
 const ROUTER_VENDORS: &[&str] = &[
@@ -300,6 +57,7 @@ const SMART_TV_VENDORS: &[&str] = &[
 /// Gaming console vendor patterns
 const GAMING_VENDORS: &[&str] = &[
     "Nintendo",
+    "Sony Interactive Entertainment",
     "Microsoft",
 ];
 
@@ -322,144 +80,609 @@ const TV_NAME_PATTERNS: &[&str] = &[
     "tv", "テレビ", "bravia", "viera", "regza", "aquos",
 ];
 
+/// Game console name patterns
+const GAME_CONSOLE_NAME_PATTERNS: &[&str] = &[
+    "playstation", "ps4", "ps5", "xbox", "nintendo",
+];
+
+/// mDNS service types that only one kind of device announces, checked in order.
+/// File sharing on a computer is caught by its name or vendor first.
+const SERVICE_TYPES: &[(&str, DeviceType)] = &[
+    ("_ipp._tcp", DeviceType::Printer),
+    ("_ipps._tcp", DeviceType::Printer),
+    ("_printer._tcp", DeviceType::Printer),
+    ("_pdl-datastream._tcp", DeviceType::Printer),
+    ("_uscan._tcp", DeviceType::Printer),
+    ("_scanner._tcp", DeviceType::Printer),
+    ("_adisk._tcp", DeviceType::Nas),
+    ("_smb._tcp", DeviceType::Nas),
+    ("_afpovertcp._tcp", DeviceType::Nas),
+    ("_nfs._tcp", DeviceType::Nas),
+    ("_hap._tcp", DeviceType::SmartPlug),
+    ("_hue._tcp", DeviceType::SmartPlug),
+];
+
+/// Open ports that together identify a device type, checked in order
+const PORT_SIGNATURES: &[(&[u16], DeviceType)] = &[
+    // RTSP
+    (&[554], DeviceType::Camera),
+    // Raw printing (JetDirect), IPP, LPD
+    (&[9100], DeviceType::Printer),
+    (&[631], DeviceType::Printer),
+    (&[515], DeviceType::Printer),
+    // SMB with the Synology / QNAP admin ports
+    (&[445, 5000], DeviceType::Nas),
+    (&[445, 5001], DeviceType::Nas),
+    (&[445, 8080, 8081], DeviceType::Nas),
+    // Xbox Live, PlayStation Remote Play
+    (&[3074], DeviceType::GameConsole),
+    (&[9295], DeviceType::GameConsole),
+    // TP-Link Kasa, Tuya
+    (&[9999], DeviceType::SmartPlug),
+    (&[6668], DeviceType::SmartPlug),
+];
+
 /// Computer name patterns
 const COMPUTER_NAME_PATTERNS: &[&str] = &[
     "macbook", "imac", "mac-mini", "desktop", "laptop",
     "surface", "thinkpad", "dell", "hp-", "lenovo",
 ];
 
-/// Look up vendor from MAC address
+/// Whether the MAC address is locally administered rather than assigned by
+/// the maker: phones and PCs use such addresses for privacy, randomized per network
+pub fn is_randomized_mac(mac: &str) -> bool {
+    // Second bit of the first octet
+    mac.get(0..2)
+        .and_then(|octet| u8::from_str_radix(octet, 16).ok())
+        .is_some_and(|octet| octet & 0x02 != 0)
+}
+
+/// Look up vendor from MAC address. Randomized addresses have no vendor:
+/// their prefix is not an assignment, so any registry match would be wrong.
 pub fn lookup_vendor(mac: &str) -> Option<String> {
+    if is_randomized_mac(mac) {
+        return None;
+    }
+
     let prefix = mac.to_uppercase()
         .chars()
         .take(8)
         .collect::<String>();
 
-    for (oui, vendor) in OUI_DATABASE {
-        if prefix == *oui {
-            return Some(vendor.to_string());
-        }
+    // Data pack entries take precedence over the IEEE registry
+    if let Some(vendor) = datapack::lookup_oui(&prefix) {
+        return Some(vendor);
     }
 
-    None
+    oui::lookup(mac)
+}
+
+/// Whether a vendor name has one of the patterns at the start of a word,
+/// ignoring case: registry names are often upper case, and "NEC" must not
+/// match "CONNECTED"
+fn vendor_matches(vendor: &str, patterns: &[&str]) -> bool {
+    let vendor = vendor.to_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        vendor.match_indices(&pattern).any(|(i, _)| {
+            vendor[..i].chars().last().map_or(true, |c| !c.is_alphanumeric())
+        })
+    })
 }
 
-/// Identify device type from MAC, vendor, and resolved device name
-pub fn identify_device_type(_mac: &str, vendor: &Option<String>, name: &Option<String>) -> DeviceType {
-    // First, check name-based patterns (most reliable when a name is available)
-    if let Some(ref n) = name {
-        let lower = n.to_lowercase();
+/// What kind of signal told the device type, from the most to the least reliable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeSource {
+    /// Apple model identifier announced over mDNS
+    AppleModel,
+    /// ONVIF device information
+    Onvif,
+    /// UPnP device type URN
+    Ssdp,
+    /// WS-Discovery type or ONVIF scope
+    Wsd,
+    /// Data pack model table or name pattern
+    ModelTable,
+    /// SNMP system description
+    Snmp,
+    /// mDNS service type
+    Mdns,
+    /// Open firmware identified from its web UI
+    Firmware,
+    /// Web interface title, realm or server header
+    WebInterface,
+    /// Built-in name pattern
+    Name,
+    /// DHCP parameter request list
+    Dhcp,
+    /// Combination of open ports
+    Ports,
+    /// Vendor that mostly makes one kind of device
+    Vendor,
+    /// Vendor that makes many kinds of device
+    VendorDefault,
+}
 
-        // Check smartphone patterns
-        if SMARTPHONE_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
-            return DeviceType::Smartphone;
+impl TypeSource {
+    /// Confidence, in percent, of a type told by this kind of signal alone
+    fn confidence(self) -> u8 {
+        match self {
+            TypeSource::AppleModel => 95,
+            TypeSource::Onvif => 90,
+            TypeSource::Ssdp | TypeSource::Wsd | TypeSource::ModelTable | TypeSource::Snmp | TypeSource::Firmware => 85,
+            TypeSource::Mdns => 80,
+            TypeSource::WebInterface => 75,
+            TypeSource::Name | TypeSource::Dhcp => 70,
+            TypeSource::Ports => 60,
+            TypeSource::Vendor => 50,
+            TypeSource::VendorDefault => 35,
         }
+    }
+}
 
-        // Check computer patterns
-        if COMPUTER_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
-            return DeviceType::Computer;
+/// Confidence added per further signal agreeing with the type
+const AGREEMENT_BONUS: u8 = 10;
+
+/// Certainty is never claimed from fingerprints
+const MAX_CONFIDENCE: u8 = 99;
+
+/// How sure the device type is, and what it rests on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Identification {
+    /// 0-100; 0 while the type is unknown
+    pub confidence: u8,
+    /// Signals that told the type, e.g. "mDNS: _ipp._tcp", "OUI: Google, Inc.", "ポート 9100/tcp"
+    pub evidence: Vec<String>,
+}
+
+impl Identification {
+    pub fn new(source: TypeSource, evidence: String) -> Self {
+        Identification {
+            confidence: source.confidence(),
+            evidence: vec![evidence],
         }
+    }
 
-        // Check TV patterns (but skip if it also matches smartphone patterns like "AQUOS")
-        if TV_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
-            return DeviceType::SmartTv;
+    /// Another signal pointing to the same type
+    fn agree(&mut self, source: TypeSource, evidence: String) {
+        if self.evidence.contains(&evidence) {
+            return;
+        }
+        if self.evidence.is_empty() {
+            *self = Identification::new(source, evidence);
+            return;
         }
+        self.confidence = self.confidence.max(source.confidence()).saturating_add(AGREEMENT_BONUS).min(MAX_CONFIDENCE);
+        self.evidence.push(evidence);
+    }
+}
+
+/// Record a signal about the device type found after discovery: it sets the
+/// type while none is known and backs the known type when it agrees
+pub fn record_type(device: &mut Device, device_type: DeviceType, source: TypeSource, evidence: String) {
+    if device.device_type == DeviceType::Unknown {
+        device.device_type = device_type;
+        device.identification = Identification::new(source, evidence);
+    } else if device.device_type == device_type {
+        device.identification.agree(source, evidence);
+    }
+}
+
+/// Identify device type from the resolved name, the vendor, and the types
+/// the device announces over SSDP, WS-Discovery and mDNS, with the signals
+/// it rests on
+pub fn identify_device_type(
+    _mac: &str,
+    vendor: &Option<String>,
+    name: &Option<String>,
+    mdns_services: &[String],
+    ssdp: Option<&ssdp::SsdpDescription>,
+    wsd: Option<&wsd::WsdDevice>,
+) -> (DeviceType, Identification) {
+    let named = type_from_name(name).map(|(t, source)| (t, source, format!("名前: {}", name.as_deref().unwrap_or_default())));
+    let vendored = type_from_vendor(vendor).map(|(t, source)| (t, source, format!("OUI: {}", vendor.as_deref().unwrap_or_default())));
+    let announced = ssdp.and_then(|d| {
+        let device_type = ssdp::classify(d)?;
+        Some((device_type, TypeSource::Ssdp, format!("SSDP: {}", d.device_types.join(", "))))
+    });
+    let probed = wsd.and_then(|d| {
+        let device_type = wsd::classify(d)?;
+        Some((device_type, TypeSource::Wsd, format!("WSD: {}", d.types.join(", "))))
+    });
+    let services = type_from_services(mdns_services).map(|(t, service)| (t, TypeSource::Mdns, format!("mDNS: {}", service)));
+
+    // Announced UPnP and WS-Discovery types beat guessing from names and vendors.
+    // Computers and phones are kept: their media players announce
+    // themselves as renderers and servers too.
+    let guessed = named.as_ref().or(vendored.as_ref()).map(|(t, _, _)| *t);
+    let device_type = match guessed {
+        Some(t @ (DeviceType::Computer | DeviceType::Smartphone)) => t,
+        _ => [&probed, &announced, &named, &services, &vendored]
+            .into_iter()
+            .find_map(|signal| signal.as_ref().map(|(t, _, _)| *t))
+            .unwrap_or(DeviceType::Unknown),
+    };
+
+    // Every signal that agrees backs the type, the most reliable first
+    let mut agreeing = [probed, announced, named, services, vendored]
+        .into_iter()
+        .flatten()
+        .filter(|(t, _, _)| *t == device_type);
+    let Some((_, source, evidence)) = agreeing.next() else {
+        return (DeviceType::Unknown, Identification::default());
+    };
+    let mut identification = Identification::new(source, evidence);
+    for (_, source, evidence) in agreeing {
+        identification.agree(source, evidence);
+    }
+    (device_type, identification)
+}
+
+/// Device type the mDNS service types point to, and the service
+fn type_from_services(mdns_services: &[String]) -> Option<(DeviceType, &'static str)> {
+    SERVICE_TYPES
+        .iter()
+        .find(|(service, _)| mdns_services.iter().any(|s| s == service))
+        .map(|(service, device_type)| (*device_type, *service))
+}
+
+/// Device type named by the resolved device name
+fn type_from_name(name: &Option<String>) -> Option<(DeviceType, TypeSource)> {
+    let lower = name.as_ref()?.to_lowercase();
+
+    // Data pack model tables and name patterns take precedence
+    if let Some(device_type) = datapack::match_name(&lower) {
+        return Some((device_type, TypeSource::ModelTable));
     }
 
+    // Check smartphone patterns
+    if SMARTPHONE_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some((DeviceType::Smartphone, TypeSource::Name));
+    }
+
+    // Check computer patterns
+    if COMPUTER_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some((DeviceType::Computer, TypeSource::Name));
+    }
+
+    // Check TV patterns (but skip if it also matches smartphone patterns like "AQUOS")
+    if TV_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some((DeviceType::SmartTv, TypeSource::Name));
+    }
+
+    // Check game console patterns
+    if GAME_CONSOLE_NAME_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some((DeviceType::GameConsole, TypeSource::Name));
+    }
+
+    None
+}
+
+/// Device type the vendor usually makes
+fn type_from_vendor(vendor: &Option<String>) -> Option<(DeviceType, TypeSource)> {
     let vendor_str = vendor.as_deref().unwrap_or("");
 
     // Check for router
-    if ROUTER_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::Router;
+    if vendor_matches(vendor_str, ROUTER_VENDORS) {
+        return Some((DeviceType::Router, TypeSource::Vendor));
     }
 
     // Check for camera
-    if CAMERA_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::Camera;
+    if vendor_matches(vendor_str, CAMERA_VENDORS) {
+        return Some((DeviceType::Camera, TypeSource::Vendor));
     }
 
     // Check for smart speaker
-    if SMART_SPEAKER_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::SmartSpeaker;
+    if vendor_matches(vendor_str, SMART_SPEAKER_VENDORS) {
+        return Some((DeviceType::SmartSpeaker, TypeSource::Vendor));
     }
 
     // Check for smartphone vendors
-    if SMARTPHONE_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::Smartphone;
+    if vendor_matches(vendor_str, SMARTPHONE_VENDORS) {
+        return Some((DeviceType::Smartphone, TypeSource::Vendor));
     }
 
     // Check for smart TV vendors
-    if SMART_TV_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::SmartTv;
+    if vendor_matches(vendor_str, SMART_TV_VENDORS) {
+        return Some((DeviceType::SmartTv, TypeSource::Vendor));
     }
 
     // Check for gaming consoles
-    if GAMING_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::SmartPlug; // Reusing as gaming doesn't have own type
+    if vendor_matches(vendor_str, GAMING_VENDORS) {
+        return Some((DeviceType::GameConsole, TypeSource::Vendor));
     }
 
     // Apple devices: need name to distinguish iPhone/Mac/Apple TV
-    if vendor_str.contains("Apple") {
+    if vendor_matches(vendor_str, &["Apple"]) {
         // Default Apple to smartphone (most common on home networks)
-        return DeviceType::Smartphone;
+        return Some((DeviceType::Smartphone, TypeSource::VendorDefault));
     }
 
     // Google devices: could be Pixel phone or Nest/Chromecast
-    if vendor_str.contains("Google") {
-        return DeviceType::SmartSpeaker;
+    if vendor_matches(vendor_str, &["Google"]) {
+        return Some((DeviceType::SmartSpeaker, TypeSource::VendorDefault));
     }
 
     // Amazon devices: usually Echo speakers
-    if vendor_str.contains("Amazon") {
-        return DeviceType::SmartSpeaker;
+    if vendor_matches(vendor_str, &["Amazon"]) {
+        return Some((DeviceType::SmartSpeaker, TypeSource::VendorDefault));
     }
 
     // Sony: could be TV, camera, or phone
-    if vendor_str.contains("Sony") {
-        return DeviceType::SmartTv;
+    if vendor_matches(vendor_str, &["Sony"]) {
+        return Some((DeviceType::SmartTv, TypeSource::VendorDefault));
     }
 
     // IoT devices
-    if IOT_VENDORS.iter().any(|v| vendor_str.contains(v)) {
-        return DeviceType::SmartPlug;
+    if vendor_matches(vendor_str, IOT_VENDORS) {
+        return Some((DeviceType::SmartPlug, TypeSource::Vendor));
     }
 
     // Intel usually means PC/laptop
-    if vendor_str.contains("Intel") {
-        return DeviceType::Computer;
+    if vendor_matches(vendor_str, &["Intel Corporate", "Intel Corporation"]) {
+        return Some((DeviceType::Computer, TypeSource::Vendor));
     }
 
-    DeviceType::Unknown
+    None
 }
 
 /// Identify services running on device
 pub async fn identify_services(device: &mut Device) {
-    // Check open ports for service identification
-    for port in &device.open_ports {
-        match port.number {
-            554 => {
-                // RTSP usually means camera
-                if device.device_type == DeviceType::Unknown {
-                    device.device_type = DeviceType::Camera;
-                }
-            }
-            1883 | 8883 => {
-                // MQTT is common for IoT devices
-            }
-            631 => {
-                // IPP - Printer
-                if device.device_type == DeviceType::Unknown {
-                    device.device_type = DeviceType::Printer;
-                }
+    // Ask the services that identify themselves for their product and version
+    let mut grabs = tokio::task::JoinSet::new();
+    for port in device.open_ports.iter().filter(|p| p.protocol == "tcp" && ports::has_banner(p.number)) {
+        let (ip, number) = (device.ip.clone(), port.number);
+        grabs.spawn(async move { (number, ports::grab_banner(&ip, number).await) });
+    }
+    while let Some(joined) = grabs.join_next().await {
+        let Ok((number, Some(banner))) = joined else {
+            continue;
+        };
+        if let Some(port) = device.open_ports.iter_mut().find(|p| p.number == number && p.protocol == "tcp") {
+            port.version = ports::service_version(&banner);
+        }
+    }
+
+    // Ports only one kind of device opens
+    if let Some((device_type, ports)) = type_from_ports(&device.open_ports) {
+        let ports: Vec<String> = ports.iter().map(|p| format!("{}/tcp", p)).collect();
+        record_type(device, device_type, TypeSource::Ports, format!("ポート {}", ports.join(", ")));
+    }
+}
+
+/// Device type the open TCP ports point to, and the ports
+pub fn type_from_ports(open_ports: &[Port]) -> Option<(DeviceType, &'static [u16])> {
+    let open = |port: &u16| open_ports.iter().any(|p| p.protocol == "tcp" && p.number == *port);
+    PORT_SIGNATURES
+        .iter()
+        .find(|(ports, _)| ports.iter().all(open))
+        .map(|(ports, device_type)| (*device_type, *ports))
+}
+
+/// Operating systems [`guess_os`] tells apart
+pub const OS_WINDOWS: &str = "Windows";
+pub const OS_LINUX: &str = "Linux";
+pub const OS_EMBEDDED_LINUX: &str = "Linux (embedded)";
+pub const OS_APPLE: &str = "macOS / iOS";
+/// Initial TTL 255: network equipment OSes and RTOS stacks
+pub const OS_NETWORK: &str = "RTOS / network OS";
+/// A stack without window scaling or SACK, from before the 2000s
+pub const OS_LEGACY_EMBEDDED: &str = "Legacy embedded stack";
+
+/// How long to wait for the echo reply or the SYN-ACK
+const STACK_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Ports only Windows opens together
+const WINDOWS_PORTS: &[u16] = &[135, 3389];
+
+/// Ports only Apple devices open: iPhone sync, AFP
+const APPLE_PORTS: &[u16] = &[62078, 548];
+
+/// Banner words of the small servers embedded Linux firmware ships
+const EMBEDDED_BANNERS: &[&str] = &["dropbear", "busybox", "uhttpd", "lighttpd", "boa", "goahead", "mini_httpd", "thttpd"];
+
+/// SYN-ACK characteristics that differ between TCP/IP stacks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct SynAck {
+    pub ttl: u8,
+    pub window: u16,
+    pub mss: Option<u16>,
+    pub window_scale: Option<u8>,
+    pub sack: bool,
+    pub timestamps: bool,
+}
+
+/// What the device's TCP/IP stack gave away
+#[derive(Debug, Clone, Default)]
+pub(super) struct StackTraits {
+    /// TTL of the ICMP echo reply
+    pub icmp_ttl: Option<u8>,
+    pub syn_ack: Option<SynAck>,
+}
+
+/// Guess the device's operating system from its ICMP TTL, its SYN-ACK and its
+/// open ports. The stack probes need raw sockets; without them only the
+/// ports and banners are used.
+pub async fn guess_os(device: &mut Device) {
+    // The DHCP client already told the OS, more precisely than the stack can
+    if device.os_guess.is_some() {
+        return;
+    }
+    let traits = match device.ip.parse::<Ipv4Addr>() {
+        Ok(ip) => {
+            let port = device.open_ports.iter().find(|p| p.protocol == "tcp").map(|p| p.number);
+            tokio::task::spawn_blocking(move || probe_stack(ip, port)).await.unwrap_or_default()
+        }
+        Err(_) => StackTraits::default(),
+    };
+    let apple = device.vendor.as_deref().is_some_and(|v| v.contains("Apple"));
+    if let Some(os) = classify(&traits, &device.open_ports, device.device_type, apple) {
+        device.os_guess = Some(os.to_string());
+    }
+}
+
+fn probe_stack(ip: Ipv4Addr, port: Option<u16>) -> StackTraits {
+    if net::check_active().is_err() {
+        return StackTraits::default();
+    }
+    StackTraits {
+        icmp_ttl: echo_ttl(ip),
+        syn_ack: port.and_then(|port| syn_ack(ip, port)),
+    }
+}
+
+/// TTL of the reply to an ICMP echo request
+fn echo_ttl(ip: Ipv4Addr) -> Option<u8> {
+    let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).ok()?;
+    socket.set_read_timeout(Some(STACK_PROBE_TIMEOUT)).ok()?;
+
+    let id = (std::process::id() as u16 ^ 0x4f53).to_be_bytes();
+    let mut packet = [0u8; 16];
+    packet[0] = 8;
+    packet[4..6].copy_from_slice(&id);
+    packet[7] = 1;
+    let sum = stealth::checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    socket.send_to(&packet, &SocketAddrV4::new(ip, 0).into()).ok()?;
+
+    let deadline = Instant::now() + STACK_PROBE_TIMEOUT;
+    let mut buf = [0u8; 512];
+    while Instant::now() < deadline {
+        let len = (&socket).read(&mut buf).ok()?;
+        // Raw IPv4 sockets deliver the IP header too
+        let header_len = usize::from(buf[0] & 0x0f) * 4;
+        let from = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+        let Some(icmp) = buf.get(header_len..len) else {
+            continue;
+        };
+        // Echo reply (type 0) to our request
+        if from == ip && icmp.len() >= 8 && icmp[0] == 0 && icmp[4..6] == id {
+            return Some(buf[8]);
+        }
+    }
+    None
+}
+
+/// SYN-ACK of an open port, read off a raw TCP socket while connecting to it.
+/// Only Linux hands received TCP segments to raw sockets; elsewhere this is `None`.
+fn syn_ack(ip: Ipv4Addr, port: u16) -> Option<SynAck> {
+    let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::TCP)).ok()?;
+    socket.set_read_timeout(Some(STACK_PROBE_TIMEOUT)).ok()?;
+    let stream = net::tcp_connect(&SocketAddr::from((ip, port)), STACK_PROBE_TIMEOUT).ok()?;
+    let local_port = stream.local_addr().ok()?.port();
+
+    let deadline = Instant::now() + STACK_PROBE_TIMEOUT;
+    let mut buf = [0u8; 1500];
+    while Instant::now() < deadline {
+        let len = (&socket).read(&mut buf).ok()?;
+        let from = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+        if from != ip {
+            continue;
+        }
+        if let Some(syn_ack) = parse_syn_ack(&buf[..len], port, local_port) {
+            return Some(syn_ack);
+        }
+    }
+    None
+}
+
+/// SYN-ACK from `port` to `local_port` in an IPv4 packet
+pub(super) fn parse_syn_ack(packet: &[u8], port: u16, local_port: u16) -> Option<SynAck> {
+    let header_len = usize::from(packet.first()? & 0x0f) * 4;
+    let ttl = *packet.get(8)?;
+    let tcp = packet.get(header_len..)?;
+    if tcp.len() < 20 || tcp[0..2] != port.to_be_bytes() || tcp[2..4] != local_port.to_be_bytes() {
+        return None;
+    }
+    // SYN and ACK set
+    if tcp[13] & 0x12 != 0x12 {
+        return None;
+    }
+
+    let mut syn_ack = SynAck {
+        ttl,
+        window: u16::from_be_bytes([tcp[14], tcp[15]]),
+        ..SynAck::default()
+    };
+    let options_end = (usize::from(tcp[12] >> 4) * 4).min(tcp.len());
+    let mut options = tcp.get(20..options_end).unwrap_or_default();
+    while let Some(&kind) = options.first() {
+        match kind {
+            // End of options
+            0 => break,
+            // No-op padding
+            1 => {
+                options = &options[1..];
+                continue;
             }
             _ => {}
         }
+        let len = usize::from(*options.get(1)?).max(2);
+        let data = options.get(2..len)?;
+        match (kind, data.len()) {
+            (2, 2) => syn_ack.mss = Some(u16::from_be_bytes([data[0], data[1]])),
+            (3, 1) => syn_ack.window_scale = Some(data[0]),
+            (4, _) => syn_ack.sack = true,
+            (8, _) => syn_ack.timestamps = true,
+            _ => {}
+        }
+        options = &options[len..];
+    }
+    Some(syn_ack)
+}
+
+/// TTL the stack started with; the observed one is lower by the hops crossed
+fn initial_ttl(ttl: u8) -> u8 {
+    match ttl {
+        0..=32 => 32,
+        33..=64 => 64,
+        65..=128 => 128,
+        _ => 255,
     }
 }
 
-/// Check if device is using default credentials
-pub async fn check_default_credentials(_device: &Device) -> bool {
-    // TODO: Implement default credential checking
-    // This would require a database of default credentials per vendor/model
-    false
+/// Operating system the stack traits and open ports point to
+pub(super) fn classify(
+    traits: &StackTraits,
+    open_ports: &[Port],
+    device_type: DeviceType,
+    apple: bool,
+) -> Option<&'static str> {
+    let has_port = |numbers: &[u16]| open_ports.iter().any(|p| p.protocol == "tcp" && numbers.contains(&p.number));
+    let embedded_banner = open_ports.iter().filter_map(|p| p.version.as_deref()).any(|version| {
+        let version = version.to_lowercase();
+        EMBEDDED_BANNERS.iter().any(|banner| version.contains(banner))
+    });
+    let embedded_type = matches!(
+        device_type,
+        DeviceType::Router | DeviceType::Camera | DeviceType::SmartPlug | DeviceType::Printer | DeviceType::Nas
+    );
+
+    // Every current stack offers window scaling or SACK in its SYN-ACK
+    if traits.syn_ack.as_ref().is_some_and(|s| s.window_scale.is_none() && !s.sack) {
+        return Some(OS_LEGACY_EMBEDDED);
+    }
+
+    let ttl = traits.syn_ack.as_ref().map(|s| s.ttl).or(traits.icmp_ttl).map(initial_ttl);
+    match ttl {
+        Some(128) => Some(OS_WINDOWS),
+        Some(255) => Some(OS_NETWORK),
+        Some(32) => Some(OS_LEGACY_EMBEDDED),
+        Some(_) => {
+            // Apple stacks advertise a 65535 window with timestamps; Linux scales a smaller one
+            let apple_window = traits.syn_ack.as_ref().is_some_and(|s| s.window == 65535 && s.timestamps);
+            if has_port(APPLE_PORTS) || apple || (apple_window && !embedded_banner && !embedded_type) {
+                Some(OS_APPLE)
+            } else if embedded_banner || embedded_type {
+                Some(OS_EMBEDDED_LINUX)
+            } else {
+                Some(OS_LINUX)
+            }
+        }
+        // No stack probe answered: the services alone
+        None if has_port(WINDOWS_PORTS) => Some(OS_WINDOWS),
+        None if has_port(APPLE_PORTS) => Some(OS_APPLE),
+        None if embedded_banner => Some(OS_EMBEDDED_LINUX),
+        None => None,
+    }
 }
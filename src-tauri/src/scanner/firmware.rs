@@ -0,0 +1,389 @@
+//! Open router firmware detection and hardening checklist
+//!
+//! OpenWrt and DD-WRT give the owner a full Linux system with an SSH server
+//! and a web UI, and with it settings that stock firmware never exposes.
+//! Once detected from the web UI, the router gets a checklist tailored to its
+//! firmware, shown as its own section of the report.
+
+use super::exposure::{self, ExposureSettings};
+use super::fingerprint::{self, TypeSource};
+use super::{arp, login_page, ports, Device, DeviceType, IssueSeverity, SecurityIssue};
+use crate::vulndb::{content, knowledge};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Per-request timeout for the web UI
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Dropbear releases before this have published vulnerabilities
+/// (CVE-2018-15599 user enumeration, fixed after 2018.76, and earlier)
+pub(super) const MIN_DROPBEAR_VERSION: (u32, u32) = (2019, 77);
+
+/// Detected open firmware
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareKind {
+    OpenWrt,
+    DdWrt,
+}
+
+impl FirmwareKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OpenWrt => "OpenWrt",
+            Self::DdWrt => "DD-WRT",
+        }
+    }
+}
+
+/// Outcome of a checklist item
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Could not be determined (e.g. requires Level 3 or the exposure probe)
+    Unknown,
+}
+
+/// One checklist item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardeningCheck {
+    pub id: String,
+    pub title: String,
+    pub status: CheckStatus,
+    pub detail: Option<String>,
+}
+
+/// Detected firmware and its checklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareReport {
+    pub kind: FirmwareKind,
+    pub version: Option<String>,
+    /// SSH server banner, e.g. `SSH-2.0-dropbear_2022.82`
+    pub ssh_banner: Option<String>,
+    pub checks: Vec<HardeningCheck>,
+}
+
+/// A fetched web page
+pub(super) struct Page {
+    pub status: u16,
+    pub location: Option<String>,
+    pub set_cookie: bool,
+    pub headers: reqwest::header::HeaderMap,
+    pub body: String,
+}
+
+pub(super) fn client() -> Option<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        // Router admin pages use self-signed certificates
+        .danger_accept_invalid_certs(true)
+        .build()
+        .ok()
+}
+
+async fn read_page(response: reqwest::Response) -> Page {
+    let status = response.status().as_u16();
+    let headers = response.headers();
+    let location = headers
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let set_cookie = headers
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .any(|v| v.to_str().is_ok_and(|c| c.starts_with("sysauth")));
+    let headers = headers.clone();
+    let body = response.text().await.unwrap_or_default();
+    Page {
+        status,
+        location,
+        set_cookie,
+        headers,
+        body,
+    }
+}
+
+pub(super) async fn fetch(client: &reqwest::Client, url: &str) -> Option<Page> {
+    Some(read_page(client.get(url).send().await.ok()?).await)
+}
+
+/// Identify the firmware from its web UI
+pub(super) fn identify(body: &str) -> Option<(FirmwareKind, Option<String>)> {
+    if body.contains("DD-WRT") || body.contains("dd-wrt.com") {
+        return Some((FirmwareKind::DdWrt, version_after(body, "DD-WRT v")));
+    }
+    if body.contains("cgi-bin/luci") || body.contains("LuCI") || body.contains("OpenWrt") {
+        return Some((FirmwareKind::OpenWrt, version_after(body, "OpenWrt ")));
+    }
+    None
+}
+
+/// Version token following `marker`, e.g. "22.03.5" after "OpenWrt "
+fn version_after(text: &str, marker: &str) -> Option<String> {
+    text.match_indices(marker).find_map(|(i, _)| {
+        let version: String = text[i + marker.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-'))
+            .collect();
+        version.starts_with(|c: char| c.is_ascii_digit()).then_some(version)
+    })
+}
+
+/// Dropbear version from an SSH banner ("SSH-2.0-dropbear_2019.78" → (2019, 78))
+pub(super) fn dropbear_version(banner: &str) -> Option<(u32, u32)> {
+    let version = banner.split("dropbear_").nth(1)?;
+    let (year, release) = version.split_once('.')?;
+    let release: String = release.chars().take_while(char::is_ascii_digit).collect();
+    Some((year.parse().ok()?, release.parse().ok()?))
+}
+
+fn check(id: &str, status: CheckStatus, detail: Option<String>) -> HardeningCheck {
+    HardeningCheck {
+        id: id.to_string(),
+        title: content::checklist_title(id, content::language()),
+        status,
+        detail,
+    }
+}
+
+/// Detect open firmware on a device and run the checklist.
+///
+/// `wan_address` is the router's internet-side address (gateway only);
+/// login attempts are made only when `verify_credentials` is set (Level 3).
+pub async fn inspect(
+    device: &Device,
+    is_gateway: bool,
+    wan_address: Option<&str>,
+    exposure: &ExposureSettings,
+    verify_credentials: bool,
+) -> Option<FirmwareReport> {
+    let client = client()?;
+    let has_port = |port: u16| device.open_ports.iter().any(|p| p.number == port);
+
+    let http_root = if has_port(80) {
+        fetch(&client, &format!("http://{}/", device.ip)).await
+    } else {
+        None
+    };
+    let https_root = if has_port(443) {
+        fetch(&client, &format!("https://{}/", device.ip)).await
+    } else {
+        None
+    };
+
+    let (kind, mut version) = [&http_root, &https_root]
+        .into_iter()
+        .flatten()
+        .find_map(|page| identify(&page.body))?;
+
+    let base = if https_root.is_some() {
+        format!("https://{}", device.ip)
+    } else {
+        format!("http://{}", device.ip)
+    };
+
+    // The LuCI login page footer carries the release
+    if kind == FirmwareKind::OpenWrt && version.is_none() {
+        if let Some(page) = fetch(&client, &format!("{}/cgi-bin/luci/", base)).await {
+            version = version_after(&page.body, "OpenWrt ");
+        }
+    }
+
+    let ssh_banner = if has_port(22) {
+        ports::grab_banner(&device.ip, 22).await
+    } else {
+        None
+    };
+
+    let mut checks = Vec::new();
+
+    // Admin UI over plain HTTP
+    let admin_http = http_root.as_ref().filter(|page| {
+        let to_https = page.location.as_deref().is_some_and(|l| l.starts_with("https://"));
+        !to_https && identify(&page.body).is_some()
+    });
+    checks.push(match admin_http {
+        Some(_) => check(
+            "admin-http",
+            CheckStatus::Fail,
+            Some(format!("http://{}/ で管理画面が表示されます", device.ip)),
+        ),
+        None => check("admin-http", CheckStatus::Pass, None),
+    });
+
+    // SSH reachable from the internet (only meaningful on the gateway)
+    if is_gateway {
+        checks.push(match wan_address {
+            Some(wan) => match exposure::is_reachable(exposure, wan, 22).await {
+                Some(true) => check(
+                    "ssh-wan-exposed",
+                    CheckStatus::Fail,
+                    Some(format!("{}:22 にインターネット側から接続できます", wan)),
+                ),
+                Some(false) => check("ssh-wan-exposed", CheckStatus::Pass, None),
+                None => check(
+                    "ssh-wan-exposed",
+                    CheckStatus::Unknown,
+                    Some("外部からの到達性確認を有効にすると確認できます".to_string()),
+                ),
+            },
+            None => check(
+                "ssh-wan-exposed",
+                CheckStatus::Unknown,
+                Some("ルーターのインターネット側アドレスを取得できませんでした".to_string()),
+            ),
+        });
+    }
+
+    // Default / empty root password
+    checks.push(if verify_credentials {
+        let status = match kind {
+            FirmwareKind::OpenWrt => openwrt_empty_password(&client, &base).await,
+            FirmwareKind::DdWrt => ddwrt_default_password(&client, &base).await,
+        };
+        check("default-password", status, None)
+    } else {
+        check(
+            "default-password",
+            CheckStatus::Unknown,
+            Some("レベル3の診断で確認します".to_string()),
+        )
+    });
+
+    // Outdated dropbear
+    if let Some((year, release)) = ssh_banner.as_deref().and_then(dropbear_version) {
+        let status = if (year, release) < MIN_DROPBEAR_VERSION {
+            CheckStatus::Fail
+        } else {
+            CheckStatus::Pass
+        };
+        checks.push(check(
+            "dropbear-outdated",
+            status,
+            Some(format!("dropbear {}.{}", year, release)),
+        ));
+    }
+
+    Some(FirmwareReport {
+        kind,
+        version,
+        ssh_banner,
+        checks,
+    })
+}
+
+/// OpenWrt ships without a root password; LuCI then accepts an empty login
+async fn openwrt_empty_password(client: &reqwest::Client, base: &str) -> CheckStatus {
+    let response = client
+        .post(format!("{}/cgi-bin/luci/", base))
+        .header(reqwest::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("luci_username=root&luci_password=")
+        .send()
+        .await;
+    match response {
+        Ok(response) => {
+            let page = read_page(response).await;
+            if page.status == 302 && page.set_cookie {
+                CheckStatus::Fail
+            } else {
+                CheckStatus::Pass
+            }
+        }
+        Err(_) => CheckStatus::Unknown,
+    }
+}
+
+/// DD-WRT's factory login is root / admin over HTTP basic auth
+async fn ddwrt_default_password(client: &reqwest::Client, base: &str) -> CheckStatus {
+    let response = client
+        .get(format!("{}/Management.asp", base))
+        .basic_auth("root", Some("admin"))
+        .send()
+        .await;
+    match response.map(|r| r.status().as_u16()) {
+        Ok(200) => CheckStatus::Fail,
+        Ok(401) => CheckStatus::Pass,
+        _ => CheckStatus::Unknown,
+    }
+}
+
+/// Build findings for failed checklist items
+pub fn to_issues(report: &FirmwareReport) -> Vec<SecurityIssue> {
+    // Remediation steps differ per firmware, see the `<id>.<variant>` pack entries
+    let variant = match report.kind {
+        FirmwareKind::OpenWrt => "openwrt",
+        FirmwareKind::DdWrt => "ddwrt",
+    };
+    report
+        .checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .filter_map(|c| {
+            let severity = match c.id.as_str() {
+                "admin-http" => IssueSeverity::Medium,
+                "ssh-wan-exposed" => IssueSeverity::High,
+                "default-password" => IssueSeverity::Critical,
+                "dropbear-outdated" => IssueSeverity::Medium,
+                _ => return None,
+            };
+            let mut issue = content::issue(
+                &c.id,
+                severity,
+                &[
+                    ("variant", variant.to_string()),
+                    ("label", report.kind.label().to_string()),
+                    ("detail", c.detail.clone().unwrap_or_default()),
+                ],
+            );
+            if c.id == "dropbear-outdated" {
+                issue.references.push(knowledge::cve_reference("CVE-2018-15599"));
+            }
+            Some(issue)
+        })
+        .collect()
+}
+
+/// Look for open firmware on routers and unidentified devices with a web UI
+/// and attach the checklist and its findings, along with the login page advisory
+pub async fn check_devices(
+    devices: &mut [Device],
+    wan_address: Option<&str>,
+    exposure: &ExposureSettings,
+    verify_credentials: bool,
+) {
+    let gateway = arp::get_default_gateway().map(|ip| ip.to_string());
+
+    for device in devices.iter_mut() {
+        let is_gateway = gateway.as_deref() == Some(device.ip.as_str());
+        let candidate = is_gateway || matches!(device.device_type, DeviceType::Router | DeviceType::Unknown);
+        let has_web_ui = device.open_ports.iter().any(|p| matches!(p.number, 80 | 443));
+        if !candidate || !has_web_ui {
+            continue;
+        }
+
+        let wan = if is_gateway { wan_address } else { None };
+        let report = inspect(device, is_gateway, wan, exposure, verify_credentials).await;
+        let firmware_http = report
+            .as_ref()
+            .is_some_and(|r| r.checks.iter().any(|c| c.id == "admin-http" && c.status == CheckStatus::Fail));
+        if let Some(indicators) = login_page::analyze(device).await {
+            let http_reported = firmware_http || device.issues.iter().any(|i| i.id == "admin-interface-http");
+            device.issues.extend(login_page::to_issues(&indicators, http_reported));
+        }
+        let Some(report) = report else {
+            continue;
+        };
+        // The checklist's finding names the firmware's own settings
+        if firmware_http {
+            device.issues.retain(|i| i.id != "admin-interface-http");
+        }
+
+        let evidence = format!("ファームウェア: {}", report.kind.label());
+        fingerprint::record_type(device, DeviceType::Router, TypeSource::Firmware, evidence);
+        device.issues.extend(to_issues(&report));
+        device.firmware = Some(report);
+    }
+}
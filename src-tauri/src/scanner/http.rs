@@ -0,0 +1,235 @@
+//! Web admin interface analysis
+//!
+//! Most IoT devices are configured through a web page. Its server header,
+//! title and Basic auth realm usually name the maker and often the model,
+//! and whether the login is served over plain HTTP decides if the admin
+//! password crosses the network readable to anyone listening.
+
+use super::fingerprint::{self, TypeSource};
+use super::{datapack, Device, DeviceType, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Web ports inspected, with the scheme each is fetched over
+const WEB_PORTS: &[(u16, &str)] = &[(80, "http"), (8080, "http"), (443, "https")];
+
+/// Per-request timeout, redirects included
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Redirects followed from `/`
+const MAX_REDIRECTS: usize = 5;
+
+/// Longest title or realm kept
+const MAX_TEXT_LEN: usize = 120;
+
+/// Makers named by server headers, titles and realms: (keyword, vendor, device type)
+const VENDOR_PATTERNS: &[(&str, &str, Option<DeviceType>)] = &[
+    ("netgear", "NETGEAR", None),
+    ("tp-link", "TP-Link", None),
+    ("asus", "ASUS", None),
+    ("airstation", "Buffalo", Some(DeviceType::Router)),
+    ("buffalo", "Buffalo", None),
+    ("aterm", "NEC", Some(DeviceType::Router)),
+    ("fritz!box", "AVM", Some(DeviceType::Router)),
+    ("mikrotik", "MikroTik", Some(DeviceType::Router)),
+    ("routeros", "MikroTik", Some(DeviceType::Router)),
+    ("unifi", "Ubiquiti", None),
+    ("yamaha", "Yamaha", None),
+    ("elecom", "ELECOM", None),
+    ("i-o data", "I-O DATA", None),
+    ("hikvision", "Hikvision", Some(DeviceType::Camera)),
+    ("dahua", "Dahua", Some(DeviceType::Camera)),
+    ("axis", "Axis", Some(DeviceType::Camera)),
+    ("synology", "Synology", Some(DeviceType::Nas)),
+    ("diskstation", "Synology", Some(DeviceType::Nas)),
+    ("qnap", "QNAP", Some(DeviceType::Nas)),
+    ("brother", "Brother", Some(DeviceType::Printer)),
+    ("epson", "Epson", Some(DeviceType::Printer)),
+    ("canon", "Canon", Some(DeviceType::Printer)),
+    ("laserjet", "HP", Some(DeviceType::Printer)),
+];
+
+/// What a device's web interface shows before logging in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebInterface {
+    pub port: u16,
+    /// URL the redirects from `/` ended at
+    pub url: String,
+    pub status: u16,
+    /// `Server` response header
+    pub server: Option<String>,
+    pub title: Option<String>,
+    /// Realm of an HTTP Basic auth challenge
+    pub basic_realm: Option<String>,
+    /// A login (password form or Basic auth) is served over plain HTTP
+    pub plaintext_login: bool,
+}
+
+impl WebInterface {
+    /// Server header, title and realm, for matching names
+    fn texts(&self) -> impl Iterator<Item = &str> {
+        [&self.server, &self.title, &self.basic_realm].into_iter().flatten().map(String::as_str)
+    }
+}
+
+fn client() -> Option<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        // Admin pages use self-signed certificates
+        .danger_accept_invalid_certs(true)
+        .build()
+        .ok()
+}
+
+/// Inspect the web interfaces of a device with a web port open, record them,
+/// fill in the maker and model they name and raise the plain-HTTP login finding
+pub async fn analyze(device: &mut Device) {
+    let open: Vec<(u16, &str)> = WEB_PORTS
+        .iter()
+        .copied()
+        .filter(|(port, _)| device.open_ports.iter().any(|p| p.protocol == "tcp" && p.number == *port))
+        .collect();
+    if open.is_empty() {
+        return;
+    }
+    let Some(client) = client() else {
+        return;
+    };
+
+    let mut interfaces = Vec::new();
+    for (port, scheme) in open {
+        if let Some(interface) = fetch(&client, &device.ip, port, scheme).await {
+            interfaces.push(interface);
+        }
+    }
+
+    identify(device, &interfaces);
+    if let Some(interface) = interfaces.iter().find(|i| i.plaintext_login) {
+        device.issues.push(to_issue(interface));
+    }
+    device.web_interfaces = interfaces;
+}
+
+async fn fetch(client: &reqwest::Client, ip: &str, port: u16, scheme: &str) -> Option<WebInterface> {
+    let response = client.get(format!("{}://{}:{}/", scheme, ip, port)).send().await.ok()?;
+    let url = response.url().to_string();
+    let status = response.status().as_u16();
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    let server = header(reqwest::header::SERVER);
+    let challenge = header(reqwest::header::WWW_AUTHENTICATE);
+    let body = response.text().await.unwrap_or_default();
+    Some(inspect(port, url, status, server, challenge.as_deref(), &body))
+}
+
+/// What the page at `url` shows
+pub(super) fn inspect(
+    port: u16,
+    url: String,
+    status: u16,
+    server: Option<String>,
+    challenge: Option<&str>,
+    body: &str,
+) -> WebInterface {
+    let basic_realm = challenge.and_then(basic_realm);
+    let lower = body.to_lowercase();
+    let password_form = ["type=\"password\"", "type='password'", "type=password"]
+        .iter()
+        .any(|p| lower.contains(p));
+    // Basic auth sends the password base64-encoded, which is plain text too
+    let login = password_form || (status == 401 && basic_realm.is_some());
+    let posts_to_https = lower.contains("action=\"https://") || lower.contains("action='https://");
+
+    WebInterface {
+        port,
+        plaintext_login: login && url.starts_with("http://") && !posts_to_https,
+        url,
+        status,
+        server: server.map(|s| clean(&s)).filter(|s| !s.is_empty()),
+        title: title(body),
+        basic_realm,
+    }
+}
+
+/// Realm of a `Basic realm="..."` challenge
+fn basic_realm(challenge: &str) -> Option<String> {
+    // ASCII lowercasing keeps the byte offsets valid in `challenge`
+    let lower = challenge.to_ascii_lowercase();
+    let start = lower.find("basic")?;
+    let realm = lower[start..].find("realm=")? + start + "realm=".len();
+    let value = challenge[realm..].trim_start();
+    let value = match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => value.split([',', ' ']).next()?,
+    };
+    let value = clean(value);
+    (!value.is_empty()).then_some(value)
+}
+
+/// Text of the page's `<title>`
+fn title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let text = body[start..end]
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ");
+    let text = clean(&text);
+    (!text.is_empty()).then_some(text)
+}
+
+/// One line of printable text, shortened
+fn clean(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_TEXT_LEN)
+        .collect()
+}
+
+/// Maker, model and type named by the web interfaces, for what discovery
+/// left open. A realm naming the maker is usually the model, e.g. "NETGEAR R7000".
+fn identify(device: &mut Device, interfaces: &[WebInterface]) {
+    for interface in interfaces {
+        for text in interface.texts() {
+            let lower = text.to_lowercase();
+            let vendor = VENDOR_PATTERNS.iter().find(|(keyword, _, _)| lower.contains(keyword));
+
+            if device.vendor.is_none() {
+                device.vendor = vendor.map(|(_, name, _)| name.to_string());
+            }
+            if device.model.is_none() {
+                device.model = datapack::match_model(&lower).or_else(|| {
+                    let realm = interface.basic_realm.as_deref() == Some(text);
+                    (realm && vendor.is_some()).then(|| text.to_string())
+                });
+            }
+            if let Some(device_type) = datapack::match_name(&lower).or(vendor.and_then(|(_, _, t)| *t)) {
+                fingerprint::record_type(device, device_type, TypeSource::WebInterface, format!("管理画面: {}", text));
+            }
+        }
+    }
+}
+
+/// Finding for a login served over plain HTTP
+pub(super) fn to_issue(interface: &WebInterface) -> SecurityIssue {
+    let kind = match interface.basic_realm {
+        Some(_) => "@admin-interface-http.basic",
+        None => "@admin-interface-http.form",
+    };
+    content::issue(
+        "admin-interface-http",
+        IssueSeverity::Medium,
+        &[("url", interface.url.clone()), ("kind", kind.to_string())],
+    )
+}
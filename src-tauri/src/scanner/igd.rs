@@ -0,0 +1,237 @@
+//! UPnP Internet Gateway Device control
+//!
+//! Unlike NAT-PMP and PCP, UPnP IGD lets anyone on the LAN list the
+//! gateway's port mappings (`GetGenericPortMappingEntry`) and remove them
+//! again (`DeletePortMapping`). Listing feeds the port-mapping finding;
+//! deleting is the one remediation the app can perform by itself.
+
+use super::net;
+use super::portmap::{MappingProtocol, PortMapping};
+use super::router::parse_xml_items;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use thiserror::Error;
+
+const SSDP_MULTICAST_ADDR: &str = "239.255.255.250:1900";
+
+/// M-SEARCH request for internet gateways
+const M_SEARCH_REQUEST: &str = "\
+M-SEARCH * HTTP/1.1\r\n\
+HOST: 239.255.255.250:1900\r\n\
+MAN: \"ssdp:discover\"\r\n\
+MX: 2\r\n\
+ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+\r\n";
+
+/// Services that manage the WAN connection's port mappings
+const CONNECTION_SERVICES: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:",
+    "urn:schemas-upnp-org:service:WANPPPConnection:",
+];
+
+/// Upper bound on mappings listed, in case a gateway never reports the end
+const MAX_MAPPINGS: u32 = 256;
+
+/// IGD control errors
+#[derive(Error, Debug)]
+pub enum IgdError {
+    #[error("The gateway does not offer UPnP port mapping control")]
+    NotFound,
+
+    #[error("Only UPnP IGD mappings can be removed")]
+    Unsupported,
+
+    #[error("Gateway request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("The gateway refused the request: {0}")]
+    Rejected(String),
+}
+
+/// The gateway's WAN connection service
+#[derive(Debug, Clone)]
+pub struct ConnectionService {
+    pub service_type: String,
+    pub control_url: String,
+}
+
+/// Find the connection service of the IGD at `gateway`
+pub async fn discover(gateway: Ipv4Addr, timeout: Duration) -> Option<ConnectionService> {
+    let socket = net::udp_bind(net::ANY).await.ok()?;
+    socket.send_to(M_SEARCH_REQUEST.as_bytes(), SSDP_MULTICAST_ADDR).await.ok()?;
+
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + timeout;
+    let location = loop {
+        match tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, addr))) if addr.ip() == gateway => {
+                let response = String::from_utf8_lossy(&buf[..len]);
+                if let Some(location) = header(&response, "LOCATION") {
+                    break location;
+                }
+            }
+            Ok(Ok(_)) => {}
+            _ => return None,
+        }
+    };
+
+    connection_service(&location, timeout).await
+}
+
+/// Find the connection service in the device description at `location`
+pub async fn connection_service(location: &str, timeout: Duration) -> Option<ConnectionService> {
+    let client = reqwest::Client::builder().timeout(timeout).build().ok()?;
+    let description = client.get(location).send().await.ok()?.text().await.ok()?;
+    let base = reqwest::Url::parse(location).ok()?;
+
+    parse_xml_items(&description, b"service", &["serviceType", "controlURL"])
+        .into_iter()
+        .find_map(|service| {
+            let service_type = service.get("serviceType")?;
+            if !CONNECTION_SERVICES.iter().any(|s| service_type.starts_with(s)) {
+                return None;
+            }
+            Some(ConnectionService {
+                service_type: service_type.clone(),
+                control_url: base.join(service.get("controlURL")?).ok()?.to_string(),
+            })
+        })
+}
+
+fn header(response: &str, name: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+    })
+}
+
+/// Call `action` on the connection service; a SOAP fault becomes `Rejected`
+async fn call(
+    service: &ConnectionService,
+    action: &str,
+    args: &[(&str, String)],
+    timeout: Duration,
+) -> Result<String, IgdError> {
+    let arguments: String = args
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, quick_xml::escape::escape(value.as_str())))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+        <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+        s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+        <u:{action} xmlns:u=\"{service}\">{arguments}</u:{action}>\
+        </s:Body></s:Envelope>",
+        action = action,
+        service = service.service_type,
+        arguments = arguments,
+    );
+
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let response = client
+        .post(&service.control_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{}#{}\"", service.service_type, action))
+        .body(body)
+        .send()
+        .await?;
+    let success = response.status().is_success();
+    let envelope = response.text().await?;
+    if success {
+        return Ok(envelope);
+    }
+
+    let fault = parse_xml_items(&envelope, b"UPnPError", &["errorCode", "errorDescription"])
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    Err(IgdError::Rejected(format!(
+        "{} {}",
+        fault.get("errorCode").map_or("?", String::as_str),
+        fault.get("errorDescription").map_or("", String::as_str),
+    )))
+}
+
+/// List the gateway's UPnP port mappings; `None` if it has no IGD
+pub async fn list_mappings(gateway: Ipv4Addr, timeout: Duration) -> Option<Vec<PortMapping>> {
+    let service = discover(gateway, timeout).await?;
+    Some(enumerate(&service, timeout).await)
+}
+
+/// List the UPnP port mappings of an internet gateway found over SSDP, from
+/// its description URLs; `None` if none of them offers port mapping control
+pub async fn list_mappings_at(ip: &str, locations: &[String], timeout: Duration) -> Option<Vec<PortMapping>> {
+    // A description on another host is not this device's
+    for location in locations.iter().filter(|l| reqwest::Url::parse(l).is_ok_and(|u| u.host_str() == Some(ip))) {
+        if let Some(service) = connection_service(location, timeout).await {
+            return Some(enumerate(&service, timeout).await);
+        }
+    }
+    None
+}
+
+/// List the port mappings of the connection service
+pub async fn enumerate(service: &ConnectionService, timeout: Duration) -> Vec<PortMapping> {
+    let mut mappings = Vec::new();
+
+    // The list ends with a SpecifiedArrayIndexInvalid fault
+    for index in 0..MAX_MAPPINGS {
+        let Ok(envelope) = call(
+            service,
+            "GetGenericPortMappingEntry",
+            &[("NewPortMappingIndex", index.to_string())],
+            timeout,
+        )
+        .await
+        else {
+            break;
+        };
+        let fields = [
+            "NewExternalPort",
+            "NewProtocol",
+            "NewInternalPort",
+            "NewInternalClient",
+            "NewPortMappingDescription",
+        ];
+        let Some(entry) = parse_xml_items(&envelope, b"GetGenericPortMappingEntryResponse", &fields)
+            .into_iter()
+            .next()
+        else {
+            break;
+        };
+        let port = |name: &str| entry.get(name).and_then(|p| p.parse().ok());
+        let (Some(external_port), Some(internal_port)) = (port("NewExternalPort"), port("NewInternalPort")) else {
+            continue;
+        };
+        mappings.push(PortMapping {
+            protocol: MappingProtocol::UpnpIgd,
+            external_port,
+            internal_client: entry.get("NewInternalClient").cloned().unwrap_or_default(),
+            internal_port,
+            transport: entry.get("NewProtocol").cloned().unwrap_or_default().to_uppercase(),
+            description: entry.get("NewPortMappingDescription").filter(|d| !d.is_empty()).cloned(),
+        });
+    }
+
+    mappings
+}
+
+/// Remove a UPnP port mapping from the gateway
+pub async fn delete_mapping(gateway: Ipv4Addr, mapping: &PortMapping, timeout: Duration) -> Result<(), IgdError> {
+    if mapping.protocol != MappingProtocol::UpnpIgd {
+        return Err(IgdError::Unsupported);
+    }
+    let service = discover(gateway, timeout).await.ok_or(IgdError::NotFound)?;
+    call(
+        &service,
+        "DeletePortMapping",
+        &[
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", mapping.external_port.to_string()),
+            ("NewProtocol", mapping.transport.to_uppercase()),
+        ],
+        timeout,
+    )
+    .await?;
+    Ok(())
+}
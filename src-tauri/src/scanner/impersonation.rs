@@ -0,0 +1,93 @@
+//! mDNS name conflicts and impersonation
+//!
+//! mDNS responders are required to rename themselves ("Printer (2)") when a
+//! name is taken, so two machines answering for the same service instance
+//! means one of them ignores the protocol, which is what a spoofing tool
+//! does. A name that moves to a different MAC between scans is also worth a
+//! look: a fake printer or NAS share collects documents and passwords sent
+//! to the real one.
+
+use super::{Device, IssueSeverity};
+use crate::vulndb::content;
+use std::collections::HashMap;
+
+/// Services whose impersonation exposes documents or credentials
+const SENSITIVE_SERVICES: &[&str] = &[
+    "._ipp._tcp.", "._ipps._tcp.", "._printer._tcp.", "._pdl-datastream._tcp.",
+    "._smb._tcp.", "._afpovertcp._tcp.", "._nfs._tcp.", "._webdav._tcp.",
+];
+
+fn is_sensitive(instance: &str) -> bool {
+    SENSITIVE_SERVICES.iter().any(|s| instance.contains(s))
+}
+
+/// Instance name without the service and domain ("Office Printer._ipp._tcp.local." → "Office Printer")
+fn label(instance: &str) -> &str {
+    instance
+        .find("._")
+        .map_or(instance, |pos| &instance[..pos])
+}
+
+/// Flag service instances claimed by more than one device. `instances` maps
+/// full instance names to the IPs that announced them, `hosts` mDNS
+/// hostnames to their addresses.
+pub fn check_duplicate_claims(
+    devices: &mut [Device],
+    instances: &HashMap<String, Vec<String>>,
+    hosts: &HashMap<String, Vec<String>>,
+) {
+    for (instance, ips) in instances {
+        // A device with two interfaces answers from two MACs under one
+        // hostname; an address without a hostname is told apart by its MAC
+        let mut claimants: Vec<String> = ips
+            .iter()
+            .filter_map(|ip| {
+                let host = hosts.iter().find(|(_, addresses)| addresses.contains(ip));
+                host.map(|(host, _)| host.to_lowercase()).or_else(|| devices.iter().find(|d| &d.ip == ip).map(|d| d.mac.to_lowercase()))
+            })
+            .collect();
+        claimants.sort();
+        claimants.dedup();
+        if claimants.len() < 2 {
+            continue;
+        }
+
+        let severity = if is_sensitive(instance) { IssueSeverity::High } else { IssueSeverity::Medium };
+        let issue = content::issue(
+            "mdns-name-conflict",
+            severity,
+            &[("name", label(instance).to_string()), ("ips", ips.join(", "))],
+        );
+        for device in devices.iter_mut().filter(|d| ips.contains(&d.ip)) {
+            device.issues.push(issue.clone());
+        }
+    }
+}
+
+/// Flag sensitive service names now announced by a different MAC than in the previous scan
+pub fn check_moved_names(devices: &mut [Device], known_devices: &[Device]) {
+    for device in devices.iter_mut() {
+        let moved: Vec<(&str, &Device)> = device
+            .mdns_instances
+            .iter()
+            .filter(|instance| is_sensitive(instance))
+            .filter_map(|instance| {
+                let previous = known_devices.iter().find(|k| k.mdns_instances.contains(instance))?;
+                (!previous.mac.eq_ignore_ascii_case(&device.mac)).then_some((instance.as_str(), previous))
+            })
+            .collect();
+
+        for (instance, previous) in moved {
+            device.issues.push(content::issue(
+                "mdns-name-moved",
+                IssueSeverity::Medium,
+                &[
+                    ("previous_mac", previous.mac.clone()),
+                    ("previous_ip", previous.ip.clone()),
+                    ("name", label(instance).to_string()),
+                    ("mac", device.mac.clone()),
+                ],
+            ));
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Interfaces and subnets to sweep
+//!
+//! Machines with a VPN, virtual adapters for VMs and containers, or more than
+//! one NIC have several IPv4 networks. Without a choice the one holding the
+//! default gateway is swept. The user can pick other interfaces, or enter
+//! ranges in CIDR notation; results of every sweep are merged. A range has to
+//! lie on one of the interfaces' links: hosts behind a router all answer with
+//! the router's MAC address, so they could not be told apart.
+//!
+//! Sweeping every address of a /16 guest network takes so long the app looks
+//! hung, so sweeps are capped. A network above the cap is narrowed to the
+//! addresses seen in the ARP cache and in the last scan, then to the /24
+//! blocks around this machine, the gateway and those hosts.
+
+use super::{arp, net, ScanError};
+use network_interface::{NetworkInterface, NetworkInterfaceConfig, V4IfAddr};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+/// Name prefixes of VPN tunnels and virtual adapters of VMs and containers
+const VIRTUAL_PREFIXES: &[&str] = &[
+    "docker", "br-", "veth", "virbr", "vboxnet", "vmnet", "utun", "tun", "tap", "wg", "tailscale", "zt", "ham",
+    "vEthernet", "VirtualBox", "VMware",
+];
+
+/// Addresses swept per scan unless configured otherwise: a /22
+pub const DEFAULT_MAX_HOSTS: usize = 1024;
+
+/// Blocks a network above the cap is narrowed to
+const BLOCK_PREFIX: u8 = 24;
+
+/// Size limit of the sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SweepSettings {
+    /// Most addresses swept per scan
+    pub max_hosts: usize,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        Self {
+            max_hosts: DEFAULT_MAX_HOSTS,
+        }
+    }
+}
+
+/// An IPv4 network of this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanInterface {
+    pub name: String,
+    pub ip: String,
+    pub netmask: String,
+    /// Network in CIDR notation, e.g. "192.168.1.0/24"
+    pub cidr: String,
+    /// Addresses of the network, before any cap
+    pub host_count: usize,
+    /// Holds the default gateway; swept when nothing is chosen
+    pub is_default: bool,
+    /// VPN tunnel or adapter of a VM or container
+    pub is_virtual: bool,
+}
+
+/// What the user chose to sweep
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanTarget {
+    /// Every address of an interface's network
+    Interface(String),
+    /// A range entered in CIDR notation
+    Subnet { network: Ipv4Addr, prefix: u8 },
+}
+
+impl FromStr for ScanTarget {
+    type Err = ScanError;
+
+    /// "192.168.10.0/24" is a range, anything else an interface name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some((address, prefix)) = s.split_once('/') else {
+            return Ok(ScanTarget::Interface(s.to_string()));
+        };
+        let invalid = || ScanError::InvalidTarget(s.to_string());
+        let address: Ipv4Addr = address.parse().map_err(|_| invalid())?;
+        let prefix: u8 = prefix.parse().ok().filter(|p| *p <= 32).ok_or_else(invalid)?;
+        Ok(ScanTarget::Subnet {
+            network: Ipv4Addr::from(u32::from(address) & mask(prefix)),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for ScanTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanTarget::Interface(name) => write!(f, "{}", name),
+            ScanTarget::Subnet { network, prefix } => write!(f, "{}/{}", network, prefix),
+        }
+    }
+}
+
+/// Addresses to sweep through one interface
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub interface: String,
+    pub local_ip: Ipv4Addr,
+    pub addresses: Vec<Ipv4Addr>,
+    /// Addresses of the chosen networks left out by the cap
+    pub skipped: usize,
+}
+
+/// Part of a chosen network on one interface: (first, last) host address
+struct Piece {
+    interface: String,
+    local_ip: Ipv4Addr,
+    range: (u32, u32),
+}
+
+/// IPv4 networks of this machine, loopback left out
+pub fn list_interfaces() -> Vec<ScanInterface> {
+    let gateway = arp::get_default_gateway();
+    let mut found = Vec::new();
+    for iface in NetworkInterface::show().unwrap_or_default() {
+        for addr in &iface.addr {
+            let network_interface::Addr::V4(V4IfAddr { ip, netmask: Some(netmask), .. }) = addr else {
+                continue;
+            };
+            if ip.is_loopback() || ip.is_unspecified() {
+                continue;
+            }
+            let prefix = u32::from(*netmask).leading_ones() as u8;
+            let network = Ipv4Addr::from(u32::from(*ip) & u32::from(*netmask));
+            found.push(ScanInterface {
+                name: iface.name.clone(),
+                ip: ip.to_string(),
+                netmask: netmask.to_string(),
+                cidr: format!("{}/{}", network, prefix),
+                host_count: host_range(*ip, *netmask).map_or(0, |(first, last)| (last - first) as usize + 1),
+                is_default: gateway.is_some_and(|gw| contains(*ip, *netmask, gw)),
+                is_virtual: is_virtual(&iface.name),
+            });
+        }
+    }
+    found
+}
+
+/// The interface swept when nothing is chosen: the pinned one, else the one
+/// holding the default gateway, else the first that is not virtual
+pub fn default_interface() -> Option<ScanInterface> {
+    let interfaces = list_interfaces();
+    if let Some(pinned) = net::interface() {
+        return interfaces.into_iter().find(|i| i.name == pinned);
+    }
+    let real = |i: &&ScanInterface| !i.is_virtual;
+    interfaces
+        .iter()
+        .filter(real)
+        .find(|i| i.is_default)
+        .or_else(|| interfaces.iter().find(|i| i.is_default))
+        .or_else(|| interfaces.iter().find(real))
+        .cloned()
+}
+
+/// The interface whose network holds `address`, preferring real ones
+pub fn interface_for(address: Ipv4Addr) -> Option<ScanInterface> {
+    let on_link = |i: &ScanInterface| {
+        let (Ok(ip), Ok(netmask)) = (i.ip.parse(), i.netmask.parse()) else {
+            return false;
+        };
+        contains(ip, netmask, address)
+    };
+    let interfaces: Vec<ScanInterface> = list_interfaces().into_iter().filter(on_link).collect();
+    interfaces.iter().find(|i| !i.is_virtual).or(interfaces.first()).cloned()
+}
+
+/// Whether the name is that of a VPN tunnel or virtual adapter
+fn is_virtual(name: &str) -> bool {
+    VIRTUAL_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+fn mask(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn contains(ip: Ipv4Addr, netmask: Ipv4Addr, address: Ipv4Addr) -> bool {
+    u32::from(ip) & u32::from(netmask) == u32::from(address) & u32::from(netmask)
+}
+
+/// Host addresses of the network, without its network and broadcast addresses
+fn host_range(ip: Ipv4Addr, netmask: Ipv4Addr) -> Option<(u32, u32)> {
+    let network = u32::from(ip) & u32::from(netmask);
+    let broadcast = network | !u32::from(netmask);
+    (broadcast > network.saturating_add(1)).then_some((network + 1, broadcast - 1))
+}
+
+/// Sweeps for the chosen targets, one per interface; the default interface
+/// when none is chosen. At most `settings.max_hosts` addresses are swept in
+/// all; `seeds` are addresses hosts were seen at, kept when narrowing.
+pub fn resolve(targets: &[ScanTarget], settings: &SweepSettings, seeds: &[Ipv4Addr]) -> Result<Vec<Sweep>, ScanError> {
+    let interfaces = if targets.is_empty() {
+        default_interface().into_iter().collect()
+    } else {
+        list_interfaces()
+    };
+    if interfaces.is_empty() {
+        return Err(ScanError::NetworkError("Could not find suitable network interface".to_string()));
+    }
+
+    let mut pieces = Vec::new();
+    if targets.is_empty() {
+        pieces.extend(interfaces.iter().filter_map(|i| piece(i, None)));
+    }
+    for target in targets {
+        let found = match target {
+            ScanTarget::Interface(name) => interfaces.iter().filter(|i| i.name == *name).find_map(|i| piece(i, None)),
+            ScanTarget::Subnet { network, prefix } => interfaces.iter().find_map(|i| piece(i, Some((*network, *prefix)))),
+        };
+        pieces.push(found.ok_or_else(|| ScanError::InvalidTarget(target.to_string()))?);
+    }
+
+    let gateway = arp::get_default_gateway();
+    let mut budget = settings.max_hosts.max(1);
+    let mut sweeps: Vec<Sweep> = Vec::new();
+    for piece in pieces {
+        let (addresses, skipped) = narrow(piece.range, piece.local_ip, gateway, seeds, budget);
+        budget = budget.saturating_sub(addresses.len());
+        match sweeps.iter_mut().find(|s| s.interface == piece.interface && s.local_ip == piece.local_ip) {
+            Some(existing) => {
+                existing.addresses.extend(addresses);
+                existing.addresses.sort();
+                existing.addresses.dedup();
+                existing.skipped += skipped;
+            }
+            None => sweeps.push(Sweep {
+                interface: piece.interface,
+                local_ip: piece.local_ip,
+                addresses,
+                skipped,
+            }),
+        }
+    }
+    Ok(sweeps)
+}
+
+/// The interface's network, or the part of `subnet` on its link
+fn piece(interface: &ScanInterface, subnet: Option<(Ipv4Addr, u8)>) -> Option<Piece> {
+    let local_ip: Ipv4Addr = interface.ip.parse().ok()?;
+    let (mut first, mut last) = host_range(local_ip, interface.netmask.parse().ok()?)?;
+    if let Some((network, prefix)) = subnet {
+        first = first.max(u32::from(network));
+        last = last.min(u32::from(network) | !mask(prefix));
+    }
+    (first <= last).then(|| Piece {
+        interface: interface.name.clone(),
+        local_ip,
+        range: (first, last),
+    })
+}
+
+/// At most `budget` addresses of the range, and how many were left out. Above
+/// the budget the seeds come first, then the blocks around this machine, the
+/// gateway and the seeds.
+fn narrow(
+    range: (u32, u32),
+    local_ip: Ipv4Addr,
+    gateway: Option<Ipv4Addr>,
+    seeds: &[Ipv4Addr],
+    budget: usize,
+) -> (Vec<Ipv4Addr>, usize) {
+    let (first, last) = range;
+    let size = (last - first) as usize + 1;
+    if size <= budget {
+        return ((first..=last).map(Ipv4Addr::from).collect(), 0);
+    }
+
+    let inside = |a: &u32| (first..=last).contains(a);
+    let seeds: Vec<u32> = seeds.iter().map(|s| u32::from(*s)).filter(inside).collect();
+    let mut chosen: HashSet<u32> = HashSet::new();
+    let mut addresses: Vec<u32> = Vec::new();
+    let mut add = |a: u32| {
+        if addresses.len() < budget && chosen.insert(a) {
+            addresses.push(a);
+        }
+    };
+    seeds.iter().copied().for_each(&mut add);
+    let anchors = [u32::from(local_ip)].into_iter().chain(gateway.map(u32::from)).chain(seeds.iter().copied());
+    for anchor in anchors.filter(inside) {
+        let block = anchor & mask(BLOCK_PREFIX);
+        (block.max(first)..=(block | !mask(BLOCK_PREFIX)).min(last)).for_each(&mut add);
+    }
+
+    addresses.sort_unstable();
+    let skipped = size - addresses.len();
+    (addresses.into_iter().map(Ipv4Addr::from).collect(), skipped)
+}
@@ -0,0 +1,189 @@
+//! Router login page advisory
+//!
+//! Without trying a single password, the login page of a router's web UI
+//! tells a lot about how well the admin account is protected: whether the
+//! password travels unencrypted, whether the page gives away the default
+//! user name, whether repeated attempts look throttled and whether a WPS PIN
+//! can be entered. Each indicator becomes an advisory finding.
+
+use super::firmware::{self, Page};
+use super::{Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+
+/// Login pages of common router UIs that the root page does not redirect to
+const LOGIN_PATHS: &[&str] = &["/login.html", "/login.htm", "/cgi-bin/luci/"];
+
+/// User names routers ship with
+const DEFAULT_USERS: &[&str] = &["admin", "root", "user", "administrator", "supervisor"];
+
+/// Page text pointing at the factory credentials
+const DEFAULT_HINTS: &[&str] = &[
+    "default password",
+    "default username",
+    "default user name",
+    "初期パスワード",
+    "初期設定のパスワード",
+    "工場出荷時",
+];
+
+/// Signs that repeated login attempts are limited
+const LOCKOUT_HINTS: &[&str] = &["captcha", "lockout", "locked", "too many", "attempts", "試行", "ロック"];
+
+/// Response headers of rate-limited endpoints
+const RATE_LIMIT_HEADERS: &[&str] = &["retry-after", "ratelimit-limit", "x-ratelimit-limit", "x-rate-limit-limit"];
+
+/// What the login page reveals
+#[derive(Debug, Clone, Default)]
+pub struct LoginIndicators {
+    pub url: String,
+    /// The password form is served and submitted over plain HTTP
+    pub http_only: bool,
+    /// User name filled in by the page itself
+    pub prefilled_user: Option<String>,
+    /// The page mentions the factory credentials
+    pub default_hint: bool,
+    /// Neither headers nor text suggest that attempts are limited
+    pub no_lockout_signs: bool,
+    pub wps_pin: bool,
+}
+
+fn has_password_field(body: &str) -> bool {
+    ["type=\"password\"", "type='password'", "type=password"].iter().any(|p| body.contains(p))
+}
+
+/// Value of `name` in a lowercased tag, e.g. `value` in `<input value="admin"`
+pub(super) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.match_indices(name).find_map(|(i, _)| {
+        let before = tag[..i].chars().last()?;
+        let rest = tag[i + name.len()..].trim_start().strip_prefix('=')?;
+        before.is_whitespace().then_some(rest.trim_start())
+    })?;
+    match start.chars().next()? {
+        quote @ ('"' | '\'') => start[1..].split(quote).next(),
+        _ => start.split(|c: char| c.is_whitespace() || c == '>').next(),
+    }
+}
+
+pub(super) fn input_tags(body: &str) -> impl Iterator<Item = &str> {
+    body.match_indices("<input").map(|(i, _)| {
+        let tag = &body[i..];
+        &tag[..tag.find('>').unwrap_or(tag.len())]
+    })
+}
+
+/// Find the login page, following one redirect within the same scheme
+async fn find_login_page(client: &reqwest::Client, base: &str) -> Option<(String, Page)> {
+    let url = format!("{}/", base);
+    let root = firmware::fetch(client, &url).await?;
+    if has_password_field(&root.body.to_lowercase()) {
+        return Some((url, root));
+    }
+
+    let redirect = root
+        .location
+        .filter(|l| (300..400).contains(&root.status) && !l.starts_with("https://"))
+        .map(|l| if l.starts_with("http://") { l } else { format!("{}/{}", base, l.trim_start_matches('/')) });
+    for url in redirect.into_iter().chain(LOGIN_PATHS.iter().map(|path| format!("{}{}", base, path))) {
+        if let Some(page) = firmware::fetch(client, &url).await {
+            if page.status == 200 && has_password_field(&page.body.to_lowercase()) {
+                return Some((url, page));
+            }
+        }
+    }
+    None
+}
+
+/// Analyze the device's login page; `None` if no login form was found
+pub async fn analyze(device: &Device) -> Option<LoginIndicators> {
+    let client = firmware::client()?;
+    let has_port = |port: u16| device.open_ports.iter().any(|p| p.number == port);
+
+    // A root page that redirects to HTTPS keeps the password encrypted
+    let http_root = if has_port(80) {
+        firmware::fetch(&client, &format!("http://{}/", device.ip)).await
+    } else {
+        None
+    };
+    let to_https = http_root
+        .as_ref()
+        .and_then(|page| page.location.as_deref())
+        .is_some_and(|l| l.starts_with("https://"));
+
+    let mut found = None;
+    if has_port(80) && !to_https {
+        found = find_login_page(&client, &format!("http://{}", device.ip)).await;
+    }
+    if found.is_none() && has_port(443) {
+        found = find_login_page(&client, &format!("https://{}", device.ip)).await;
+    }
+    let (url, page) = found?;
+    Some(inspect(url, &page))
+}
+
+/// Indicators of the login page at `url`
+pub(super) fn inspect(url: String, page: &Page) -> LoginIndicators {
+    let body = page.body.to_lowercase();
+
+    let posts_to_https = body.contains("action=\"https://") || body.contains("action='https://");
+    let prefilled_user = input_tags(&body)
+        .filter(|tag| attribute(tag, "type").map_or(true, |t| t == "text"))
+        .filter(|tag| {
+            let field = attribute(tag, "name").or_else(|| attribute(tag, "id")).unwrap_or_default();
+            field.contains("user") || field.contains("login") || field.contains("name")
+        })
+        .filter_map(|tag| attribute(tag, "value"))
+        .find(|value| DEFAULT_USERS.contains(value))
+        .map(str::to_string);
+    let rate_limited = page
+        .headers
+        .keys()
+        .any(|name| RATE_LIMIT_HEADERS.contains(&name.as_str()))
+        || LOCKOUT_HINTS.iter().any(|hint| body.contains(hint));
+    let wps_pin = body.contains("wps pin")
+        || input_tags(&body).any(|tag| {
+            let field = attribute(tag, "name").or_else(|| attribute(tag, "id")).unwrap_or_default();
+            field.contains("wps") && field.contains("pin")
+        });
+
+    LoginIndicators {
+        http_only: url.starts_with("http://") && !posts_to_https,
+        url,
+        prefilled_user,
+        default_hint: DEFAULT_HINTS.iter().any(|hint| body.contains(hint)),
+        no_lockout_signs: !rate_limited,
+        wps_pin,
+    }
+}
+
+/// Advisory findings; `http_reported` skips the plain-HTTP one when the
+/// firmware checklist already raised it
+pub fn to_issues(indicators: &LoginIndicators, http_reported: bool) -> Vec<SecurityIssue> {
+    let url = || ("url", indicators.url.clone());
+    let mut issues = Vec::new();
+
+    if indicators.http_only && !http_reported {
+        issues.push(content::issue("login-page-http", IssueSeverity::Medium, &[url()]));
+    }
+    if indicators.prefilled_user.is_some() || indicators.default_hint {
+        let detail = match &indicators.prefilled_user {
+            Some(_) => "@login-page.prefilled",
+            None => "@login-page.default-text",
+        };
+        issues.push(content::issue(
+            "login-page-default-user",
+            IssueSeverity::Low,
+            &[
+                url(),
+                ("detail", detail.to_string()),
+                ("user", indicators.prefilled_user.clone().unwrap_or_default()),
+            ],
+        ));
+    }
+    if indicators.no_lockout_signs {
+        issues.push(content::issue("login-page-no-lockout", IssueSeverity::Info, &[url()]));
+    }
+    if indicators.wps_pin {
+        issues.push(content::issue("login-page-wps-pin", IssueSeverity::Medium, &[url()]));
+    }
+    issues
+}
@@ -1,115 +1,436 @@
 //! mDNS-based device discovery
 //!
 //! Uses Multicast DNS to discover devices and resolve their hostnames.
+//! A and AAAA records of the same hostname tie a dual-stack device's IPv6
+//! addresses to its IPv4 address. Devices are passed on as they resolve, so
+//! the UI fills in while the rest of discovery still runs, and the service
+//! types each device answers for are kept for fingerprinting.
 
+use super::ProgressSink;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
-use mdns_sd::{ServiceDaemon, ServiceEvent};
-
-/// Scan for mDNS services and resolve hostnames
-pub fn scan_mdns(timeout: Duration) -> HashMap<String, String> {
-    let mut resolved_names = HashMap::new();
-    
-    // Create a daemon
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+const MDNS_PORT: u16 = 5353;
+
+/// DNS-SD meta-query: answered with the service types a host offers
+const META_QUERY: &str = "_services._dns-sd._udp.local.";
+
+/// Service types browsed for from the start, as the meta-query goes
+/// unanswered by some devices
+const SERVICE_TYPES: &[&str] = &[
+    "_googlecast._tcp.local.",       // Google devices
+    "_airplay._tcp.local.",          // Apple devices (AirPlay)
+    "_companion-link._tcp.local.",   // Apple devices (HomeKit/Sidecar)
+    "_device-info._tcp.local.",      // General device info
+    "_ipp._tcp.local.",              // Printers
+    "_ipps._tcp.local.",             // Printers (TLS)
+    "_printer._tcp.local.",          // LPD printers
+    "_http._tcp.local.",             // Web interfaces
+    "_smb._tcp.local.",              // Windows/NAS file sharing
+    "_afpovertcp._tcp.local.",       // Apple/NAS file sharing
+    "_rdp._tcp.local.",              // Remote desktop
+    "_raop._tcp.local.",             // AirPlay audio (Apple)
+    "_sleep-proxy._udp.local.",      // Apple sleep proxy
+    "_workstation._tcp.local.",      // Linux/macOS workstations
+    "_homekit._tcp.local.",          // HomeKit accessories
+    "_hap._tcp.local.",              // HomeKit Accessory Protocol
+    "_matter._tcp.local.",           // Matter smart home devices
+    "_spotify-connect._tcp.local.",  // Spotify Connect devices
+    "_amzn-wplay._tcp.local.",       // Amazon devices
+    "_androidtvremote2._tcp.local.", // Android TV
+    "_touch-able._tcp.local.",       // iOS Remote app
+    "_hue._tcp.local.",              // Philips Hue bridges
+    "_meshcop._udp.local.",          // Thread border routers
+];
+
+/// DNS record types read from overheard responses
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+
+/// Names resolved during an mDNS scan
+#[derive(Debug, Default)]
+pub struct MdnsResults {
+    /// IP → best display name
+    pub names: HashMap<String, String>,
+    /// Service instance full name (e.g. "Office Printer._ipp._tcp.local.") → IPs claiming it
+    pub instances: HashMap<String, Vec<String>>,
+    /// Service instance full name → TXT record
+    pub txt: HashMap<String, HashMap<String, String>>,
+    /// Hostname (without `.local`) → its IPv4 and IPv6 addresses
+    pub hosts: HashMap<String, Vec<String>>,
+    /// IP → service types it answers for, e.g. "_ipp._tcp"
+    pub services: HashMap<String, Vec<String>>,
+}
+
+/// A device as mDNS resolved it, sent while the scan still runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MdnsDiscovery {
+    pub ip: String,
+    pub name: String,
+    /// Service types found so far, e.g. "_ipp._tcp"
+    pub services: Vec<String>,
+}
+
+impl MdnsResults {
+    /// IPv6 addresses announced under the same hostname as `ip`
+    pub fn ipv6_for(&self, ip: &str) -> Vec<String> {
+        let mut found: Vec<String> = self
+            .hosts
+            .values()
+            .filter(|addresses| addresses.iter().any(|a| a == ip))
+            .flatten()
+            .filter(|a| a.parse::<Ipv6Addr>().is_ok())
+            .cloned()
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    /// Service types any of the addresses answers for, sorted
+    pub fn services_for(&self, addresses: &[String]) -> Vec<String> {
+        let mut found: Vec<String> =
+            addresses.iter().filter_map(|a| self.services.get(a)).flatten().cloned().collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    fn add_host_address(&mut self, host: &str, address: IpAddr) {
+        let addresses = self.hosts.entry(host.to_string()).or_default();
+        let address = address.to_string();
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    fn add_service(&mut self, ip: &str, instance: &str) {
+        let Some(service) = service_type(instance) else {
+            return;
+        };
+        let services = self.services.entry(ip.to_string()).or_default();
+        if !services.contains(&service) {
+            services.push(service);
+        }
+    }
+
+    /// Record a resolved service instance; returns the addresses it answered from
+    fn add_resolved(&mut self, info: &ServiceInfo) -> Vec<String> {
+        let fullname = info.get_fullname();
+        // Instance name: the part before the first dot
+        let instance_name = fullname.split('.').next().unwrap_or("").to_string();
+        // Hostname, e.g. "My-iPhone.local."
+        let hostname = info.get_hostname().trim_end_matches('.');
+
+        // TXT records may carry a friendly name (fn, n, name keys)
+        let txt_name = info
+            .get_properties()
+            .iter()
+            .find(|prop| matches!(prop.key(), "fn" | "n" | "name") && !prop.val_str().is_empty())
+            .map(|prop| prop.val_str().to_string());
+        self.txt.insert(
+            fullname.to_string(),
+            info.get_properties()
+                .iter()
+                .map(|prop| (prop.key().to_string(), prop.val_str().to_string()))
+                .collect(),
+        );
+
+        // Priority: TXT friendly name > instance name > hostname
+        let display_name = match txt_name {
+            Some(name) => name,
+            None if !instance_name.is_empty() && instance_name != hostname => instance_name,
+            None => hostname.to_string(),
+        };
+
+        // A and AAAA records alike
+        let mut addresses = Vec::new();
+        for ip in info.get_addresses() {
+            self.add_host_address(hostname.trim_end_matches(".local"), *ip);
+            let ip = ip.to_string();
+            let claimants = self.instances.entry(fullname.to_string()).or_default();
+            if !claimants.contains(&ip) {
+                claimants.push(ip.clone());
+            }
+            self.add_service(&ip, fullname);
+            // Keep the longer, more descriptive name
+            let existing = self.names.get(&ip).map_or(0, String::len);
+            if display_name.len() > existing {
+                self.names.insert(ip.clone(), display_name.clone());
+            }
+            addresses.push(ip);
+        }
+        addresses
+    }
+}
+
+/// Service type of an instance or type name, e.g.
+/// "Office Printer._ipp._tcp.local." → "_ipp._tcp"
+pub fn service_type(name: &str) -> Option<String> {
+    let labels: Vec<&str> = name.trim_end_matches('.').trim_end_matches(".local").rsplit('.').take(2).collect();
+    match labels[..] {
+        [protocol, service] if service.starts_with('_') && (protocol == "_tcp" || protocol == "_udp") => {
+            Some(format!("{}.{}", service, protocol))
+        }
+        _ => None,
+    }
+}
+
+/// Whether an mDNS daemon can be started (multicast sockets are available)
+pub fn daemon_available() -> Result<(), String> {
+    let mdns = ServiceDaemon::new().map_err(|e| e.to_string())?;
+    let _ = mdns.shutdown();
+    Ok(())
+}
+
+/// Browse for mDNS services for `timeout` and resolve hostnames, passing
+/// each device to `progress` as soon as one of its services resolves
+pub async fn scan_mdns(timeout: Duration, progress: &dyn ProgressSink) -> MdnsResults {
+    let mut results = MdnsResults::default();
     let mdns = match ServiceDaemon::new() {
         Ok(d) => d,
         Err(e) => {
             eprintln!("Failed to create mDNS daemon: {}", e);
-            return resolved_names;
+            return results;
         }
     };
 
-    // Browse for common services
-    // Scanning for efficient service types for device discovery
-    let services_to_scan = vec![
-        "_googlecast._tcp.local.",       // Google devices
-        "_airplay._tcp.local.",          // Apple devices (AirPlay)
-        "_companion-link._tcp.local.",   // Apple devices (HomeKit/Sidecar)
-        "_device-info._tcp.local.",      // General device info
-        "_ipp._tcp.local.",              // Printers
-        "_http._tcp.local.",             // Web interfaces
-        "_smb._tcp.local.",              // Windows/NAS file sharing
-        "_rdp._tcp.local.",              // Remote desktop
-        "_raop._tcp.local.",             // AirPlay audio (Apple)
-        "_sleep-proxy._udp.local.",      // Apple sleep proxy
-        "_workstation._tcp.local.",      // Linux/macOS workstations
-        "_homekit._tcp.local.",          // HomeKit accessories
-        "_hap._tcp.local.",              // HomeKit Accessory Protocol
-        "_matter._tcp.local.",           // Matter smart home devices
-        "_spotify-connect._tcp.local.",  // Spotify Connect devices
-        "_amzn-wplay._tcp.local.",       // Amazon devices
-        "_androidtvremote2._tcp.local.", // Android TV
-        "_touch-able._tcp.local.",       // iOS Remote app
-    ];
-    
-    // Using a receiver to collect events
-    // We browse all services concurrently
-    let receiver = mdns.browse("_services._dns-sd._udp.local.").expect("Failed to browse");
-    
-    // Also explicitly browse specific services as discovery of _services can be unreliable for some devices
-    for service in &services_to_scan {
-        let _ = mdns.browse(service);
-    }
-    
-    let deadline = Instant::now() + timeout;
-    
-    while Instant::now() < deadline {
-        // Use a short timeout for recv to allow checking deadline
-        if let Ok(event) = receiver.recv_timeout(Duration::from_millis(100)) {
-            if let ServiceEvent::ServiceResolved(info) = event {
-                // Get the "friendly name" part of the service instance name if possible
-                let fullname = info.get_fullname();
-                // Extract instance name (part before first dot)
-                let instance_name = fullname.split('.').next().unwrap_or("").to_string();
-
-                // Get hostname (e.g. "My-iPhone.local.")
-                let hostname = info.get_hostname();
-                let clean_hostname = hostname.trim_end_matches('.');
-
-                // Check TXT records for friendly name (fn, n, name keys)
-                let txt_name: Option<String> = info.get_properties().iter().find_map(|prop| {
-                    let key = prop.key();
-                    if key == "fn" || key == "n" || key == "name" {
-                        let val = prop.val_str();
-                        if !val.is_empty() {
-                            Some(val.to_string())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
+    // Every browse feeds one channel
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let mut browsing: Vec<String> = Vec::new();
+    for service in std::iter::once(META_QUERY).chain(SERVICE_TYPES.iter().copied()) {
+        browse(&mdns, service, &sender, &mut browsing);
+    }
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(Some(event)) = tokio::time::timeout_at(deadline, events.recv()).await {
+        match event {
+            // The meta-query names service types not in the list
+            ServiceEvent::ServiceFound(service_type, name) if service_type == META_QUERY => {
+                browse(&mdns, &name, &sender, &mut browsing);
+            }
+            ServiceEvent::ServiceResolved(info) => {
+                for ip in results.add_resolved(&info) {
+                    progress.discovered(&MdnsDiscovery {
+                        name: results.names.get(&ip).cloned().unwrap_or_default(),
+                        services: results.services.get(&ip).cloned().unwrap_or_default(),
+                        ip,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for service in &browsing {
+        let _ = mdns.stop_browse(service);
+    }
+    // Closes the receivers, which ends the forwarding tasks
+    let _ = mdns.shutdown();
+    results
+}
+
+/// Start browsing for `service` unless already browsing, forwarding its events to `sender`
+fn browse(
+    mdns: &ServiceDaemon,
+    service: &str,
+    sender: &mpsc::UnboundedSender<ServiceEvent>,
+    browsing: &mut Vec<String>,
+) {
+    if browsing.iter().any(|s| s == service) {
+        return;
+    }
+    let receiver = match mdns.browse(service) {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            eprintln!("Failed to browse {}: {}", service, e);
+            return;
+        }
+    };
+    browsing.push(service.to_string());
+    let sender = sender.clone();
+    tokio::spawn(async move {
+        while let Ok(event) = receiver.recv_async().await {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Collect mDNS responses other hosts send to the multicast group for
+/// `duration`, without sending any query. Devices announce themselves when
+/// they join the network and answer other machines' queries to the group.
+pub async fn listen(duration: Duration) -> MdnsResults {
+    let mut results = MdnsResults::default();
+    let deadline = tokio::time::Instant::now() + duration;
+
+    let socket = match listen_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to join the mDNS multicast group: {}", e);
+            return results;
+        }
+    };
+
+    let mut buf = [0u8; 9000];
+    while let Ok(Ok((len, addr))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let source = addr.ip().to_string();
+        for record in parse_response(&buf[..len]) {
+            match record {
+                Record::Address(host, address) => {
+                    results.add_host_address(&host, address);
+                    // AAAA records come with A records; the A record names the sender
+                    if address.is_ipv4() {
+                        results.names.entry(source.clone()).or_insert(host);
                     }
-                });
-
-                // Priority: TXT friendly name > instance name > hostname
-                let display_name = if let Some(ref tn) = txt_name {
-                    tn.clone()
-                } else if !instance_name.is_empty() && instance_name != clean_hostname {
-                    instance_name
-                } else {
-                    clean_hostname.to_string()
-                };
-
-                for ip in info.get_addresses() {
-                    // Update if we don't have a name or if current name is better (longer/more descriptive)
-                    let ip_str = ip.to_string();
-                    if let Some(existing) = resolved_names.get(&ip_str) {
-                         if display_name.len() > existing.len() {
-                             resolved_names.insert(ip_str, display_name.clone());
-                         }
-                    } else {
-                        resolved_names.insert(ip_str, display_name.clone());
+                }
+                Record::Instance(instance) => {
+                    let claimants = results.instances.entry(instance.clone()).or_default();
+                    if !claimants.contains(&source) {
+                        claimants.push(source.clone());
                     }
+                    results.add_service(&source, &instance);
+                    // An instance name beats a hostname
+                    let label = instance.split('.').next().unwrap_or_default().to_string();
+                    if !label.is_empty() {
+                        results.names.insert(source.clone(), label);
+                    }
+                }
+                Record::Txt(instance, entries) => {
+                    results.txt.entry(instance).or_default().extend(entries);
                 }
             }
         }
     }
-    
-    // Stop browsing (daemon drop handles this, but good practice)
-    let _ = mdns.stop_browse("_services._dns-sd._udp.local.");
-    for service in &services_to_scan {
-        let _ = mdns.stop_browse(service);
+    results
+}
+
+/// A socket on the mDNS port that receives the group's traffic. The port is
+/// shared with the OS resolver.
+fn listen_socket() -> std::io::Result<tokio::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket.into())
+}
+
+/// What an overheard record says about its sender
+enum Record {
+    /// Hostname and address of an A or AAAA record, without `.local`
+    Address(String, IpAddr),
+    /// Service instance the sender answers for
+    Instance(String),
+    /// TXT entries of a service instance
+    Txt(String, HashMap<String, String>),
+}
+
+/// Records of a DNS response; queries and malformed packets yield nothing
+fn parse_response(packet: &[u8]) -> Vec<Record> {
+    let mut records = Vec::new();
+    let Some(header) = packet.get(..12) else {
+        return records;
+    };
+    let is_response = header[2] & 0x80 != 0;
+    let count = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]) as usize;
+    if !is_response {
+        return records;
     }
 
-    resolved_names
+    let mut pos = 12;
+    for _ in 0..count(4) {
+        let Some((_, next)) = read_name(packet, pos) else {
+            return records;
+        };
+        pos = next + 4;
+    }
+    // Answers, authority and additional records
+    for _ in 0..count(6) + count(8) + count(10) {
+        let Some((name, next)) = read_name(packet, pos) else {
+            break;
+        };
+        let Some(fixed) = packet.get(next..next + 10) else {
+            break;
+        };
+        let record_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let length = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let data_start = next + 10;
+        let Some(data) = packet.get(data_start..data_start + length) else {
+            break;
+        };
+        match record_type {
+            TYPE_A if length == 4 => {
+                let host = name.trim_end_matches('.').trim_end_matches(".local");
+                let address = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
+                records.push(Record::Address(host.to_string(), address.into()));
+            }
+            TYPE_AAAA if length == 16 => {
+                let host = name.trim_end_matches('.').trim_end_matches(".local");
+                let octets: [u8; 16] = data.try_into().unwrap_or_default();
+                records.push(Record::Address(host.to_string(), Ipv6Addr::from(octets).into()));
+            }
+            TYPE_PTR if name.starts_with('_') && !name.starts_with("_services._dns-sd") => {
+                if let Some((instance, _)) = read_name(packet, data_start) {
+                    records.push(Record::Instance(instance));
+                }
+            }
+            TYPE_TXT => records.push(Record::Txt(name, parse_txt(data))),
+            _ => {}
+        }
+        pos = data_start + length;
+    }
+    records
+}
+
+/// Read a possibly compressed name at `pos`; returns it with a trailing dot
+/// and the position after it
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    // Bounds the pointer chain of a malicious packet
+    for _ in 0..128 {
+        let length = *packet.get(pos)? as usize;
+        if length == 0 {
+            return Some((name, end.unwrap_or(pos + 1)));
+        }
+        if length & 0xC0 == 0xC0 {
+            let pointer = ((length & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + length)?;
+        name.push_str(&String::from_utf8_lossy(label));
+        name.push('.');
+        pos += 1 + length;
+    }
+    None
+}
+
+fn parse_txt(data: &[u8]) -> HashMap<String, String> {
+    let mut entries = HashMap::new();
+    let mut pos = 0;
+    while let Some(&length) = data.get(pos) {
+        let Some(entry) = data.get(pos + 1..pos + 1 + length as usize) else {
+            break;
+        };
+        let entry = String::from_utf8_lossy(entry);
+        let (key, value) = entry.split_once('=').unwrap_or((&entry, ""));
+        if !key.is_empty() {
+            entries.insert(key.to_string(), value.to_string());
+        }
+        pos += 1 + length as usize;
+    }
+    entries
 }
@@ -5,17 +5,118 @@
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 use tauri::Emitter;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::future::Future;
+use std::sync::Mutex;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
+use crate::settings::locale::Language;
+use crate::vulndb::content;
+
+pub mod apple;
 pub mod arp;
+pub mod ble;
+pub mod budget;
+pub mod conflict;
+pub mod credentials;
+pub mod datapack;
+pub mod dependency;
+pub mod dhcp;
+pub mod dns;
+pub mod estimate;
+pub mod exposure;
+pub mod firmware;
 pub mod ports;
 pub mod fingerprint;
+pub mod http;
+pub mod igd;
+pub mod impersonation;
+pub mod interfaces;
+pub mod login_page;
 pub mod mdns;
 pub mod nbns;
+pub mod net;
+pub mod onvif;
+pub mod oui;
+pub mod passive;
+pub mod platform;
+pub mod portmap;
+pub mod preview;
+pub mod privacy;
+pub mod quick;
+pub mod router;
+pub mod router_audit;
+pub mod rtsp;
+pub mod scoring;
+pub mod selftest;
+pub mod shares;
+pub mod snmp;
 pub mod ssdp;
+pub mod stealth;
+pub mod topology;
+pub mod wifi;
+pub mod wan;
+pub mod wsd;
+
+pub use scoring::{DeploymentContext, ScoringPolicy};
+
+impl ScanOptions {
+    /// Whether the user excluded the device from active checks
+    pub fn is_excluded(&self, mac: &str) -> bool {
+        self.excluded.iter().any(|m| m.eq_ignore_ascii_case(mac))
+    }
+
+    /// Whether the user allowed login attempts with factory credentials on the device
+    pub fn credentials_consented(&self, mac: &str) -> bool {
+        !self.is_excluded(mac) && self.credential_consent.iter().any(|m| m.eq_ignore_ascii_case(mac))
+    }
+}
+
+/// Settings that influence a scan beyond its level
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    pub policy: ScoringPolicy,
+    pub exposure: exposure::ExposureSettings,
+    /// Devices from the previous scan, re-verified if they don't respond
+    pub known_devices: Vec<Device>,
+    /// Router API to merge the DHCP client list from
+    pub router: router::RouterSettings,
+    /// Lowercase MACs of devices the user excluded from active checks
+    pub excluded: Vec<String>,
+    /// Lowercase MACs of devices the user allowed default credential logins on (Level 3)
+    pub credential_consent: Vec<String>,
+    /// Time limit of the whole scan; `None` for no limit
+    pub max_duration: Option<std::time::Duration>,
+    /// Discover by listening only, whatever level is requested
+    pub strictly_passive: bool,
+    /// Aborts the scan; [`cancel_scan`] triggers the one that is running
+    pub cancel: CancellationToken,
+    /// Ports probed on each device
+    pub port_config: ports::ScanConfig,
+    /// Data pack port profile whose ports are added; every profile's when `None`
+    pub port_profile: Option<String>,
+    /// Interfaces and ranges to sweep; the default interface when empty
+    pub targets: Vec<interfaces::ScanTarget>,
+    /// Size limit of the sweep
+    pub sweep: interfaces::SweepSettings,
+}
+
+/// How long mDNS and SSDP listen for responses
+pub const DISCOVERY_WINDOW: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// NetBIOS name query timeout
+pub const NBNS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Gateway port-mapping probe timeout
+pub const GATEWAY_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long to keep re-probing known devices that did not respond
+pub const REVERIFY_WINDOW: std::time::Duration = std::time::Duration::from_secs(20);
 
 /// Scan level determining the depth of security analysis
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "snake_case")]
 pub enum ScanLevel {
     /// Passive information gathering (ARP, mDNS, NetBIOS)
@@ -34,6 +135,8 @@ pub enum SecurityLevel {
     Safe,
     Warning,
     Danger,
+    /// No findings, but not checked deeply enough for its device type to be called safe
+    InsufficientData,
     Unknown,
 }
 
@@ -44,20 +147,94 @@ impl Default for SecurityLevel {
 }
 
 /// Discovered network device
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Device {
     pub id: String,
     pub name: Option<String>,
     pub device_type: DeviceType,
+    /// How sure the device type is, and the signals it rests on
+    #[serde(default)]
+    pub identification: fingerprint::Identification,
     pub ip: String,
+    /// IPv6 addresses sharing this device's MAC address
+    #[serde(default)]
+    pub ipv6_addresses: Vec<String>,
     pub mac: String,
+    /// The MAC address is locally administered, so it names no vendor
+    #[serde(default)]
+    pub mac_randomized: bool,
     pub vendor: Option<String>,
+    /// Hardware model, e.g. from an Apple `model=` identifier
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Operating system guessed from the TCP/IP stack and the services, e.g. "Linux (embedded)"
+    #[serde(default)]
+    pub os_guess: Option<String>,
+    /// UPnP device description, if the device answered SSDP
+    #[serde(default)]
+    pub ssdp: Option<ssdp::SsdpDescription>,
+    /// WS-Discovery types and scopes, if the device answered a Probe
+    #[serde(default)]
+    pub wsd: Option<wsd::WsdDevice>,
+    /// DHCP client fingerprint, if the device was seen asking for an address
+    #[serde(default)]
+    pub dhcp: Option<dhcp::DhcpFingerprint>,
+    /// ONVIF device information, if the camera answered without credentials
+    #[serde(default)]
+    pub onvif: Option<onvif::OnvifInfo>,
+    /// What the SNMP agent answered with the default community
+    #[serde(default)]
+    pub snmp: Option<snmp::SnmpInfo>,
+    /// Web interfaces on the common web ports, as seen before logging in
+    #[serde(default)]
+    pub web_interfaces: Vec<http::WebInterface>,
     pub hostname: Option<String>,
     pub open_ports: Vec<Port>,
     pub security_level: SecurityLevel,
     pub security_score: u8,
     pub issues: Vec<SecurityIssue>,
     pub last_seen: chrono::DateTime<chrono::Utc>,
+    /// Checks that could not be completed for this device
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Lease information reported by the router
+    #[serde(default)]
+    pub dhcp_lease: Option<router::DhcpLease>,
+    /// Open router firmware and its hardening checklist
+    #[serde(default)]
+    pub firmware: Option<firmware::FirmwareReport>,
+    /// Checklist of the default gateway (Level 2)
+    #[serde(default)]
+    pub router_audit: Option<router_audit::RouterAudit>,
+    /// Ports the router forwards to the device and whether the internet reaches them
+    #[serde(default)]
+    pub forwarded_ports: Vec<exposure::ForwardedPort>,
+    /// How the maker handles personal data (not part of the security score)
+    #[serde(default)]
+    pub privacy_note: Option<privacy::PrivacyNote>,
+    /// mDNS service instances this device announced
+    #[serde(default)]
+    pub mdns_instances: Vec<String>,
+    /// mDNS service types it answers for, e.g. "_ipp._tcp"
+    #[serde(default)]
+    pub mdns_services: Vec<String>,
+    /// Every way the device was found in this scan
+    #[serde(default)]
+    pub discovery_sources: Vec<DiscoverySource>,
+    /// Level of the checks the device went through; `None` for scans stored
+    /// before this was recorded
+    #[serde(default)]
+    pub scan_level: Option<ScanLevel>,
+    /// Devices that likely stop working without this one
+    #[serde(default)]
+    pub dependents: Vec<dependency::Dependency>,
+    /// Phases the scan's time limit skipped or cut short for this device
+    #[serde(default)]
+    pub reduced_coverage: Vec<budget::ScanPhase>,
+    /// The maker and model's known vulnerabilities could not be looked up
+    /// (offline); the lookup is queued
+    #[serde(default)]
+    pub enrichment_pending: bool,
 }
 
 /// Device type classification
@@ -74,10 +251,52 @@ pub enum DeviceType {
     Nas,
     Computer,
     Smartphone,
+    GameConsole,
     #[default]
     Unknown,
 }
 
+/// How a device was found
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoverySource {
+    /// Answered the sweep or was in the OS neighbour cache
+    Arp,
+    /// Listed as connected in the router's client list
+    RouterLease,
+    Mdns,
+    Nbns,
+    Ssdp,
+    Wsd,
+    /// Reverse DNS name
+    DnsPtr,
+    /// Overheard DHCP request
+    Dhcp,
+    /// IPv6 neighbour sharing its MAC address
+    Ipv6Neighbor,
+    /// Seen in an earlier scan and answered a re-probe
+    Reverify,
+}
+
+impl DiscoverySource {
+    /// Name shown in the UI and reports
+    pub fn label(&self, language: Language) -> String {
+        let key = match self {
+            DiscoverySource::Arp => "source.arp",
+            DiscoverySource::RouterLease => "source.router-lease",
+            DiscoverySource::Mdns => "source.mdns",
+            DiscoverySource::Nbns => "source.nbns",
+            DiscoverySource::Ssdp => "source.ssdp",
+            DiscoverySource::Wsd => "source.wsd",
+            DiscoverySource::DnsPtr => "source.dns-ptr",
+            DiscoverySource::Dhcp => "source.dhcp",
+            DiscoverySource::Ipv6Neighbor => "source.ipv6-neighbor",
+            DiscoverySource::Reverify => "source.reverify",
+        };
+        content::label(key, language)
+    }
+}
+
 /// Open port information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Port {
@@ -86,6 +305,23 @@ pub struct Port {
     pub service: Option<String>,
     pub version: Option<String>,
     pub is_secure: bool,
+    /// Whether a second probe confirmed the port
+    #[serde(default)]
+    pub verification: PortVerification,
+    /// What the port is commonly used for and why it matters
+    #[serde(default)]
+    pub risk_note: Option<String>,
+}
+
+/// How many probes agreed that a port is open
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortVerification {
+    /// Open on both the scan and the verification pass
+    Verified,
+    /// Seen open only once; the device may be rate-limiting probes
+    #[default]
+    SingleAttempt,
 }
 
 /// Security issue found on a device
@@ -93,9 +329,46 @@ pub struct Port {
 pub struct SecurityIssue {
     pub id: String,
     pub severity: IssueSeverity,
+    /// Severity the issue was raised with, kept once the scoring policy
+    /// adjusted `severity` for the deployment context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_severity: Option<IssueSeverity>,
     pub title: String,
     pub description: String,
     pub remediation: String,
+    /// Learn-more links (CVE entries, vendor advisories, explainers)
+    #[serde(default)]
+    pub references: Vec<Reference>,
+    /// Values substituted into the content pack text, kept so the issue can
+    /// be rendered again in another language
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+
+/// Finding about the network as a whole rather than one device, such as its DNS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkIssue {
+    pub issue: SecurityIssue,
+    /// IP addresses of the devices it concerns, e.g. the resolver
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+/// Kind of learn-more link
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    Cve,
+    VendorAdvisory,
+    Explainer,
+}
+
+/// Learn-more link attached to an issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub title: String,
+    pub url: String,
 }
 
 /// Severity level of a security issue
@@ -117,6 +390,37 @@ pub struct ScanProgress {
     pub message: String,
 }
 
+/// Receives progress updates while a scan runs
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, update: &ScanProgress);
+
+    /// A device resolved over mDNS before discovery finished
+    fn discovered(&self, _device: &mdns::MdnsDiscovery) {}
+}
+
+/// The desktop app forwards progress to the UI
+impl ProgressSink for tauri::AppHandle {
+    fn report(&self, update: &ScanProgress) {
+        let _ = self.emit("scan-progress", update);
+    }
+
+    fn discovered(&self, device: &mdns::MdnsDiscovery) {
+        let _ = self.emit("mdns-discovered", device);
+    }
+}
+
+/// Set while a scan is running
+static SCAN_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Token of the running scan
+static RUNNING_CANCEL: Mutex<Option<CancellationToken>> = Mutex::new(None);
+
+/// Last progress update of the running scan
+static LAST_PROGRESS: Mutex<Option<ScanProgress>> = Mutex::new(None);
+
+/// Network-wide findings of the last scan
+static LAST_NETWORK_ISSUES: Mutex<Vec<NetworkIssue>> = Mutex::new(Vec::new());
+
 /// Scanner errors
 #[derive(Error, Debug)]
 pub enum ScanError {
@@ -132,193 +436,709 @@ pub enum ScanError {
     #[error("Scan cancelled")]
     Cancelled,
 
+    #[error("Not available in strictly passive mode")]
+    StrictlyPassive,
+
+    #[error("Invalid scan target: {0}")]
+    InvalidTarget(String),
+
+    #[error("A scan is already running")]
+    AlreadyRunning,
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
 /// Perform network scan at specified level
 pub async fn scan_network(
-    app: &tauri::AppHandle,
+    progress: &dyn ProgressSink,
     level: ScanLevel,
+    options: &ScanOptions,
 ) -> Result<Vec<Device>, ScanError> {
+    let _running = RunningGuard::claim()?;
+    *RUNNING_CANCEL.lock().unwrap() = Some(options.cancel.clone());
+    let cancel = &options.cancel;
+    let started = tokio::time::Instant::now();
+    // Strictly passive mode overrides the requested level
+    let passive = options.strictly_passive;
+    let level = if passive { ScanLevel::Level1 } else { level };
+
     let mut devices = Vec::new();
+    LAST_NETWORK_ISSUES.lock().unwrap().clear();
 
     // Emit progress: Starting scan
-    emit_progress(app, "初期化中...", 0);
+    emit_progress(progress, "progress.init", 0);
 
-    // Level 1: Passive scanning
-    emit_progress(app, "ネットワークを検索中...", 10);
-
-    // Phase 1: Execute ARP + mDNS + SSDP concurrently
-    let (discovered_result, mdns_names, ssdp_names) = tokio::join!(
-        arp::discover_devices(),
-        // Run mDNS scan in a blocking thread since mdns-sd is synchronous
-        tokio::task::spawn_blocking(|| {
-            mdns::scan_mdns(std::time::Duration::from_secs(3))
-        }),
-        ssdp::scan_ssdp(std::time::Duration::from_secs(3)),
-    );
+    let Discovered {
+        arp: arp::Discovery { hosts: mut discovered, observed },
+        mdns,
+        ssdp: ssdp_descriptions,
+        nbns: nbns_names,
+        wsd: wsd_devices,
+        dhcp: dhcp_fingerprints,
+    } = if passive {
+        emit_progress(progress, "progress.passive", 10);
+        until_cancelled(cancel, passive::discover()).await??
+    } else {
+        until_cancelled(cancel, discover(progress, options)).await??
+    };
+    let mdns_map = &mdns.names;
 
-    let discovered = discovered_result?;
-    let mdns_map = mdns_names.map_err(|e| ScanError::Internal(e.to_string()))?;
+    let fetch_leases = async {
+        if passive {
+            Ok(None)
+        } else {
+            router::fetch_leases(&options.router).await
+        }
+    };
+    let (ipv6_neighbors, router_leases) =
+        until_cancelled(cancel, async { tokio::join!(arp::get_ipv6_neighbors(), fetch_leases) }).await?;
 
-    // Phase 2: Run NBNS queries on discovered IPs (needs ARP results first)
-    emit_progress(app, "デバイス名を解決中...", 25);
-    let ip_list: Vec<String> = discovered.iter().map(|(ip, _)| ip.clone()).collect();
-    let nbns_names = nbns::scan_nbns(&ip_list, std::time::Duration::from_secs(2)).await;
+    // The router's client list is optional; discovery works without it
+    let leases = router_leases.unwrap_or_else(|e| {
+        eprintln!("Failed to fetch router client list: {}", e);
+        None
+    }).unwrap_or_default();
+
+    // Clients the router sees as connected but that did not answer the sweep
+    let mut lease_only = Vec::new();
+    for lease in &leases {
+        let missing = !discovered.iter().any(|(_, mac)| mac.eq_ignore_ascii_case(&lease.mac));
+        if lease.active == Some(true) && missing && !lease.ip.is_empty() {
+            discovered.push((lease.ip.clone(), lease.mac.clone()));
+            lease_only.push(lease.mac.clone());
+        }
+    }
+
+    let conflicts = conflict::find_conflicts(&observed, &leases);
 
-    emit_progress(app, "デバイスを識別中...", 35);
+    emit_progress(progress, "progress.identify", 35);
+    // Devices that announced no name or service of their own
+    let mut quiet_ips = Vec::new();
     for (ip, mac) in discovered {
+        ensure_active(cancel)?;
         let vendor = fingerprint::lookup_vendor(&mac);
 
-        // Resolve hostname (DNS PTR)
+        // Resolve hostname (DNS PTR); a query to the resolver is traffic too
         let dns_hostname: Option<String> = match ip.parse::<std::net::IpAddr>() {
-            Ok(ip_addr) => dns_lookup::lookup_addr(&ip_addr).ok(),
-            Err(_) => None,
+            Ok(ip_addr) if !passive => dns_lookup::lookup_addr(&ip_addr).ok(),
+            _ => None,
         };
 
         // Gather names from all resolution methods
         let m_name = mdns_map.get(&ip).cloned();
         let nb_name = nbns_names.get(&ip).cloned();
-        let ssdp_name = ssdp_names.get(&ip).cloned();
+        let ssdp = ssdp_descriptions.get(&ip).cloned();
+        let ssdp_name = ssdp.as_ref().and_then(|d| d.friendly_name.clone());
+        let wsd = wsd_devices.get(&ip).cloned();
+        let announced = m_name.is_some() || nb_name.is_some() || ssdp_name.is_some() || wsd.is_some();
+        let dhcp_lease = leases.iter().find(|l| l.mac.eq_ignore_ascii_case(&mac)).cloned();
+        let router_name = dhcp_lease.as_ref().and_then(|l| l.hostname.clone());
+        let dhcp_fingerprint = dhcp_fingerprints.get(&mac.to_lowercase()).cloned().or_else(|| dhcp::seen(&mac));
+        let dhcp_name = dhcp_fingerprint.as_ref().and_then(|f| f.hostname.clone());
+
+        // IPv6 addresses sharing the MAC, or announced under the same mDNS hostname
+        let mut ipv6_addresses: Vec<String> = ipv6_neighbors
+            .iter()
+            .filter(|(_, neighbor_mac)| neighbor_mac.eq_ignore_ascii_case(&mac))
+            .map(|(addr, _)| addr.clone())
+            .chain(mdns.ipv6_for(&ip))
+            .collect();
+        ipv6_addresses.sort();
+        ipv6_addresses.dedup();
+
+        let mut mdns_instances: Vec<String> = mdns
+            .instances
+            .iter()
+            .filter(|(_, ips)| ips.contains(&ip) || ipv6_addresses.iter().any(|a| ips.contains(a)))
+            .map(|(instance, _)| instance.clone())
+            .collect();
+        mdns_instances.sort();
+        let mdns_addresses: Vec<String> = std::iter::once(ip.clone()).chain(ipv6_addresses.iter().cloned()).collect();
+        let mdns_services = mdns.services_for(&mdns_addresses);
+        let apple_model = apple::from_txt(&mdns_instances, &mdns.txt);
 
         // Determine display name
-        // Priority: mDNS > NBNS > SSDP > DNS PTR > Vendor fallback
+        // Priority: mDNS > NBNS > router > DHCP > SSDP > ONVIF > DNS PTR > Apple model > Vendor fallback
         let name: Option<String> = m_name.clone()
             .or(nb_name.clone())
+            .or(router_name.clone())
+            .or(dhcp_name.clone())
             .or(ssdp_name)
+            .or(wsd.as_ref().and_then(|d| d.name()))
             .or(dns_hostname.clone())
+            .or(apple_model.as_ref().map(|m| m.name.clone()))
             .or(vendor.as_ref().map(|v| format!("{} デバイス", v)));
 
-        // Identify device type using resolved name for better classification
-        let device_type = fingerprint::identify_device_type(&mac, &vendor, &name);
+        // The announced hardware model beats everything else
+        let (device_type, identification) = match &apple_model {
+            Some(model) => (
+                model.device_type,
+                fingerprint::Identification::new(fingerprint::TypeSource::AppleModel, format!("Apple モデル: {}", model.identifier)),
+            ),
+            None => {
+                fingerprint::identify_device_type(&mac, &vendor, &name, &mdns_services, ssdp.as_ref(), wsd.as_ref())
+            }
+        };
 
-        // hostname field: prefer DNS PTR, then mDNS, then NBNS
-        let hostname = dns_hostname.or(m_name).or(nb_name);
+        // Names broadcast to everyone on the network
+        let broadcast_names: Vec<&str> = m_name.iter().chain(nb_name.iter()).map(String::as_str).collect();
+        let mut issues: Vec<SecurityIssue> = privacy::check_broadcast_names(&broadcast_names).into_iter().collect();
+        issues.extend(conflicts.iter().filter(|c| c.ip == ip).map(conflict::to_issue));
+        let product_names: Vec<&str> = name.as_deref().into_iter().chain(broadcast_names.iter().copied()).collect();
+        let privacy_note = privacy::privacy_note(vendor.as_deref(), &product_names);
 
-        devices.push(Device {
+        let discovery_sources: Vec<DiscoverySource> = [
+            (!lease_only.contains(&mac), DiscoverySource::Arp),
+            (dhcp_lease.is_some(), DiscoverySource::RouterLease),
+            (m_name.is_some() || !mdns_instances.is_empty(), DiscoverySource::Mdns),
+            (nb_name.is_some(), DiscoverySource::Nbns),
+            (ssdp.is_some(), DiscoverySource::Ssdp),
+            (wsd.is_some(), DiscoverySource::Wsd),
+            (dns_hostname.is_some(), DiscoverySource::DnsPtr),
+            (dhcp_fingerprint.is_some(), DiscoverySource::Dhcp),
+            (ipv6_neighbors.iter().any(|(_, m)| m.eq_ignore_ascii_case(&mac)), DiscoverySource::Ipv6Neighbor),
+        ]
+        .into_iter()
+        .filter(|(found, _)| *found)
+        .map(|(_, source)| source)
+        .collect();
+
+        // hostname field: the router's DHCP name is authoritative, then the one the
+        // device sent in its DHCP request, DNS PTR, mDNS, NBNS
+        let hostname = router_name.or(dhcp_name).or(dns_hostname).or(m_name).or(nb_name);
+
+        if !announced && mdns_instances.is_empty() && !options.is_excluded(&mac) {
+            quiet_ips.push(ip.clone());
+        }
+
+        let mut device = Device {
             id: uuid::Uuid::new_v4().to_string(),
             name,
             device_type,
+            identification,
             ip,
+            ipv6_addresses,
+            mac_randomized: fingerprint::is_randomized_mac(&mac),
             mac,
             vendor,
+            model: apple_model
+                .map(|m| m.name)
+                .or_else(|| ssdp.as_ref().and_then(|d| d.model_name.clone()))
+                .or_else(|| wsd.as_ref().and_then(|d| d.hardware())),
+            os_guess: None,
+            ssdp,
+            wsd,
+            dhcp: None,
+            onvif: None,
+            snmp: None,
+            web_interfaces: Vec::new(),
             hostname,
             open_ports: Vec::new(),
             security_level: SecurityLevel::Unknown,
             security_score: 0,
-            issues: Vec::new(),
+            issues,
             last_seen: chrono::Utc::now(),
-        });
+            warnings: Vec::new(),
+            dhcp_lease,
+            firmware: None,
+            router_audit: None,
+            forwarded_ports: Vec::new(),
+            privacy_note,
+            mdns_instances,
+            mdns_services,
+            discovery_sources,
+            scan_level: Some(level),
+            dependents: Vec::new(),
+            reduced_coverage: Vec::new(),
+            enrichment_pending: false,
+        };
+        if let Some(fingerprint) = dhcp_fingerprint {
+            dhcp::apply(&mut device, fingerprint);
+        }
+        devices.push(device);
     }
 
+    // Spoofed printer / NAS names
+    impersonation::check_duplicate_claims(&mut devices, &mdns.instances, &mdns.hosts);
+    impersonation::check_moved_names(&mut devices, &options.known_devices);
+
+    emit_progress(progress, "progress.wifi", 45);
+    let wps = until_cancelled(cancel, wifi::check_gateway(&mut devices, &options.excluded)).await?;
+
+    let active = matches!(level, ScanLevel::Level2 | ScanLevel::Level3);
+    let verify = matches!(level, ScanLevel::Level3);
+    let exposure_enabled = options.exposure.endpoint().is_some();
+    let phases: Vec<budget::ScanPhase> = [
+        (budget::ScanPhase::Ports, active),
+        (budget::ScanPhase::SilentDevices, active),
+        (budget::ScanPhase::Services, active),
+        (budget::ScanPhase::Gateway, active),
+        (budget::ScanPhase::Dns, active),
+        (budget::ScanPhase::Firmware, active),
+        (budget::ScanPhase::Exposure, active && exposure_enabled),
+        (budget::ScanPhase::Vulnerabilities, verify),
+        (budget::ScanPhase::Shares, verify),
+        (budget::ScanPhase::Reverify, !passive),
+    ]
+    .into_iter()
+    .filter(|(_, runs)| *runs)
+    .map(|(phase, _)| phase)
+    .collect();
+    let mut planner = budget::Planner::new(started, options.max_duration, &phases);
+    let order = budget::priority_order(&devices, &options.known_devices);
+
     // Level 2: Active scanning (if requested)
-    if matches!(level, ScanLevel::Level2 | ScanLevel::Level3) {
-        emit_progress(app, "ポートをスキャン中...", 50);
-        for device in &mut devices {
-            device.open_ports = ports::scan_ports(&device.ip).await?;
+    if active {
+        emit_progress(progress, "progress.ports", 50);
+        let phase = planner.start(budget::ScanPhase::Ports);
+        let port_list = std::sync::Arc::new(ports::ports_to_scan(&options.port_config, options.port_profile.as_deref()));
+        let limiter = ports::ConnectLimiter::new(&options.port_config);
+        // Every device at once, started in priority order; the limiter's
+        // semaphore hands out connection slots first come, first served.
+        // Dropping the set on an early return aborts the scans still running.
+        let mut scans = tokio::task::JoinSet::new();
+        for &i in &order {
+            if options.is_excluded(&devices[i].mac) {
+                continue;
+            }
+            let (ip, port_list, limiter) = (devices[i].ip.clone(), port_list.clone(), limiter.clone());
+            let (phase, cancel) = (phase.clone(), cancel.clone());
+            let scan = async move {
+                let mut open_ports = ports::scan_ports(&ip, &port_list, &limiter, &cancel).await?;
+                open_ports.extend(ports::scan_udp_ports(&ip, &limiter, &cancel).await?);
+                Ok::<_, ScanError>(open_ports)
+            };
+            scans.spawn(async move { (i, phase.run(scan).await) });
+        }
+        while let Some(joined) = until_cancelled(cancel, scans.join_next()).await? {
+            let (i, result) = joined.map_err(|e| ScanError::Internal(e.to_string()))?;
+            let device = &mut devices[i];
+            // A failure on one device must not discard the rest of the scan
+            match result {
+                Ok(Err(ScanError::Cancelled)) => return Err(ScanError::Cancelled),
+                Ok(Ok(open_ports)) => device.open_ports = open_ports,
+                Ok(Err(e)) => device.warnings.push(format!("ポートスキャンに失敗しました: {}", e)),
+                Err(shortfall) => phase.mark(device, shortfall),
+            }
+        }
+
+        emit_progress(progress, "progress.silent-devices", 65);
+        let phase = planner.start(budget::ScanPhase::SilentDevices);
+        for &i in &order {
+            ensure_active(cancel)?;
+            if options.is_excluded(&devices[i].mac) {
+                continue;
+            }
+            let device = &mut devices[i];
+            if !device.open_ports.is_empty() || !quiet_ips.contains(&device.ip) {
+                continue;
+            }
+            if let Err(shortfall) = phase.run(stealth::investigate(std::slice::from_mut(device), &quiet_ips)).await {
+                phase.mark(device, shortfall);
+            }
+        }
+
+        emit_progress(progress, "progress.services", 70);
+        let phase = planner.start(budget::ScanPhase::Services);
+        for &i in &order {
+            ensure_active(cancel)?;
+            // Banner grabs, ONVIF requests and OS stack probes are all active
+            if options.is_excluded(&devices[i].mac) {
+                continue;
+            }
+            let device = &mut devices[i];
+            let identify = async {
+                fingerprint::identify_services(device).await;
+                snmp::interrogate(device).await;
+                http::analyze(device).await;
+                onvif::probe(device).await;
+                fingerprint::guess_os(device).await;
+            };
+            if let Err(shortfall) = phase.run(identify).await {
+                phase.mark(device, shortfall);
+            }
         }
 
-        emit_progress(app, "サービスを識別中...", 70);
-        for device in &mut devices {
-            fingerprint::identify_services(device).await;
+        emit_progress(progress, "progress.gateway", 75);
+        let phase = planner.start(budget::ScanPhase::Gateway);
+        let gateway_ip = arp::get_default_gateway().map(|gw| gw.to_string());
+        let gateway = devices.iter().position(|d| gateway_ip.as_deref() == Some(d.ip.as_str()));
+        let gateway_excluded = gateway.is_some_and(|i| options.is_excluded(&devices[i].mac));
+        let port_mapping = if gateway_excluded {
+            None
+        } else {
+            let check = check_gateway(&mut devices, wps, &options.exposure);
+            match until_cancelled(cancel, phase.run(check)).await? {
+                Ok(report) => report,
+                Err(shortfall) => {
+                    if let Some(i) = gateway {
+                        phase.mark(&mut devices[i], shortfall);
+                    }
+                    None
+                }
+            }
+        };
+        for &i in &order {
+            ensure_active(cancel)?;
+            let other_gateway = Some(i) != gateway
+                && devices[i].ssdp.as_ref().is_some_and(|d| d.is_internet_gateway())
+                && !options.is_excluded(&devices[i].mac);
+            if !other_gateway {
+                continue;
+            }
+            let check = portmap::check_other_gateway(&mut devices, i, GATEWAY_PROBE_TIMEOUT);
+            if let Err(shortfall) = phase.run(check).await {
+                phase.mark(&mut devices[i], shortfall);
+            }
+        }
+
+        let wan_address = port_mapping.as_ref().and_then(|r| r.external_address.clone());
+
+        emit_progress(progress, "progress.dns", 76);
+        let phase = planner.start(budget::ScanPhase::Dns);
+        let excluded: Vec<String> =
+            devices.iter().filter(|d| options.is_excluded(&d.mac)).map(|d| d.ip.clone()).collect();
+        match until_cancelled(cancel, phase.run(dns::audit(arp::get_default_gateway(), &excluded))).await? {
+            Ok(issues) => *LAST_NETWORK_ISSUES.lock().unwrap() = issues,
+            Err(shortfall) => {
+                if let Some(i) = gateway {
+                    phase.mark(&mut devices[i], shortfall);
+                }
+            }
+        }
+
+        emit_progress(progress, "progress.firmware", 77);
+        let phase = planner.start(budget::ScanPhase::Firmware);
+        for &i in &order {
+            ensure_active(cancel)?;
+            let device = &mut devices[i];
+            let checks = firmware::check_devices(
+                std::slice::from_mut(device),
+                wan_address.as_deref(),
+                &options.exposure,
+                verify,
+            );
+            if let Err(shortfall) = phase.run(checks).await {
+                phase.mark(device, shortfall);
+            }
+        }
+
+        if exposure_enabled {
+            emit_progress(progress, "progress.exposure", 80);
+            let phase = planner.start(budget::ScanPhase::Exposure);
+            for &i in &order {
+                ensure_active(cancel)?;
+                let device = &mut devices[i];
+                let checks =
+                    exposure::check_ipv6_exposure(std::slice::from_mut(device), &options.exposure, &options.excluded);
+                if let Err(shortfall) = phase.run(checks).await {
+                    phase.mark(device, shortfall);
+                }
+            }
+
+            let mappings = port_mapping.map(|r| r.mappings).unwrap_or_default();
+            let checks = exposure::check_external_exposure(
+                &mut devices,
+                &mappings,
+                wan_address.as_deref(),
+                &options.exposure,
+                &options.excluded,
+            );
+            if let Err(shortfall) = until_cancelled(cancel, phase.run(checks)).await? {
+                if let Some(i) = gateway {
+                    phase.mark(&mut devices[i], shortfall);
+                }
+            }
         }
     }
 
     // Level 3: Vulnerability verification (if requested and consented)
-    if matches!(level, ScanLevel::Level3) {
-        emit_progress(app, "脆弱性を確認中...", 85);
-        for device in &mut devices {
-            check_vulnerabilities(device).await;
+    if verify {
+        emit_progress(progress, "progress.vulnerabilities", 85);
+        let phase = planner.start(budget::ScanPhase::Vulnerabilities);
+        for &i in &order {
+            ensure_active(cancel)?;
+            let device = &mut devices[i];
+            let try_credentials = options.credentials_consented(&device.mac);
+            if let Err(shortfall) = phase.run(check_vulnerabilities(device, try_credentials)).await {
+                phase.mark(device, shortfall);
+            }
+        }
+
+        emit_progress(progress, "progress.shares", 87);
+        let phase = planner.start(budget::ScanPhase::Shares);
+        for &i in &order {
+            ensure_active(cancel)?;
+            let device = &mut devices[i];
+            let checks = shares::check_devices(std::slice::from_mut(device), &options.excluded);
+            if let Err(shortfall) = phase.run(checks).await {
+                phase.mark(device, shortfall);
+            }
+        }
+    }
+
+    // Battery devices often sleep through discovery; give them another chance
+    // before they show up as disappeared
+    emit_progress(progress, "progress.reverify", 90);
+    if !passive {
+        let phase = planner.start(budget::ScanPhase::Reverify);
+        if let Some(window) = phase.time_left() {
+            let reverify = reverify_known_devices(&mut devices, &options.known_devices, window.min(REVERIFY_WINDOW));
+            until_cancelled(cancel, reverify).await?;
         }
     }
+    dependency::infer(&mut devices, &mdns.txt);
 
     // Calculate security scores
-    emit_progress(app, "セキュリティスコアを計算中...", 95);
+    emit_progress(progress, "progress.scoring", 95);
     for device in &mut devices {
-        calculate_security_score(device);
+        options.policy.apply(device);
     }
 
-    emit_progress(app, "完了", 100);
+    emit_progress(progress, "progress.done", 100);
 
     Ok(devices)
 }
 
-fn emit_progress(app: &tauri::AppHandle, phase: &str, progress: u8) {
-    let _ = app.emit("scan-progress", ScanProgress {
-        phase: phase.to_string(),
-        progress,
-        message: phase.to_string(),
-    });
-}
-
-async fn check_vulnerabilities(device: &mut Device) {
-    // Check for default passwords
-    if has_default_password(device).await {
-        device.issues.push(SecurityIssue {
-            id: "default-password".to_string(),
-            severity: IssueSeverity::Critical,
-            title: "デフォルトパスワードが使用されています".to_string(),
-            description: "このデバイスは工場出荷時のパスワードが使用されています。\
-                         悪意のある第三者に不正アクセスされる危険があります。".to_string(),
-            remediation: "デバイスの管理画面にログインし、パスワードを強力なものに変更してください。".to_string(),
-        });
+/// What discovery found, before devices are assembled from it
+struct Discovered {
+    arp: arp::Discovery,
+    mdns: mdns::MdnsResults,
+    ssdp: HashMap<String, ssdp::SsdpDescription>,
+    /// IP → NetBIOS name
+    nbns: HashMap<String, String>,
+    wsd: HashMap<String, wsd::WsdDevice>,
+    /// Lowercase MAC → DHCP fingerprint
+    dhcp: HashMap<String, dhcp::DhcpFingerprint>,
+}
+
+/// Sweep the subnet, query mDNS, SSDP, WS-Discovery and NetBIOS names
+async fn discover(progress: &dyn ProgressSink, options: &ScanOptions) -> Result<Discovered, ScanError> {
+    // Level 1: Passive scanning
+    emit_progress(progress, "progress.discovery", 10);
+    let known: Vec<std::net::Ipv4Addr> = options.known_devices.iter().filter_map(|d| d.ip.parse().ok()).collect();
+
+    // Phase 1: Execute ARP + mDNS + SSDP + WSD + the IPv6 all-nodes ping concurrently,
+    // listening for DHCP requests meanwhile
+    let (discovered_result, mdns, ssdp_descriptions, wsd, dhcp, ()) = tokio::join!(
+        arp::discover_devices(&options.targets, &options.sweep, &known, progress),
+        mdns::scan_mdns(DISCOVERY_WINDOW, progress),
+        ssdp::scan_ssdp(DISCOVERY_WINDOW),
+        wsd::scan_wsd(DISCOVERY_WINDOW),
+        dhcp::listen(DISCOVERY_WINDOW),
+        arp::solicit_ipv6_neighbors(),
+    );
+
+    let discovery = discovered_result?;
+
+    // Phase 2: Run NBNS queries on discovered IPs (needs ARP results first)
+    emit_progress(progress, "progress.names", 25);
+    let ip_list: Vec<String> = discovery.hosts.iter().map(|(ip, _)| ip.clone()).collect();
+    let nbns = nbns::scan_nbns(&ip_list, NBNS_TIMEOUT).await;
+
+    Ok(Discovered {
+        arp: discovery,
+        mdns,
+        ssdp: ssdp_descriptions,
+        nbns,
+        wsd,
+        dhcp,
+    })
+}
+
+/// Re-probe previously seen devices missing from this scan and add those that answer
+async fn reverify_known_devices(devices: &mut Vec<Device>, known_devices: &[Device], window: std::time::Duration) {
+    let missing: Vec<&Device> = known_devices
+        .iter()
+        .filter(|k| !devices.iter().any(|d| d.mac.eq_ignore_ascii_case(&k.mac)))
+        .collect();
+    if missing.is_empty() {
+        return;
     }
 
-    // Check for open telnet
-    if device.open_ports.iter().any(|p| p.number == 23) {
-        device.issues.push(SecurityIssue {
-            id: "telnet-open".to_string(),
-            severity: IssueSeverity::High,
-            title: "Telnetポートが開放されています".to_string(),
-            description: "Telnetは暗号化されていない通信プロトコルです。\
-                         パスワードが平文で送信されるため、盗聴される危険があります。".to_string(),
-            remediation: "Telnetを無効化し、SSHを使用するか、デバイスの管理画面からリモート管理を無効にしてください。".to_string(),
+    let targets: Vec<(String, String)> = missing
+        .iter()
+        .map(|k| (k.ip.clone(), k.mac.clone()))
+        .collect();
+
+    for (ip, mac) in arp::reverify(&targets, window).await {
+        let Some(known) = missing.iter().find(|k| k.mac.eq_ignore_ascii_case(&mac)) else {
+            continue;
+        };
+        // Only discovery data is carried over; the device was not port scanned this time
+        devices.push(Device {
+            id: uuid::Uuid::new_v4().to_string(),
+            ip,
+            mac,
+            open_ports: Vec::new(),
+            security_level: SecurityLevel::Unknown,
+            security_score: 0,
+            issues: Vec::new(),
+            last_seen: chrono::Utc::now(),
+            warnings: Vec::new(),
+            firmware: None,
+            router_audit: None,
+            forwarded_ports: Vec::new(),
+            discovery_sources: vec![DiscoverySource::Reverify],
+            scan_level: Some(ScanLevel::Level1),
+            dependents: Vec::new(),
+            reduced_coverage: Vec::new(),
+            enrichment_pending: false,
+            ..(*known).clone()
         });
     }
+}
 
-    // Check for UPnP
-    if device.open_ports.iter().any(|p| p.number == 1900) {
-        device.issues.push(SecurityIssue {
-            id: "upnp-enabled".to_string(),
-            severity: IssueSeverity::Medium,
-            title: "UPnPが有効です".to_string(),
-            description: "UPnPは自動的にポートを開放する機能です。\
-                         悪意のあるソフトウェアに悪用される可能性があります。".to_string(),
-            remediation: "ルーターの管理画面からUPnPを無効にすることを検討してください。".to_string(),
-        });
+/// Check the default gateway's port mapping and topology, run its checklist and
+/// attach the findings to it. Returns what the gateway reported about port mapping.
+async fn check_gateway(
+    devices: &mut [Device],
+    wps: Option<bool>,
+    exposure: &exposure::ExposureSettings,
+) -> Option<portmap::PortMappingReport> {
+    let gateway = arp::get_default_gateway()?;
+    let local_ip = arp::get_local_interface().and_then(|(_, ip, _)| ip.parse().ok())?;
+
+    let port_mapping = portmap::probe_gateway(gateway, local_ip, GATEWAY_PROBE_TIMEOUT).await;
+    let topology = topology::analyze(gateway, &port_mapping).await;
+
+    let gateway_ip = gateway.to_string();
+    if let Some(device) = devices.iter_mut().find(|d| d.ip == gateway_ip) {
+        device.issues.extend(portmap::to_issue(&port_mapping));
+        device.issues.extend(topology::to_issue(&topology));
+        let audit = router_audit::audit(device, &port_mapping, wps, exposure).await;
+        device.issues.extend(router_audit::to_issues(&audit));
+        device.router_audit = Some(audit);
     }
+    portmap::attach_mappings(devices, &gateway_ip, &port_mapping.mappings);
+    Some(port_mapping)
+}
+
+/// Report progress; `key` names the phase's label in the content pack
+fn emit_progress(sink: &dyn ProgressSink, key: &str, progress: u8) {
+    emit_progress_message(sink, content::label(key, content::language()), progress);
 }
 
-async fn has_default_password(_device: &Device) -> bool {
-    // TODO: Implement actual default password checking
-    false
+/// Report progress with an already localized message
+fn emit_progress_message(sink: &dyn ProgressSink, phase: String, progress: u8) {
+    let update = ScanProgress {
+        message: phase.clone(),
+        phase,
+        progress,
+    };
+    sink.report(&update);
+    *LAST_PROGRESS.lock().unwrap() = Some(update);
 }
 
-fn calculate_security_score(device: &mut Device) {
-    let mut score: i32 = 100;
+/// Whether a scan is currently running
+pub fn is_scan_running() -> bool {
+    SCAN_RUNNING.load(Ordering::SeqCst)
+}
 
-    for issue in &device.issues {
-        score -= match issue.severity {
-            IssueSeverity::Critical => 40,
-            IssueSeverity::High => 25,
-            IssueSeverity::Medium => 15,
-            IssueSeverity::Low => 5,
-            IssueSeverity::Info => 0,
-        };
+/// Progress of the running scan, if any
+pub fn current_progress() -> Option<ScanProgress> {
+    if is_scan_running() {
+        LAST_PROGRESS.lock().unwrap().clone()
+    } else {
+        None
+    }
+}
+
+/// Findings of the last scan that concern the network rather than one device
+pub fn network_issues() -> Vec<NetworkIssue> {
+    LAST_NETWORK_ISSUES.lock().unwrap().clone()
+}
+
+/// Clears the running flag however the scan ends
+struct RunningGuard;
+
+impl RunningGuard {
+    /// Set the running flag, unless another scan already holds it
+    fn claim() -> Result<Self, ScanError> {
+        SCAN_RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .map(|_| RunningGuard)
+            .map_err(|_| ScanError::AlreadyRunning)
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        SCAN_RUNNING.store(false, Ordering::SeqCst);
+        *RUNNING_CANCEL.lock().unwrap() = None;
+    }
+}
+
+/// Abort the running scan. Returns whether one was running.
+pub fn cancel_scan() -> bool {
+    match RUNNING_CANCEL.lock().unwrap().as_ref() {
+        Some(cancel) => {
+            cancel.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// `Err(Cancelled)` once `cancel` was triggered
+pub(crate) fn ensure_active(cancel: &CancellationToken) -> Result<(), ScanError> {
+    if cancel.is_cancelled() {
+        return Err(ScanError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Run `work` unless `cancel` is triggered first
+pub(crate) async fn until_cancelled<F: Future>(cancel: &CancellationToken, work: F) -> Result<F::Output, ScanError> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(ScanError::Cancelled),
+        output = work => Ok(output),
+    }
+}
+
+/// Level 3 checks, skipped for a device unchanged since a recent scan
+async fn check_vulnerabilities(device: &mut Device, try_credentials: bool) {
+    let fingerprint = crate::vulndb::cache::Fingerprint::of(device, try_credentials);
+    if let Some(issues) = crate::vulndb::cache::get(&fingerprint) {
+        device.issues.extend(issues);
+        return;
     }
+    let before = device.issues.len();
 
-    // Deduct for open risky ports
-    for port in &device.open_ports {
-        if !port.is_secure {
-            score -= 5;
+    // Login attempts can lock accounts, so only on devices the user consented to
+    if try_credentials {
+        if let Some(login) = credentials::check_device(device).await {
+            device.issues.push(credentials::to_issue(&login));
         }
     }
 
-    device.security_score = score.clamp(0, 100) as u8;
-    device.security_level = match device.security_score {
-        80..=100 => SecurityLevel::Safe,
-        50..=79 => SecurityLevel::Warning,
-        _ => SecurityLevel::Danger,
-    };
+    let issues: Vec<SecurityIssue> = device.open_ports.iter().flat_map(|p| port_issues(p.number)).collect();
+    device.issues.extend(issues);
+
+    rtsp::check_device(device).await;
+
+    match crate::vulndb::check_vendor_vulnerabilities(device).await {
+        Ok(vulns) => device.issues.extend(vulns.iter().map(crate::vulndb::vulnerability_to_issue)),
+        // Not cached, so the next scan looks again
+        Err(crate::vulndb::VulnDbError::Deferred(_)) => {
+            device.enrichment_pending = true;
+            device
+                .warnings
+                .push("ネットワークに接続できなかったため、メーカー・型番の既知の脆弱性の確認を保留しました".to_string());
+            return;
+        }
+        Err(e) => device.warnings.push(format!("既知の脆弱性を確認できませんでした: {}", e)),
+    }
+    crate::vulndb::cache::put(&fingerprint, &device.issues[before..]);
+}
+
+/// Findings raised for an open port alone (also re-run by monitoring when a port opens)
+pub fn port_issues(port: u16) -> Vec<SecurityIssue> {
+    match port {
+        23 => vec![content::issue("telnet-open", IssueSeverity::High, &[])],
+        1900 => vec![content::issue("upnp-enabled", IssueSeverity::Medium, &[])],
+        _ => Vec::new(),
+    }
 }
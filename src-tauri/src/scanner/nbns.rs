@@ -3,9 +3,16 @@
 //! Sends NBNS Node Status Requests (UDP port 137) to discover device hostnames.
 //! Android phones, Windows PCs, and many IoT devices respond with their configured names.
 
+use super::net;
+use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::Duration;
-use tokio::net::UdpSocket;
+
+const NBNS_PORT: u16 = 137;
+
+/// Opcodes of name registrations and refreshes hosts broadcast on their own
+const REGISTRATION_OPCODES: [u8; 3] = [5, 8, 9];
 
 /// NBNS Node Status Request packet for wildcard name query ("*")
 /// This queries the remote host for all registered NetBIOS names.
@@ -146,7 +153,7 @@ pub async fn scan_nbns(ips: &[String], timeout: Duration) -> HashMap<String, Str
 
 /// Send a single NBNS query to the specified IP and return the resolved name.
 async fn query_nbns_name(ip: &str, timeout: Duration) -> Option<String> {
-    let socket = UdpSocket::bind("0.0.0.0:0").await.ok()?;
+    let socket = net::udp_bind(net::ANY).await.ok()?;
     let target = format!("{}:137", ip);
 
     // Use a simple counter-based transaction ID
@@ -161,3 +168,63 @@ async fn query_nbns_name(ip: &str, timeout: Duration) -> Option<String> {
         _ => None,
     }
 }
+
+/// Collect the names hosts register by broadcast for `duration`, without
+/// sending anything. Binding the NetBIOS port needs elevated privileges on
+/// most systems; without them nothing is collected.
+pub async fn listen(duration: Duration) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let socket = match listen_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to listen for NetBIOS broadcasts: {}", e);
+            return names;
+        }
+    };
+
+    let deadline = tokio::time::Instant::now() + duration;
+    let mut buf = [0u8; 1024];
+    while let Ok(Ok((len, _))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        if let Some((ip, name)) = parse_registration(&buf[..len]) {
+            names.entry(ip).or_insert(name);
+        }
+    }
+    names
+}
+
+fn listen_socket() -> std::io::Result<tokio::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_broadcast(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, NBNS_PORT).into())?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket.into())
+}
+
+/// (registered IP, workstation name) of a unique name registration
+fn parse_registration(data: &[u8]) -> Option<(String, String)> {
+    // Header (12) + encoded name (34) + type/class (4) + additional record:
+    // name pointer (2), type/class (4), TTL (4), length (2), flags (2), address (4)
+    let packet = data.get(..68)?;
+    let is_request = packet[2] & 0x80 == 0;
+    let opcode = (packet[2] >> 3) & 0x0F;
+    if !is_request || !REGISTRATION_OPCODES.contains(&opcode) || packet[12] != 0x20 {
+        return None;
+    }
+
+    // First-level encoding: each byte is two letters 'A' + nibble
+    let decoded: Vec<u8> = packet[13..45]
+        .chunks(2)
+        .map(|pair| (pair[0].wrapping_sub(b'A') << 4) | (pair[1].wrapping_sub(b'A') & 0x0F))
+        .collect();
+    let suffix = decoded[15];
+    let is_group = packet[62] & 0x80 != 0;
+    // Suffix 0x00 is the workstation name; group names are workgroups
+    if suffix != 0x00 || is_group {
+        return None;
+    }
+
+    let name = String::from_utf8_lossy(&decoded[..15]).trim_end().to_string();
+    let ip = Ipv4Addr::new(packet[64], packet[65], packet[66], packet[67]);
+    (!name.is_empty()).then(|| (ip.to_string(), name))
+}
@@ -0,0 +1,122 @@
+//! Socket creation and timing seams
+//!
+//! Scanner modules open their sockets through this module instead of
+//! calling `TcpStream::connect` / `UdpSocket::bind` directly, so a test
+//! harness (see `testnet`) can route probes into a simulated network, pin
+//! the interface that is swept, and turn off random jitter to make runs
+//! reproducible. The app itself only changes the defaults through strictly
+//! passive mode, which refuses every probe socket.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Bind to any local address and port
+pub const ANY: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+/// Creates the sockets scanner modules use
+pub trait SocketFactory: Send + Sync {
+    fn tcp_connect(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream>;
+    fn udp_bind(&self, addr: SocketAddr) -> io::Result<UdpSocket>;
+}
+
+/// Plain OS sockets
+pub struct SystemSockets;
+
+impl SocketFactory for SystemSockets {
+    fn tcp_connect(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        TcpStream::connect_timeout(addr, timeout)
+    }
+
+    fn udp_bind(&self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        UdpSocket::bind(addr)
+    }
+}
+
+static FACTORY: RwLock<Option<Arc<dyn SocketFactory>>> = RwLock::new(None);
+
+/// Interface to sweep instead of the first suitable one
+static INTERFACE: RwLock<Option<String>> = RwLock::new(None);
+
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+
+static STRICTLY_PASSIVE: AtomicBool = AtomicBool::new(false);
+
+/// Replace the socket factory; `None` restores OS sockets
+pub fn set_socket_factory(factory: Option<Arc<dyn SocketFactory>>) {
+    *FACTORY.write().unwrap() = factory;
+}
+
+fn factory() -> Arc<dyn SocketFactory> {
+    FACTORY.read().unwrap().clone().unwrap_or_else(|| Arc::new(SystemSockets))
+}
+
+/// Pin discovery to one interface; `None` picks the first suitable one
+pub fn set_interface(name: Option<String>) {
+    *INTERFACE.write().unwrap() = name;
+}
+
+/// Interface discovery is pinned to, if any
+pub fn interface() -> Option<String> {
+    INTERFACE.read().unwrap().clone()
+}
+
+/// Disable random delays so probe timing is the same on every run
+pub fn set_deterministic(deterministic: bool) {
+    DETERMINISTIC.store(deterministic, Ordering::SeqCst);
+}
+
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::SeqCst)
+}
+
+/// Refuse every probe socket while strictly passive mode is on
+pub fn set_strictly_passive(passive: bool) {
+    STRICTLY_PASSIVE.store(passive, Ordering::SeqCst);
+}
+
+pub fn is_strictly_passive() -> bool {
+    STRICTLY_PASSIVE.load(Ordering::SeqCst)
+}
+
+pub(super) fn check_active() -> io::Result<()> {
+    if is_strictly_passive() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "strictly passive mode is on"));
+    }
+    Ok(())
+}
+
+/// Blocking TCP connect with a timeout
+pub fn tcp_connect(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+    check_active()?;
+    factory().tcp_connect(addr, timeout)
+}
+
+/// TCP connect for async callers; OS sockets connect without blocking a thread
+pub async fn tcp_connect_async(addr: SocketAddr, timeout: Duration) -> io::Result<tokio::net::TcpStream> {
+    check_active()?;
+    let factory = FACTORY.read().unwrap().clone();
+    let Some(factory) = factory else {
+        return match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+            Ok(stream) => stream,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "connection timed out")),
+        };
+    };
+
+    // A harness factory hands out blocking sockets
+    let stream = tokio::task::spawn_blocking(move || factory.tcp_connect(&addr, timeout))
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))??;
+    stream.set_nonblocking(true)?;
+    tokio::net::TcpStream::from_std(stream)
+}
+
+/// Bind a UDP socket for async callers
+pub async fn udp_bind(addr: SocketAddr) -> io::Result<tokio::net::UdpSocket> {
+    check_active()?;
+    let socket = factory().udp_bind(addr)?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket)
+}
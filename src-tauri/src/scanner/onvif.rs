@@ -0,0 +1,124 @@
+//! ONVIF device information (Level 2)
+//!
+//! IP cameras and recorders speaking ONVIF answer `GetDeviceInformation` on
+//! their device service with the maker, model and firmware version, which is
+//! what known-vulnerability matching needs. Many cameras answer it without
+//! credentials; those that ask for them are left alone, so nothing is
+//! logged as a failed login.
+
+use super::fingerprint::{self, TypeSource};
+use super::{firmware, ssdp, Device, DeviceType};
+use serde::{Deserialize, Serialize};
+
+/// Ports the device service is looked for on when WS-Discovery gave no address
+const SERVICE_PORTS: &[u16] = &[80, 8080, 8000, 8899];
+
+/// Path of the device service on most cameras
+const SERVICE_PATH: &str = "/onvif/device_service";
+
+const GET_DEVICE_INFORMATION: &str = "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\">\
+<s:Body><GetDeviceInformation xmlns=\"http://www.onvif.org/ver10/device/wsdl\"/></s:Body>\
+</s:Envelope>";
+
+const CONTENT_TYPE: &str = "application/soap+xml; charset=utf-8; \
+action=\"http://www.onvif.org/ver10/device/wsdl/GetDeviceInformation\"";
+
+/// What the device service said about the device
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnvifInfo {
+    /// Device service that answered
+    pub url: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+    pub hardware_id: Option<String>,
+}
+
+/// Ask the device service of a camera, or of a device announcing ONVIF, for
+/// the device information and fill in the maker and model it names. The
+/// request is active: callers skip devices the user excluded.
+pub async fn probe(device: &mut Device) {
+    let onvif = device.wsd.as_ref().is_some_and(|d| d.is_onvif());
+    if device.device_type != DeviceType::Camera && !onvif {
+        return;
+    }
+    let Some(client) = firmware::client() else {
+        return;
+    };
+
+    for url in service_urls(device) {
+        let Some(info) = get_device_information(&client, &url).await else {
+            continue;
+        };
+        if device.vendor.is_none() {
+            device.vendor = info.manufacturer.clone();
+        }
+        if device.model.is_none() {
+            device.model = info.model.clone();
+        }
+        let product = [&info.manufacturer, &info.model].into_iter().flatten().cloned().collect::<Vec<_>>();
+        let evidence = format!("ONVIF: {}", product.join(" "));
+        fingerprint::record_type(device, DeviceType::Camera, TypeSource::Onvif, evidence);
+        device.onvif = Some(info);
+        return;
+    }
+}
+
+/// Device service addresses announced over WS-Discovery, or the usual path
+/// on the open web ports
+fn service_urls(device: &Device) -> Vec<String> {
+    let announced = device
+        .wsd
+        .iter()
+        .flat_map(|d| &d.xaddrs)
+        // An address on another interface of the device is not reachable
+        .filter(|url| url.starts_with("http") && url.contains(&device.ip))
+        .cloned();
+    let guessed = SERVICE_PORTS
+        .iter()
+        .filter(|port| device.open_ports.iter().any(|p| p.protocol == "tcp" && p.number == **port))
+        .map(|port| format!("http://{}:{}{}", device.ip, port, SERVICE_PATH));
+
+    let mut urls: Vec<String> = Vec::new();
+    for url in announced.chain(guessed) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+async fn get_device_information(client: &reqwest::Client, url: &str) -> Option<OnvifInfo> {
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+        .body(GET_DEVICE_INFORMATION)
+        .send()
+        .await
+        .ok()?;
+    // Cameras that require credentials answer 401 or a NotAuthorized fault
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    parse_device_information(url, &body)
+}
+
+/// Fields of a `GetDeviceInformationResponse`
+pub(super) fn parse_device_information(url: &str, body: &str) -> Option<OnvifInfo> {
+    if !body.contains("GetDeviceInformationResponse") {
+        return None;
+    }
+    let field = |name: &str| ssdp::extract_xml_elements(body, name).into_iter().next();
+    let info = OnvifInfo {
+        url: url.to_string(),
+        manufacturer: field("Manufacturer"),
+        model: field("Model"),
+        firmware_version: field("FirmwareVersion"),
+        hardware_id: field("HardwareId"),
+    };
+    let empty = info.manufacturer.is_none() && info.model.is_none() && info.firmware_version.is_none();
+    (!empty).then_some(info)
+}
@@ -0,0 +1,211 @@
+//! IEEE OUI registry
+//!
+//! MAC address prefix → organisation, for the MA-L blocks the IEEE assigns.
+//! A gzip-compressed copy ships with the app; `update_oui_database` fetches
+//! the current registry and caches it in the app data directory, where it
+//! takes over from the bundled copy. Both are `AABBCC\tOrganisation` lines
+//! sorted by prefix. The bundled file is built from the IEEE's `oui.csv`
+//! with `iot-doctor-data oui`.
+
+use super::datapack::DataPackError;
+use crate::database;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Where `update_oui_database` downloads the registry from
+pub const OUI_URL: &str = "https://standards-oui.ieee.org/oui/oui.csv";
+
+/// Registry bundled with the app
+const BUNDLED: &[u8] = include_bytes!("oui.tsv.gz");
+
+/// Fewer assignments than this means the download was cut short or is not the registry
+const MIN_DOWNLOADED_ENTRIES: usize = 10_000;
+
+static ACTIVE: RwLock<Option<Registry>> = RwLock::new(None);
+
+/// Where the active registry came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OuiSource {
+    Bundled,
+    Downloaded,
+}
+
+/// Summary of the active registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OuiInfo {
+    pub source: OuiSource,
+    pub entries: usize,
+    /// When the downloaded copy was fetched
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Prefixes and organisations, sorted by prefix for binary search
+struct Registry {
+    prefixes: Vec<u32>,
+    vendors: Vec<Box<str>>,
+    info: OuiInfo,
+}
+
+impl Registry {
+    fn lookup(&self, prefix: u32) -> Option<&str> {
+        let index = self.prefixes.binary_search(&prefix).ok()?;
+        Some(&self.vendors[index])
+    }
+}
+
+fn cached_path() -> PathBuf {
+    database::app_data_dir().join("oui").join("oui.tsv.gz")
+}
+
+/// Load the downloaded registry if there is one, the bundled copy otherwise
+pub fn load() -> Result<OuiInfo, DataPackError> {
+    let registry = match std::fs::read(cached_path()) {
+        Ok(compressed) => {
+            let updated_at = std::fs::metadata(cached_path())?.modified().ok().map(chrono::DateTime::from);
+            decode(&compressed, OuiSource::Downloaded, updated_at)?
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => decode(BUNDLED, OuiSource::Bundled, None)?,
+        Err(e) => return Err(e.into()),
+    };
+    let info = registry.info.clone();
+    *ACTIVE.write().unwrap() = Some(registry);
+    Ok(info)
+}
+
+/// Download the current registry, cache it and make it active
+pub async fn update() -> Result<OuiInfo, DataPackError> {
+    // A forced update must not be answered from the lookup cache
+    let text = crate::vulndb::client::shared().get_fresh(OUI_URL).await?;
+    let (compressed, _) = compress_registry(&text)?;
+    let path = cached_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, &compressed)?;
+
+    let registry = decode(&compressed, OuiSource::Downloaded, Some(chrono::Utc::now()))?;
+    let info = registry.info.clone();
+    *ACTIVE.write().unwrap() = Some(registry);
+    Ok(info)
+}
+
+/// The IEEE's `oui.csv` in the compressed form the app reads, and the number
+/// of assignments in it
+pub fn compress_registry(csv: &str) -> Result<(Vec<u8>, usize), DataPackError> {
+    let entries = parse_ieee_csv(csv);
+    if entries.len() < MIN_DOWNLOADED_ENTRIES {
+        return Err(DataPackError::Invalid(format!("only {} OUI assignments in the registry", entries.len())));
+    }
+    Ok((encode(&entries)?, entries.len()))
+}
+
+/// Summary of the active registry
+pub fn info() -> Option<OuiInfo> {
+    ACTIVE.read().unwrap().as_ref().map(|r| r.info.clone())
+}
+
+/// Organisation the MAC address' OUI is assigned to
+pub fn lookup(mac: &str) -> Option<String> {
+    let prefix = prefix_of(mac)?;
+    if ACTIVE.read().unwrap().is_none() {
+        // Used before startup loaded it, e.g. from the CLI
+        let _ = load();
+    }
+    let active = ACTIVE.read().unwrap();
+    active.as_ref()?.lookup(prefix).map(str::to_string)
+}
+
+/// First three octets of a MAC address in any common notation
+fn prefix_of(mac: &str) -> Option<u32> {
+    let hex: String = mac.chars().filter(char::is_ascii_hexdigit).take(6).collect();
+    if hex.len() < 6 {
+        return None;
+    }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+fn decode(
+    compressed: &[u8],
+    source: OuiSource,
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Registry, DataPackError> {
+    let mut text = String::new();
+    GzDecoder::new(compressed).read_to_string(&mut text)?;
+
+    let mut entries: Vec<(u32, Box<str>)> = text
+        .lines()
+        .filter_map(|line| {
+            let (prefix, vendor) = line.split_once('\t')?;
+            Some((u32::from_str_radix(prefix, 16).ok()?, vendor.into()))
+        })
+        .collect();
+    // Both files are written sorted; a hand-edited one may not be
+    entries.sort_by_key(|(prefix, _)| *prefix);
+    entries.dedup_by_key(|(prefix, _)| *prefix);
+
+    let (prefixes, vendors): (Vec<u32>, Vec<Box<str>>) = entries.into_iter().unzip();
+    Ok(Registry {
+        info: OuiInfo {
+            source,
+            entries: prefixes.len(),
+            updated_at,
+        },
+        prefixes,
+        vendors,
+    })
+}
+
+fn encode(entries: &[(u32, String)]) -> Result<Vec<u8>, DataPackError> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::best());
+    for (prefix, vendor) in entries {
+        writeln!(encoder, "{:06X}\t{}", prefix, vendor)?;
+    }
+    Ok(encoder.finish()?)
+}
+
+/// MA-L assignments of the IEEE's `oui.csv`
+/// (`Registry,Assignment,Organization Name,Organization Address`), sorted by prefix
+pub(super) fn parse_ieee_csv(text: &str) -> Vec<(u32, String)> {
+    let mut entries: Vec<(u32, String)> = text
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = csv_fields(line);
+            if fields.first().map(String::as_str) != Some("MA-L") {
+                return None;
+            }
+            let prefix = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+            let vendor = fields.get(2)?.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!vendor.is_empty()).then_some((prefix, vendor))
+        })
+        .collect();
+    entries.sort_by_key(|(prefix, _)| *prefix);
+    entries.dedup_by_key(|(prefix, _)| *prefix);
+    entries
+}
+
+/// Fields of a CSV line; quoted fields may hold commas and doubled quotes
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
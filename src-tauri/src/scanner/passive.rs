@@ -0,0 +1,50 @@
+//! Strictly passive mode
+//!
+//! On networks the user does not own, or during sensitive work, nothing may
+//! be sent to the devices at all. With the toggle on, every scan is planned
+//! as passive discovery whatever level was requested: the OS neighbour cache
+//! is read, and names come from what devices broadcast on their own (mDNS
+//! responses, SSDP NOTIFY messages, NetBIOS name registrations, WS-Discovery
+//! Hellos, DHCP requests). No sweep, port connect, name query or HTTP request is made; the
+//! socket seam in [`super::net`] refuses probe sockets as a second line of
+//! defence.
+
+use super::{arp, dhcp, mdns, nbns, ssdp, wsd, Discovered, ScanError};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long to listen; announcements are sporadic, so far longer than an active query
+pub const LISTEN_WINDOW: Duration = Duration::from_secs(30);
+
+/// Safe mode settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeModeSettings {
+    /// Send nothing to the network: no sweep, port connects or HTTP requests
+    pub strictly_passive: bool,
+}
+
+/// Discover devices by listening only
+pub(super) async fn discover() -> Result<Discovered, ScanError> {
+    let (mdns, announcements, nbns, wsd, dhcp) = tokio::join!(
+        mdns::listen(LISTEN_WINDOW),
+        ssdp::listen_notify(LISTEN_WINDOW),
+        nbns::listen(LISTEN_WINDOW),
+        wsd::listen(LISTEN_WINDOW),
+        dhcp::listen(LISTEN_WINDOW),
+    );
+    // Read last: hosts that talked during the window are in the cache now
+    let hosts = arp::parse_arp_table().await?;
+
+    Ok(Discovered {
+        arp: arp::Discovery {
+            observed: hosts.clone(),
+            hosts,
+        },
+        mdns,
+        ssdp: ssdp::from_headers(&announcements),
+        nbns,
+        wsd,
+        dhcp,
+    })
+}
@@ -0,0 +1,306 @@
+//! OS-specific sources of neighbour and routing information
+//!
+//! Discovery needs the OS neighbour caches, the default gateway, the DNS
+//! resolvers and a way to ping one address or the IPv6 all-nodes group. Linux exposes the first two under `/proc`; macOS and
+//! Windows only through their `arp`, `ndp`, `netsh` and `route` tools, whose
+//! output is parsed here. Every backend is compiled on every platform so the
+//! parsers stay type-checked; [`current`] picks the one for the running OS.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Where the OS keeps what discovery reads
+pub trait Platform: Send + Sync {
+    /// (IP, MAC) pairs in the IPv4 neighbour (ARP) cache
+    fn arp_table(&self) -> io::Result<Vec<(String, String)>>;
+
+    /// (address, MAC) pairs in the IPv6 neighbour cache
+    fn ipv6_neighbors(&self) -> io::Result<Vec<(String, String)>>;
+
+    /// Default IPv4 gateway
+    fn default_gateway(&self) -> Option<Ipv4Addr>;
+
+    /// DNS resolvers the OS sends queries to
+    fn dns_servers(&self) -> io::Result<Vec<IpAddr>>;
+
+    /// Program and arguments that send one echo request to `target`
+    fn ping_command(&self, target: Ipv4Addr, timeout: Duration) -> (&'static str, Vec<String>);
+
+    /// Program and arguments that send one echo request to `target` that
+    /// expires after `ttl` hops, waiting a second for the answer
+    fn ttl_ping_command(&self, target: Ipv4Addr, ttl: u8) -> (&'static str, Vec<String>);
+
+    /// Program and arguments that ping the IPv6 all-nodes group `ff02::1` on
+    /// the interface with this name and index
+    fn ping_all_nodes_command(&self, interface: &str, index: u32) -> (&'static str, Vec<String>);
+}
+
+/// Backend for the OS the app runs on
+pub fn current() -> &'static dyn Platform {
+    if cfg!(target_os = "windows") {
+        &Windows
+    } else if cfg!(target_os = "macos") {
+        &MacOs
+    } else {
+        &Linux
+    }
+}
+
+/// `/proc/net` and iproute2
+pub struct Linux;
+
+/// `arp`, `ndp` and `route` from the BSD userland
+pub struct MacOs;
+
+/// `arp`, `netsh` and `route` from Windows
+pub struct Windows;
+
+impl Platform for Linux {
+    fn arp_table(&self) -> io::Result<Vec<(String, String)>> {
+        let table = std::fs::read_to_string("/proc/net/arp")?;
+
+        // Format: IP address       HW type     Flags       HW address            Mask     Device
+        //         192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:ff     *        eth0
+        Ok(table
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() < 6 {
+                    return None;
+                }
+                Some((parts[0].to_string(), normalize_mac(parts[3])?))
+            })
+            .collect())
+    }
+
+    fn ipv6_neighbors(&self) -> io::Result<Vec<(String, String)>> {
+        let output = run("ip", &["-6", "neigh", "show"])?;
+
+        // Format: 2001:db8::10 dev eth0 lladdr aa:bb:cc:dd:ee:ff REACHABLE
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                let mac_pos = parts.iter().position(|p| *p == "lladdr")? + 1;
+                Some((parts.first()?.to_string(), normalize_mac(parts.get(mac_pos)?)?))
+            })
+            .collect())
+    }
+
+    fn default_gateway(&self) -> Option<Ipv4Addr> {
+        let table = std::fs::read_to_string("/proc/net/route").ok()?;
+
+        // Format: Iface  Destination  Gateway   Flags ...
+        //         eth0   00000000     0101A8C0  0003  ...
+        for line in table.lines().skip(1) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 3 || parts[1] != "00000000" {
+                continue;
+            }
+            // Addresses are little-endian hex
+            if let Ok(raw) = u32::from_str_radix(parts[2], 16) {
+                let gateway = Ipv4Addr::from(raw.swap_bytes());
+                if !gateway.is_unspecified() {
+                    return Some(gateway);
+                }
+            }
+        }
+        None
+    }
+
+    fn dns_servers(&self) -> io::Result<Vec<IpAddr>> {
+        // With systemd-resolved, resolv.conf names its local stub; the
+        // upstream servers are listed here instead
+        let conf = std::fs::read_to_string("/run/systemd/resolve/resolv.conf")
+            .or_else(|_| std::fs::read_to_string("/etc/resolv.conf"))?;
+
+        // Format: nameserver 192.168.1.1
+        Ok(conf
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|address| parse_ip(address.trim()))
+            .collect())
+    }
+
+    fn ping_command(&self, target: Ipv4Addr, timeout: Duration) -> (&'static str, Vec<String>) {
+        let wait = timeout.as_secs().max(1).to_string();
+        ("ping", vec!["-c".into(), "1".into(), "-W".into(), wait, target.to_string()])
+    }
+
+    fn ttl_ping_command(&self, target: Ipv4Addr, ttl: u8) -> (&'static str, Vec<String>) {
+        ("ping", vec!["-c".into(), "1".into(), "-W".into(), "1".into(), "-t".into(), ttl.to_string(), target.to_string()])
+    }
+
+    fn ping_all_nodes_command(&self, interface: &str, _index: u32) -> (&'static str, Vec<String>) {
+        ("ping", vec!["-6".into(), "-c".into(), "2".into(), "-W".into(), "1".into(), format!("ff02::1%{}", interface)])
+    }
+}
+
+impl Platform for MacOs {
+    fn arp_table(&self) -> io::Result<Vec<(String, String)>> {
+        let output = run("arp", &["-an"])?;
+
+        // Format: ? (192.168.1.1) at 0:1a:2b:3c:4d:5e on en0 ifscope [ethernet]
+        //         ? (192.168.1.7) at (incomplete) on en0 ifscope [ethernet]
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let ip = line.split_once('(')?.1.split_once(')')?.0;
+                let mac = line.split_once(" at ")?.1.split_whitespace().next()?;
+                Some((ip.parse::<Ipv4Addr>().ok()?.to_string(), normalize_mac(mac)?))
+            })
+            .collect())
+    }
+
+    fn ipv6_neighbors(&self) -> io::Result<Vec<(String, String)>> {
+        let output = run("ndp", &["-an"])?;
+
+        // Format: Neighbor                 Linklayer Address  Netif Expire    St Flgs Prbs
+        //         fe80::1%en0              0:1a:2b:3c:4d:5e   en0   23h59m58s S  R
+        Ok(output
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                // Link-local addresses carry a %scope
+                let address = parts.next()?.split('%').next()?;
+                let mac = normalize_mac(parts.next()?)?;
+                Some((address.parse::<Ipv6Addr>().ok()?.to_string(), mac))
+            })
+            .collect())
+    }
+
+    fn default_gateway(&self) -> Option<Ipv4Addr> {
+        let output = run("route", &["-n", "get", "default"]).ok()?;
+
+        // Format: "    gateway: 192.168.1.1"
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("gateway:"))
+            .and_then(|gateway| gateway.trim().parse().ok())
+    }
+
+    fn dns_servers(&self) -> io::Result<Vec<IpAddr>> {
+        let output = run("scutil", &["--dns"])?;
+
+        // Format: "  nameserver[0] : 192.168.1.1", repeated for every resolver scope
+        Ok(output
+            .lines()
+            .filter(|line| line.trim_start().starts_with("nameserver["))
+            .filter_map(|line| parse_ip(line.split_once(':')?.1.trim()))
+            .collect())
+    }
+
+    fn ping_command(&self, target: Ipv4Addr, timeout: Duration) -> (&'static str, Vec<String>) {
+        // -W is in milliseconds here
+        let wait = timeout.as_millis().max(1).to_string();
+        ("ping", vec!["-c".into(), "1".into(), "-W".into(), wait, target.to_string()])
+    }
+
+    fn ttl_ping_command(&self, target: Ipv4Addr, ttl: u8) -> (&'static str, Vec<String>) {
+        // -t is a timeout here; -m sets the TTL
+        ("ping", vec!["-c".into(), "1".into(), "-W".into(), "1000".into(), "-m".into(), ttl.to_string(), target.to_string()])
+    }
+
+    fn ping_all_nodes_command(&self, interface: &str, _index: u32) -> (&'static str, Vec<String>) {
+        ("ping6", vec!["-c".into(), "2".into(), format!("ff02::1%{}", interface)])
+    }
+}
+
+impl Platform for Windows {
+    fn arp_table(&self) -> io::Result<Vec<(String, String)>> {
+        let output = run("arp", &["-a"])?;
+
+        // Headers are localized; rows are not:
+        //   192.168.1.1           aa-bb-cc-dd-ee-ff     dynamic
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let ip: Ipv4Addr = parts.next()?.parse().ok()?;
+                Some((ip.to_string(), normalize_mac(parts.next()?)?))
+            })
+            .collect())
+    }
+
+    fn ipv6_neighbors(&self) -> io::Result<Vec<(String, String)>> {
+        let output = run("netsh", &["interface", "ipv6", "show", "neighbors"])?;
+
+        // Format: fe80::1                                     aa-bb-cc-dd-ee-ff  Reachable (Router)
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let address: Ipv6Addr = parts.next()?.split('%').next()?.parse().ok()?;
+                Some((address.to_string(), normalize_mac(parts.next()?)?))
+            })
+            .collect())
+    }
+
+    fn default_gateway(&self) -> Option<Ipv4Addr> {
+        let output = run("route", &["print", "-4", "0.0.0.0"]).ok()?;
+
+        // Format: Network Destination  Netmask   Gateway      Interface     Metric
+        //         0.0.0.0              0.0.0.0   192.168.1.1  192.168.1.10  25
+        output.lines().find_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 3 || parts[0] != "0.0.0.0" || parts[1] != "0.0.0.0" {
+                return None;
+            }
+            parts[2].parse().ok().filter(|gateway: &Ipv4Addr| !gateway.is_unspecified())
+        })
+    }
+
+    fn dns_servers(&self) -> io::Result<Vec<IpAddr>> {
+        // ipconfig's labels are localized; the cmdlet prints bare addresses
+        let output = run(
+            "powershell",
+            &["-NoProfile", "-Command", "(Get-DnsClientServerAddress).ServerAddresses"],
+        )?;
+        Ok(output.lines().filter_map(|line| parse_ip(line.trim())).collect())
+    }
+
+    fn ping_command(&self, target: Ipv4Addr, timeout: Duration) -> (&'static str, Vec<String>) {
+        let wait = timeout.as_millis().max(1).to_string();
+        ("ping", vec!["-n".into(), "1".into(), "-w".into(), wait, target.to_string()])
+    }
+
+    fn ttl_ping_command(&self, target: Ipv4Addr, ttl: u8) -> (&'static str, Vec<String>) {
+        ("ping", vec!["-n".into(), "1".into(), "-w".into(), "1000".into(), "-i".into(), ttl.to_string(), target.to_string()])
+    }
+
+    fn ping_all_nodes_command(&self, _interface: &str, index: u32) -> (&'static str, Vec<String>) {
+        // Zone IDs are interface indexes here
+        ("ping", vec!["-n".into(), "2".into(), "-w".into(), "1000".into(), format!("ff02::1%{}", index)])
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Address with an IPv6 `%scope` dropped
+fn parse_ip(address: &str) -> Option<IpAddr> {
+    address.split('%').next()?.parse().ok()
+}
+
+/// `aa:bb:cc:dd:ee:ff` from the spellings the tools use (`0:1a:…`, `AA-BB-…`).
+/// `None` for incomplete, broadcast and multicast entries.
+fn normalize_mac(mac: &str) -> Option<String> {
+    let octets = mac
+        .split([':', '-'])
+        .map(|octet| u8::from_str_radix(octet, 16).ok().filter(|_| (1..=2).contains(&octet.len())))
+        .collect::<Option<Vec<u8>>>()?;
+    if octets.len() != 6 || octets.iter().all(|&b| b == 0) || octets[0] & 1 == 1 {
+        return None;
+    }
+    Some(octets.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":"))
+}
@@ -0,0 +1,228 @@
+//! Gateway port-mapping protocols (UPnP IGD, NAT-PMP, PCP)
+//!
+//! Port-mapping protocols let any device on the LAN open ports on the router
+//! without authentication. NAT-PMP (RFC 6886) and PCP (RFC 6887) provide no
+//! way to list existing mappings, so for those we can only detect that the
+//! gateway accepts them; UPnP IGD mappings are listed by [`super::igd`].
+//! All protocols feed a single port-mapping finding on the gateway, and each
+//! listed mapping is reported on the LAN host it exposes.
+
+use super::{igd, net, Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// NAT-PMP and PCP server port on the gateway
+const NAT_PMP_PORT: u16 = 5351;
+
+/// Port-mapping protocol
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingProtocol {
+    UpnpIgd,
+    NatPmp,
+    Pcp,
+}
+
+impl MappingProtocol {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::UpnpIgd => "UPnP IGD",
+            Self::NatPmp => "NAT-PMP",
+            Self::Pcp => "PCP",
+        }
+    }
+}
+
+/// A port forward that exposes a LAN host on the WAN side
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub protocol: MappingProtocol,
+    pub external_port: u16,
+    pub internal_client: String,
+    pub internal_port: u16,
+    /// "TCP" or "UDP"
+    pub transport: String,
+    pub description: Option<String>,
+}
+
+/// Port-mapping capabilities and mappings found on the gateway
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PortMappingReport {
+    pub enabled_protocols: Vec<MappingProtocol>,
+    /// WAN address reported by the gateway
+    pub external_address: Option<String>,
+    pub mappings: Vec<PortMapping>,
+}
+
+/// Probe the gateway for UPnP IGD, NAT-PMP and PCP support
+pub async fn probe_gateway(gateway: Ipv4Addr, local_ip: Ipv4Addr, timeout: Duration) -> PortMappingReport {
+    let mut report = PortMappingReport::default();
+
+    let (igd, nat_pmp, pcp) = tokio::join!(
+        igd::list_mappings(gateway, timeout),
+        probe_nat_pmp(gateway, timeout),
+        probe_pcp(gateway, local_ip, timeout),
+    );
+
+    if let Some(mappings) = igd {
+        report.enabled_protocols.push(MappingProtocol::UpnpIgd);
+        report.mappings = mappings;
+    }
+
+    if let Some(external) = nat_pmp {
+        report.enabled_protocols.push(MappingProtocol::NatPmp);
+        report.external_address = Some(external.to_string());
+    }
+    if pcp {
+        report.enabled_protocols.push(MappingProtocol::Pcp);
+    }
+
+    report
+}
+
+async fn exchange(gateway: Ipv4Addr, request: &[u8], timeout: Duration) -> Option<Vec<u8>> {
+    let socket = net::udp_bind(net::ANY).await.ok()?;
+    socket.send_to(request, (gateway, NAT_PMP_PORT)).await.ok()?;
+
+    let mut buf = [0u8; 1100];
+    match tokio::time::timeout(timeout, socket.recv_from(&mut buf)).await {
+        Ok(Ok((len, addr))) if addr.ip() == gateway => Some(buf[..len].to_vec()),
+        _ => None,
+    }
+}
+
+/// Send a NAT-PMP external address request; returns the WAN address on success
+async fn probe_nat_pmp(gateway: Ipv4Addr, timeout: Duration) -> Option<Ipv4Addr> {
+    // Version 0, opcode 0 (external address)
+    let response = exchange(gateway, &[0, 0], timeout).await?;
+
+    // Version (1) | opcode 128 (1) | result (2) | epoch (4) | address (4)
+    if response.len() < 12 || response[0] != 0 || response[1] != 128 {
+        return None;
+    }
+    let result = u16::from_be_bytes([response[2], response[3]]);
+    if result != 0 {
+        return None;
+    }
+    Some(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+}
+
+/// Send a PCP ANNOUNCE request; returns true if the gateway speaks PCP
+async fn probe_pcp(gateway: Ipv4Addr, local_ip: Ipv4Addr, timeout: Duration) -> bool {
+    let mut request = Vec::with_capacity(24);
+    // Version 2, R=0 + opcode 0 (ANNOUNCE), reserved
+    request.extend_from_slice(&[2, 0, 0, 0]);
+    // Requested lifetime
+    request.extend_from_slice(&0u32.to_be_bytes());
+    // Client address as IPv4-mapped IPv6
+    request.extend_from_slice(&local_ip.to_ipv6_mapped().octets());
+
+    match exchange(gateway, &request, timeout).await {
+        // Version 2, R=1 + opcode 0, result code SUCCESS
+        Some(response) => response.len() >= 24 && response[0] == 2 && response[1] == 0x80 && response[3] == 0,
+        None => false,
+    }
+}
+
+/// Build the port-mapping finding for the gateway, if anything was found
+pub fn to_issue(report: &PortMappingReport) -> Option<SecurityIssue> {
+    if report.enabled_protocols.is_empty() && report.mappings.is_empty() {
+        return None;
+    }
+
+    let protocols = report
+        .enabled_protocols
+        .iter()
+        .map(|p| p.label())
+        .collect::<Vec<_>>()
+        .join(" / ");
+
+    let mappings: String = report
+        .mappings
+        .iter()
+        .map(|m| {
+            format!(
+                "\n・{} {} → {}:{} ({})",
+                m.transport,
+                m.external_port,
+                m.internal_client,
+                m.internal_port,
+                m.protocol.label()
+            )
+        })
+        .collect();
+    let mapping_list = if mappings.is_empty() {
+        String::new()
+    } else {
+        "@port-mapping.mappings".to_string()
+    };
+
+    let severity = if report.mappings.is_empty() {
+        IssueSeverity::Medium
+    } else {
+        IssueSeverity::High
+    };
+
+    Some(content::issue(
+        "port-mapping",
+        severity,
+        &[("protocols", protocols), ("mapping_list", mapping_list), ("mappings", mappings)],
+    ))
+}
+
+/// Finding for a mapping that exposes a LAN host on the gateway's WAN side
+pub fn to_mapping_issue(mapping: &PortMapping, gateway: &str) -> SecurityIssue {
+    let note = if mapping.description.is_some() {
+        "@port-forwarded.description".to_string()
+    } else {
+        String::new()
+    };
+    content::issue(
+        "port-forwarded",
+        IssueSeverity::High,
+        &[
+            ("gateway", gateway.to_string()),
+            ("external_port", mapping.external_port.to_string()),
+            ("transport", mapping.transport.clone()),
+            ("internal", format!("{}:{}", mapping.internal_client, mapping.internal_port)),
+            ("note", note),
+            ("description", mapping.description.clone().unwrap_or_default()),
+        ],
+    )
+}
+
+/// Attach a finding for each mapping to the LAN host it exposes, or to the
+/// gateway when that host is not among `devices`
+pub fn attach_mappings(devices: &mut [Device], gateway_ip: &str, mappings: &[PortMapping]) {
+    for mapping in mappings {
+        let target = devices
+            .iter()
+            .position(|d| d.ip == mapping.internal_client)
+            .or_else(|| devices.iter().position(|d| d.ip == gateway_ip));
+        if let Some(i) = target {
+            devices[i].issues.push(to_mapping_issue(mapping, gateway_ip));
+        }
+    }
+}
+
+/// List the mappings of a UPnP internet gateway other than the default
+/// gateway (a second router, a mesh node) and attach the findings
+pub async fn check_other_gateway(devices: &mut [Device], index: usize, timeout: Duration) {
+    let device = &devices[index];
+    let Some(ref description) = device.ssdp else {
+        return;
+    };
+    let ip = device.ip.clone();
+    let Some(mappings) = igd::list_mappings_at(&ip, &description.locations, timeout).await else {
+        return;
+    };
+    let report = PortMappingReport {
+        enabled_protocols: vec![MappingProtocol::UpnpIgd],
+        external_address: None,
+        mappings,
+    };
+    devices[index].issues.extend(to_issue(&report));
+    attach_mappings(devices, &ip, &report.mappings);
+}
@@ -1,6 +1,104 @@
 //! Port scanning functionality
 
-use super::{Port, ScanError};
+use super::{datapack, net, until_cancelled, Port, PortVerification, ScanError};
+use crate::database::{self, DbError};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// TCP connect timeout per port
+pub const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Connect timeout during the verification pass
+const RETRY_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Pause between verification probes, plus up to the same again as jitter,
+/// so devices that rate-limit SYNs are not probed in a burst
+const RETRY_PACING: Duration = Duration::from_millis(150);
+
+/// How long to wait for a service to send its banner
+const BANNER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Services that greet first: FTP, SSH, Telnet, SMTP
+const GREETING_PORTS: &[u16] = &[21, 22, 23, 25, 587, 2323];
+
+/// Web ports asked with a HEAD request
+const HTTP_PORTS: &[u16] = &[80, 5000, 8000, 8008, 8080, 8081, 8888];
+
+/// RTSP ports asked with an OPTIONS request
+const RTSP_PORTS: &[u16] = &[554, 8554];
+
+/// Longest service version kept
+const MAX_VERSION_LEN: usize = 80;
+
+/// Probes of one device queued at once; bounds memory for the full range
+const PROBE_BATCH_SIZE: usize = 512;
+
+/// How long to wait for a UDP service to answer a probe
+pub const UDP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Sends per UDP probe; datagrams get lost
+pub const UDP_ATTEMPTS: u32 = 2;
+
+/// DNS query for the server version (TXT "version.bind" in class CHAOS)
+const DNS_VERSION_QUERY: &[u8] = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\x07version\x04bind\x00\x00\x10\x00\x03";
+
+/// TFTP read request for a file no device has; the error reply is enough
+const TFTP_READ_REQUEST: &[u8] = b"\x00\x01iot-doctor-probe\x00octet\x00";
+
+/// SNMPv2c GetRequest for sysDescr.0 with the default "public" community
+const SNMP_SYSDESCR_REQUEST: &[u8] = b"\x30\x26\x02\x01\x01\x04\x06public\xa0\x19\x02\x01\x01\x02\x01\x00\x02\x01\x00\x30\x0e\x30\x0c\x06\x08\x2b\x06\x01\x02\x01\x01\x01\x00\x05\x00";
+
+/// OID of sysDescr.0 as encoded in SNMP messages
+const SNMP_SYSDESCR_OID: &[u8] = b"\x2b\x06\x01\x02\x01\x01\x01\x00";
+
+/// IKEv1 Main Mode offer with one transform (3DES, SHA1, PSK, group 2)
+const IKE_MAIN_MODE: &[u8] = b"\
+    \x69\x6f\x74\x64\x6f\x63\x30\x31\x00\x00\x00\x00\x00\x00\x00\x00\
+    \x01\x10\x02\x00\x00\x00\x00\x00\x00\x00\x00\x54\
+    \x00\x00\x00\x38\x00\x00\x00\x01\x00\x00\x00\x01\
+    \x00\x00\x00\x2c\x01\x01\x00\x01\
+    \x00\x00\x00\x24\x01\x01\x00\x00\
+    \x80\x01\x00\x05\x80\x02\x00\x02\x80\x03\x00\x01\x80\x04\x00\x02\
+    \x80\x0b\x00\x01\x00\x0c\x00\x04\x00\x00\x70\x80";
+
+/// CoAP confirmable GET /.well-known/core
+const COAP_DISCOVERY: &[u8] = b"\x40\x01\x12\x34\xbb.well-known\x04core";
+
+/// UDP services probed with a request they have to answer. A silent UDP port
+/// cannot be told apart from a filtered one, so only answers count as open.
+const UDP_PROBES: &[(u16, &[u8])] = &[
+    (53, DNS_VERSION_QUERY),
+    (69, TFTP_READ_REQUEST),
+    (161, SNMP_SYSDESCR_REQUEST),
+    (500, IKE_MAIN_MODE),
+    (5683, COAP_DISCOVERY),
+];
+
+/// Default cap on connection attempts in flight across all devices
+pub const DEFAULT_MAX_CONNECTIONS: usize = 256;
+
+/// Default connection attempts per second to one device
+pub const DEFAULT_PER_HOST_RATE: u32 = 100;
+
+/// Highest per-device rate that can be set; far beyond what a device or the
+/// network keeps up with, and short of a zero pacing interval
+pub const MAX_PER_HOST_RATE: u32 = 100_000;
+
+/// Most unanswered ports worth a second try; a host dropping more is
+/// filtering them, not rate-limiting
+const RETRY_LIMIT: usize = 64;
+
+/// Key under which the port selection is stored
+const CONFIG_KEY: &str = "scan_config";
+
+/// The 1000 TCP ports most often found open (nmap's frequency ranking)
+const TOP_PORTS: &str = include_str!("top_ports.txt");
 
 /// Common ports to scan for IoT devices
 const COMMON_PORTS: &[u16] = &[
@@ -22,51 +120,409 @@ const COMMON_PORTS: &[u16] = &[
     9000,  // Various IoT
 ];
 
-/// Scan common ports on target IP
-pub async fn scan_ports(ip: &str) -> Result<Vec<Port>, ScanError> {
-    let mut tasks = Vec::new();
+/// Set of ports a scan probes
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortPreset {
+    /// The common IoT ports
+    #[default]
+    IotMinimal,
+    /// The 1000 most common TCP ports
+    Top1000,
+    /// Every TCP port; slow
+    Full,
+    /// The user's own list
+    Custom,
+}
+
+/// Port selection and pacing for scans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanConfig {
+    pub preset: PortPreset,
+    /// Ports and ranges for the custom preset, e.g. "22,80,8000-8100"
+    pub custom_ports: String,
+    /// Most connection attempts in flight across all devices
+    pub max_connections: usize,
+    /// Connection attempts per second to one device; 0 for no limit
+    pub per_host_rate: u32,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            preset: PortPreset::default(),
+            custom_ports: String::new(),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            per_host_rate: DEFAULT_PER_HOST_RATE,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.preset == PortPreset::Custom {
+            parse_port_list(&self.custom_ports)?;
+        }
+        if self.max_connections == 0 {
+            return Err("max_connections must be at least 1".to_string());
+        }
+        if self.per_host_rate > MAX_PER_HOST_RATE {
+            return Err(format!("per_host_rate must be at most {}", MAX_PER_HOST_RATE));
+        }
+        Ok(())
+    }
+
+    /// Ports of the chosen preset
+    pub fn ports(&self) -> Vec<u16> {
+        match self.preset {
+            PortPreset::IotMinimal => COMMON_PORTS.to_vec(),
+            PortPreset::Top1000 => parse_port_list(TOP_PORTS).unwrap_or_default(),
+            PortPreset::Full => (1..=u16::MAX).collect(),
+            // Validated on save; an unreadable list falls back to the common ports
+            PortPreset::Custom => parse_port_list(&self.custom_ports).unwrap_or_else(|_| COMMON_PORTS.to_vec()),
+        }
+    }
+}
+
+/// Parse a comma-separated list of ports and ranges ("22, 80, 8000-8100")
+pub fn parse_port_list(spec: &str) -> Result<Vec<u16>, String> {
+    let parse = |port: &str| match port.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(format!("Invalid port: {}", port.trim())),
+        Ok(port) => Ok(port),
+    };
+
+    let mut ports = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("Invalid port range: {}", item));
+                }
+                ports.extend(start..=end);
+            }
+            None => ports.push(parse(item)?),
+        }
+    }
+    if ports.is_empty() {
+        return Err("No ports given".to_string());
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Stored port selection, defaulting to the common IoT ports
+pub fn load_config() -> Result<ScanConfig, DbError> {
+    match database::get_setting(CONFIG_KEY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(ScanConfig::default()),
+    }
+}
+
+/// Persist the port selection
+pub fn save_config(config: &ScanConfig) -> Result<(), DbError> {
+    database::set_setting(CONFIG_KEY, &serde_json::to_string(config)?)
+}
+
+/// Ports of `config` plus those of a data pack port profile (all profiles when `None`)
+pub fn ports_to_scan(config: &ScanConfig, profile: Option<&str>) -> Vec<u16> {
+    let extra = match profile {
+        Some(name) => datapack::port_profile(name).unwrap_or_default(),
+        None => datapack::profile_ports(),
+    };
+
+    let mut ports = config.ports();
+    ports.extend(extra);
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+/// Outcome of a single connect probe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ProbeResult {
+    Open,
+    /// Actively refused (RST) or unreachable
+    Closed,
+    /// No answer; the probe may have been dropped
+    TimedOut,
+}
+
+/// Shared by every device of a scan: caps the connection attempts in flight
+/// and paces the attempts made to each device
+#[derive(Clone)]
+pub struct ConnectLimiter {
+    slots: Arc<Semaphore>,
+    per_host_rate: u32,
+}
+
+impl ConnectLimiter {
+    pub fn new(config: &ScanConfig) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(config.max_connections.max(1))),
+            // A stored config may predate the bound
+            per_host_rate: config.per_host_rate.min(MAX_PER_HOST_RATE),
+        }
+    }
+
+    /// Ticks at the per-device rate; `None` without a limit
+    fn pacing(&self) -> Option<tokio::time::Interval> {
+        (self.per_host_rate > 0).then(|| {
+            let mut interval = tokio::time::interval(Duration::from_secs(1) / self.per_host_rate);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        })
+    }
+
+    async fn probe(&self, ip: &str, port: u16, timeout: Duration) -> ProbeResult {
+        // The semaphore is never closed
+        let _slot = self.slots.acquire().await;
+        probe_port(ip, port, timeout).await
+    }
+
+    async fn exchange_udp(&self, ip: &str, port: u16, request: &[u8]) -> Option<Vec<u8>> {
+        let _slot = self.slots.acquire().await;
+        udp_exchange(ip, port, request).await
+    }
+}
+
+/// Scan the given ports on target IP
+///
+/// Ports that were open or timed out are probed again in a slower, jittered
+/// verification pass: some IoT stacks rate-limit SYNs and silently drop the
+/// later probes of a burst. Returns `Err(Cancelled)` as soon as `cancel` is triggered.
+pub async fn scan_ports(
+    ip: &str,
+    ports: &[u16],
+    limiter: &ConnectLimiter,
+    cancel: &CancellationToken,
+) -> Result<Vec<Port>, ScanError> {
     let ip = ip.to_string();
+    let mut pacing = limiter.pacing();
 
-    for &port in COMMON_PORTS {
-        let ip_clone = ip.clone();
-        tasks.push(tokio::spawn(async move {
-            if is_port_open(&ip_clone, port).await {
-                let service = identify_service(port);
-                let is_secure = is_secure_service(port);
-
-                Some(Port {
-                    number: port,
-                    protocol: "tcp".to_string(),
-                    service: Some(service.to_string()),
-                    version: None,
-                    is_secure,
-                })
-            } else {
-                None
+    // Returning early drops the set, which aborts the probes still running
+    let mut probes = JoinSet::new();
+    let mut first_pass = Vec::with_capacity(ports.len());
+    for &port in ports {
+        while probes.len() >= PROBE_BATCH_SIZE {
+            if let Some(Ok(result)) = until_cancelled(cancel, probes.join_next()).await? {
+                first_pass.push(result);
             }
-        }));
+        }
+        if let Some(interval) = pacing.as_mut() {
+            until_cancelled(cancel, interval.tick()).await?;
+        }
+        let (ip, limiter) = (ip.clone(), limiter.clone());
+        probes.spawn(async move { (port, limiter.probe(&ip, port, CONNECT_TIMEOUT).await) });
+    }
+    while let Some(joined) = until_cancelled(cancel, probes.join_next()).await? {
+        if let Ok(result) = joined {
+            first_pass.push(result);
+        }
     }
+    first_pass.sort_unstable_by_key(|(port, _)| *port);
+
+    // A host that answered nothing is filtering every port, not rate-limiting
+    let answered = first_pass.iter().any(|(_, r)| *r != ProbeResult::TimedOut);
+    let timed_out = first_pass.iter().filter(|(_, r)| *r == ProbeResult::TimedOut).count();
+    let retry_timeouts = answered && timed_out <= RETRY_LIMIT;
 
     let mut open_ports = Vec::new();
-    for task in tasks {
-        if let Ok(Some(port)) = task.await {
-            open_ports.push(port);
+    for (port, first) in first_pass {
+        if first == ProbeResult::Closed || (first == ProbeResult::TimedOut && !retry_timeouts) {
+            continue;
         }
+
+        let pacing = tokio::time::sleep(RETRY_PACING + jitter(RETRY_PACING));
+        until_cancelled(cancel, pacing).await?;
+        let second = until_cancelled(cancel, limiter.probe(&ip, port, RETRY_TIMEOUT)).await?;
+
+        let verification = match (first, second) {
+            (ProbeResult::Open, ProbeResult::Open) => PortVerification::Verified,
+            (ProbeResult::Open, _) | (_, ProbeResult::Open) => PortVerification::SingleAttempt,
+            _ => continue,
+        };
+
+        open_ports.push(tcp_port(port, verification));
+    }
+
+    Ok(open_ports)
+}
+
+/// Which of `ports` accept a connection, probed once each without verification
+pub async fn probe_open(ip: &str, ports: &[u16]) -> Vec<u16> {
+    let mut open = Vec::new();
+    for &port in ports {
+        if probe_port(ip, port, CONNECT_TIMEOUT).await == ProbeResult::Open {
+            open.push(port);
+        }
+    }
+    open
+}
+
+/// Open TCP port entry
+pub fn tcp_port(number: u16, verification: PortVerification) -> Port {
+    Port {
+        number,
+        protocol: "tcp".to_string(),
+        service: Some(identify_service(number).to_string()),
+        version: None,
+        is_secure: is_secure_service(number),
+        verification,
+        risk_note: content::port_note(number, content::language()),
+    }
+}
+
+/// Open UDP port entry
+pub fn udp_port(number: u16, version: Option<String>) -> Port {
+    Port {
+        number,
+        protocol: "udp".to_string(),
+        service: Some(identify_service(number).to_string()),
+        version,
+        is_secure: is_secure_service(number),
+        verification: PortVerification::Verified,
+        risk_note: content::port_note(number, content::language()),
+    }
+}
+
+/// UDP ports [`scan_udp_ports`] probes
+pub fn udp_ports() -> Vec<u16> {
+    UDP_PROBES.iter().map(|(port, _)| *port).collect()
+}
+
+/// Probe the IoT UDP services (DNS, TFTP, SNMP, IKE, CoAP) on target IP
+///
+/// Only services that answered are returned; SNMP and DNS answers also give
+/// the service version. Returns `Err(Cancelled)` as soon as `cancel` is triggered.
+pub async fn scan_udp_ports(
+    ip: &str,
+    limiter: &ConnectLimiter,
+    cancel: &CancellationToken,
+) -> Result<Vec<Port>, ScanError> {
+    let mut probes = JoinSet::new();
+    for &(port, request) in UDP_PROBES {
+        let (ip, limiter) = (ip.to_string(), limiter.clone());
+        probes.spawn(async move { (port, limiter.exchange_udp(&ip, port, request).await) });
     }
 
+    let mut open_ports = Vec::new();
+    while let Some(joined) = until_cancelled(cancel, probes.join_next()).await? {
+        if let Ok((port, Some(reply))) = joined {
+            let version = match port {
+                53 => dns_version(&reply),
+                161 => snmp_sysdescr(&reply),
+                _ => None,
+            };
+            open_ports.push(udp_port(port, version.and_then(|v| service_version(&v))));
+        }
+    }
+    open_ports.sort_unstable_by_key(|p| p.number);
     Ok(open_ports)
 }
 
-async fn is_port_open(ip: &str, port: u16) -> bool {
-    use std::net::TcpStream;
-    use std::time::Duration;
+/// Send `request` to a UDP service and return its answer
+async fn udp_exchange(ip: &str, port: u16, request: &[u8]) -> Option<Vec<u8>> {
+    let addr: std::net::SocketAddr = format!("{}:{}", ip, port).parse().ok()?;
+    let socket = net::udp_bind(net::ANY).await.ok()?;
+    // Connected, the socket only receives from the target and sees ICMP unreachables
+    socket.connect(addr).await.ok()?;
+
+    let mut buf = [0u8; 1500];
+    for _ in 0..UDP_ATTEMPTS {
+        socket.send(request).await.ok()?;
+        match tokio::time::timeout(UDP_TIMEOUT, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) if len > 0 => return Some(buf[..len].to_vec()),
+            // Port unreachable: closed
+            Ok(Err(_)) => return None,
+            _ => {}
+        }
+    }
+    None
+}
 
+/// Text of the TXT answer to [`DNS_VERSION_QUERY`]
+fn dns_version(reply: &[u8]) -> Option<String> {
+    if reply.len() < 12 || u16::from_be_bytes([reply[6], reply[7]]) == 0 {
+        return None;
+    }
+    // Skip the echoed question: name, type, class
+    let mut pos = skip_dns_name(reply, 12)? + 4;
+    // Answer: name, type, class, TTL, data length, then length-prefixed text
+    pos = skip_dns_name(reply, pos)?;
+    let rtype = u16::from_be_bytes([*reply.get(pos)?, *reply.get(pos + 1)?]);
+    if rtype != 16 {
+        return None;
+    }
+    let text_len = usize::from(*reply.get(pos + 10)?);
+    let text = reply.get(pos + 11..pos + 11 + text_len)?;
+    let text = String::from_utf8_lossy(text).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Offset after the DNS name at `pos`
+fn skip_dns_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // Compression pointer ends the name
+            _ if len & 0xc0 == 0xc0 => return Some(pos + 2),
+            _ => pos += usize::from(len) + 1,
+        }
+    }
+}
+
+/// sysDescr.0 from the answer to [`SNMP_SYSDESCR_REQUEST`]
+fn snmp_sysdescr(reply: &[u8]) -> Option<String> {
+    let oid_end = reply
+        .windows(SNMP_SYSDESCR_OID.len())
+        .position(|w| w == SNMP_SYSDESCR_OID)?
+        + SNMP_SYSDESCR_OID.len();
+    // The value follows the OID as an OCTET STRING
+    if *reply.get(oid_end)? != 0x04 {
+        return None;
+    }
+    let (len, start) = match *reply.get(oid_end + 1)? {
+        short if short < 0x80 => (usize::from(short), oid_end + 2),
+        0x81 => (usize::from(*reply.get(oid_end + 2)?), oid_end + 3),
+        0x82 => (usize::from(u16::from_be_bytes([*reply.get(oid_end + 2)?, *reply.get(oid_end + 3)?])), oid_end + 4),
+        _ => return None,
+    };
+    let text = String::from_utf8_lossy(reply.get(start..start + len)?);
+    // Descriptions are often several lines; the first names the firmware
+    let line = text.lines().next()?.trim().to_string();
+    (!line.is_empty()).then_some(line)
+}
+
+/// Random delay in `0..max`; none in deterministic mode
+fn jitter(max: Duration) -> Duration {
+    if net::is_deterministic() {
+        return Duration::ZERO;
+    }
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let max_ms = max.as_millis().max(1) as u32;
+    Duration::from_millis(u64::from(nanos % max_ms))
+}
+
+pub(super) async fn probe_port(ip: &str, port: u16, timeout: Duration) -> ProbeResult {
     let addr = format!("{}:{}", ip, port);
-    std::net::TcpStream::connect_timeout(
-        &addr.parse().unwrap(),
-        Duration::from_millis(500),
-    )
-    .is_ok()
+    let Ok(addr) = addr.parse() else {
+        return ProbeResult::Closed;
+    };
+
+    match net::tcp_connect_async(addr, timeout).await {
+        Ok(_) => ProbeResult::Open,
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) => {
+            ProbeResult::TimedOut
+        }
+        Err(_) => ProbeResult::Closed,
+    }
 }
 
 fn identify_service(port: u16) -> &'static str {
@@ -76,13 +532,17 @@ fn identify_service(port: u16) -> &'static str {
         23 => "Telnet",
         25 => "SMTP",
         53 => "DNS",
+        69 => "TFTP",
         80 => "HTTP",
+        161 => "SNMP",
         443 => "HTTPS",
+        500 => "IKE",
         554 => "RTSP",
         1883 => "MQTT",
         1900 => "UPnP",
         5000 => "UPnP",
         5353 => "mDNS",
+        5683 => "CoAP",
         8080 => "HTTP",
         8443 => "HTTPS",
         8883 => "MQTT-TLS",
@@ -91,11 +551,135 @@ fn identify_service(port: u16) -> &'static str {
 }
 
 fn is_secure_service(port: u16) -> bool {
-    matches!(port, 22 | 443 | 8443 | 8883)
+    matches!(port, 22 | 443 | 500 | 8443 | 8883)
+}
+
+/// Whether [`grab_banner`] knows how to make the service on `port` identify itself
+pub fn has_banner(port: u16) -> bool {
+    GREETING_PORTS.contains(&port) || HTTP_PORTS.contains(&port) || RTSP_PORTS.contains(&port)
 }
 
 /// Grab banner from service
-pub async fn grab_banner(_ip: &str, _port: u16) -> Option<String> {
-    // TODO: Implement banner grabbing
-    None
+///
+/// Returns the first line a service sends on connect (SSH, FTP, SMTP, Telnet),
+/// or the Server header of the answer to a HEAD (HTTP) or OPTIONS (RTSP) request.
+pub async fn grab_banner(ip: &str, port: u16) -> Option<String> {
+    use tokio::io::AsyncWriteExt;
+
+    let addr = format!("{}:{}", ip, port).parse().ok()?;
+    let mut stream = net::tcp_connect_async(addr, CONNECT_TIMEOUT).await.ok()?;
+
+    let request = if HTTP_PORTS.contains(&port) {
+        format!("HEAD / HTTP/1.0\r\nHost: {}\r\n\r\n", ip)
+    } else if RTSP_PORTS.contains(&port) {
+        format!("OPTIONS rtsp://{}:{}/ RTSP/1.0\r\nCSeq: 1\r\n\r\n", ip, port)
+    } else {
+        return read_greeting(&mut stream).await;
+    };
+    stream.write_all(request.as_bytes()).await.ok()?;
+
+    let head = read_head(&mut stream).await?;
+    head.lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("server").then(|| value.trim().to_string())
+        })
+        .filter(|server| !server.is_empty())
+}
+
+/// First non-empty line the service sends, without Telnet option negotiation
+async fn read_greeting(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    use tokio::io::AsyncReadExt;
+
+    let deadline = tokio::time::Instant::now() + BANNER_TIMEOUT;
+    let mut received = Vec::new();
+    let mut buf = [0u8; 256];
+    // Telnet servers may negotiate before they print anything; read on
+    // until a complete line arrived, or keep the partial one (a bare prompt)
+    while received.len() < 1024 {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => received.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+        // Every piece but the last ended with a newline
+        let text = strip_telnet_commands(&received);
+        let mut complete_lines = text.split(|&b| b == b'\n').rev().skip(1);
+        if complete_lines.any(|line| line.iter().any(|b| !b.is_ascii_whitespace())) {
+            break;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&strip_telnet_commands(&received)).to_string();
+    text.lines().map(str::trim).find(|l| !l.is_empty()).map(str::to_string)
+}
+
+/// Status line and headers of an HTTP or RTSP response
+async fn read_head(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    use tokio::io::AsyncReadExt;
+
+    let deadline = tokio::time::Instant::now() + BANNER_TIMEOUT;
+    let mut received = Vec::new();
+    let mut buf = [0u8; 1024];
+    while received.len() < 8192 && !received.windows(4).any(|w| w == b"\r\n\r\n") {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => received.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+    }
+    (!received.is_empty()).then(|| String::from_utf8_lossy(&received).to_string())
+}
+
+/// `data` without Telnet IAC command sequences
+pub(super) fn strip_telnet_commands(data: &[u8]) -> Vec<u8> {
+    const IAC: u8 = 255;
+    const SB: u8 = 250;
+    const SE: u8 = 240;
+
+    let mut text = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match (data[i], data.get(i + 1).copied()) {
+            (IAC, Some(IAC)) => {
+                text.push(IAC);
+                i += 2;
+            }
+            // Subnegotiation runs until IAC SE
+            (IAC, Some(SB)) => {
+                i += 2;
+                while i < data.len() && !(data[i] == IAC && data.get(i + 1) == Some(&SE)) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            // WILL, WON'T, DO, DON'T carry an option byte
+            (IAC, Some(251..=254)) => i += 3,
+            (IAC, _) => i += 2,
+            (byte, _) => {
+                text.push(byte);
+                i += 1;
+            }
+        }
+    }
+    text
+}
+
+/// Product and version from a banner: "SSH-2.0-OpenSSH_8.4p1 Debian-5" gives
+/// "OpenSSH 8.4p1", "220 ProFTPD 1.3.5 Server ready." gives "ProFTPD 1.3.5 Server ready."
+pub fn service_version(banner: &str) -> Option<String> {
+    let banner = banner.trim();
+    let version = match banner.strip_prefix("SSH-") {
+        // SSH-protoversion-softwareversion SP comments
+        Some(rest) => rest.split_once('-')?.1.split_whitespace().next()?.replacen('_', " ", 1),
+        None => {
+            // FTP and SMTP greetings start with a reply code
+            let code = banner.len() > 3
+                && banner.as_bytes()[..3].iter().all(u8::is_ascii_digit)
+                && matches!(banner.as_bytes()[3], b' ' | b'-');
+            if code { &banner[4..] } else { banner }.trim().to_string()
+        }
+    };
+
+    let version: String = version.chars().filter(|c| !c.is_control()).take(MAX_VERSION_LEN).collect();
+    // A bare "login:" prompt says nothing about the software
+    (!version.is_empty() && !version.ends_with(':')).then_some(version)
 }
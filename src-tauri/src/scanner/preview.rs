@@ -0,0 +1,272 @@
+//! What-would-run preview
+//!
+//! Lists the active and intrusive checks a scan at a given level would run
+//! against each device, taking the user's exclusions, opt-ins and what is
+//! already known about the devices into account. Nothing is sent on the
+//! network; the plan mirrors the decisions `scan_network` makes.
+
+use super::{arp, exposure, ports, stealth, Device, DeviceType, ScanLevel, ScanOptions};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv6Addr;
+
+/// A check that would run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedCheck {
+    pub id: String,
+    pub title: String,
+    /// Lowest scan level that runs the check
+    pub level: ScanLevel,
+    /// What the check depends on, when the scan decides it on the spot
+    pub condition: Option<String>,
+}
+
+/// A check that applies to the device but will not run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedCheck {
+    pub id: String,
+    pub title: String,
+    pub reason: String,
+}
+
+/// Plan for one device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicePlan {
+    pub mac: String,
+    pub ip: String,
+    pub name: Option<String>,
+    pub device_type: DeviceType,
+    pub excluded: bool,
+    pub checks: Vec<PlannedCheck>,
+    pub skipped: Vec<SkippedCheck>,
+}
+
+/// Plan for a whole scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckPreview {
+    pub level: ScanLevel,
+    pub devices: Vec<DevicePlan>,
+    /// Checks run on devices the scan finds that are not in the list
+    pub new_devices: Vec<PlannedCheck>,
+}
+
+fn check(id: &str, title: String, level: ScanLevel, condition: Option<&str>) -> PlannedCheck {
+    PlannedCheck {
+        id: id.to_string(),
+        title,
+        level,
+        condition: condition.map(str::to_string),
+    }
+}
+
+/// Checks that apply to `device`, and why any of them would not run
+fn applicable(
+    device: Option<&Device>,
+    is_gateway: bool,
+    options: &ScanOptions,
+) -> (Vec<PlannedCheck>, Vec<SkippedCheck>) {
+    let known_ports = device.is_some_and(|d| !d.open_ports.is_empty());
+    let has_port = |port: u16| device.is_some_and(|d| d.open_ports.iter().any(|p| p.number == port));
+    let mut checks = vec![check(
+        "port-scan",
+        format!(
+            "ポートスキャン（TCP {}ポート・UDP {}ポート）",
+            ports::ports_to_scan(&options.port_config, options.port_profile.as_deref()).len(),
+            ports::udp_ports().len()
+        ),
+        ScanLevel::Level2,
+        None,
+    )];
+    checks.push(check(
+        "os-guess",
+        "OSの推定（ICMPエコー1回・開いているポートへの接続1回）".to_string(),
+        ScanLevel::Level2,
+        None,
+    ));
+    let mut skipped = Vec::new();
+
+    let silent = device.map_or(true, |d| d.open_ports.is_empty() && d.mdns_instances.is_empty());
+    if silent {
+        checks.push(check(
+            "silent-follow-up",
+            format!("応答しない機器への追加調査（TCP {}ポート・ICMPタイムスタンプ）", stealth::SAMPLE_PORTS.len()),
+            ScanLevel::Level2,
+            Some("名前もサービスも公開していない場合"),
+        ));
+    }
+
+    if is_gateway {
+        checks.push(check(
+            "wifi",
+            "ルーターのWi-Fiの暗号化方式（暗号化なし・WEP・WPA）とWPSの確認（OSが記録しているWi-Fiの情報から）".to_string(),
+            ScanLevel::Level1,
+            None,
+        ));
+        checks.push(check(
+            "gateway",
+            "自動ポート開放機能（UPnP・NAT-PMP・PCP）と登録済みのポート転送、ネットワーク構成の確認".to_string(),
+            ScanLevel::Level2,
+            None,
+        ));
+        checks.push(check(
+            "router-audit",
+            "ルーターの診断（管理画面の暗号化・インターネット側からの管理画面への接続・Webサーバーの古さ）".to_string(),
+            ScanLevel::Level2,
+            None,
+        ));
+        checks.push(check(
+            "dns",
+            "DNSの設定の確認（不正なDNSサーバー・DNSリバインディング対策・暗号化されたDNSへの対応）".to_string(),
+            ScanLevel::Level2,
+            None,
+        ));
+    }
+
+    let camera = device.map_or(true, |d| {
+        d.device_type == DeviceType::Camera || d.wsd.as_ref().is_some_and(|w| w.is_onvif())
+    });
+    if camera {
+        checks.push(check(
+            "onvif",
+            "カメラの機種・ファームウェアの取得（ONVIF、パスワードなし）".to_string(),
+            ScanLevel::Level2,
+            device.is_none().then_some("カメラと判定された場合"),
+        ));
+    }
+
+    let router_candidate = is_gateway
+        || device.map_or(true, |d| matches!(d.device_type, DeviceType::Router | DeviceType::Unknown));
+    if router_candidate && (!known_ports || has_port(80) || has_port(443)) {
+        let condition = (!known_ports).then_some("管理画面（HTTP/HTTPS）が見つかった場合");
+        checks.push(check(
+            "router-web",
+            "管理画面の取得（ファームウェアの判定・ログイン画面の確認）".to_string(),
+            ScanLevel::Level2,
+            condition,
+        ));
+        checks.push(check(
+            "router-login",
+            "初期パスワードでのログイン試行".to_string(),
+            ScanLevel::Level3,
+            Some("OpenWrt・DD-WRTと判定された場合"),
+        ));
+    }
+
+    if !known_ports || has_port(554) || has_port(8554) {
+        let condition = (!known_ports).then_some("RTSP（554・8554）が開いている場合");
+        checks.push(check(
+            "rtsp-stream",
+            "カメラ映像（RTSP）へのパスワードなしでの接続（映像の情報のみ取得）".to_string(),
+            ScanLevel::Level3,
+            condition,
+        ));
+    }
+
+    let has_credentials = device.map_or(true, |d| matches!(d.device_type, DeviceType::Router | DeviceType::Camera));
+    if has_credentials {
+        let title = "機器の初期パスワードでのログイン試行（管理画面・Telnet）".to_string();
+        match device {
+            Some(d) if !options.credentials_consented(&d.mac) => skipped.push(SkippedCheck {
+                id: "default-credentials".to_string(),
+                title,
+                reason: "初期パスワードでのログインが許可されていません".to_string(),
+            }),
+            Some(_) => checks.push(check(
+                "default-credentials",
+                title,
+                ScanLevel::Level3,
+                Some("管理画面かTelnetが開いている場合"),
+            )),
+            None => checks.push(check(
+                "default-credentials",
+                title,
+                ScanLevel::Level3,
+                Some("ルーター・カメラと判定され、ログインを許可した場合"),
+            )),
+        }
+    }
+
+    checks.push(check(
+        "anonymous-share",
+        "共有フォルダへのパスワードなしでの接続（共有名と最上位のフォルダ名のみ）".to_string(),
+        ScanLevel::Level3,
+        Some("FTP・SMBが開いている場合"),
+    ));
+
+    let has_global_ipv6 = device.is_some_and(|d| {
+        d.ipv6_addresses
+            .iter()
+            .any(|a| a.parse::<Ipv6Addr>().is_ok_and(|a| exposure::is_global_ipv6(&a)))
+    });
+    if has_global_ipv6 || device.is_none() {
+        let title = "インターネット側からの到達性確認（外部の確認サービス経由）".to_string();
+        if options.exposure.endpoint().is_some() {
+            let condition = device.is_none().then_some("グローバルIPv6アドレスを持つ場合");
+            checks.push(check("ipv6-exposure", title, ScanLevel::Level2, condition));
+        } else {
+            skipped.push(SkippedCheck {
+                id: "ipv6-exposure".to_string(),
+                title,
+                reason: "外部からの到達性確認が有効になっていません".to_string(),
+            });
+        }
+    }
+
+    let forwarded = device.map_or(true, |d| d.issues.iter().any(|i| i.id == "port-forwarded"));
+    if forwarded {
+        let title = "ルーターが転送しているポートへのインターネット側からの到達性確認（外部の確認サービス経由）".to_string();
+        if options.exposure.endpoint().is_some() {
+            let condition = device.is_none().then_some("UPnPでポートが転送されている場合");
+            checks.push(check("forward-exposure", title, ScanLevel::Level2, condition));
+        } else {
+            skipped.push(SkippedCheck {
+                id: "forward-exposure".to_string(),
+                title,
+                reason: "外部からの到達性確認が有効になっていません".to_string(),
+            });
+        }
+    }
+
+    (checks, skipped)
+}
+
+/// Plan a scan at `level` of `devices`
+pub fn preview(devices: &[Device], level: ScanLevel, options: &ScanOptions) -> CheckPreview {
+    let level = if options.strictly_passive { ScanLevel::Level1 } else { level };
+    let gateway = arp::get_default_gateway().map(|ip| ip.to_string());
+    let at_level = |checks: Vec<PlannedCheck>| -> Vec<PlannedCheck> {
+        checks.into_iter().filter(|c| c.level <= level).collect()
+    };
+
+    let plans = devices
+        .iter()
+        .map(|device| {
+            let is_gateway = gateway.as_deref() == Some(device.ip.as_str());
+            let (checks, mut skipped) = applicable(Some(device), is_gateway, options);
+            let mut checks = at_level(checks);
+            let excluded = options.is_excluded(&device.mac);
+            if excluded {
+                skipped.extend(checks.drain(..).map(|c| SkippedCheck {
+                    id: c.id,
+                    title: c.title,
+                    reason: "除外された機器です".to_string(),
+                }));
+            }
+            DevicePlan {
+                mac: device.mac.clone(),
+                ip: device.ip.clone(),
+                name: device.name.clone(),
+                device_type: device.device_type,
+                excluded,
+                checks,
+                skipped,
+            }
+        })
+        .collect();
+
+    let (new_devices, _) = applicable(None, false, options);
+    CheckPreview {
+        level,
+        devices: plans,
+        new_devices: at_level(new_devices),
+    }
+}
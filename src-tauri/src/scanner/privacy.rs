@@ -0,0 +1,117 @@
+//! Privacy checks on names devices broadcast
+//!
+//! mDNS and NetBIOS names are visible to everyone on the network. Phones and
+//! computers often default to names like "Taro's iPhone" (advertised over
+//! mDNS as "Taros-iPhone") or "太郎のiPhone", revealing the owner to guests
+//! on shared Wi-Fi.
+//!
+//! Separately, when the data pack ships vendor privacy ratings, each device
+//! gets a privacy note describing how its maker handles data. The note is
+//! independent of the security score: a well-secured device can still send
+//! everything it records to the vendor's cloud.
+
+use super::datapack::{self, PrivacyRating};
+use super::{IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+
+/// Product names that follow the owner's name in default device names
+const PRODUCT_NAMES: &[&str] = &[
+    "iphone", "ipad", "macbook", "imac", "mac-mini", "apple-watch", "watch",
+    "galaxy", "pixel", "android", "xperia", "aquos", "surface", "laptop", "pc",
+];
+
+/// Whether the name contains something that looks like an email address
+fn contains_email(name: &str) -> bool {
+    name.split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .any(|token| {
+            let Some((local, domain)) = token.split_once('@') else {
+                return false;
+            };
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+        })
+}
+
+/// Whether the name looks like "<owner>'s <product>" or "<owner>のproduct"
+fn contains_owner_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+
+    // "Taro's iPhone", "Taro’s MacBook"
+    if lower.contains("'s ") || lower.contains("\u{2019}s ") {
+        return true;
+    }
+
+    // "太郎のiPhone"
+    if let Some((owner, product)) = lower.split_once('の') {
+        if !owner.trim().is_empty() && PRODUCT_NAMES.iter().any(|p| product.contains(p)) {
+            return true;
+        }
+    }
+
+    // mDNS form of the above: "Taros-iPhone", "taros-macbook-pro"
+    PRODUCT_NAMES.iter().any(|product| {
+        lower
+            .find(&format!("s-{}", product))
+            .is_some_and(|pos| pos >= 2 && lower[..pos].chars().all(|c| c.is_alphanumeric() || c == '-'))
+    })
+}
+
+/// Informational finding if a broadcast name appears to identify a person
+pub fn check_broadcast_names(names: &[&str]) -> Option<SecurityIssue> {
+    let personal: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|n| contains_email(n) || contains_owner_name(n))
+        .collect();
+    if personal.is_empty() {
+        return None;
+    }
+
+    Some(content::issue(
+        "personal-hostname",
+        IssueSeverity::Info,
+        &[("names", personal.join(", "))],
+    ))
+}
+
+/// Privacy characteristics of a device's maker, shown alongside (not in) the security score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyNote {
+    pub rating: PrivacyRating,
+    pub cloud_required: bool,
+    pub incidents: Vec<String>,
+    /// One-line summary for reports
+    pub summary: String,
+    pub source: Option<String>,
+}
+
+fn rating_text(rating: PrivacyRating) -> &'static str {
+    match rating {
+        PrivacyRating::Good => "個人データの扱いに配慮されています",
+        PrivacyRating::Acceptable => "個人データの扱いは標準的です",
+        PrivacyRating::Concerning => "個人データの扱いに注意が必要です",
+        PrivacyRating::Poor => "個人データの扱いに懸念があります",
+    }
+}
+
+/// Privacy note for a device from the data pack's vendor ratings
+pub fn privacy_note(vendor: Option<&str>, names: &[&str]) -> Option<PrivacyNote> {
+    let lower_names: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    let entry = datapack::privacy_entry(vendor?, &lower_names)?;
+
+    let mut summary = rating_text(entry.rating).to_string();
+    if entry.cloud_required {
+        summary.push_str("。メーカーのクラウドに接続しないと使えません");
+    }
+    if !entry.incidents.is_empty() {
+        summary.push_str(&format!("。過去にデータ共有の問題が{}件報告されています", entry.incidents.len()));
+    }
+
+    Some(PrivacyNote {
+        rating: entry.rating,
+        cloud_required: entry.cloud_required,
+        incidents: entry.incidents,
+        summary,
+        source: entry.source,
+    })
+}
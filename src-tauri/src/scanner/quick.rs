@@ -0,0 +1,86 @@
+//! Quick refresh
+//!
+//! After changing a setting on a device, users want to know whether the fix
+//! worked without waiting for a full scan. A quick refresh skips discovery
+//! and the sweep: it takes the devices of the last scan, checks which are
+//! still there, and re-probes only the ports that were open. Findings raised
+//! for a port that has since closed are dropped; everything else is carried
+//! over from the last scan.
+
+use super::{
+    arp, emit_progress, port_issues, ports, Device, DiscoverySource, ProgressSink, RunningGuard, ScanError, ScanOptions,
+};
+use std::time::Duration;
+
+/// How long to wait for known devices to answer
+const LIVENESS_WINDOW: Duration = Duration::from_secs(3);
+
+/// Refresh `options.known_devices`. Devices that no longer answer are left out.
+pub async fn refresh(progress: &dyn ProgressSink, options: &ScanOptions) -> Result<Vec<Device>, ScanError> {
+    // Re-probing is active by definition
+    if options.strictly_passive {
+        return Err(ScanError::StrictlyPassive);
+    }
+    let _running = RunningGuard::claim()?;
+
+    emit_progress(progress, "progress.known-devices", 10);
+    let targets: Vec<(String, String)> = options
+        .known_devices
+        .iter()
+        .map(|d| (d.ip.clone(), d.mac.clone()))
+        .collect();
+    let alive = arp::reverify(&targets, LIVENESS_WINDOW).await;
+
+    emit_progress(progress, "progress.known-ports", 40);
+    let mut tasks = Vec::new();
+    for (ip, mac) in alive {
+        let Some(known) = options.known_devices.iter().find(|k| k.mac.eq_ignore_ascii_case(&mac)) else {
+            continue;
+        };
+        let mut device = Device {
+            id: uuid::Uuid::new_v4().to_string(),
+            ip,
+            last_seen: chrono::Utc::now(),
+            discovery_sources: vec![DiscoverySource::Reverify],
+            ..known.clone()
+        };
+        tasks.push(tokio::spawn(async move {
+            recheck_ports(&mut device).await;
+            device
+        }));
+    }
+
+    let mut devices = Vec::new();
+    for task in tasks {
+        if let Ok(device) = task.await {
+            devices.push(device);
+        }
+    }
+
+    emit_progress(progress, "progress.scoring", 90);
+    for device in &mut devices {
+        options.policy.apply(device);
+    }
+
+    emit_progress(progress, "progress.done", 100);
+    Ok(devices)
+}
+
+/// Keep the previously open ports that still accept connections and drop
+/// the findings of those that closed
+async fn recheck_ports(device: &mut Device) {
+    if device.open_ports.is_empty() {
+        return;
+    }
+    let previous: Vec<u16> = device.open_ports.iter().map(|p| p.number).collect();
+    let still_open = ports::probe_open(&device.ip, &previous).await;
+
+    let closed_issues: Vec<String> = previous
+        .iter()
+        .filter(|port| !still_open.contains(port))
+        .flat_map(|&port| port_issues(port))
+        .map(|issue| issue.id)
+        .collect();
+    device.open_ports.retain(|p| still_open.contains(&p.number));
+    device.issues.retain(|issue| !closed_issues.contains(&issue.id));
+}
@@ -0,0 +1,376 @@
+//! Router API integrations (opt-in)
+//!
+//! ARP and multicast discovery only see devices that happen to answer during
+//! the scan. The router knows every DHCP client, so with user-supplied
+//! credentials we fetch its client list and merge authoritative hostnames and
+//! lease information into the results. Supported APIs:
+//!
+//! - Fritz!Box: TR-064 `X_AVM-DE_GetHostListPath` (HTTP digest auth)
+//! - ASUSWRT: `login.cgi` + `appGet.cgi?hook=get_clientlist()`
+//! - OpenWrt: ubus JSON-RPC `luci-rpc getDHCPLeases`
+//!
+//! The password is read from the OS keychain (`SecretKind::RouterPassword`).
+
+use crate::secrets::{self, SecretKind, SecretsError};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Per-request timeout for router APIs
+const ROUTER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// TR-064 port on Fritz!Box routers
+const TR064_PORT: u16 = 49000;
+
+/// Router integration errors
+#[derive(Error, Debug)]
+pub enum RouterError {
+    #[error("Router integration is not configured")]
+    NotConfigured,
+
+    #[error("Router rejected the credentials")]
+    AuthenticationFailed,
+
+    #[error("Router request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Unexpected router response: {0}")]
+    Protocol(String),
+
+    #[error(transparent)]
+    Secrets(#[from] SecretsError),
+}
+
+/// Router API flavour
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RouterKind {
+    FritzBox,
+    Asuswrt,
+    Openwrt,
+}
+
+/// Router integration settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouterSettings {
+    /// `None` disables the integration
+    pub kind: Option<RouterKind>,
+    /// Host name or URL of the router; the default gateway when unset
+    pub address: Option<String>,
+    pub username: Option<String>,
+}
+
+/// A client known to the router
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DhcpLease {
+    pub mac: String,
+    pub ip: String,
+    pub hostname: Option<String>,
+    /// Whether the router currently sees the client (if the API reports it)
+    pub active: Option<bool>,
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct Credentials {
+    base_url: String,
+    host: String,
+    username: String,
+    password: String,
+}
+
+fn credentials(settings: &RouterSettings) -> Result<Credentials, RouterError> {
+    let address = match settings.address.as_deref().filter(|a| !a.is_empty()) {
+        Some(address) => address.trim_end_matches('/').to_string(),
+        None => super::arp::get_default_gateway()
+            .ok_or(RouterError::NotConfigured)?
+            .to_string(),
+    };
+    let base_url = if address.contains("://") {
+        address
+    } else {
+        format!("http://{}", address)
+    };
+    let host = reqwest::Url::parse(&base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .ok_or(RouterError::NotConfigured)?;
+
+    Ok(Credentials {
+        base_url,
+        host,
+        username: settings.username.clone().unwrap_or_default(),
+        password: secrets::get_secret(SecretKind::RouterPassword)?.unwrap_or_default(),
+    })
+}
+
+fn client() -> Result<reqwest::Client, RouterError> {
+    Ok(reqwest::Client::builder().timeout(ROUTER_TIMEOUT).build()?)
+}
+
+/// Fetch the router's client list, or `None` when the integration is disabled
+pub async fn fetch_leases(settings: &RouterSettings) -> Result<Option<Vec<DhcpLease>>, RouterError> {
+    let Some(kind) = settings.kind else {
+        return Ok(None);
+    };
+    let credentials = credentials(settings)?;
+
+    let leases = match kind {
+        RouterKind::FritzBox => fetch_fritzbox(&credentials).await?,
+        RouterKind::Asuswrt => fetch_asuswrt(&credentials).await?,
+        RouterKind::Openwrt => fetch_openwrt(&credentials).await?,
+    };
+    Ok(Some(leases))
+}
+
+async fn fetch_fritzbox(credentials: &Credentials) -> Result<Vec<DhcpLease>, RouterError> {
+    let client = client()?;
+    let base = format!("http://{}:{}", credentials.host, TR064_PORT);
+    let path = "/upnp/control/hosts";
+    let body = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+        <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+        s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+        <u:X_AVM-DE_GetHostListPath xmlns:u=\"urn:dslforum-org:service:Hosts:1\"/>\
+        </s:Body></s:Envelope>";
+
+    let request = |authorization: Option<String>| {
+        let mut builder = client
+            .post(format!("{}{}", base, path))
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header("SOAPAction", "urn:dslforum-org:service:Hosts:1#X_AVM-DE_GetHostListPath")
+            .body(body);
+        if let Some(authorization) = authorization {
+            builder = builder.header("Authorization", authorization);
+        }
+        builder.send()
+    };
+
+    let mut response = request(None).await?;
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(RouterError::AuthenticationFailed)?;
+        let mut prompt = digest_auth::parse(challenge)
+            .map_err(|e| RouterError::Protocol(e.to_string()))?;
+        let context = digest_auth::AuthContext::new_post(
+            credentials.username.as_str(),
+            credentials.password.as_str(),
+            path,
+            Some(body.as_bytes()),
+        );
+        let answer = prompt
+            .respond(&context)
+            .map_err(|e| RouterError::Protocol(e.to_string()))?;
+        response = request(Some(answer.to_header_string())).await?;
+    }
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(RouterError::AuthenticationFailed);
+    }
+
+    let envelope = response.text().await?;
+    let list_path = xml_element(&envelope, "NewX_AVM-DE_HostListPath")
+        .ok_or_else(|| RouterError::Protocol("host list path missing".to_string()))?;
+
+    let list = client.get(format!("{}{}", base, list_path)).send().await?.text().await?;
+    Ok(parse_fritzbox_hosts(&list))
+}
+
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    parse_xml_items(xml, name.as_bytes(), &[name])
+        .into_iter()
+        .next()
+        .and_then(|mut item| item.remove(name))
+}
+
+/// Collect the text of `fields` within each `item` element
+pub(super) fn parse_xml_items(xml: &str, item: &[u8], fields: &[&str]) -> Vec<HashMap<String, String>> {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    let mut reader = Reader::from_str(xml);
+    let mut items = Vec::new();
+    let mut current: Option<HashMap<String, String>> = None;
+    let mut field: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => {
+                let name = e.local_name();
+                if name.as_ref() == item {
+                    current = Some(HashMap::new());
+                }
+                let name = String::from_utf8_lossy(name.as_ref()).to_string();
+                field = fields.contains(&name.as_str()).then_some(name);
+            }
+            Ok(Event::Text(ref e)) => {
+                if let (Some(map), Some(name)) = (current.as_mut(), field.as_ref()) {
+                    if let Ok(text) = e.unescape() {
+                        map.insert(name.clone(), text.trim().to_string());
+                    }
+                }
+            }
+            Ok(Event::End(ref e)) => {
+                field = None;
+                if e.local_name().as_ref() == item {
+                    items.extend(current.take());
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    items
+}
+
+fn parse_fritzbox_hosts(xml: &str) -> Vec<DhcpLease> {
+    parse_xml_items(xml, b"Item", &["IPAddress", "MACAddress", "Active", "HostName"])
+        .into_iter()
+        .filter_map(|item| {
+            Some(DhcpLease {
+                mac: item.get("MACAddress").filter(|m| !m.is_empty())?.to_lowercase(),
+                ip: item.get("IPAddress").cloned().unwrap_or_default(),
+                hostname: item.get("HostName").filter(|h| !h.is_empty()).cloned(),
+                active: item.get("Active").map(|a| a == "1"),
+                expires: None,
+            })
+        })
+        .collect()
+}
+
+async fn fetch_asuswrt(credentials: &Credentials) -> Result<Vec<DhcpLease>, RouterError> {
+    let client = client()?;
+    let authorization = base64::engine::general_purpose::STANDARD
+        .encode(format!("{}:{}", credentials.username, credentials.password));
+
+    // The web UI rejects requests that do not look like they come from its own apps
+    let login = client
+        .post(format!("{}/login.cgi", credentials.base_url))
+        .header("User-Agent", "asusrouter-Android-DUTUtil-1.0.0.245")
+        .form(&[("login_authorization", authorization)])
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    let token = serde_json::from_str::<serde_json::Value>(&login)
+        .ok()
+        .and_then(|v| v["asus_token"].as_str().map(str::to_string))
+        .ok_or(RouterError::AuthenticationFailed)?;
+
+    let clients = client
+        .get(format!("{}/appGet.cgi?hook=get_clientlist()", credentials.base_url))
+        .header("User-Agent", "asusrouter-Android-DUTUtil-1.0.0.245")
+        .header("Cookie", format!("asus_token={}", token))
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    let value: serde_json::Value =
+        serde_json::from_str(&clients).map_err(|e| RouterError::Protocol(e.to_string()))?;
+    let list = value["get_clientlist"]
+        .as_object()
+        .ok_or_else(|| RouterError::Protocol("client list missing".to_string()))?;
+
+    Ok(list
+        .iter()
+        .filter(|(key, _)| key.contains(':'))
+        .map(|(mac, client)| {
+            let name = client["nickName"]
+                .as_str()
+                .filter(|n| !n.is_empty())
+                .or_else(|| client["name"].as_str().filter(|n| !n.is_empty()));
+            DhcpLease {
+                mac: mac.to_lowercase(),
+                ip: client["ip"].as_str().unwrap_or_default().to_string(),
+                hostname: name.map(str::to_string),
+                active: client["isOnline"].as_str().map(|o| o == "1"),
+                expires: None,
+            }
+        })
+        .collect())
+}
+
+/// Session ID used before logging in
+const UBUS_ANONYMOUS_SESSION: &str = "00000000000000000000000000000000";
+
+async fn ubus_call(
+    client: &reqwest::Client,
+    url: &str,
+    session: &str,
+    object: &str,
+    method: &str,
+    args: serde_json::Value,
+) -> Result<serde_json::Value, RouterError> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "call",
+        "params": [session, object, method, args],
+    });
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(request.to_string())
+        .send()
+        .await?
+        .text()
+        .await?;
+    let value: serde_json::Value =
+        serde_json::from_str(&response).map_err(|e| RouterError::Protocol(e.to_string()))?;
+
+    // result is [status, data]; status 6 means permission denied
+    match value["result"][0].as_u64() {
+        Some(0) => Ok(value["result"][1].clone()),
+        Some(6) => Err(RouterError::AuthenticationFailed),
+        _ => Err(RouterError::Protocol(format!("ubus {}.{} failed", object, method))),
+    }
+}
+
+async fn fetch_openwrt(credentials: &Credentials) -> Result<Vec<DhcpLease>, RouterError> {
+    let client = client()?;
+    let url = format!("{}/ubus", credentials.base_url);
+
+    let login = ubus_call(
+        &client,
+        &url,
+        UBUS_ANONYMOUS_SESSION,
+        "session",
+        "login",
+        serde_json::json!({ "username": credentials.username, "password": credentials.password }),
+    )
+    .await?;
+    let session = login["ubus_rpc_session"]
+        .as_str()
+        .ok_or(RouterError::AuthenticationFailed)?;
+
+    let leases = ubus_call(&client, &url, session, "luci-rpc", "getDHCPLeases", serde_json::json!({})).await?;
+    let now = chrono::Utc::now();
+
+    Ok(leases["dhcp_leases"]
+        .as_array()
+        .map(|leases| {
+            leases
+                .iter()
+                .filter_map(|lease| {
+                    Some(DhcpLease {
+                        mac: lease["macaddr"].as_str()?.to_lowercase(),
+                        ip: lease["ipaddr"].as_str().unwrap_or_default().to_string(),
+                        hostname: lease["hostname"].as_str().map(str::to_string),
+                        active: None,
+                        // Seconds remaining on the lease
+                        expires: lease["expires"]
+                            .as_i64()
+                            .filter(|s| *s > 0)
+                            .map(|s| now + chrono::Duration::seconds(s)),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
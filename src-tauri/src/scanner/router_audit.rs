@@ -0,0 +1,268 @@
+//! Security audit of the default gateway
+//!
+//! The router faces the internet for every other device, so whatever brand it
+//! is, it gets a checklist of its own: whether its admin UI is served over
+//! plain HTTP or reachable from the internet, whether WPS and UPnP IGD are on,
+//! and whether the web server it answers with dates from a firmware that no
+//! longer gets updates. Open firmware adds its own checklist on top
+//! (see `firmware`).
+
+use super::exposure::{self, ExposureSettings};
+use super::firmware::{CheckStatus, HardeningCheck};
+use super::portmap::{MappingProtocol, PortMappingReport};
+use super::{topology, Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::{content, knowledge};
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+/// Ports routers serve remote administration on
+const ADMIN_PORTS: &[u16] = &[80, 443, 8080, 8443];
+
+/// A server built this many years ago has not seen a firmware update since
+const ANCIENT_YEARS: i32 = 10;
+
+/// Embedded web servers whose old releases have published vulnerabilities:
+/// (product in the `Server` header, first fixed release, advisory). Without a
+/// fixed release every version is affected, as the server is no longer maintained.
+const OUTDATED_SERVERS: &[(&str, &[u32], Option<&str>)] = &[
+    ("RomPager", &[4, 34], Some("CVE-2014-9222")),
+    ("Allegro-Software-RomPager", &[4, 34], Some("CVE-2014-9222")),
+    ("mini_httpd", &[1, 30], Some("CVE-2018-18778")),
+    ("Boa", &[], None),
+    ("GoAhead-Webs", &[], None),
+];
+
+/// The gateway's checklist
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouterAudit {
+    /// `Server` header of the admin UI
+    pub server: Option<String>,
+    /// Published vulnerability of that server release, e.g. "CVE-2014-9222"
+    pub advisory: Option<String>,
+    pub checks: Vec<HardeningCheck>,
+}
+
+/// Run the checklist on the gateway. `wps` is whether its Wi-Fi advertises
+/// WPS, `None` when its access points were not in the OS scan results.
+pub async fn audit(
+    gateway: &Device,
+    port_mapping: &PortMappingReport,
+    wps: Option<bool>,
+    exposure: &ExposureSettings,
+) -> RouterAudit {
+    let server = gateway.web_interfaces.iter().find_map(|w| w.server.clone());
+    let outdated = server.as_deref().and_then(|s| outdated_server(s, chrono::Utc::now().year()));
+    let checks = vec![
+        admin_http(gateway),
+        wan_admin(port_mapping.external_address.as_deref(), exposure).await,
+        wps_check(wps),
+        upnp_igd(port_mapping),
+        firmware_age(server.as_deref(), outdated.as_ref().map(|(reason, _)| reason.as_str())),
+    ];
+    RouterAudit {
+        server,
+        advisory: outdated.and_then(|(_, advisory)| advisory).map(str::to_string),
+        checks,
+    }
+}
+
+fn check(id: &str, status: CheckStatus, detail: Option<String>) -> HardeningCheck {
+    HardeningCheck {
+        id: id.to_string(),
+        title: content::checklist_title(id, content::language()),
+        status,
+        detail,
+    }
+}
+
+/// The admin UI redirects plain HTTP to HTTPS and takes no password over HTTP
+fn admin_http(gateway: &Device) -> HardeningCheck {
+    let interfaces = &gateway.web_interfaces;
+    if interfaces.is_empty() {
+        return check(
+            "router-admin-http",
+            CheckStatus::Unknown,
+            Some("管理画面が見つかりませんでした".to_string()),
+        );
+    }
+    let plaintext = interfaces.iter().find(|w| w.plaintext_login).or_else(|| {
+        let https = interfaces.iter().any(|w| w.url.starts_with("https://"));
+        interfaces.iter().find(|w| !https && w.url.starts_with("http://"))
+    });
+    match plaintext {
+        Some(interface) => check(
+            "router-admin-http",
+            CheckStatus::Fail,
+            Some(format!("{} で管理画面が暗号化されずに表示されます", interface.url)),
+        ),
+        None => check("router-admin-http", CheckStatus::Pass, None),
+    }
+}
+
+/// Remote administration: the admin ports answer on the WAN address
+async fn wan_admin(wan_address: Option<&str>, exposure: &ExposureSettings) -> HardeningCheck {
+    let Some(wan) = wan_address else {
+        return check(
+            "router-wan-admin",
+            CheckStatus::Unknown,
+            Some("ルーターのインターネット側アドレスを取得できませんでした".to_string()),
+        );
+    };
+    let shared = wan.parse::<Ipv4Addr>().is_ok_and(|ip| ip.is_private() || topology::is_cgnat(ip));
+    if shared {
+        // Behind another router or the provider's NAT, the probe cannot reach this router
+        return check(
+            "router-wan-admin",
+            CheckStatus::Unknown,
+            Some(format!("インターネット側アドレス {} がプライベートアドレスのため確認できません", wan)),
+        );
+    }
+    if exposure.endpoint().is_none() {
+        return check(
+            "router-wan-admin",
+            CheckStatus::Unknown,
+            Some("外部からの到達性確認を有効にすると確認できます".to_string()),
+        );
+    }
+
+    let mut reachable = Vec::new();
+    let mut answered = false;
+    for &port in ADMIN_PORTS {
+        match exposure::is_reachable(exposure, wan, port).await {
+            Some(true) => reachable.push(format!("{}:{}", wan, port)),
+            Some(false) => answered = true,
+            None => {}
+        }
+    }
+    match (reachable.is_empty(), answered) {
+        (false, _) => check(
+            "router-wan-admin",
+            CheckStatus::Fail,
+            Some(format!("{} にインターネット側から接続できます", reachable.join("、"))),
+        ),
+        (true, true) => check("router-wan-admin", CheckStatus::Pass, None),
+        (true, false) => check(
+            "router-wan-admin",
+            CheckStatus::Unknown,
+            Some("外部からの到達性確認に失敗しました".to_string()),
+        ),
+    }
+}
+
+fn wps_check(wps: Option<bool>) -> HardeningCheck {
+    match wps {
+        Some(true) => check(
+            "router-wps",
+            CheckStatus::Fail,
+            Some("WPSのPIN方式は総当たりで突破されるおそれがあります".to_string()),
+        ),
+        Some(false) => check("router-wps", CheckStatus::Pass, None),
+        None => check(
+            "router-wps",
+            CheckStatus::Unknown,
+            Some("周辺のWi-Fiの一覧にこのルーターのWi-Fiが見つかりませんでした".to_string()),
+        ),
+    }
+}
+
+fn upnp_igd(port_mapping: &PortMappingReport) -> HardeningCheck {
+    if port_mapping.enabled_protocols.contains(&MappingProtocol::UpnpIgd) {
+        let detail = format!("公開中のポート: {} 件", port_mapping.mappings.len());
+        check("router-upnp-igd", CheckStatus::Fail, Some(detail))
+    } else {
+        check("router-upnp-igd", CheckStatus::Pass, None)
+    }
+}
+
+/// The admin UI's web server is neither a release with published
+/// vulnerabilities nor built too long ago
+fn firmware_age(server: Option<&str>, outdated: Option<&str>) -> HardeningCheck {
+    let Some(server) = server else {
+        return check(
+            "router-outdated-server",
+            CheckStatus::Unknown,
+            Some("管理画面がWebサーバーの名前を返しませんでした".to_string()),
+        );
+    };
+    match outdated {
+        Some(reason) => check(
+            "router-outdated-server",
+            CheckStatus::Fail,
+            Some(format!("{}（{}）", server, reason)),
+        ),
+        None => check("router-outdated-server", CheckStatus::Pass, Some(server.to_string())),
+    }
+}
+
+/// Why the `Server` header points to an outdated firmware, with the advisory
+/// of the affected release
+pub(super) fn outdated_server(server: &str, current_year: i32) -> Option<(String, Option<&'static str>)> {
+    for token in server.split_whitespace() {
+        let (product, version) = token.split_once('/').unwrap_or((token, ""));
+        let Some((_, fixed, advisory)) = OUTDATED_SERVERS.iter().find(|(name, ..)| name.eq_ignore_ascii_case(product))
+        else {
+            continue;
+        };
+        let unmaintained = fixed.is_empty();
+        let affected = unmaintained || parse_version(version).is_some_and(|v| v.as_slice() < *fixed);
+        if affected {
+            let reason = match advisory {
+                _ if unmaintained => "開発が終了したWebサーバーです".to_string(),
+                Some(advisory) => format!("{} の影響を受けるバージョンです", advisory),
+                None => "既知の脆弱性があるバージョンです".to_string(),
+            };
+            return Some((reason, *advisory));
+        }
+    }
+
+    // mini_httpd and thttpd append their build date, e.g. "19dec2003"
+    let built = server.split_whitespace().find_map(build_year)?;
+    (current_year - built >= ANCIENT_YEARS).then(|| (format!("{}年に作られたWebサーバーです", built), None))
+}
+
+/// "1.19" → [1, 19]; suffixes such as "rc21" are ignored
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let parts: Vec<u32> = version
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect();
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// Year of a build date token such as "19dec2003"
+fn build_year(token: &str) -> Option<i32> {
+    let split = token.len().checked_sub(4)?;
+    let (day_month, year) = (token.get(..split)?, token.get(split..)?);
+    let month: String = day_month.chars().skip_while(char::is_ascii_digit).collect();
+    let is_month = month.len() == 3 && month.chars().all(|c| c.is_ascii_alphabetic());
+    if !is_month || day_month.len() == month.len() {
+        return None;
+    }
+    year.parse().ok().filter(|y| (1990..2100).contains(y))
+}
+
+/// Findings for the failed items not already reported elsewhere: plain HTTP,
+/// WPS and UPnP have their own findings
+pub fn to_issues(audit: &RouterAudit) -> Vec<SecurityIssue> {
+    audit
+        .checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .filter_map(|c| {
+            let detail = c.detail.clone().unwrap_or_default();
+            match c.id.as_str() {
+                "router-wan-admin" => Some(content::issue(c.id.as_str(), IssueSeverity::High, &[("detail", detail)])),
+                "router-outdated-server" => {
+                    let mut issue = content::issue(c.id.as_str(), IssueSeverity::Medium, &[("detail", detail)]);
+                    issue.references.extend(audit.advisory.as_deref().map(knowledge::cve_reference));
+                    Some(issue)
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
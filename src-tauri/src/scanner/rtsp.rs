@@ -0,0 +1,137 @@
+//! Camera stream exposure (Level 3)
+//!
+//! Home IP cameras often serve their RTSP stream to anyone who knows the
+//! path. Each well-known stream path is asked for its session description
+//! with `DESCRIBE`: a `200` carrying an SDP body means the stream plays
+//! without a password. Only the description is read; no media is requested.
+
+use super::{net, Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// RTSP ports checked
+const RTSP_PORTS: &[u16] = &[554, 8554];
+
+/// Stream paths of common camera firmwares (Hikvision, Dahua, Foscam, TP-Link
+/// Tapo, generic ONVIF)
+const STREAM_PATHS: &[&str] = &[
+    "/",
+    "/live",
+    "/live.sdp",
+    "/stream1",
+    "/h264",
+    "/11",
+    "/Streaming/Channels/101",
+    "/cam/realmonitor?channel=1&subtype=0",
+    "/videoMain",
+    "/live/ch00_0",
+    "/media/video1",
+];
+
+/// Timeout for the connection and the answer
+const RTSP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Bytes of an answer read; an SDP description is far smaller
+const MAX_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// How a stream path answered `DESCRIBE`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum StreamAccess {
+    /// The description was sent without credentials
+    Open,
+    /// Credentials were asked for
+    Protected,
+    /// No stream at this path, or no usable answer
+    Missing,
+}
+
+/// Ask every RTSP port of the device for its streams and raise the finding
+/// for the first one that plays without a password
+pub async fn check_device(device: &mut Device) {
+    let ports: Vec<u16> = device
+        .open_ports
+        .iter()
+        .filter(|p| p.protocol == "tcp" && RTSP_PORTS.contains(&p.number))
+        .map(|p| p.number)
+        .collect();
+
+    for port in ports {
+        for path in STREAM_PATHS {
+            let url = format!("rtsp://{}:{}{}", device.ip, port, path);
+            match describe(&device.ip, port, &url).await {
+                StreamAccess::Open => {
+                    device.issues.push(to_issue(&url));
+                    return;
+                }
+                // A server that asks for credentials asks on every path
+                StreamAccess::Protected => break,
+                StreamAccess::Missing => {}
+            }
+        }
+    }
+}
+
+async fn describe(ip: &str, port: u16, url: &str) -> StreamAccess {
+    let Ok(addr) = format!("{}:{}", ip, port).parse() else {
+        return StreamAccess::Missing;
+    };
+    let Ok(mut stream) = net::tcp_connect_async(addr, RTSP_TIMEOUT).await else {
+        return StreamAccess::Missing;
+    };
+
+    let request = format!("DESCRIBE {} RTSP/1.0\r\nCSeq: 2\r\nAccept: application/sdp\r\n\r\n", url);
+    if stream.write_all(request.as_bytes()).await.is_err() {
+        return StreamAccess::Missing;
+    }
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + RTSP_TIMEOUT;
+    while response.len() < MAX_RESPONSE_BYTES {
+        match tokio::time::timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => response.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+        // The description is complete once the body is in
+        if is_complete(&response) {
+            break;
+        }
+    }
+    classify(&String::from_utf8_lossy(&response))
+}
+
+/// Whether the headers and the `Content-Length` bytes of body have arrived
+fn is_complete(response: &[u8]) -> bool {
+    let Some(end) = response.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return false;
+    };
+    let headers = String::from_utf8_lossy(&response[..end]).to_lowercase();
+    let length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    response.len() >= end + 4 + length
+}
+
+/// Access a `DESCRIBE` answer grants
+pub(super) fn classify(response: &str) -> StreamAccess {
+    let status = response
+        .lines()
+        .next()
+        .filter(|line| line.starts_with("RTSP/"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok());
+    match status {
+        // An empty or non-SDP answer is not a stream
+        Some(200) if response.contains("\nm=video") || response.contains("\nm=audio") => StreamAccess::Open,
+        Some(401 | 403) => StreamAccess::Protected,
+        _ => StreamAccess::Missing,
+    }
+}
+
+/// Finding for a stream that plays without a password
+pub(super) fn to_issue(url: &str) -> SecurityIssue {
+    content::issue("rtsp-stream-open", IssueSeverity::Critical, &[("url", url.to_string())])
+}
@@ -0,0 +1,277 @@
+//! Security scoring policy
+//!
+//! Converts a device's issues and open ports into a 0-100 score. Issue
+//! severities are first recalibrated for the deployment context, so the same
+//! finding weighs more in a business network than at home. Recalibration
+//! always starts from the severity an issue was raised with, so scoring a
+//! device again under another context does not compound.
+
+use super::{fingerprint, Device, DeviceType, IssueSeverity, ScanLevel, SecurityIssue, SecurityLevel};
+use crate::settings::locale::Language;
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+
+/// Environment the scanned network is used in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentContext {
+    #[default]
+    Home,
+    HomeOffice,
+    SmallBusiness,
+}
+
+impl DeploymentContext {
+    /// Label used in reports
+    pub fn label(&self, language: Language) -> String {
+        let key = match self {
+            Self::Home => "context.home",
+            Self::HomeOffice => "context.home-office",
+            Self::SmallBusiness => "context.small-business",
+        };
+        content::label(key, language)
+    }
+}
+
+/// Severity overrides: (issue ID, context, severity)
+const CONTEXT_SEVERITIES: &[(&str, DeploymentContext, IssueSeverity)] = &[
+    ("telnet-open", DeploymentContext::SmallBusiness, IssueSeverity::Critical),
+    ("upnp-enabled", DeploymentContext::HomeOffice, IssueSeverity::High),
+    ("upnp-enabled", DeploymentContext::SmallBusiness, IssueSeverity::High),
+    ("IOTDOC-002", DeploymentContext::Home, IssueSeverity::Low),
+    ("IOTDOC-002", DeploymentContext::SmallBusiness, IssueSeverity::High),
+    ("smbv1-enabled", DeploymentContext::HomeOffice, IssueSeverity::High),
+    ("smbv1-enabled", DeploymentContext::SmallBusiness, IssueSeverity::Critical),
+];
+
+/// Scan level a device type needs before it can be rated Safe. Routers,
+/// cameras and NAS are the usual targets and keep their weaknesses behind
+/// open ports, so a discovery-only scan says little about them; a smart plug
+/// has little more to check.
+const TYPE_BASELINES: &[(DeviceType, ScanLevel)] = &[
+    (DeviceType::Router, ScanLevel::Level2),
+    (DeviceType::Camera, ScanLevel::Level2),
+    (DeviceType::Nas, ScanLevel::Level2),
+    (DeviceType::Printer, ScanLevel::Level2),
+    (DeviceType::Unknown, ScanLevel::Level2),
+];
+
+/// Scan level a device type needs before it can be rated Safe
+pub fn required_level(device_type: DeviceType) -> ScanLevel {
+    TYPE_BASELINES
+        .iter()
+        .find(|(t, _)| *t == device_type)
+        .map_or(ScanLevel::Level1, |(_, level)| *level)
+}
+
+/// User-configured severity for an issue, optionally limited to one context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeverityOverride {
+    pub issue_id: String,
+    #[serde(default)]
+    pub context: Option<DeploymentContext>,
+    pub severity: IssueSeverity,
+}
+
+/// What a deduction was made for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeductionSource {
+    Issue,
+    InsecurePort,
+    /// A TCP/IP stack from before window scaling and SACK; such firmware is
+    /// rarely updated and its parsers predate most hardening
+    LegacyStack,
+}
+
+/// A single score deduction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deduction {
+    pub source: DeductionSource,
+    /// Issue ID or port number
+    pub reference: String,
+    pub label: String,
+    pub severity: Option<IssueSeverity>,
+    pub points: i32,
+}
+
+/// Breakdown of how a device's score was computed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreExplanation {
+    pub context: DeploymentContext,
+    pub base_score: i32,
+    pub deductions: Vec<Deduction>,
+    pub total_deducted: i32,
+    pub final_score: u8,
+    pub security_level: SecurityLevel,
+    /// Scan level the device type needs before it can be rated Safe
+    pub required_level: ScanLevel,
+}
+
+/// Starting score before deductions
+const BASE_SCORE: i32 = 100;
+
+/// Scoring policy applied at the end of a scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringPolicy {
+    pub context: DeploymentContext,
+    /// User overrides; take precedence over the built-in context table
+    pub overrides: Vec<SeverityOverride>,
+}
+
+impl ScoringPolicy {
+    pub fn new(context: DeploymentContext) -> Self {
+        Self {
+            context,
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn with_overrides(mut self, overrides: Vec<SeverityOverride>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Severity of an issue in the deployment context, derived from the
+    /// severity it was raised with
+    pub fn severity_for(&self, issue: &SecurityIssue) -> IssueSeverity {
+        let user = self.overrides.iter().find(|o| {
+            o.issue_id == issue.id && o.context.map_or(true, |c| c == self.context)
+        });
+
+        if let Some(o) = user {
+            o.severity
+        } else if let Some((_, _, severity)) = CONTEXT_SEVERITIES
+            .iter()
+            .find(|(id, ctx, _)| *id == issue.id && *ctx == self.context)
+        {
+            *severity
+        } else {
+            issue.base_severity.unwrap_or(issue.severity)
+        }
+    }
+
+    /// Adjust issue severities for the deployment context
+    pub fn recalibrate(&self, device: &mut Device) {
+        for issue in &mut device.issues {
+            let severity = self.severity_for(issue);
+            issue.base_severity.get_or_insert(issue.severity);
+            issue.severity = severity;
+        }
+    }
+
+    /// Points deducted for an issue of the given severity
+    fn deduction(&self, severity: IssueSeverity) -> i32 {
+        match severity {
+            IssueSeverity::Critical => 40,
+            IssueSeverity::High => 25,
+            IssueSeverity::Medium => 15,
+            IssueSeverity::Low => 5,
+            IssueSeverity::Info => 0,
+        }
+    }
+
+    /// Points deducted per open port without encryption
+    fn insecure_port_deduction(&self) -> i32 {
+        match self.context {
+            DeploymentContext::Home => 5,
+            DeploymentContext::HomeOffice | DeploymentContext::SmallBusiness => 8,
+        }
+    }
+
+    /// Points deducted for a legacy embedded TCP/IP stack
+    fn legacy_stack_deduction(&self) -> i32 {
+        match self.context {
+            DeploymentContext::Home => 10,
+            DeploymentContext::HomeOffice | DeploymentContext::SmallBusiness => 15,
+        }
+    }
+
+    /// Compute the score breakdown for a device without modifying it
+    pub fn explain(&self, device: &Device) -> ScoreExplanation {
+        let language = content::language();
+        let mut deductions = Vec::new();
+
+        for issue in &device.issues {
+            let severity = self.severity_for(issue);
+            deductions.push(Deduction {
+                source: DeductionSource::Issue,
+                reference: issue.id.clone(),
+                label: issue.title.clone(),
+                severity: Some(severity),
+                points: self.deduction(severity),
+            });
+        }
+
+        // Deduct for open risky ports
+        for port in &device.open_ports {
+            if !port.is_secure {
+                let service = port.service.clone().unwrap_or_else(|| content::label("score.unknown-service", language));
+                deductions.push(Deduction {
+                    source: DeductionSource::InsecurePort,
+                    reference: port.number.to_string(),
+                    label: content::label("score.insecure-port", language)
+                        .replace("{port}", &port.number.to_string())
+                        .replace("{service}", &service),
+                    severity: None,
+                    points: self.insecure_port_deduction(),
+                });
+            }
+        }
+
+        if let Some(os) = device.os_guess.as_deref().filter(|os| *os == fingerprint::OS_LEGACY_EMBEDDED) {
+            deductions.push(Deduction {
+                source: DeductionSource::LegacyStack,
+                reference: os.to_string(),
+                label: content::label("score.legacy-stack", language),
+                severity: None,
+                points: self.legacy_stack_deduction(),
+            });
+        }
+
+        let total_deducted: i32 = deductions.iter().map(|d| d.points).sum();
+        let final_score = (BASE_SCORE - total_deducted).clamp(0, 100) as u8;
+
+        // A clean result only counts once the device type's baseline checks ran,
+        // the scan's time limit did not cut any of them and no lookup is pending
+        let required_level = required_level(device.device_type);
+        let security_level = match level_for_score(final_score) {
+            SecurityLevel::Safe
+                if device.scan_level.is_some_and(|l| l < required_level)
+                    || !device.reduced_coverage.is_empty()
+                    || device.enrichment_pending =>
+            {
+                SecurityLevel::InsufficientData
+            }
+            level => level,
+        };
+
+        ScoreExplanation {
+            context: self.context,
+            base_score: BASE_SCORE,
+            deductions,
+            total_deducted,
+            final_score,
+            security_level,
+            required_level,
+        }
+    }
+
+    /// Recalibrate severities, then compute score and security level
+    pub fn apply(&self, device: &mut Device) {
+        self.recalibrate(device);
+
+        let explanation = self.explain(device);
+        device.security_score = explanation.final_score;
+        device.security_level = explanation.security_level;
+    }
+}
+
+fn level_for_score(score: u8) -> SecurityLevel {
+    match score {
+        80..=100 => SecurityLevel::Safe,
+        50..=79 => SecurityLevel::Warning,
+        _ => SecurityLevel::Danger,
+    }
+}
@@ -0,0 +1,557 @@
+//! Self-test of the checks
+//!
+//! An "all clear" is only worth something if the checks behind it work. Each
+//! check's decision logic is run against bundled synthetic responses (a
+//! router's web page, an `iw` scan dump, an FTP reply, ...) and must raise
+//! its finding, with text in every language. Checks that need a tool or
+//! privilege this machine lacks are reported as unavailable.
+
+use super::portmap::{MappingProtocol, PortMapping, PortMappingReport};
+use super::topology::TopologyReport;
+use super::{
+    ble, conflict, credentials, dns, exposure, firmware, http, impersonation, login_page, port_issues, privacy, router_audit,
+    rtsp, shares, snmp, stealth, topology, wifi, Device, DeviceType, IssueSeverity, SecurityIssue,
+};
+use crate::settings::locale::Language;
+use crate::vulndb::{catalog, content};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+/// `iw dev wlan0 scan dump` of an open, a WEP, a WPS-enabled and a WPA (TKIP) network
+const IW_DUMP: &str = "\
+BSS 12:34:56:78:9a:bd(on wlan0) -- associated
+\tcapability: ESS ShortSlotTime (0x0401)
+\tSSID: Open-Net
+BSS 12:34:56:78:9a:be(on wlan0)
+\tcapability: ESS Privacy ShortSlotTime (0x0411)
+\tSSID: Old-Net
+BSS 12:34:56:78:9a:bf(on wlan0)
+\tcapability: ESS Privacy ShortSlotTime (0x0411)
+\tSSID: Wps-Net
+\tRSN:\t * Version: 1
+\t\t * Pairwise ciphers: CCMP
+\t\t * Authentication suites: PSK
+\tWPS:\t * Version: 1.0
+BSS 12:34:56:78:9a:c0(on wlan0)
+\tcapability: ESS Privacy ShortSlotTime (0x0411)
+\tSSID: Legacy-Net
+\tWPA:\t * Version: 1
+\t\t * Group cipher: TKIP
+\t\t * Pairwise ciphers: TKIP
+";
+
+/// `netsh wlan show interfaces` on Japanese Windows, connected with WPA (TKIP)
+const NETSH_INTERFACES: &str = "\
+    名前                   : Wi-Fi\r
+    状態                   : 接続されました\r
+    SSID                   : Legacy-Net\r
+    BSSID                  : 12:34:56:78:9a:c0\r
+    認証                   : WPA-Personal\r
+    暗号                   : TKIP\r
+";
+
+/// `system_profiler SPAirPortDataType` on macOS, connected with WPA2/WPA3
+const SYSTEM_PROFILER: &str = "\
+        Current Network Information:
+          Home-Net:
+            PHY Mode: 802.11ax
+            Security: WPA2/WPA3 Personal
+        Other Local Wi-Fi Networks:
+          Neighbor-Net:
+            Security: None
+";
+
+/// Start of an OpenWrt LuCI login page
+const LUCI_PAGE: &str = "<html><head><title>OpenWrt - LuCI</title></head>\
+<body><a href=\"/cgi-bin/luci\">Powered by LuCI openwrt-22.03 branch / OpenWrt 22.03.5</a></body></html>";
+
+/// Login page of a router's web interface
+const LOGIN_FORM: &str = "<html><body><form method=\"post\" action=\"/cgi-bin/login\">\
+<input type=\"hidden\" name=\"token\" value=\"a1b2\"><input type=\"text\" name=\"username\">\
+<input type=\"password\" name=\"password\"><input type=\"submit\" value=\"Login\"></form></body></html>";
+
+/// Router login page with the user name filled in and a WPS PIN field
+const WPS_LOGIN_PAGE: &str = "<html><body><form method=\"post\" action=\"/login.cgi\">\
+<input type=\"text\" name=\"username\" value=\"admin\"><input type=\"password\" name=\"password\">\
+<input type=\"text\" name=\"wps_pin\"></form></body></html>";
+
+/// RTSP answers of a camera to `DESCRIBE`: the stream's description, and a
+/// request for credentials
+const RTSP_OPEN: &str = "RTSP/1.0 200 OK\r\nCSeq: 2\r\nContent-Type: application/sdp\r\nContent-Length: 67\r\n\r\n\
+v=0\r\no=- 0 0 IN IP4 0.0.0.0\r\ns=Media Server\r\nm=video 0 RTP/AVP 96\r\n";
+const RTSP_PROTECTED: &str = "RTSP/1.0 401 Unauthorized\r\nCSeq: 2\r\nWWW-Authenticate: Digest realm=\"IP Camera\"\r\n\r\n";
+
+/// SNMPv2c GetResponse of a printer to the sysDescr, sysObjectID and sysName query
+const SNMP_RESPONSE: &[u8] = b"\
+\x30\x68\x02\x01\x01\x04\x06public\xa2\x5b\x02\x01\x21\x02\x01\x00\x02\x01\x00\x30\x50\
+\x30\x1f\x06\x08\x2b\x06\x01\x02\x01\x01\x01\x00\x04\x13RICOH IM C3000 1.02\
+\x30\x15\x06\x08\x2b\x06\x01\x02\x01\x01\x02\x00\x06\x09\x2b\x06\x01\x04\x01\x82\x6f\x01\x01\
+\x30\x16\x06\x08\x2b\x06\x01\x02\x01\x01\x05\x00\x04\x0aoffice-mfp";
+
+/// Outcome of a check's self-test
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    Pass,
+    Fail,
+    /// Needs a tool, privilege or setting this machine does not have
+    Unavailable,
+    /// No fixture exists for the check
+    NotCovered,
+}
+
+/// Self-test result of one check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckSelfTest {
+    pub id: String,
+    pub title: String,
+    pub status: SelfTestStatus,
+    pub detail: Option<String>,
+}
+
+/// Self-test results of every registered check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub checks: Vec<CheckSelfTest>,
+    pub passed: usize,
+    pub failed: usize,
+    pub unavailable: usize,
+    pub not_covered: usize,
+}
+
+/// What a fixture produced
+enum Outcome {
+    /// Findings raised from the fixture, plus a note on parts that cannot run here
+    Raised(Vec<SecurityIssue>, Option<String>),
+    Failed(String),
+    Unavailable(String),
+    NotCovered(String),
+}
+
+fn device(ip: &str, mac: &str) -> Device {
+    Device {
+        ip: ip.to_string(),
+        mac: mac.to_string(),
+        ..Device::default()
+    }
+}
+
+/// Run the fixture for check `id`
+async fn fixture(id: &str) -> Outcome {
+    let raised = |issues: Vec<SecurityIssue>| Outcome::Raised(issues, None);
+    match id {
+        "personal-hostname" => raised(privacy::check_broadcast_names(&["Taros-iPhone", "printer"]).into_iter().collect()),
+        "ip-conflict" => {
+            let observed = [
+                ("192.168.1.20".to_string(), "02:00:00:00:00:01".to_string()),
+                ("192.168.1.20".to_string(), "02:00:00:00:00:02".to_string()),
+            ];
+            raised(conflict::find_conflicts(&observed, &[]).iter().map(conflict::to_issue).collect())
+        }
+        "mdns-name-conflict" => {
+            let mut devices = [device("192.168.1.30", "02:00:00:00:00:03"), device("192.168.1.31", "02:00:00:00:00:04")];
+            let instances = HashMap::from([(
+                "Office Printer._ipp._tcp.local.".to_string(),
+                vec!["192.168.1.30".to_string(), "192.168.1.31".to_string()],
+            )]);
+            impersonation::check_duplicate_claims(&mut devices, &instances, &HashMap::new());
+            raised(devices.into_iter().flat_map(|d| d.issues).collect())
+        }
+        "mdns-name-moved" => {
+            let instance = "Office Printer._ipp._tcp.local.".to_string();
+            let known = Device {
+                mdns_instances: vec![instance.clone()],
+                ..device("192.168.1.30", "02:00:00:00:00:03")
+            };
+            let mut devices = [Device {
+                mdns_instances: vec![instance],
+                ..device("192.168.1.31", "02:00:00:00:00:04")
+            }];
+            impersonation::check_moved_names(&mut devices, &[known]);
+            raised(devices.into_iter().flat_map(|d| d.issues).collect())
+        }
+        "wifi-open" | "wifi-wep" | "wifi-wpa" | "wifi-wps" => {
+            let access_points = wifi::parse_iw(IW_DUMP);
+            if !wifi::is_same_router("12:34:56:78:9a:bf", "10:34:56:78:9a:bc")
+                || wifi::is_same_router("12:34:57:00:00:01", "10:34:56:78:9a:bc")
+            {
+                return Outcome::Failed("BSSIDとルーターの対応付けが正しくありません".to_string());
+            }
+            let netsh = wifi::parse_netsh(NETSH_INTERFACES);
+            let profiler = wifi::parse_system_profiler(SYSTEM_PROFILER);
+            let netsh_ids: Vec<String> =
+                wifi::to_issues(&netsh.iter().collect::<Vec<_>>()).into_iter().map(|i| i.id).collect();
+            if netsh_ids != ["wifi-wpa"] || profiler.len() != 1 || !wifi::to_issues(&[&profiler[0]]).is_empty() {
+                return Outcome::Failed("Windows・macOSの接続中のWi-Fiの読み取りが正しくありません".to_string());
+            }
+            let issues = wifi::to_issues(&access_points.iter().collect::<Vec<_>>());
+            if !wifi::tool_available().await {
+                return Outcome::Unavailable("iw または nmcli がインストールされていません".to_string());
+            }
+            raised(issues)
+        }
+        "port-mapping" => raised(
+            super::portmap::to_issue(&PortMappingReport {
+                enabled_protocols: vec![MappingProtocol::UpnpIgd],
+                external_address: Some("203.0.113.5".to_string()),
+                mappings: vec![PortMapping {
+                    protocol: MappingProtocol::UpnpIgd,
+                    external_port: 8080,
+                    internal_client: "192.168.1.40".to_string(),
+                    internal_port: 80,
+                    transport: "TCP".to_string(),
+                    description: None,
+                }],
+            })
+            .into_iter()
+            .collect(),
+        ),
+        "port-forwarded" => {
+            let mapping = PortMapping {
+                protocol: MappingProtocol::UpnpIgd,
+                external_port: 8080,
+                internal_client: "192.168.1.40".to_string(),
+                internal_port: 80,
+                transport: "TCP".to_string(),
+                description: Some("IPCamera".to_string()),
+            };
+            let mut devices = [device("192.168.1.1", "02:00:00:00:00:01"), device("192.168.1.40", "02:00:00:00:00:02")];
+            super::portmap::attach_mappings(&mut devices, "192.168.1.1", &[mapping]);
+            if !devices[0].issues.is_empty() {
+                return Outcome::Failed("ポート転送の指摘が転送先の機器に付いていません".to_string());
+            }
+            raised(devices.into_iter().flat_map(|d| d.issues).collect())
+        }
+        "double-nat" => raised(
+            topology::to_issue(&TopologyReport {
+                second_hop: Some("192.168.0.1".to_string()),
+                double_nat: true,
+                ..TopologyReport::default()
+            })
+            .into_iter()
+            .collect(),
+        ),
+        "carrier-grade-nat" => {
+            if !topology::is_cgnat(Ipv4Addr::new(100, 64, 12, 1)) || topology::is_cgnat(Ipv4Addr::new(100, 128, 0, 1)) {
+                return Outcome::Failed("CGNATのアドレス範囲の判定が正しくありません".to_string());
+            }
+            raised(
+                topology::to_issue(&TopologyReport {
+                    carrier_grade_nat: true,
+                    ..TopologyReport::default()
+                })
+                .into_iter()
+                .collect(),
+            )
+        }
+        "dns-rogue-resolver" | "dns-unknown-resolver" => {
+            let classified = dns::is_rogue(Ipv4Addr::new(85, 255, 112, 10))
+                && !dns::is_rogue(Ipv4Addr::new(85, 255, 128, 1))
+                && dns::resolver_issue(Ipv4Addr::new(8, 8, 8, 8)).is_none()
+                && dns::resolver_issue(Ipv4Addr::new(192, 168, 1, 1)).is_none();
+            if !classified {
+                return Outcome::Failed("DNSサーバーのアドレスの判定が正しくありません".to_string());
+            }
+            let resolver = if id == "dns-rogue-resolver" {
+                Ipv4Addr::new(85, 255, 112, 10)
+            } else {
+                Ipv4Addr::new(203, 0, 113, 53)
+            };
+            raised(dns::resolver_issue(resolver).into_iter().collect())
+        }
+        "dns-rebinding" | "dns-unencrypted" => {
+            let resolver = Ipv4Addr::new(192, 168, 1, 1);
+            // The router's answer for 192-168-1-1.sslip.io, by a compression pointer to the question
+            let mut response = dns::build_query(0x1234, &dns::rebind_name(resolver));
+            response[2..4].copy_from_slice(&[0x81, 0x80]);
+            response[6..8].copy_from_slice(&[0x00, 0x01]);
+            response.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x04]);
+            response.extend_from_slice(&resolver.octets());
+            let answers = dns::parse_response(&response, 0x1234);
+            if answers != Some(vec![resolver]) || dns::parse_response(&response, 0x4321).is_some() {
+                return Outcome::Failed(format!("DNSの応答の読み取りが正しくありません: {:?}", answers));
+            }
+            let rebinds = answers.is_some_and(|a| a.contains(&resolver));
+            raised(dns::lan_resolver_issues(resolver, rebinds && id == "dns-rebinding", id != "dns-unencrypted"))
+        }
+        "ipv6-exposed" => {
+            let global = "2400:4050:1::10".parse().is_ok_and(|a| exposure::is_global_ipv6(&a));
+            let link_local = "fe80::1".parse().is_ok_and(|a| exposure::is_global_ipv6(&a));
+            if !global || link_local {
+                return Outcome::Failed("IPv6アドレスの種類の判定が正しくありません".to_string());
+            }
+            let issue = exposure::ipv6_exposure_issue(DeviceType::Camera, &["[2400:4050:1::10]:80".to_string()]);
+            if crate::config::scan_options().exposure.endpoint().is_none() {
+                return Outcome::Unavailable("外部からの到達性確認が有効になっていません".to_string());
+            }
+            raised(vec![issue])
+        }
+        "port-forward-reachable" => {
+            let forwarded = |reachable| exposure::ForwardedPort {
+                address: "203.0.113.5:8080".to_string(),
+                transport: "TCP".to_string(),
+                internal_port: 80,
+                reachable,
+                method: Some(exposure::ProbeMethod::ProbeService),
+            };
+            let mut camera = Device {
+                device_type: DeviceType::Camera,
+                forwarded_ports: vec![forwarded(Some(false))],
+                ..device("192.168.1.40", "02:00:00:00:00:02")
+            };
+            if exposure::forward_exposure_issue(&camera).is_some() {
+                return Outcome::Failed("到達できない転送を公開として判定しました".to_string());
+            }
+            camera.forwarded_ports.push(forwarded(Some(true)));
+            let issue = exposure::forward_exposure_issue(&camera);
+            if crate::config::scan_options().exposure.endpoint().is_none() {
+                return Outcome::Unavailable("外部からの到達性確認が有効になっていません".to_string());
+            }
+            raised(issue.into_iter().collect())
+        }
+        "admin-http" | "ssh-wan-exposed" | "dropbear-outdated" => {
+            let Some((kind, version)) = firmware::identify(LUCI_PAGE) else {
+                return Outcome::Failed("OpenWrtの管理画面を識別できませんでした".to_string());
+            };
+            if version.as_deref() != Some("22.03.5") {
+                return Outcome::Failed(format!("ファームウェアのバージョンの読み取りが正しくありません: {:?}", version));
+            }
+            let outdated = firmware::dropbear_version("SSH-2.0-dropbear_2018.76")
+                .is_some_and(|v| v < firmware::MIN_DROPBEAR_VERSION);
+            if !outdated {
+                return Outcome::Failed("dropbearのバージョンの判定が正しくありません".to_string());
+            }
+            let fail = |id: &str| firmware::HardeningCheck {
+                id: id.to_string(),
+                title: id.to_string(),
+                status: firmware::CheckStatus::Fail,
+                detail: None,
+            };
+            raised(firmware::to_issues(&firmware::FirmwareReport {
+                kind,
+                version,
+                ssh_banner: None,
+                checks: vec![fail(id)],
+            }))
+        }
+        "login-page-http" | "login-page-default-user" | "login-page-no-lockout" | "login-page-wps-pin" => {
+            let page = firmware::Page {
+                status: 200,
+                location: None,
+                set_cookie: false,
+                headers: Default::default(),
+                body: WPS_LOGIN_PAGE.to_string(),
+            };
+            let indicators = login_page::inspect("http://192.168.1.1/login.html".to_string(), &page);
+            if indicators.prefilled_user.as_deref() != Some("admin") || !indicators.wps_pin {
+                return Outcome::Failed("ログイン画面の読み取りが正しくありません".to_string());
+            }
+            raised(login_page::to_issues(&indicators, false))
+        }
+        "router-wan-admin" | "router-outdated-server" => {
+            let rompager = router_audit::outdated_server("RomPager/4.07 UPnP/1.0", 2026);
+            let outdated = rompager.as_ref().is_some_and(|(_, advisory)| *advisory == Some("CVE-2014-9222"))
+                && router_audit::outdated_server("thttpd/2.25b 29dec2003", 2026).is_some()
+                && router_audit::outdated_server("lighttpd/1.4.59", 2026).is_none()
+                && router_audit::outdated_server("mini_httpd/1.30 26oct2018", 2026).is_none();
+            if !outdated {
+                return Outcome::Failed("管理画面のWebサーバーの古さの判定が正しくありません".to_string());
+            }
+            raised(router_audit::to_issues(&router_audit::RouterAudit {
+                server: Some("RomPager/4.07 UPnP/1.0".to_string()),
+                advisory: Some("CVE-2014-9222".to_string()),
+                checks: vec![firmware::HardeningCheck {
+                    id: id.to_string(),
+                    title: id.to_string(),
+                    status: firmware::CheckStatus::Fail,
+                    detail: Some("203.0.113.5:8080".to_string()),
+                }],
+            }))
+        }
+        "default-password" => {
+            let Some(form) = credentials::login_form("http://192.168.1.1/login.html", LOGIN_FORM) else {
+                return Outcome::Failed("ログイン画面のフォームを読み取れませんでした".to_string());
+            };
+            let fields_read = form.action == "http://192.168.1.1/cgi-bin/login"
+                && form.user_field.as_deref() == Some("username")
+                && form.password_field == "password"
+                && form.hidden == [("token".to_string(), "a1b2".to_string())];
+            if !fields_read {
+                return Outcome::Failed(format!("ログイン画面のフォームの読み取りが正しくありません: {:?}", form));
+            }
+            if credentials::form_accepted(200, false, LOGIN_FORM)
+                || credentials::telnet_accepted("login incorrect\r\nlogin:")
+                || !credentials::telnet_accepted("last login: mon\r\n~ #")
+            {
+                return Outcome::Failed("ログイン結果の判定が正しくありません".to_string());
+            }
+            raised(vec![credentials::to_issue(&credentials::AcceptedLogin {
+                service: "Telnet",
+                target: "192.168.1.1:23".to_string(),
+                username: "admin".to_string(),
+            })])
+        }
+        "admin-interface-http" => {
+            let page = "<html><head><title>401 Unauthorized</title></head></html>";
+            let realm = "Basic realm=\"NETGEAR R7000\"";
+            let interface = http::inspect(80, "http://192.168.1.1/".to_string(), 401, None, Some(realm), page);
+            if interface.basic_realm.as_deref() != Some("NETGEAR R7000") || !interface.plaintext_login {
+                return Outcome::Failed("Basic認証の応答の読み取りが正しくありません".to_string());
+            }
+            let https = http::inspect(443, "https://192.168.1.1/".to_string(), 401, None, Some(realm), page);
+            if https.plaintext_login {
+                return Outcome::Failed("HTTPSのログイン画面を暗号化なしと判定しました".to_string());
+            }
+            raised(vec![http::to_issue(&interface)])
+        }
+        "silent-device" => {
+            let mac = "02:00:00:00:00:05";
+            let findings = stealth::Findings {
+                tcp_refused: true,
+                vendor_hint: stealth::vendor_hint(mac, None),
+                ..stealth::Findings::default()
+            };
+            if findings.vendor_hint != Some("randomized") {
+                return Outcome::Failed("ランダムなMACアドレスの判定が正しくありません".to_string());
+            }
+            let note = (!stealth::raw_sockets_permitted())
+                .then(|| "ICMPタイムスタンプの確認には管理者権限が必要です".to_string());
+            Outcome::Raised(vec![stealth::to_issue(&device("192.168.1.50", mac), &findings)], note)
+        }
+        "snmp-default-community" => {
+            let Some(info) = snmp::parse_response(SNMP_RESPONSE, snmp::SnmpVersion::V2c) else {
+                return Outcome::Failed("SNMPの応答を読み取れませんでした".to_string());
+            };
+            let printer = snmp::device_type(&info) == Some(DeviceType::Printer);
+            if info.sys_object_id.as_deref() != Some("1.3.6.1.4.1.367.1.1") || !printer {
+                return Outcome::Failed("SNMPの応答の読み取りが正しくありません".to_string());
+            }
+            raised(vec![snmp::to_issue(&info)])
+        }
+        "ble-no-pairing" => {
+            if !ble::is_public_service("0000180a-0000-1000-8000-00805f9b34fb")
+                || ble::is_public_service("0000fe95-0000-1000-8000-00805f9b34fb")
+            {
+                return Outcome::Failed("Bluetoothのサービスの判定が正しくありません".to_string());
+            }
+            let mut device = ble::from_advertisement(
+                "C4:7C:8D:6A:12:34".to_string(),
+                Some("Smart Lock".to_string()),
+                &[0x038F],
+                Vec::new(),
+                Some(-60),
+            );
+            if device.vendor.as_deref() != Some("Xiaomi") {
+                return Outcome::Failed("Bluetoothのメーカーの判定が正しくありません".to_string());
+            }
+            device.open_characteristics.push("00000001-0000-1000-8000-00805f9b34fb".to_string());
+            raised(vec![ble::to_issue(&device)])
+        }
+        "telnet-open" => raised(port_issues(23)),
+        "upnp-enabled" => raised(port_issues(1900)),
+        "rtsp-stream-open" => {
+            if rtsp::classify(RTSP_PROTECTED) != rtsp::StreamAccess::Protected
+                || rtsp::classify(RTSP_OPEN) != rtsp::StreamAccess::Open
+            {
+                return Outcome::Failed("RTSPの応答の判定が正しくありません".to_string());
+            }
+            raised(vec![rtsp::to_issue("rtsp://192.168.1.60:554/stream1")])
+        }
+        "smbv1-enabled" => {
+            let mut reply = vec![0x00, 0x00, 0x00, 0x55, 0xff, b'S', b'M', b'B', 0x72];
+            reply.extend([0; 27]);
+            reply.extend([17, 0, 0]);
+            let mut smb2 = reply.clone();
+            smb2[4] = 0xfe;
+            if !shares::smb1_accepted(&reply) || shares::smb1_accepted(&smb2) {
+                return Outcome::Failed("SMBv1の応答の判定が正しくありません".to_string());
+            }
+            raised(vec![content::issue("smbv1-enabled", IssueSeverity::Medium, &[])])
+        }
+        "anonymous-share" => {
+            if shares::pasv_port("227 Entering Passive Mode (192,168,1,2,195,80)") != Some(50000)
+                || shares::pasv_port("227 Entering Passive Mode (192,168,1,2,300,1)").is_some()
+            {
+                return Outcome::Failed("FTPの応答の読み取りが正しくありません".to_string());
+            }
+            let entry = shares::smb_entry("  Photos                              D        0  Mon Jan  1 00:00:00 2024");
+            if entry.as_deref() != Some("Photos/") {
+                return Outcome::Failed("SMBの一覧の読み取りが正しくありません".to_string());
+            }
+            let share = shares::ExposedShare {
+                name: "/".to_string(),
+                entries: vec!["Photos/".to_string()],
+                truncated: false,
+            };
+            let note = shares::smbclient(&["--version"])
+                .await
+                .err()
+                .map(|_| "SMBの確認には smbclient（Samba）が必要です".to_string());
+            Outcome::Raised(vec![shares::to_issue("FTP", &[share])], note)
+        }
+        _ => Outcome::NotCovered("このチェックの確認用データはありません".to_string()),
+    }
+}
+
+/// Every finding must have text in every language
+fn check_text(issue: &SecurityIssue) -> Result<(), String> {
+    for language in [Language::Ja, Language::En] {
+        let mut localized = issue.clone();
+        content::localize(&mut localized, language);
+        if localized.title == localized.id || localized.description.is_empty() {
+            return Err(format!("{:?} の文言がありません", language));
+        }
+    }
+    Ok(())
+}
+
+/// Run the fixtures of every registered check
+pub async fn run() -> SelfTestReport {
+    let mut checks = Vec::new();
+    for check in catalog::list_checks() {
+        let (status, detail) = match fixture(&check.id).await {
+            Outcome::Raised(issues, note) => match issues.iter().find(|i| i.id == check.id) {
+                None => (SelfTestStatus::Fail, Some("確認用データから問題が検出されませんでした".to_string())),
+                Some(issue) => match check_text(issue) {
+                    Ok(()) => (SelfTestStatus::Pass, note),
+                    Err(e) => (SelfTestStatus::Fail, Some(e)),
+                },
+            },
+            Outcome::Failed(reason) => (SelfTestStatus::Fail, Some(reason)),
+            Outcome::Unavailable(reason) => (SelfTestStatus::Unavailable, Some(reason)),
+            Outcome::NotCovered(reason) => (SelfTestStatus::NotCovered, Some(reason)),
+        };
+        checks.push(CheckSelfTest {
+            id: check.id,
+            title: check.title,
+            status,
+            detail,
+        });
+    }
+
+    // Built-in vulnerability entries
+    for vuln in catalog::builtin() {
+        let missing = [Language::Ja, Language::En].into_iter().find(|&language| {
+            let mut localized = vuln.clone();
+            localized.title = localized.id.clone();
+            content::localize_vulnerability(&mut localized, language);
+            localized.title == localized.id
+        });
+        checks.push(CheckSelfTest {
+            id: vuln.id.clone(),
+            title: vuln.title.clone(),
+            status: if missing.is_some() { SelfTestStatus::Fail } else { SelfTestStatus::Pass },
+            detail: missing.map(|language| format!("{:?} の文言がありません", language)),
+        });
+    }
+
+    let count = |status: SelfTestStatus| checks.iter().filter(|c| c.status == status).count();
+    SelfTestReport {
+        passed: count(SelfTestStatus::Pass),
+        failed: count(SelfTestStatus::Fail),
+        unavailable: count(SelfTestStatus::Unavailable),
+        not_covered: count(SelfTestStatus::NotCovered),
+        checks,
+    }
+}
@@ -0,0 +1,291 @@
+//! Anonymous file share exposure (Level 3)
+//!
+//! Printers and NAS boxes often publish FTP or SMB shares that anyone on the
+//! network can open without a password. To show users what an attacker could
+//! reach, share names and the names at the top level of each share are
+//! collected as evidence. File contents are never read: FTP is only asked for
+//! a name list (`NLST`) and SMB for a directory listing, and every listing is
+//! capped.
+//!
+//! SMB goes through `smbclient` (Samba) when it is installed. Servers with
+//! SMB open are also asked whether they still accept SMBv1, the protocol
+//! WannaCry-style worms spread over.
+
+use super::{net, ports, Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+
+/// Shares listed per device
+const MAX_SHARES: usize = 10;
+
+/// Top-level names listed per share
+const MAX_ENTRIES: usize = 15;
+
+/// Bytes read from an FTP name list or reply
+const MAX_LISTING_BYTES: u64 = 16 * 1024;
+
+/// Timeout per FTP reply or SMB command
+const SHARE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SMB1 Negotiate Protocol request in a NetBIOS session message, offering
+/// only the "NT LM 0.12" dialect
+const SMB1_NEGOTIATE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x2f, // NetBIOS session message, 47 bytes
+    0xff, b'S', b'M', b'B', 0x72, // SMB1 Negotiate Protocol
+    0x00, 0x00, 0x00, 0x00, // status
+    0x18, 0x01, 0x00, // flags, flags2
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // PID high, signature
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // reserved, TID, PID, UID, MID
+    0x00, 0x0c, 0x00, // no words, 12 bytes
+    0x02, b'N', b'T', b' ', b'L', b'M', b' ', b'0', b'.', b'1', b'2', 0x00,
+];
+
+/// NetBIOS header, SMB1 header, word count and dialect index
+const SMB1_RESPONSE_LEN: usize = 4 + 32 + 3;
+
+/// A share reachable without credentials
+#[derive(Debug, Clone)]
+pub struct ExposedShare {
+    pub name: String,
+    /// Top-level names; directories end in `/`
+    pub entries: Vec<String>,
+    /// More names existed than were listed
+    pub truncated: bool,
+}
+
+/// Check devices with FTP or SMB open for anonymous access and attach findings
+pub async fn check_devices(devices: &mut [Device], excluded: &[String]) {
+    for device in devices.iter_mut().filter(|d| !excluded.iter().any(|m| m.eq_ignore_ascii_case(&d.mac))) {
+        let open = ports::probe_open(&device.ip, &[21, 445]).await;
+        if open.contains(&21) {
+            if let Some(share) = ftp_anonymous(&device.ip).await {
+                device.issues.push(to_issue("FTP", &[share]));
+            }
+        }
+        if open.contains(&445) {
+            if smb1_enabled(&device.ip).await {
+                device.issues.push(content::issue("smbv1-enabled", IssueSeverity::Medium, &[]));
+            }
+            match smb_anonymous(&device.ip).await {
+                Ok(shares) if !shares.is_empty() => device.issues.push(to_issue("SMB", &shares)),
+                Ok(_) => {}
+                Err(warning) => device.warnings.push(warning),
+            }
+        }
+    }
+}
+
+fn truncate(mut names: Vec<String>) -> (Vec<String>, bool) {
+    names.retain(|n| !n.is_empty() && n != "." && n != "..");
+    let truncated = names.len() > MAX_ENTRIES;
+    names.truncate(MAX_ENTRIES);
+    (names, truncated)
+}
+
+/// Read one (possibly multi-line) FTP reply and return its code and last
+/// line; a reply longer than `MAX_LISTING_BYTES` counts as none
+async fn ftp_reply(reader: &mut BufReader<TcpStream>) -> Option<(u16, String)> {
+    let mut reply = (&mut *reader).take(MAX_LISTING_BYTES);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = tokio::time::timeout(SHARE_TIMEOUT, reply.read_line(&mut line)).await.ok()?.ok()?;
+        if read == 0 {
+            return None;
+        }
+        // The last line of a reply is "123 text", continuation lines "123-text"
+        if line.len() >= 4 && line.as_bytes()[3] == b' ' {
+            return Some((line[..3].parse().ok()?, line.trim_end().to_string()));
+        }
+    }
+}
+
+async fn ftp_command(reader: &mut BufReader<TcpStream>, command: &str) -> Option<(u16, String)> {
+    reader.get_mut().write_all(format!("{}\r\n", command).as_bytes()).await.ok()?;
+    ftp_reply(reader).await
+}
+
+/// Data port from a PASV reply, "227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)"
+pub(super) fn pasv_port(reply: &str) -> Option<u16> {
+    let inner = reply.split('(').nth(1)?.split(')').next()?;
+    let numbers: Vec<u8> = inner.split(',').map(|n| n.trim().parse().ok()).collect::<Option<_>>()?;
+    match numbers[..] {
+        [_, _, _, _, high, low] => Some(u16::from(high) * 256 + u16::from(low)),
+        _ => None,
+    }
+}
+
+/// Log in as `anonymous` and list the names at the root
+async fn ftp_anonymous(ip: &str) -> Option<ExposedShare> {
+    let addr: SocketAddr = format!("{}:21", ip).parse().ok()?;
+    let stream = net::tcp_connect_async(addr, ports::CONNECT_TIMEOUT).await.ok()?;
+    let mut control = BufReader::new(stream);
+
+    if ftp_reply(&mut control).await?.0 != 220 {
+        return None;
+    }
+    let (code, _) = ftp_command(&mut control, "USER anonymous").await?;
+    let logged_in = match code {
+        230 => true,
+        331 => ftp_command(&mut control, "PASS iot-doctor@").await?.0 == 230,
+        _ => false,
+    };
+    if !logged_in {
+        return None;
+    }
+
+    let mut entries = Vec::new();
+    let (code, reply) = ftp_command(&mut control, "PASV").await?;
+    // The data connection always goes to the device itself, whatever the reply says
+    if let Some(port) = pasv_port(&reply).filter(|_| code == 227) {
+        let data_addr: SocketAddr = format!("{}:{}", ip, port).parse().ok()?;
+        if let Ok(data) = net::tcp_connect_async(data_addr, ports::CONNECT_TIMEOUT).await {
+            if matches!(ftp_command(&mut control, "NLST").await, Some((125 | 150, _))) {
+                let mut listing = Vec::new();
+                let _ = tokio::time::timeout(SHARE_TIMEOUT, data.take(MAX_LISTING_BYTES).read_to_end(&mut listing)).await;
+                entries = String::from_utf8_lossy(&listing)
+                    .lines()
+                    .map(|l| l.trim().trim_start_matches("./").trim_start_matches('/').to_string())
+                    .collect();
+                let _ = ftp_reply(&mut control).await;
+            }
+        }
+    }
+    let _ = ftp_command(&mut control, "QUIT").await;
+
+    let (entries, truncated) = truncate(entries);
+    Some(ExposedShare {
+        name: "/".to_string(),
+        entries,
+        truncated,
+    })
+}
+
+/// Whether the SMB server agrees to speak SMBv1
+async fn smb1_enabled(ip: &str) -> bool {
+    let Ok(addr) = format!("{}:445", ip).parse::<SocketAddr>() else {
+        return false;
+    };
+    let Ok(mut stream) = net::tcp_connect_async(addr, ports::CONNECT_TIMEOUT).await else {
+        return false;
+    };
+    let exchange = async {
+        stream.write_all(SMB1_NEGOTIATE).await.ok()?;
+        let mut response = [0u8; SMB1_RESPONSE_LEN];
+        stream.read_exact(&mut response).await.ok()?;
+        Some(smb1_accepted(&response))
+    };
+    tokio::time::timeout(SHARE_TIMEOUT, exchange).await.ok().flatten().unwrap_or(false)
+}
+
+/// Whether a reply to [`SMB1_NEGOTIATE`] picked its dialect. Servers without
+/// SMBv1 close the connection or answer in SMB2.
+pub(super) fn smb1_accepted(response: &[u8]) -> bool {
+    let Some(smb) = response.get(4..SMB1_RESPONSE_LEN) else {
+        return false;
+    };
+    let status = &smb[5..9];
+    let (word_count, dialect) = (smb[32], u16::from_le_bytes([smb[33], smb[34]]));
+    smb.starts_with(b"\xffSMB\x72") && status == [0; 4] && word_count > 0 && dialect != 0xffff
+}
+
+pub(super) async fn smbclient(args: &[&str]) -> Result<Option<String>, String> {
+    let child = Command::new("smbclient")
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(SHARE_TIMEOUT, child).await {
+        Ok(Ok(output)) => Ok(output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err("SMBの匿名アクセスの確認には smbclient（Samba）が必要です".to_string())
+        }
+        Ok(Err(e)) => Err(format!("SMBの匿名アクセスを確認できませんでした: {}", e)),
+        // Timed out
+        Err(_) => Ok(None),
+    }
+}
+
+/// Name from a `smbclient ls` line, e.g.
+/// "  Photos                              D        0  Mon Jan  1 00:00:00 2024"
+pub(super) fn smb_entry(line: &str) -> Option<String> {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    // Date (5 tokens) and size
+    if tokens.len() < 7 {
+        return None;
+    }
+    tokens.truncate(tokens.len() - 6);
+    let attributes = tokens
+        .last()
+        .copied()
+        .filter(|t| tokens.len() > 1 && t.chars().all(|c| "DAHSRN".contains(c)));
+    if attributes.is_some() {
+        tokens.pop();
+    }
+    let name = tokens.join(" ");
+    Some(if attributes.is_some_and(|a| a.contains('D')) {
+        format!("{}/", name)
+    } else {
+        name
+    })
+}
+
+/// List disk shares with a null session and the top level of each
+async fn smb_anonymous(ip: &str) -> Result<Vec<ExposedShare>, String> {
+    let server = format!("//{}", ip);
+    let Some(list) = smbclient(&["-N", "-g", "-L", &server]).await? else {
+        return Ok(Vec::new());
+    };
+
+    // "Disk|name|comment"; administrative shares end in '$'
+    let names: Vec<String> = list
+        .lines()
+        .filter_map(|line| line.strip_prefix("Disk|"))
+        .filter_map(|rest| rest.split('|').next())
+        .filter(|name| !name.ends_with('$'))
+        .take(MAX_SHARES)
+        .map(str::to_string)
+        .collect();
+
+    let mut shares = Vec::new();
+    for name in names {
+        let service = format!("//{}/{}", ip, name);
+        // Shares that refuse the null session are not exposed
+        let Some(listing) = smbclient(&["-N", &service, "-c", "ls"]).await? else {
+            continue;
+        };
+        let (entries, truncated) = truncate(listing.lines().filter(|l| l.starts_with("  ")).filter_map(smb_entry).collect());
+        shares.push(ExposedShare {
+            name,
+            entries,
+            truncated,
+        });
+    }
+    Ok(shares)
+}
+
+pub(super) fn to_issue(protocol: &str, shares: &[ExposedShare]) -> SecurityIssue {
+    let listing: String = shares
+        .iter()
+        .map(|share| {
+            let more = if share.truncated { ", …" } else { "" };
+            format!("\n・{}: {}{}", share.name, share.entries.join(", "), more)
+        })
+        .collect();
+    let severity = if shares.iter().any(|s| !s.entries.is_empty()) {
+        IssueSeverity::High
+    } else {
+        IssueSeverity::Medium
+    };
+    content::issue(
+        "anonymous-share",
+        severity,
+        &[("protocol", protocol.to_string()), ("shares", listing)],
+    )
+}
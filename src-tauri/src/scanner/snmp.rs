@@ -0,0 +1,331 @@
+//! SNMP device interrogation
+//!
+//! Asks SNMP agents for their system description, name and object ID with
+//! the factory-default "public" community, over SNMPv2c and then SNMPv1. An
+//! agent that answers gives its model and firmware away to anyone on the
+//! network; the description also tells printers, routers and NAS apart.
+
+use super::fingerprint::{self, TypeSource};
+use super::{net, Device, DeviceType, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const SNMP_PORT: u16 = 161;
+
+/// Read community most agents ship with
+const DEFAULT_COMMUNITY: &str = "public";
+
+/// How long to wait for the agent's answer
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest description kept
+const MAX_DESCR_LEN: usize = 255;
+
+const SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+const SYS_OBJECT_ID: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 2, 0];
+const SYS_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 5, 0];
+
+/// BER tags used by SNMP messages
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_ID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const GET_REQUEST: u8 = 0xa0;
+const GET_RESPONSE: u8 = 0xa2;
+
+/// sysDescr keywords, checked in order
+const DESCR_PATTERNS: &[(&str, DeviceType)] = &[
+    ("synology", DeviceType::Nas),
+    ("diskstation", DeviceType::Nas),
+    ("qnap", DeviceType::Nas),
+    ("readynas", DeviceType::Nas),
+    ("terastation", DeviceType::Nas),
+    ("linkstation", DeviceType::Nas),
+    ("truenas", DeviceType::Nas),
+    ("printer", DeviceType::Printer),
+    ("laserjet", DeviceType::Printer),
+    ("officejet", DeviceType::Printer),
+    ("brother nc-", DeviceType::Printer),
+    ("epson", DeviceType::Printer),
+    ("canon", DeviceType::Printer),
+    ("ricoh", DeviceType::Printer),
+    ("kyocera", DeviceType::Printer),
+    ("lexmark", DeviceType::Printer),
+    ("xerox", DeviceType::Printer),
+    ("camera", DeviceType::Camera),
+    ("ipcam", DeviceType::Camera),
+    ("hikvision", DeviceType::Camera),
+    ("network video", DeviceType::Camera),
+    ("router", DeviceType::Router),
+    ("routeros", DeviceType::Router),
+    ("openwrt", DeviceType::Router),
+    ("dd-wrt", DeviceType::Router),
+    ("cisco ios", DeviceType::Router),
+    ("edgeos", DeviceType::Router),
+    ("fritz!box", DeviceType::Router),
+    ("airstation", DeviceType::Router),
+    ("windows", DeviceType::Computer),
+];
+
+/// SNMP version an agent answered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpVersion {
+    V1,
+    V2c,
+}
+
+impl SnmpVersion {
+    /// Value of the message's version field
+    fn code(self) -> u8 {
+        match self {
+            SnmpVersion::V1 => 0,
+            SnmpVersion::V2c => 1,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SnmpVersion::V1 => "SNMPv1",
+            SnmpVersion::V2c => "SNMPv2c",
+        }
+    }
+}
+
+/// What an SNMP agent said about itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnmpInfo {
+    pub version: SnmpVersion,
+    /// Community the agent accepted
+    pub community: String,
+    pub sys_descr: Option<String>,
+    pub sys_name: Option<String>,
+    /// Product OID under the maker's enterprise number, e.g. "1.3.6.1.4.1.11.2.3.9.1"
+    pub sys_object_id: Option<String>,
+}
+
+/// Query the agent of a device whose SNMP port answered the port scan,
+/// record what it says and raise the default community finding
+pub async fn interrogate(device: &mut Device) {
+    let answered = device.open_ports.iter().any(|p| p.protocol == "udp" && p.number == SNMP_PORT);
+    if !answered {
+        return;
+    }
+    let Some(info) = query(&device.ip).await else {
+        return;
+    };
+
+    if let Some(device_type) = device_type(&info) {
+        let descr = info.sys_descr.clone().unwrap_or_default();
+        fingerprint::record_type(device, device_type, TypeSource::Snmp, format!("SNMP: {}", descr));
+    }
+    device.issues.push(to_issue(&info));
+    device.snmp = Some(info);
+}
+
+/// Ask the agent at `ip` with the default community, SNMPv2c first
+pub async fn query(ip: &str) -> Option<SnmpInfo> {
+    for version in [SnmpVersion::V2c, SnmpVersion::V1] {
+        if let Some(info) = query_version(ip, version).await {
+            return Some(info);
+        }
+    }
+    None
+}
+
+async fn query_version(ip: &str, version: SnmpVersion) -> Option<SnmpInfo> {
+    let socket = net::udp_bind(net::ANY).await.ok()?;
+    socket.connect(format!("{}:{}", ip, SNMP_PORT)).await.ok()?;
+    socket.send(&get_request(version, &[SYS_DESCR, SYS_OBJECT_ID, SYS_NAME])).await.ok()?;
+
+    let mut buf = [0u8; 1500];
+    let len = tokio::time::timeout(QUERY_TIMEOUT, socket.recv(&mut buf)).await.ok()?.ok()?;
+    parse_response(&buf[..len], version)
+}
+
+/// Request ID of the query in `version`, to match the answer
+fn request_id(version: SnmpVersion) -> u8 {
+    0x20 + version.code()
+}
+
+/// GetRequest for `oids` with the default community
+fn get_request(version: SnmpVersion, oids: &[&[u32]]) -> Vec<u8> {
+    let bindings: Vec<u8> = oids
+        .iter()
+        .flat_map(|oid| tlv(SEQUENCE, &[tlv(OBJECT_ID, &encode_oid(oid)), tlv(NULL, &[])].concat()))
+        .collect();
+    let pdu = [
+        tlv(INTEGER, &[request_id(version)]),
+        // Error status and index
+        tlv(INTEGER, &[0]),
+        tlv(INTEGER, &[0]),
+        tlv(SEQUENCE, &bindings),
+    ]
+    .concat();
+    let message = [
+        tlv(INTEGER, &[version.code()]),
+        tlv(OCTET_STRING, DEFAULT_COMMUNITY.as_bytes()),
+        tlv(GET_REQUEST, &pdu),
+    ]
+    .concat();
+    tlv(SEQUENCE, &message)
+}
+
+/// Agent information from the GetResponse to [`get_request`]. Agents drop
+/// requests with a wrong community, so any answer means it was accepted,
+/// even one that reports an error for the variables.
+pub(super) fn parse_response(packet: &[u8], version: SnmpVersion) -> Option<SnmpInfo> {
+    let (SEQUENCE, message, _) = read_tlv(packet)? else {
+        return None;
+    };
+    let (INTEGER, answered_version, rest) = read_tlv(message)? else {
+        return None;
+    };
+    let (OCTET_STRING, community, rest) = read_tlv(rest)? else {
+        return None;
+    };
+    let (GET_RESPONSE, pdu, _) = read_tlv(rest)? else {
+        return None;
+    };
+    let (INTEGER, id, rest) = read_tlv(pdu)? else {
+        return None;
+    };
+    if answered_version != [version.code()] || id != [request_id(version)] {
+        return None;
+    }
+
+    let mut info = SnmpInfo {
+        version,
+        community: String::from_utf8_lossy(community).to_string(),
+        sys_descr: None,
+        sys_name: None,
+        sys_object_id: None,
+    };
+
+    let (_, error_status, rest) = read_tlv(rest)?;
+    let (_, _error_index, rest) = read_tlv(rest)?;
+    let (SEQUENCE, mut bindings, _) = read_tlv(rest)? else {
+        return Some(info);
+    };
+    // SNMPv1 fails the whole request when one variable is missing
+    if error_status.iter().any(|&b| b != 0) {
+        return Some(info);
+    }
+
+    while let Some((_, binding, rest)) = read_tlv(bindings) {
+        bindings = rest;
+        let Some((OBJECT_ID, oid, value)) = read_tlv(binding) else {
+            continue;
+        };
+        // SNMPv2c marks missing variables with an exception value instead
+        let value = match read_tlv(value) {
+            Some((OCTET_STRING, text, _)) => clean_text(text),
+            Some((OBJECT_ID, oid, _)) => Some(decode_oid(oid)),
+            _ => None,
+        };
+        if oid == encode_oid(SYS_DESCR) {
+            info.sys_descr = value;
+        } else if oid == encode_oid(SYS_NAME) {
+            info.sys_name = value;
+        } else if oid == encode_oid(SYS_OBJECT_ID) {
+            info.sys_object_id = value;
+        }
+    }
+    Some(info)
+}
+
+/// Device type named by the agent's description
+pub fn device_type(info: &SnmpInfo) -> Option<DeviceType> {
+    let descr = info.sys_descr.as_deref()?.to_lowercase();
+    DESCR_PATTERNS
+        .iter()
+        .find(|(keyword, _)| descr.contains(keyword))
+        .map(|(_, device_type)| *device_type)
+}
+
+/// Finding for an agent that answered the default community
+pub(super) fn to_issue(info: &SnmpInfo) -> SecurityIssue {
+    let descr = info.sys_descr.clone().unwrap_or_else(|| "@unknown".to_string());
+    content::issue(
+        "snmp-default-community",
+        IssueSeverity::High,
+        &[
+            ("community", info.community.clone()),
+            ("version", info.version.label().to_string()),
+            ("descr", descr),
+        ],
+    )
+}
+
+/// Printable text of an OCTET STRING, on one line
+fn clean_text(bytes: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(bytes);
+    let text: String = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_DESCR_LEN)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// BER element with a definite length
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    match content.len() {
+        len if len < 0x80 => element.push(len as u8),
+        len if len <= 0xff => element.extend([0x81, len as u8]),
+        len => element.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+/// Tag and contents of the BER element at the start of `data`, and what follows it
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let (len, header) = match *data.get(1)? {
+        short if short < 0x80 => (usize::from(short), 2),
+        0x81 => (usize::from(*data.get(2)?), 3),
+        0x82 => (usize::from(u16::from_be_bytes([*data.get(2)?, *data.get(3)?])), 4),
+        _ => return None,
+    };
+    let content = data.get(header..header + len)?;
+    Some((tag, content, &data[header + len..]))
+}
+
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut encoded = vec![(oid[0] * 40 + oid[1]) as u8];
+    for &arc in &oid[2..] {
+        // Base 128, most significant group first, high bit on all but the last
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        encoded.extend(groups.iter().rev());
+    }
+    encoded
+}
+
+/// Dotted form of an encoded OID
+fn decode_oid(encoded: &[u8]) -> String {
+    let Some((&first, rest)) = encoded.split_first() else {
+        return String::new();
+    };
+    let mut arcs = vec![u32::from(first / 40), u32::from(first % 40)];
+    let mut arc = 0u32;
+    for &byte in rest {
+        arc = (arc << 7) | u32::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+    arcs.iter().map(u32::to_string).collect::<Vec<_>>().join(".")
+}
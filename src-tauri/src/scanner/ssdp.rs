@@ -1,14 +1,26 @@
 //! SSDP/UPnP device discovery
 //!
 //! Sends M-SEARCH multicast packets to discover UPnP devices on the network
-//! and retrieves their friendly names from XML device descriptions.
+//! and retrieves their XML device descriptions. Besides the friendly name,
+//! the `deviceType` URNs tell a TV (`urn:samsung.com:device:RemoteControlReceiver`)
+//! from a soundbar or a smart appliance better than the vendor alone.
+//!
+//! Devices that miss the M-SEARCH still announce themselves with NOTIFY
+//! messages to the multicast group, so those are listened for as well.
 
+use super::{net, DeviceType};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::Duration;
-use tokio::net::UdpSocket;
 
 const SSDP_MULTICAST_ADDR: &str = "239.255.255.250:1900";
 
+const SSDP_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+const SSDP_PORT: u16 = 1900;
+
 /// M-SEARCH request packet for discovering all UPnP devices
 const M_SEARCH_REQUEST: &str = "\
 M-SEARCH * HTTP/1.1\r\n\
@@ -18,62 +30,265 @@ MX: 2\r\n\
 ST: ssdp:all\r\n\
 \r\n";
 
-/// Discover device names via SSDP/UPnP M-SEARCH.
-/// Returns HashMap<IP address, friendly name>.
-pub async fn scan_ssdp(timeout: Duration) -> HashMap<String, String> {
-    let mut device_names: HashMap<String, String> = HashMap::new();
+/// Fields of a device's UPnP descriptions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SsdpDescription {
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    /// `deviceType` URNs of the root and embedded devices of every description
+    pub device_types: Vec<String>,
+    /// `SERVER` header of the announcements, e.g. "Linux/4.9 UPnP/1.0 Sonos/70.3"
+    #[serde(default)]
+    pub server: Option<String>,
+    /// Description URLs that could be fetched
+    #[serde(default)]
+    pub locations: Vec<String>,
+}
+
+impl SsdpDescription {
+    pub fn merge(&mut self, other: SsdpDescription) {
+        self.friendly_name = self.friendly_name.take().or(other.friendly_name);
+        self.manufacturer = self.manufacturer.take().or(other.manufacturer);
+        self.model_name = self.model_name.take().or(other.model_name);
+        self.server = self.server.take().or(other.server);
+        for device_type in other.device_types {
+            if !self.device_types.contains(&device_type) {
+                self.device_types.push(device_type);
+            }
+        }
+        for location in other.locations {
+            if !self.locations.contains(&location) {
+                self.locations.push(location);
+            }
+        }
+    }
+
+    fn announces(&self, urn_part: &str) -> bool {
+        self.device_types.iter().any(|t| t.to_lowercase().contains(urn_part))
+    }
+
+    /// Whether the device is a UPnP internet gateway, which lists its port mappings
+    pub fn is_internet_gateway(&self) -> bool {
+        self.announces("device:internetgatewaydevice")
+    }
+}
+
+/// What a device said about itself in M-SEARCH responses and NOTIFY messages
+#[derive(Debug, Clone, Default)]
+pub struct Announcement {
+    /// LOCATION URLs; TVs often run several root devices
+    pub locations: Vec<String>,
+    pub server: Option<String>,
+    /// Device type URNs from the `NT` header of NOTIFY messages
+    pub device_types: Vec<String>,
+}
+
+impl Announcement {
+    fn add(&mut self, message: &str) {
+        if let Some(location) = extract_header(message, "LOCATION") {
+            if !self.locations.contains(&location) {
+                self.locations.push(location);
+            }
+        }
+        if self.server.is_none() {
+            self.server = extract_header(message, "SERVER");
+        }
+        if let Some(nt) = extract_header(message, "NT").filter(|nt| nt.contains(":device:")) {
+            if !self.device_types.contains(&nt) {
+                self.device_types.push(nt);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Announcement) {
+        for location in other.locations {
+            if !self.locations.contains(&location) {
+                self.locations.push(location);
+            }
+        }
+        self.server = self.server.take().or(other.server);
+        for device_type in other.device_types {
+            if !self.device_types.contains(&device_type) {
+                self.device_types.push(device_type);
+            }
+        }
+    }
+
+    /// What the announcement headers alone tell, without fetching the description
+    fn headers(&self) -> Option<SsdpDescription> {
+        if self.server.is_none() && self.device_types.is_empty() {
+            return None;
+        }
+        Some(SsdpDescription {
+            server: self.server.clone(),
+            device_types: self.device_types.clone(),
+            ..SsdpDescription::default()
+        })
+    }
+}
+
+/// Discover devices via SSDP/UPnP M-SEARCH and NOTIFY announcements.
+/// Returns HashMap<IP address, merged device descriptions>.
+pub async fn scan_ssdp(timeout: Duration) -> HashMap<String, SsdpDescription> {
+    let (mut announcements, notified) = tokio::join!(search(timeout), listen_notify(timeout));
+    for (ip, announcement) in notified {
+        announcements.entry(ip).or_default().merge(announcement);
+    }
+    describe(announcements).await
+}
+
+/// Send an M-SEARCH and collect the responses
+async fn search(timeout: Duration) -> HashMap<String, Announcement> {
+    let mut announcements: HashMap<String, Announcement> = HashMap::new();
 
     // Bind to any available port
-    let socket = match UdpSocket::bind("0.0.0.0:0").await {
+    let socket = match net::udp_bind(net::ANY).await {
         Ok(s) => s,
         Err(e) => {
             eprintln!("Failed to bind SSDP socket: {}", e);
-            return device_names;
+            return announcements;
         }
     };
 
     // Send M-SEARCH multicast
     if let Err(e) = socket.send_to(M_SEARCH_REQUEST.as_bytes(), SSDP_MULTICAST_ADDR).await {
         eprintln!("Failed to send SSDP M-SEARCH: {}", e);
-        return device_names;
+        return announcements;
     }
 
-    // Collect LOCATION URLs from responses
-    let mut location_map: HashMap<String, String> = HashMap::new(); // IP -> LOCATION URL
     let mut buf = [0u8; 4096];
-
     let collect_deadline = tokio::time::Instant::now() + timeout;
     loop {
         match tokio::time::timeout_at(collect_deadline, socket.recv_from(&mut buf)).await {
             Ok(Ok((len, addr))) => {
                 let response = String::from_utf8_lossy(&buf[..len]);
-                if let Some(location) = extract_header(&response, "LOCATION") {
-                    let ip = addr.ip().to_string();
-                    location_map.entry(ip).or_insert(location);
-                }
+                announcements.entry(addr.ip().to_string()).or_default().add(&response);
             }
             _ => break, // Timeout or error
         }
     }
 
-    // Fetch device descriptions from LOCATION URLs and extract friendly names
+    announcements
+}
+
+/// Collect `ssdp:alive` NOTIFY messages sent to the multicast group for
+/// `duration`. Always takes `duration`, even when the group cannot be joined,
+/// so callers can use it in place of a sleep.
+pub async fn listen_notify(duration: Duration) -> HashMap<String, Announcement> {
+    let mut announcements: HashMap<String, Announcement> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + duration;
+
+    let socket = match notify_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to join the SSDP multicast group: {}", e);
+            tokio::time::sleep_until(deadline).await;
+            return announcements;
+        }
+    };
+
+    let mut buf = [0u8; 4096];
+    while let Ok(Ok((len, addr))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let message = String::from_utf8_lossy(&buf[..len]);
+        let alive = message.starts_with("NOTIFY")
+            && extract_header(&message, "NTS").is_some_and(|nts| nts.eq_ignore_ascii_case("ssdp:alive"));
+        if alive {
+            announcements.entry(addr.ip().to_string()).or_default().add(&message);
+        }
+    }
+    // The deadline may still be ahead if the socket failed
+    tokio::time::sleep_until(deadline).await;
+
+    announcements
+}
+
+/// A socket on the SSDP port that receives the group's traffic. The port is
+/// shared with other UPnP software on the machine.
+fn notify_socket() -> std::io::Result<tokio::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SSDP_PORT).into())?;
+    socket.join_multicast_v4(&SSDP_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket.into())
+}
+
+/// Fetch the descriptions behind the announced LOCATION URLs
+pub async fn describe(announcements: HashMap<String, Announcement>) -> HashMap<String, SsdpDescription> {
+    let mut descriptions: HashMap<String, SsdpDescription> = HashMap::new();
+
     let mut tasks = Vec::new();
-    for (ip, location_url) in location_map {
-        tasks.push(tokio::spawn(async move {
-            match fetch_friendly_name(&location_url, Duration::from_secs(2)).await {
-                Some(name) => Some((ip, name)),
-                None => None,
-            }
-        }));
+    for (ip, announcement) in announcements {
+        // Kept even when no description can be fetched
+        if let Some(headers) = announcement.headers() {
+            descriptions.entry(ip.clone()).or_default().merge(headers);
+        }
+        for location_url in announcement.locations {
+            let ip = ip.clone();
+            tasks.push(tokio::spawn(async move {
+                fetch_description(&location_url, Duration::from_secs(2))
+                    .await
+                    .map(|description| (ip, description))
+            }));
+        }
     }
 
     for task in tasks {
-        if let Ok(Some((ip, name))) = task.await {
-            device_names.insert(ip, name);
+        if let Ok(Some((ip, description))) = task.await {
+            descriptions.entry(ip).or_default().merge(description);
         }
     }
 
-    device_names
+    descriptions
+}
+
+/// Descriptions from the announcement headers only, for strictly passive mode
+pub fn from_headers(announcements: &HashMap<String, Announcement>) -> HashMap<String, SsdpDescription> {
+    announcements
+        .iter()
+        .filter_map(|(ip, announcement)| Some((ip.clone(), announcement.headers()?)))
+        .collect()
+}
+
+/// Device type implied by the announced UPnP device types
+pub fn classify(description: &SsdpDescription) -> Option<DeviceType> {
+    let text = format!(
+        "{} {}",
+        description.friendly_name.as_deref().unwrap_or_default(),
+        description.model_name.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    let tv_receiver = ["samsung.com:device:remotecontrolreceiver", "dial-multiscreen-org:device:dial", "lge-com"]
+        .iter()
+        .any(|urn| description.announces(urn));
+
+    if description.announces("device:mediarenderer") && !tv_receiver {
+        // Soundbars, AV receivers and network speakers render media too
+        return Some(if text.contains("tv") {
+            DeviceType::SmartTv
+        } else {
+            DeviceType::SmartSpeaker
+        });
+    }
+    if tv_receiver {
+        return Some(DeviceType::SmartTv);
+    }
+    if description.is_internet_gateway() || description.announces("device:wfadevice") {
+        return Some(DeviceType::Router);
+    }
+    if description.announces("device:printer") || description.announces("device:scanner") {
+        return Some(DeviceType::Printer);
+    }
+    if description.announces("device:mediaserver") {
+        return Some(DeviceType::Nas);
+    }
+    // Thermostats, lights and plugs
+    let appliance = ["device:hvac", "device:binarylight", "device:dimmablelight", "belkin:device:"];
+    if appliance.iter().any(|urn| description.announces(urn)) {
+        return Some(DeviceType::SmartPlug);
+    }
+    None
 }
 
 /// Extract a header value from an HTTP response string (case-insensitive).
@@ -90,8 +305,8 @@ fn extract_header(response: &str, header_name: &str) -> Option<String> {
     None
 }
 
-/// Fetch device description XML from a LOCATION URL and extract <friendlyName>.
-async fn fetch_friendly_name(url: &str, timeout: Duration) -> Option<String> {
+/// Fetch a device description XML from a LOCATION URL
+async fn fetch_description(url: &str, timeout: Duration) -> Option<SsdpDescription> {
     let client = reqwest::Client::builder()
         .timeout(timeout)
         .build()
@@ -100,16 +315,25 @@ async fn fetch_friendly_name(url: &str, timeout: Duration) -> Option<String> {
     let response = client.get(url).send().await.ok()?;
     let body = response.text().await.ok()?;
 
-    extract_xml_element(&body, "friendlyName")
+    // The root device comes first; embedded devices follow in <deviceList>
+    Some(SsdpDescription {
+        friendly_name: extract_xml_elements(&body, "friendlyName").into_iter().next(),
+        manufacturer: extract_xml_elements(&body, "manufacturer").into_iter().next(),
+        model_name: extract_xml_elements(&body, "modelName").into_iter().next(),
+        device_types: extract_xml_elements(&body, "deviceType"),
+        server: None,
+        locations: vec![url.to_string()],
+    })
 }
 
-/// Extract the text content of an XML element using quick-xml.
-fn extract_xml_element(xml: &str, element_name: &str) -> Option<String> {
+/// Extract the text content of every occurrence of an XML element using quick-xml.
+pub(super) fn extract_xml_elements(xml: &str, element_name: &str) -> Vec<String> {
     use quick_xml::events::Event;
     use quick_xml::Reader;
 
     let mut reader = Reader::from_str(xml);
     let mut in_target = false;
+    let mut values = Vec::new();
 
     loop {
         match reader.read_event() {
@@ -123,7 +347,7 @@ fn extract_xml_element(xml: &str, element_name: &str) -> Option<String> {
                 if let Ok(text) = e.unescape() {
                     let text = text.trim().to_string();
                     if !text.is_empty() {
-                        return Some(text);
+                        values.push(text);
                     }
                 }
             }
@@ -136,5 +360,5 @@ fn extract_xml_element(xml: &str, element_name: &str) -> Option<String> {
         }
     }
 
-    None
+    values
 }
@@ -0,0 +1,191 @@
+//! Follow-ups for silent hosts
+//!
+//! Every device on the network has to answer ARP, but some answer nothing
+//! else: no names, no services, every common port filtered. Most are
+//! sleeping gadgets, but a device that hides is exactly what users worry
+//! about most. A wider TCP sample, an ICMP timestamp request and the MAC
+//! address itself often reveal a little more; whatever is learned, the
+//! device is reported so the user can decide whether they recognise it.
+
+use super::ports::{self, ProbeResult};
+use super::{fingerprint, Device, IssueSeverity, PortVerification, SecurityIssue};
+use crate::vulndb::content;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::{Duration, Instant};
+
+/// Ports outside the regular scan that silent devices are known to use
+pub(super) const SAMPLE_PORTS: &[u16] = &[
+    7, 37, 79, 110, 111, 135, 139, 143, 179, 445, 515, 548, 631, 1080, 1723, 2000, 2323, 3000,
+    3306, 3389, 4444, 5001, 5555, 5900, 6000, 6667, 7000, 7547, 8000, 8008, 8009, 8081, 8088,
+    8888, 9100, 9999, 10000, 32400, 49152, 62078,
+];
+
+/// How long to wait for an ICMP timestamp reply
+const TIMESTAMP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What the follow-ups learned about a silent host
+#[derive(Debug, Clone, Default)]
+pub(super) struct Findings {
+    /// Ports from the sample that turned out to be open
+    pub open_ports: Vec<u16>,
+    /// Some port was actively refused, so the TCP stack is alive
+    pub tcp_refused: bool,
+    /// `None` when raw sockets are not permitted
+    pub icmp_timestamp: Option<bool>,
+    pub vendor_hint: Option<&'static str>,
+}
+
+/// Probe devices that answered neither name queries nor the port scan.
+/// `quiet_ips` are the devices that announced no name or service during discovery.
+pub async fn investigate(devices: &mut [Device], quiet_ips: &[String]) {
+    for device in devices.iter_mut() {
+        if !device.open_ports.is_empty() || !quiet_ips.contains(&device.ip) {
+            continue;
+        }
+
+        let findings = follow_up(device).await;
+        device.open_ports.extend(
+            findings
+                .open_ports
+                .iter()
+                .map(|&port| ports::tcp_port(port, PortVerification::SingleAttempt)),
+        );
+        device.issues.push(to_issue(device, &findings));
+    }
+}
+
+async fn follow_up(device: &Device) -> Findings {
+    let mut findings = Findings {
+        vendor_hint: vendor_hint(&device.mac, device.vendor.as_deref()),
+        ..Findings::default()
+    };
+
+    let mut tasks = Vec::new();
+    for &port in SAMPLE_PORTS {
+        let ip = device.ip.clone();
+        tasks.push(tokio::spawn(async move {
+            (port, ports::probe_port(&ip, port, ports::CONNECT_TIMEOUT).await)
+        }));
+    }
+    for task in tasks {
+        match task.await {
+            Ok((port, ProbeResult::Open)) => findings.open_ports.push(port),
+            Ok((_, ProbeResult::Closed)) => findings.tcp_refused = true,
+            _ => {}
+        }
+    }
+
+    if let Ok(ip) = device.ip.parse::<Ipv4Addr>() {
+        findings.icmp_timestamp = tokio::task::spawn_blocking(move || icmp_timestamp(ip))
+            .await
+            .ok()
+            .flatten();
+    }
+
+    findings
+}
+
+/// Send an ICMP timestamp request (type 13); `None` when raw sockets are not permitted
+fn icmp_timestamp(ip: Ipv4Addr) -> Option<bool> {
+    let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).ok()?;
+    socket.set_read_timeout(Some(TIMESTAMP_TIMEOUT)).ok()?;
+
+    let id = (std::process::id() as u16).to_be_bytes();
+    let mut packet = [0u8; 20];
+    packet[0] = 13;
+    packet[4..6].copy_from_slice(&id);
+    packet[7] = 1;
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    socket.send_to(&packet, &SocketAddrV4::new(ip, 0).into()).ok()?;
+
+    let deadline = Instant::now() + TIMESTAMP_TIMEOUT;
+    let mut buf = [0u8; 512];
+    while Instant::now() < deadline {
+        let Ok(len) = (&socket).read(&mut buf) else {
+            return Some(false);
+        };
+        // Raw IPv4 sockets deliver the IP header too
+        let header_len = usize::from(buf[0] & 0x0f) * 4;
+        let from = Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]);
+        let Some(icmp) = buf.get(header_len..len) else {
+            continue;
+        };
+        // Timestamp reply (type 14) to our request
+        if from == ip && icmp.len() >= 8 && icmp[0] == 14 && icmp[4..6] == id {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+/// Whether raw ICMP sockets may be opened (root or CAP_NET_RAW)
+pub(super) fn raw_sockets_permitted() -> bool {
+    Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).is_ok()
+}
+
+/// Internet checksum (RFC 1071)
+pub(super) fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Guess what kind of device hides behind the MAC address
+/// (suffix of a `silent-device.hint-*` content pack phrase)
+pub(super) fn vendor_hint(mac: &str, vendor: Option<&str>) -> Option<&'static str> {
+    if fingerprint::is_randomized_mac(mac) {
+        return Some("randomized");
+    }
+
+    let Some(vendor) = vendor else {
+        return Some("unregistered");
+    };
+    let vendor = vendor.to_lowercase();
+    if ["espressif", "tuya", "realtek", "shenzhen", "hui zhou gaoshengda"].iter().any(|v| vendor.contains(v)) {
+        Some("wifi-module")
+    } else if vendor.contains("raspberry") {
+        Some("raspberry-pi")
+    } else if ["apple", "samsung", "google", "xiaomi", "huawei"].iter().any(|v| vendor.contains(v)) {
+        Some("phone")
+    } else {
+        None
+    }
+}
+
+pub(super) fn to_issue(device: &Device, findings: &Findings) -> SecurityIssue {
+    let tcp = if !findings.open_ports.is_empty() {
+        "@silent-device.open-ports"
+    } else if findings.tcp_refused {
+        "@silent-device.refused"
+    } else {
+        "@silent-device.ignored"
+    };
+    let icmp = match findings.icmp_timestamp {
+        Some(true) => "@silent-device.icmp-reply",
+        Some(false) => "@silent-device.icmp-silent",
+        None => "",
+    };
+    let hint = findings.vendor_hint.map(|key| format!("@silent-device.hint-{}", key)).unwrap_or_default();
+    let ports: Vec<String> = findings.open_ports.iter().map(u16::to_string).collect();
+
+    content::issue(
+        "silent-device",
+        IssueSeverity::Low,
+        &[
+            ("mac", device.mac.clone()),
+            ("vendor", device.vendor.clone().unwrap_or_else(|| "@unknown".to_string())),
+            ("tcp", tcp.to_string()),
+            ("icmp", icmp.to_string()),
+            ("hint", hint),
+            ("ports", ports.join(", ")),
+        ],
+    )
+}
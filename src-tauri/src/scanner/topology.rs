@@ -0,0 +1,102 @@
+//! Network topology analysis
+//!
+//! Detects double NAT (a second router between the home gateway and the
+//! internet) and carrier-grade NAT. In a double-NAT setup the device that
+//! actually faces the internet is the upstream (often ISP-supplied) router,
+//! which changes which device needs hardening.
+
+use super::platform;
+use super::portmap::PortMappingReport;
+use super::{IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Public address used as the target for hop discovery
+const PROBE_TARGET: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
+
+/// Result of the topology analysis
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TopologyReport {
+    pub gateway: Option<String>,
+    /// WAN address as reported by the gateway (NAT-PMP / UPnP)
+    pub wan_address: Option<String>,
+    /// Router one hop beyond the gateway
+    pub second_hop: Option<String>,
+    pub double_nat: bool,
+    pub carrier_grade_nat: bool,
+}
+
+/// 100.64.0.0/10 shared address space used by carrier-grade NAT
+pub(super) fn is_cgnat(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    octets[0] == 100 && (64..=127).contains(&octets[1])
+}
+
+/// Analyze topology from the gateway's reported WAN address and the second hop
+pub async fn analyze(gateway: Ipv4Addr, port_mapping: &PortMappingReport) -> TopologyReport {
+    let wan_address: Option<Ipv4Addr> = port_mapping
+        .external_address
+        .as_deref()
+        .and_then(|a| a.parse().ok());
+    let second_hop = find_hop(2).await;
+
+    let private_upstream = |ip: Ipv4Addr| ip.is_private() && ip != gateway;
+
+    let double_nat = wan_address.is_some_and(|ip| ip.is_private())
+        || second_hop.is_some_and(private_upstream);
+    let carrier_grade_nat = wan_address.is_some_and(is_cgnat) || second_hop.is_some_and(is_cgnat);
+
+    TopologyReport {
+        gateway: Some(gateway.to_string()),
+        wan_address: wan_address.map(|ip| ip.to_string()),
+        second_hop: second_hop.map(|ip| ip.to_string()),
+        double_nat,
+        carrier_grade_nat,
+    }
+}
+
+/// Find the router at the given hop count using a TTL-limited ping
+async fn find_hop(ttl: u8) -> Option<Ipv4Addr> {
+    let (program, args) = platform::current().ttl_ping_command(PROBE_TARGET, ttl);
+    let output = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    hop_address(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Router that reported the expired ping: the first address in the output
+/// other than the target. The wording differs per OS and language, e.g.
+/// "From 192.168.0.1 icmp_seq=1 Time to live exceeded" (Linux),
+/// "92 bytes from 192.168.0.1: Time to live exceeded" (macOS) or
+/// "Reply from 192.168.0.1: TTL expired in transit." (Windows).
+fn hop_address(output: &str) -> Option<Ipv4Addr> {
+    output
+        .split_whitespace()
+        .filter_map(|word| word.trim_matches(|c: char| !c.is_ascii_digit()).parse().ok())
+        .find(|&ip| ip != PROBE_TARGET)
+}
+
+/// Build an informational finding for double NAT / CGNAT
+pub fn to_issue(report: &TopologyReport) -> Option<SecurityIssue> {
+    if report.double_nat {
+        let upstream = report
+            .second_hop
+            .clone()
+            .or(report.wan_address.clone())
+            .unwrap_or_else(|| "@unknown".to_string());
+        return Some(content::issue("double-nat", IssueSeverity::Info, &[("upstream", upstream)]));
+    }
+
+    if report.carrier_grade_nat {
+        return Some(content::issue("carrier-grade-nat", IssueSeverity::Info, &[]));
+    }
+
+    None
+}
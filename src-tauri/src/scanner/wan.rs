@@ -0,0 +1,91 @@
+//! ISP and public IP lookup for the network profile (opt-in)
+//!
+//! Asking an outside service who we are reveals the public IP to it, so this
+//! only runs when the user enabled it. The public IP is stored coarsely;
+//! it is only needed to tell networks apart, not to locate anyone.
+
+use super::Device;
+use crate::database::network::NetworkProfile;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Returns the caller's public IP and the AS owning it
+const LOOKUP_URL: &str = "https://ipinfo.io/json";
+
+/// Network profile settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WanSettings {
+    /// Record ISP, public IP and router model for each network
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    ip: Option<String>,
+    /// e.g. `AS2516 KDDI CORPORATION`
+    org: Option<String>,
+}
+
+/// Mask the host part: /24 for IPv4, /48 for IPv6
+pub fn coarse_ip(ip: &str) -> Option<String> {
+    match ip.parse::<IpAddr>().ok()? {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            Some(format!("{}.{}.{}.x", a, b, c))
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            Some(format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2]))
+        }
+    }
+}
+
+/// Best description of the gateway's model from what the scan learned
+fn router_model(gateway: &Device) -> Option<String> {
+    if let Some(ref firmware) = gateway.firmware {
+        return Some(match firmware.version {
+            Some(ref version) => format!("{} {}", firmware.kind.label(), version),
+            None => firmware.kind.label().to_string(),
+        });
+    }
+    gateway.name.clone().or_else(|| gateway.vendor.clone())
+}
+
+/// Ask the lookup service who we are
+async fn lookup() -> Option<LookupResponse> {
+    // Not through the shared client: a cached answer may belong to another network
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().ok()?;
+    let body = client.get(LOOKUP_URL).send().await.ok()?.text().await.ok()?;
+    serde_json::from_str(&body).ok()
+}
+
+/// The network's public IP as the lookup service sees it. Callers must have
+/// the user's consent to contact an outside service.
+pub async fn public_ip() -> Option<String> {
+    lookup().await?.ip
+}
+
+/// Build the profile of the network `devices` were found on, or `None`
+/// when the lookup is disabled or the gateway is unknown
+pub async fn profile(devices: &[Device], settings: &WanSettings) -> Option<NetworkProfile> {
+    if !settings.enabled {
+        return None;
+    }
+    let network_id = crate::database::network_id(devices)?;
+    let gateway = devices.iter().find(|d| d.mac.to_lowercase() == network_id)?;
+
+    let (ip, org) = lookup().await.map(|l| (l.ip, l.org)).unwrap_or_default();
+
+    Some(NetworkProfile {
+        network_id,
+        isp: org.map(|org| match org.split_once(' ') {
+            Some((asn, name)) if asn.starts_with("AS") => name.to_string(),
+            _ => org,
+        }),
+        public_ip: ip.as_deref().and_then(coarse_ip),
+        router_model: router_model(gateway),
+        updated_at: Some(chrono::Utc::now()),
+    })
+}
@@ -0,0 +1,459 @@
+//! Wi-Fi security of the user's own access points
+//!
+//! The list of nearby networks the OS already keeps (`iw scan dump`, or
+//! NetworkManager when `iw` is missing) tells whether an access point is
+//! open, still uses WEP or first-generation WPA, or advertises WPS. macOS
+//! and Windows only report the network this machine is connected to, through
+//! `system_profiler` (CoreWLAN) and `netsh wlan` (the WLAN API). Only the
+//! access point this machine is associated with and those whose BSSID is
+//! close to the gateway's MAC address are reported, so the neighbours'
+//! networks never show up as the user's problem.
+
+use super::{Device, IssueSeverity, SecurityIssue};
+use crate::vulndb::content;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a tool may take to print the cached scan results
+const TOOL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest difference between the gateway MAC and a BSSID of the same router
+const MAX_BSSID_DISTANCE: u32 = 0x20;
+
+/// Encryption an access point advertises
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    Open,
+    Wep,
+    /// First-generation WPA, or WPA2 with TKIP only
+    Wpa,
+    Wpa2,
+    Wpa3,
+}
+
+/// The Wi-Fi network this machine is connected to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WifiConnection {
+    pub ssid: String,
+    /// Not reported by recent macOS
+    pub bssid: Option<String>,
+    pub security: Security,
+    /// `None` when the OS does not report WPS
+    pub wps: Option<bool>,
+    pub issues: Vec<SecurityIssue>,
+}
+
+/// An access point from the OS scan results
+#[derive(Debug, Clone)]
+pub(super) struct AccessPoint {
+    /// Empty when the OS does not report it
+    bssid: String,
+    ssid: String,
+    security: Security,
+    /// `None` when the tool does not report WPS
+    wps: Option<bool>,
+    /// This machine is associated with it
+    associated: bool,
+}
+
+/// Report weak Wi-Fi settings of the user's access points on the gateway.
+/// Returns whether they advertise WPS, `None` when no tool reported it.
+pub async fn check_gateway(devices: &mut [Device], excluded: &[String]) -> Option<bool> {
+    let gateway_ip = super::arp::get_default_gateway()?.to_string();
+    let gateway = devices
+        .iter_mut()
+        .find(|d| d.ip == gateway_ip && !excluded.iter().any(|m| m.eq_ignore_ascii_case(&d.mac)))?;
+
+    let access_points = scan_access_points().await;
+    let own: Vec<&AccessPoint> = access_points
+        .iter()
+        .filter(|ap| ap.associated || is_same_router(&ap.bssid, &gateway.mac))
+        .collect();
+    gateway.issues.extend(to_issues(&own));
+
+    let wps: Vec<bool> = own.iter().filter_map(|ap| ap.wps).collect();
+    (!wps.is_empty()).then(|| wps.contains(&true))
+}
+
+/// Encryption and WPS of the network this machine is connected to
+pub async fn current_connection() -> Option<WifiConnection> {
+    let ap = scan_access_points().await.into_iter().find(|ap| ap.associated)?;
+    Some(WifiConnection {
+        issues: to_issues(&[&ap]),
+        ssid: ap.ssid,
+        bssid: (!ap.bssid.is_empty()).then_some(ap.bssid),
+        security: ap.security,
+        wps: ap.wps,
+    })
+}
+
+/// Access points the OS knows of, from the tools of the running OS
+async fn scan_access_points() -> Vec<AccessPoint> {
+    if cfg!(target_os = "macos") {
+        let output = run("system_profiler", &["SPAirPortDataType"]).await;
+        output.map(|o| parse_system_profiler(&o)).unwrap_or_default()
+    } else if cfg!(target_os = "windows") {
+        let output = run("netsh", &["wlan", "show", "interfaces"]).await;
+        output.map(|o| parse_netsh(&o)).unwrap_or_default()
+    } else {
+        match iw_scan().await {
+            Some(aps) => aps,
+            None => nmcli_scan().await.unwrap_or_default(),
+        }
+    }
+}
+
+/// Whether a BSSID belongs to the router with the given MAC address.
+/// Routers derive their BSSIDs from the base MAC: the same vendor prefix
+/// (sometimes with the locally administered bit set) and a nearby suffix.
+pub(super) fn is_same_router(bssid: &str, gateway_mac: &str) -> bool {
+    let (Some(a), Some(b)) = (parse_mac(bssid), parse_mac(gateway_mac)) else {
+        return false;
+    };
+    let prefix = |mac: [u8; 6]| [mac[0] & !0x02, mac[1], mac[2]];
+    let suffix = |mac: [u8; 6]| u32::from_be_bytes([0, mac[3], mac[4], mac[5]]);
+
+    suffix(a) == suffix(b) || (prefix(a) == prefix(b) && suffix(a).abs_diff(suffix(b)) <= MAX_BSSID_DISTANCE)
+}
+
+fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let octets: Vec<u8> = mac
+        .split([':', '-'])
+        .map(|octet| u8::from_str_radix(octet, 16).ok())
+        .collect::<Option<_>>()?;
+    octets.try_into().ok()
+}
+
+/// One finding per SSID and problem, however many bands broadcast it
+pub(super) fn to_issues(access_points: &[&AccessPoint]) -> Vec<SecurityIssue> {
+    let mut reported = HashSet::new();
+    let mut issues = Vec::new();
+    for ap in access_points {
+        let ssid = if ap.ssid.is_empty() { ap.bssid.clone() } else { ap.ssid.clone() };
+        let bssid = if ap.bssid.is_empty() { "@wifi.unknown-bssid".to_string() } else { ap.bssid.clone() };
+        let problems = [
+            (ap.security == Security::Open, "wifi-open", IssueSeverity::High),
+            (ap.security == Security::Wep, "wifi-wep", IssueSeverity::High),
+            (ap.security == Security::Wpa, "wifi-wpa", IssueSeverity::Medium),
+            (ap.wps == Some(true), "wifi-wps", IssueSeverity::Medium),
+        ];
+        for (_, id, severity) in problems.into_iter().filter(|(found, ..)| *found) {
+            if reported.insert((id, ssid.clone())) {
+                issues.push(content::issue(id, severity, &[("ssid", ssid.clone()), ("bssid", bssid.clone())]));
+            }
+        }
+    }
+    issues
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(TOOL_TIMEOUT, child).await.ok()?.ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Whether a tool that lists nearby access points is installed; macOS and
+/// Windows always have theirs
+pub(super) async fn tool_available() -> bool {
+    cfg!(any(target_os = "macos", target_os = "windows"))
+        || run("iw", &["--version"]).await.is_some()
+        || run("nmcli", &["--version"]).await.is_some()
+}
+
+/// SSID this machine is connected to, if any
+pub async fn connected_ssid() -> Option<String> {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return current_connection().await.map(|c| c.ssid);
+    }
+    let interfaces = run("iw", &["dev"]).await?;
+    for line in interfaces.lines() {
+        let Some(interface) = line.trim().strip_prefix("Interface ") else {
+            continue;
+        };
+        // "Connected to 12:34:56:78:9a:bc (on wlan0)" followed by "\tSSID: MyNet"
+        let link = run("iw", &["dev", interface, "link"]).await.unwrap_or_default();
+        if let Some(ssid) = link.lines().find_map(|l| l.trim().strip_prefix("SSID:")) {
+            return Some(unescape_ssid(ssid.trim()));
+        }
+    }
+    None
+}
+
+/// Cached scan results of every wireless interface; `None` when `iw` is unavailable
+async fn iw_scan() -> Option<Vec<AccessPoint>> {
+    let interfaces = run("iw", &["dev"]).await?;
+    let mut access_points = Vec::new();
+    for line in interfaces.lines() {
+        if let Some(interface) = line.trim().strip_prefix("Interface ") {
+            if let Some(dump) = run("iw", &["dev", interface, "scan", "dump"]).await {
+                access_points.extend(parse_iw(&dump));
+            }
+        }
+    }
+    Some(access_points)
+}
+
+/// Parse `iw dev <if> scan dump`:
+///
+/// ```text
+/// BSS 12:34:56:78:9a:bc(on wlan0) -- associated
+///         capability: ESS Privacy ShortSlotTime (0x0411)
+///         SSID: MyNet
+///         RSN:     * Version: 1
+///                  * Pairwise ciphers: CCMP
+///                  * Authentication suites: PSK
+///         WPS:     * Version: 1.0
+/// ```
+pub(super) fn parse_iw(dump: &str) -> Vec<AccessPoint> {
+    struct Entry {
+        bssid: String,
+        ssid: String,
+        associated: bool,
+        privacy: bool,
+        /// First-generation WPA element
+        wpa: bool,
+        /// RSN (WPA2/WPA3) element
+        rsn: bool,
+        /// Lines of the RSN element follow
+        in_rsn: bool,
+        rsn_ciphers: String,
+        rsn_suites: String,
+        wps: bool,
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for line in dump.lines() {
+        if let Some(rest) = line.strip_prefix("BSS ") {
+            entries.push(Entry {
+                bssid: rest.chars().take(17).collect(),
+                ssid: String::new(),
+                associated: rest.contains("-- associated"),
+                privacy: false,
+                wpa: false,
+                rsn: false,
+                in_rsn: false,
+                rsn_ciphers: String::new(),
+                rsn_suites: String::new(),
+                wps: false,
+            });
+            continue;
+        }
+        let Some(entry) = entries.last_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if let Some(item) = line.strip_prefix('*') {
+            // An element's items are continuation lines starting with "*"
+            if entry.in_rsn {
+                let item = item.trim();
+                if let Some(ciphers) = item.strip_prefix("Pairwise ciphers:") {
+                    entry.rsn_ciphers = ciphers.to_string();
+                } else if let Some(suites) = item.strip_prefix("Authentication suites:") {
+                    entry.rsn_suites = suites.to_string();
+                }
+            }
+            continue;
+        }
+        entry.in_rsn = false;
+        if let Some(capability) = line.strip_prefix("capability:") {
+            entry.privacy = capability.split_whitespace().any(|flag| flag == "Privacy");
+        } else if let Some(ssid) = line.strip_prefix("SSID:") {
+            entry.ssid = unescape_ssid(ssid.trim());
+        } else if line.starts_with("RSN:") {
+            entry.rsn = true;
+            entry.in_rsn = true;
+        } else if line.starts_with("WPA:") {
+            entry.wpa = true;
+        } else if line.starts_with("WPS:") {
+            entry.wps = true;
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let modern_cipher = entry.rsn_ciphers.is_empty()
+                || entry.rsn_ciphers.split_whitespace().any(|c| c.starts_with("CCMP") || c.starts_with("GCMP"));
+            let security = match entry {
+                _ if entry.rsn && entry.rsn_suites.split_whitespace().any(|s| s.starts_with("SAE")) => Security::Wpa3,
+                _ if entry.rsn && modern_cipher => Security::Wpa2,
+                _ if entry.rsn || entry.wpa => Security::Wpa,
+                _ if entry.privacy => Security::Wep,
+                _ => Security::Open,
+            };
+            AccessPoint {
+                bssid: entry.bssid.to_lowercase(),
+                ssid: entry.ssid,
+                security,
+                wps: Some(entry.wps),
+                associated: entry.associated,
+            }
+        })
+        .collect()
+}
+
+/// `iw` prints non-ASCII SSID bytes as `\xNN`
+fn unescape_ssid(ssid: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = ssid.as_bytes();
+    while let Some((&first, tail)) = rest.split_first() {
+        let escaped = (first == b'\\' && tail.first() == Some(&b'x'))
+            .then(|| tail.get(1..3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                rest = &tail[3..];
+            }
+            None => {
+                bytes.push(first);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).trim_end_matches('\0').to_string()
+}
+
+/// Cached scan results from NetworkManager, which does not report WPS
+async fn nmcli_scan() -> Option<Vec<AccessPoint>> {
+    let output = run(
+        "nmcli",
+        &["-t", "-f", "IN-USE,BSSID,SSID,SECURITY", "device", "wifi", "list", "--rescan", "no"],
+    )
+    .await?;
+    Some(output.lines().filter_map(parse_nmcli_line).collect())
+}
+
+/// Parse a terse `nmcli` line, where colons inside values are escaped:
+/// `*:12\:34\:56\:78\:9A\:BC:MyNet:WPA2`
+fn parse_nmcli_line(line: &str) -> Option<AccessPoint> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut()?.extend(chars.next()),
+            ':' => fields.push(String::new()),
+            _ => fields.last_mut()?.push(c),
+        }
+    }
+    let [in_use, bssid, ssid, security] = <[String; 4]>::try_from(fields).ok()?;
+
+    Some(AccessPoint {
+        bssid: bssid.to_lowercase(),
+        ssid,
+        security: match security.as_str() {
+            "" | "--" => Security::Open,
+            s if s.contains("WEP") => Security::Wep,
+            // Mixed modes list each, e.g. "WPA1 WPA2"
+            s if s.contains("WPA3") => Security::Wpa3,
+            s if s.contains("WPA2") => Security::Wpa2,
+            _ => Security::Wpa,
+        },
+        wps: None,
+        associated: in_use == "*",
+    })
+}
+
+/// The connected network from `system_profiler SPAirPortDataType`, which
+/// leaves out the BSSID:
+///
+/// ```text
+///         Current Network Information:
+///           MyNet:
+///             PHY Mode: 802.11ax
+///             Security: WPA2 Personal
+///         Other Local Wi-Fi Networks:
+/// ```
+pub(super) fn parse_system_profiler(output: &str) -> Vec<AccessPoint> {
+    let mut lines = output.lines().skip_while(|line| line.trim() != "Current Network Information:").skip(1);
+    let Some(ssid) = lines.next().and_then(|line| line.trim().strip_suffix(':')) else {
+        return Vec::new();
+    };
+    let security = lines
+        .take_while(|line| !line.trim().ends_with(':'))
+        .find_map(|line| line.trim().strip_prefix("Security:"))
+        .map(str::trim);
+    let Some(security) = security else {
+        return Vec::new();
+    };
+
+    // "None", "WEP", "WPA Personal", "WPA/WPA2 Personal", "WPA2/WPA3 Personal", "WPA3 Enterprise"
+    let modes: Vec<&str> = security.split_whitespace().next().unwrap_or_default().split('/').collect();
+    let security = match security {
+        "None" => Security::Open,
+        s if s.starts_with("WEP") => Security::Wep,
+        _ if modes.contains(&"WPA3") => Security::Wpa3,
+        _ if modes.contains(&"WPA2") => Security::Wpa2,
+        _ => Security::Wpa,
+    };
+    vec![AccessPoint {
+        bssid: String::new(),
+        ssid: ssid.to_string(),
+        security,
+        wps: None,
+        associated: true,
+    }]
+}
+
+/// The connected networks from `netsh wlan show interfaces`. Labels are
+/// localized, so authentication and cipher are told apart by their values:
+///
+/// ```text
+///     SSID                   : MyNet
+///     BSSID                  : 12:34:56:78:9a:bc
+///     Authentication         : WPA2-Personal
+///     Cipher                 : CCMP
+/// ```
+pub(super) fn parse_netsh(output: &str) -> Vec<AccessPoint> {
+    let mut access_points = Vec::new();
+    // One block per wireless interface
+    for block in output.split("\n\n").flat_map(|b| b.split("\r\n\r\n")) {
+        let (mut ssid, mut bssid, mut authentication, mut cipher) = (None, String::new(), None, None);
+        for line in block.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            if key == "SSID" {
+                ssid = Some(value.to_string());
+            } else if key.ends_with("BSSID") {
+                bssid = value.to_lowercase();
+            } else if ["Open", "Shared"].contains(&value) || value.starts_with("WPA") {
+                authentication = Some(value.to_string());
+            } else if ["None", "WEP", "TKIP", "CCMP", "GCMP"].iter().any(|c| value.starts_with(c)) {
+                cipher = Some(value.to_string());
+            }
+        }
+        let (Some(ssid), Some(authentication)) = (ssid, authentication) else {
+            continue;
+        };
+        let cipher = cipher.unwrap_or_default();
+        let security = match authentication.as_str() {
+            "Open" if cipher == "WEP" => Security::Wep,
+            "Open" => Security::Open,
+            "Shared" => Security::Wep,
+            _ if cipher == "TKIP" => Security::Wpa,
+            a if a.starts_with("WPA3") => Security::Wpa3,
+            a if a.starts_with("WPA2") => Security::Wpa2,
+            _ => Security::Wpa,
+        };
+        access_points.push(AccessPoint {
+            bssid,
+            ssid,
+            security,
+            wps: None,
+            associated: true,
+        });
+    }
+    access_points
+}
@@ -0,0 +1,237 @@
+//! WS-Discovery (WSD) device discovery
+//!
+//! Sends WS-Discovery Probe messages to the multicast group on UDP 3702 and
+//! collects the ProbeMatch answers. Windows machines answer WSD with NetBIOS
+//! turned off, as do network printers and scanners, and ONVIF IP cameras
+//! announce themselves only this way. ONVIF scopes also carry the camera's
+//! name and hardware model.
+//!
+//! Devices joining the network multicast a Hello message, which is listened
+//! for in strictly passive mode.
+
+use super::{net, ssdp, DeviceType};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+
+const WSD_MULTICAST_ADDR: &str = "239.255.255.250:3702";
+
+const WSD_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+const WSD_PORT: u16 = 3702;
+
+/// Types probed for: everything, and ONVIF cameras, some of which only
+/// answer a probe naming their type
+const PROBE_TYPES: &[Option<&str>] = &[
+    None,
+    Some("<d:Types xmlns:dn=\"http://www.onvif.org/ver10/network/wsdl\">dn:NetworkVideoTransmitter</d:Types>"),
+];
+
+/// Scope prefix of ONVIF devices
+const ONVIF_SCOPE: &str = "onvif://www.onvif.org/";
+
+/// What a device said about itself in ProbeMatch and Hello messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WsdDevice {
+    /// Local names of the announced types, e.g. "Computer", "NetworkVideoTransmitter"
+    pub types: Vec<String>,
+    /// Scope URIs, e.g. "onvif://www.onvif.org/hardware/DS-2CD2042WD"
+    pub scopes: Vec<String>,
+    /// Where the device serves its metadata
+    pub xaddrs: Vec<String>,
+}
+
+impl WsdDevice {
+    fn merge(&mut self, other: WsdDevice) {
+        for (own, new) in [
+            (&mut self.types, other.types),
+            (&mut self.scopes, other.scopes),
+            (&mut self.xaddrs, other.xaddrs),
+        ] {
+            for value in new {
+                if !own.contains(&value) {
+                    own.push(value);
+                }
+            }
+        }
+    }
+
+    fn announces(&self, local_name: &str) -> bool {
+        self.types.iter().any(|t| t.eq_ignore_ascii_case(local_name))
+    }
+
+    /// Whether the device is an ONVIF camera or recorder
+    pub fn is_onvif(&self) -> bool {
+        self.announces("NetworkVideoTransmitter") || self.scopes.iter().any(|s| s.starts_with(ONVIF_SCOPE))
+    }
+
+    /// Device name from the ONVIF `name` scope
+    pub fn name(&self) -> Option<String> {
+        self.onvif_scope("name")
+    }
+
+    /// Hardware model from the ONVIF `hardware` scope
+    pub fn hardware(&self) -> Option<String> {
+        self.onvif_scope("hardware")
+    }
+
+    fn onvif_scope(&self, key: &str) -> Option<String> {
+        let prefix = format!("{}{}/", ONVIF_SCOPE, key);
+        self.scopes
+            .iter()
+            .find_map(|scope| scope.strip_prefix(&prefix))
+            .map(percent_decode)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Probe for WS-Discovery devices and collect the answers.
+/// Returns HashMap<IP address, merged announcements>.
+pub async fn scan_wsd(timeout: Duration) -> HashMap<String, WsdDevice> {
+    let mut devices: HashMap<String, WsdDevice> = HashMap::new();
+
+    let socket = match net::udp_bind(net::ANY).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to bind WS-Discovery socket: {}", e);
+            return devices;
+        }
+    };
+
+    for types in PROBE_TYPES {
+        if let Err(e) = socket.send_to(probe(*types).as_bytes(), WSD_MULTICAST_ADDR).await {
+            eprintln!("Failed to send WS-Discovery Probe: {}", e);
+            return devices;
+        }
+    }
+
+    let mut buf = [0u8; 8192];
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(Ok((len, addr))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let message = String::from_utf8_lossy(&buf[..len]);
+        if let Some(device) = parse_message(&message, "ProbeMatch") {
+            devices.entry(addr.ip().to_string()).or_default().merge(device);
+        }
+    }
+
+    devices
+}
+
+/// Collect Hello messages sent to the multicast group for `duration`.
+/// Always takes `duration`, even when the group cannot be joined.
+pub async fn listen(duration: Duration) -> HashMap<String, WsdDevice> {
+    let mut devices: HashMap<String, WsdDevice> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + duration;
+
+    let socket = match hello_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to join the WS-Discovery multicast group: {}", e);
+            tokio::time::sleep_until(deadline).await;
+            return devices;
+        }
+    };
+
+    let mut buf = [0u8; 8192];
+    while let Ok(Ok((len, addr))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let message = String::from_utf8_lossy(&buf[..len]);
+        if let Some(device) = parse_message(&message, "Hello") {
+            devices.entry(addr.ip().to_string()).or_default().merge(device);
+        }
+    }
+    tokio::time::sleep_until(deadline).await;
+
+    devices
+}
+
+/// A socket on the WS-Discovery port that receives the group's traffic. The
+/// port is shared with the OS's own discovery service.
+fn hello_socket() -> std::io::Result<tokio::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, WSD_PORT).into())?;
+    socket.join_multicast_v4(&WSD_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    tokio::net::UdpSocket::from_std(socket.into())
+}
+
+/// SOAP envelope of a Probe, for the given `<d:Types>` element or for every type
+fn probe(types: Option<&str>) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" \
+xmlns:a=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" \
+xmlns:d=\"http://schemas.xmlsoap.org/ws/2005/04/discovery\">\
+<s:Header>\
+<a:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</a:Action>\
+<a:MessageID>urn:uuid:{}</a:MessageID>\
+<a:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</a:To>\
+</s:Header>\
+<s:Body><d:Probe>{}</d:Probe></s:Body>\
+</s:Envelope>",
+        uuid::Uuid::new_v4(),
+        types.unwrap_or_default()
+    )
+}
+
+/// Types, scopes and addresses of a ProbeMatches or Hello message
+pub(super) fn parse_message(message: &str, body_element: &str) -> Option<WsdDevice> {
+    if !message.contains(body_element) {
+        return None;
+    }
+    let split = |values: Vec<String>| -> Vec<String> {
+        values.iter().flat_map(|v| v.split_whitespace()).map(str::to_string).collect()
+    };
+    let types = split(ssdp::extract_xml_elements(message, "Types"))
+        .into_iter()
+        // Prefixes differ between devices; the local name is what matters
+        .map(|t| t.rsplit(':').next().unwrap_or_default().to_string())
+        .collect();
+    let device = WsdDevice {
+        types,
+        scopes: split(ssdp::extract_xml_elements(message, "Scopes")),
+        xaddrs: split(ssdp::extract_xml_elements(message, "XAddrs")),
+    };
+    let empty = device.types.is_empty() && device.scopes.is_empty() && device.xaddrs.is_empty();
+    (!empty).then_some(device)
+}
+
+/// Device type implied by the announced types and scopes
+pub fn classify(device: &WsdDevice) -> Option<DeviceType> {
+    if device.is_onvif() {
+        return Some(DeviceType::Camera);
+    }
+    if device.announces("PrintDeviceType") || device.announces("ScanDeviceType") {
+        return Some(DeviceType::Printer);
+    }
+    if device.announces("Computer") {
+        return Some(DeviceType::Computer);
+    }
+    None
+}
+
+/// `%XX` escapes of a scope segment decoded; ONVIF names use them for spaces
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).trim().to_string()
+}
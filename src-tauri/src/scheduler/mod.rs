@@ -0,0 +1,196 @@
+//! Scheduled scans
+//!
+//! The user can have Level 1 or Level 2 scans run daily or weekly at a set
+//! local time, or each time the app starts. Schedules and when each last ran
+//! are kept in the database, so a scan missed while the app was closed runs
+//! once when it starts again. Results are saved like those of any other scan.
+//!
+//! The scheduler also delivers the periodic digest (see
+//! [`crate::report::digest`]) when the user enabled it.
+//!
+//! Scheduled scans are unattended: quiet hours apply (see
+//! [`crate::monitor::quiet`]), and a schedule they hold back runs once they
+//! end. Only a profile that may scan actively can save a Level 2 schedule;
+//! it then runs whichever profile is active.
+
+use crate::database::{self, DbError};
+use crate::health::{self, Subsystem};
+use crate::monitor::{newcomers, quiet};
+use crate::report::digest;
+use crate::scanner::{self, ScanLevel};
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::Emitter;
+
+/// Title of the digest notification
+const DIGEST_NOTIFICATION_TITLE: &str = "IoT Doctor: 定期レポート";
+
+/// How often due schedules are looked for
+const TICK: Duration = Duration::from_secs(60);
+
+/// Longest expected time between heartbeats, including a scheduled scan
+pub const HEARTBEAT_PERIOD: Duration = Duration::from_secs(15 * 60);
+
+/// Time for the network to come up before on-launch scans
+const LAUNCH_DELAY: Duration = Duration::from_secs(30);
+
+/// On-launch schedules ran; a restart by the watchdog is no launch
+static LAUNCHED: AtomicBool = AtomicBool::new(false);
+
+/// When a schedule runs
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    #[default]
+    Daily,
+    Weekly,
+    OnLaunch,
+}
+
+/// A recurring scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanSchedule {
+    /// Assigned when the schedule is first saved
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Level 1 or Level 2
+    pub level: ScanLevel,
+    pub frequency: Frequency,
+    /// Local time daily and weekly scans start at
+    pub time: NaiveTime,
+    /// Day weekly scans run on
+    pub weekday: Weekday,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Kept by the scheduler; ignored when saving
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+impl Default for ScanSchedule {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            enabled: true,
+            level: ScanLevel::Level1,
+            frequency: Frequency::Daily,
+            time: NaiveTime::from_hms_opt(3, 0, 0).unwrap_or_default(),
+            weekday: Weekday::Sun,
+            created_at: None,
+            last_run: None,
+        }
+    }
+}
+
+impl ScanSchedule {
+    /// The latest time the schedule was meant to start at, up to `now`;
+    /// `None` for on-launch schedules
+    pub fn latest_occurrence(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let (days_back, period) = match self.frequency {
+            Frequency::Daily => (0, 1),
+            Frequency::Weekly => {
+                let today = now.weekday().num_days_from_monday();
+                (i64::from((today + 7 - self.weekday.num_days_from_monday()) % 7), 7)
+            }
+            Frequency::OnLaunch => return None,
+        };
+        let day = now.date_naive() - chrono::Duration::days(days_back);
+        // A start time skipped by a DST change counts as not reached
+        let occurrence = day.and_time(self.time).and_local_timezone(Local).earliest()?;
+        if occurrence <= now {
+            Some(occurrence)
+        } else {
+            let day = day - chrono::Duration::days(period);
+            day.and_time(self.time).and_local_timezone(Local).earliest()
+        }
+    }
+
+    /// Whether the schedule has not run since it was last meant to
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        let since = self.last_run.or(self.created_at);
+        match (self.latest_occurrence(now), since) {
+            (Some(occurrence), Some(since)) => since < occurrence,
+            _ => false,
+        }
+    }
+}
+
+/// Saved schedules
+pub fn list() -> Result<Vec<ScanSchedule>, DbError> {
+    database::schedules::list()
+}
+
+/// Run due schedules in the background
+pub fn start(app: tauri::AppHandle) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        if !LAUNCHED.swap(true, Ordering::SeqCst) {
+            tokio::time::sleep(LAUNCH_DELAY).await;
+            let on_launch: Vec<ScanSchedule> = list()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|s| s.enabled && s.frequency == Frequency::OnLaunch)
+                .collect();
+            run(&app, &on_launch).await;
+        }
+        loop {
+            health::beat(Subsystem::Scheduler);
+            let now = Local::now();
+            let due: Vec<ScanSchedule> = list().unwrap_or_default().into_iter().filter(|s| s.enabled && s.is_due(now)).collect();
+            run(&app, &due).await;
+            deliver_digest(&app).await;
+            tokio::time::sleep(TICK).await;
+        }
+    })
+}
+
+/// Run one scan for the schedules that are due together, at the highest of their levels
+async fn run(app: &tauri::AppHandle, due: &[ScanSchedule]) {
+    // Schedules quiet hours hold back stay due and run once they end
+    let Some(allowed) = quiet::allowed_level(ScanLevel::Level2) else {
+        return;
+    };
+    let due: Vec<&ScanSchedule> = due.iter().filter(|s| s.level.min(ScanLevel::Level2) <= allowed).collect();
+    let Some(level) = due.iter().map(|s| s.level.min(ScanLevel::Level2)).max() else {
+        return;
+    };
+    // Tried again on the next tick
+    if scanner::is_scan_running() {
+        return;
+    }
+    // Marked first, so a scan that fails is not retried every minute
+    let started = Utc::now();
+    for schedule in &due {
+        if let Err(e) = database::schedules::mark_run(&schedule.id, started) {
+            eprintln!("Failed to record scheduled scan: {}", e);
+        }
+    }
+    let _ = app.emit("scheduled-scan", &due);
+    // The level was authorized when the schedule was saved
+    if let Err(e) = crate::run_scan(app.clone(), level, None, None, None, true).await {
+        eprintln!("Scheduled scan failed: {}", e);
+    }
+}
+
+/// Write the digest when a period ended and tell the user where it is
+async fn deliver_digest(app: &tauri::AppHandle) {
+    let settings = crate::settings::load().unwrap_or_default();
+    let delivered = match digest::deliver_due(&settings.digest, &settings.locale) {
+        Ok(Some(delivered)) => delivered,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to deliver digest: {}", e);
+            return;
+        }
+    };
+    let _ = app.emit("digest-ready", &delivered);
+    if settings.digest.notify {
+        let body = format!("{}\n{}", digest::summary(&delivered.digest, &settings.locale), delivered.path.display());
+        if let Err(e) = newcomers::show_notification(DIGEST_NOTIFICATION_TITLE, &body).await {
+            eprintln!("Failed to show notification: {}", e);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Credential storage in the OS keychain
+//!
+//! API keys and passwords for integrations are never written to the settings
+//! table; they live in the platform keychain (Keychain, Credential Manager,
+//! kernel keyring) and are redacted from anything we log.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Keychain service name
+const SERVICE: &str = "iot-doctor";
+
+/// Secret values seen in this session, used for log redaction
+static KNOWN_SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Secrets errors
+#[derive(Error, Debug)]
+pub enum SecretsError {
+    #[error("Keychain error: {0}")]
+    Keychain(#[from] keyring::Error),
+}
+
+/// Credentials the app knows how to store
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretKind {
+    ShodanApiKey,
+    NvdApiKey,
+    SmtpPassword,
+    MqttPassword,
+    RouterPassword,
+}
+
+impl SecretKind {
+    fn account(&self) -> &'static str {
+        match self {
+            Self::ShodanApiKey => "shodan_api_key",
+            Self::NvdApiKey => "nvd_api_key",
+            Self::SmtpPassword => "smtp_password",
+            Self::MqttPassword => "mqtt_password",
+            Self::RouterPassword => "router_password",
+        }
+    }
+}
+
+fn entry(kind: SecretKind) -> Result<keyring::Entry, SecretsError> {
+    Ok(keyring::Entry::new(SERVICE, kind.account())?)
+}
+
+/// Account for a secret belonging to one paired remote probe
+fn probe_account(probe_id: &str) -> String {
+    format!("probe_key:{}", probe_id)
+}
+
+fn remember(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let mut known = KNOWN_SECRETS.lock().unwrap();
+    if !known.iter().any(|k| k == value) {
+        known.push(value.to_string());
+    }
+}
+
+/// Store a secret in the keychain
+pub fn set_secret(kind: SecretKind, value: &str) -> Result<(), SecretsError> {
+    entry(kind)?.set_password(value)?;
+    remember(value);
+    Ok(())
+}
+
+/// Read a secret from the keychain, if one is stored
+pub fn get_secret(kind: SecretKind) -> Result<Option<String>, SecretsError> {
+    match entry(kind)?.get_password() {
+        Ok(value) => {
+            remember(&value);
+            Ok(Some(value))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check whether a secret is stored without exposing it
+pub fn has_secret(kind: SecretKind) -> Result<bool, SecretsError> {
+    Ok(get_secret(kind)?.is_some())
+}
+
+/// Remove a secret from the keychain; removing a missing secret is not an error
+pub fn delete_secret(kind: SecretKind) -> Result<(), SecretsError> {
+    match entry(kind)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Store the pairing key of a remote probe
+pub fn set_probe_key(probe_id: &str, key: &str) -> Result<(), SecretsError> {
+    keyring::Entry::new(SERVICE, &probe_account(probe_id))?.set_password(key)?;
+    remember(key);
+    Ok(())
+}
+
+/// Read the pairing key of a remote probe
+pub fn get_probe_key(probe_id: &str) -> Result<Option<String>, SecretsError> {
+    match keyring::Entry::new(SERVICE, &probe_account(probe_id))?.get_password() {
+        Ok(value) => {
+            remember(&value);
+            Ok(Some(value))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove the pairing key of a remote probe
+pub fn delete_probe_key(probe_id: &str) -> Result<(), SecretsError> {
+    match keyring::Entry::new(SERVICE, &probe_account(probe_id))?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace every known secret value in `text` with a placeholder
+pub fn redact(text: &str) -> String {
+    let known = KNOWN_SECRETS.lock().unwrap();
+    known
+        .iter()
+        .fold(text.to_string(), |acc, secret| acc.replace(secret.as_str(), "[REDACTED]"))
+}
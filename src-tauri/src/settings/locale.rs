@@ -0,0 +1,115 @@
+//! Locale and timezone-aware date formatting
+//!
+//! Reports and scan history format timestamps through a `DateFormatter`
+//! built from the user's settings, so exports are correct for users outside
+//! Japan or running the app on a machine set to a different timezone.
+//!
+//! The language of finding text (see `vulndb::content`) is chosen here too.
+
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Date format style
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateLocale {
+    /// 2024年01月31日 13:45:00
+    #[default]
+    Ja,
+    /// Jan 31, 2024 13:45:00
+    En,
+    /// 2024-01-31 13:45:00
+    Iso,
+}
+
+/// Language of finding titles, descriptions and remediations
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    Ja,
+    En,
+}
+
+/// Locale and timezone settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocaleSettings {
+    pub locale: DateLocale,
+    /// IANA timezone name (e.g. "Asia/Tokyo"); the system timezone when unset
+    pub timezone: Option<String>,
+    /// Language of findings in the app and in reports
+    pub language: Language,
+}
+
+impl LocaleSettings {
+    /// Check that the configured timezone is known
+    pub fn validate(&self) -> Result<(), String> {
+        match self.timezone.as_deref() {
+            Some(name) if name.parse::<chrono_tz::Tz>().is_err() => {
+                Err(format!("Unknown timezone: {}", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn formatter(&self) -> DateFormatter {
+        DateFormatter {
+            locale: self.locale,
+            timezone: self.timezone.as_deref().and_then(|name| name.parse().ok()),
+        }
+    }
+}
+
+/// Formats UTC timestamps for display
+#[derive(Debug, Clone, Default)]
+pub struct DateFormatter {
+    locale: DateLocale,
+    /// `None` uses the system timezone
+    timezone: Option<chrono_tz::Tz>,
+}
+
+impl DateFormatter {
+    fn localize(&self, dt: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.timezone {
+            Some(tz) => {
+                let local = dt.with_timezone(&tz);
+                local.with_timezone(&local.offset().fix())
+            }
+            None => dt.with_timezone(&chrono::Local).into(),
+        }
+    }
+
+    fn format(&self, dt: DateTime<Utc>, pattern: &str) -> String {
+        self.localize(dt).format(pattern).to_string()
+    }
+
+    /// Calendar date
+    pub fn date(&self, dt: DateTime<Utc>) -> String {
+        let pattern = match self.locale {
+            DateLocale::Ja => "%Y年%m月%d日",
+            DateLocale::En => "%b %-d, %Y",
+            DateLocale::Iso => "%Y-%m-%d",
+        };
+        self.format(dt, pattern)
+    }
+
+    /// Date and time; non-Japanese formats include the UTC offset
+    pub fn datetime(&self, dt: DateTime<Utc>) -> String {
+        let pattern = match self.locale {
+            DateLocale::Ja => "%Y年%m月%d日 %H:%M:%S",
+            DateLocale::En => "%b %-d, %Y %H:%M:%S (UTC%:z)",
+            DateLocale::Iso => "%Y-%m-%d %H:%M:%S%:z",
+        };
+        self.format(dt, pattern)
+    }
+
+    /// Compact month/day and time for tables
+    pub fn short(&self, dt: DateTime<Utc>) -> String {
+        let pattern = match self.locale {
+            DateLocale::Ja | DateLocale::En => "%m/%d %H:%M",
+            DateLocale::Iso => "%m-%d %H:%M",
+        };
+        self.format(dt, pattern)
+    }
+}
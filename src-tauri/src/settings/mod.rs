@@ -0,0 +1,76 @@
+//! Application settings persisted in the local database
+
+use crate::control::ControlSettings;
+use crate::database::presence::PresenceSettings;
+use crate::database::{self, DbError};
+use crate::monitor::quiet::QuietHours;
+use crate::monitor::MonitorSettings;
+use crate::probe::RemoteProbe;
+use crate::report::digest::DigestSettings;
+use crate::report::Branding;
+use crate::scanner::budget::BudgetSettings;
+use crate::scanner::exposure::ExposureSettings;
+use crate::scanner::interfaces::SweepSettings;
+use crate::scanner::passive::SafeModeSettings;
+use crate::scanner::router::RouterSettings;
+use crate::scanner::wan::WanSettings;
+use crate::scanner::DeploymentContext;
+use serde::{Deserialize, Serialize};
+
+pub mod locale;
+pub mod profiles;
+
+use locale::LocaleSettings;
+
+/// Key under which the settings document is stored
+const SETTINGS_KEY: &str = "app";
+
+/// User-configurable application settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Branding rendered into generated reports
+    pub branding: Branding,
+    /// Environment the network is used in; adjusts issue severities
+    pub context: DeploymentContext,
+    /// Opt-in external exposure checks
+    pub exposure: ExposureSettings,
+    /// Alerts for devices online at unusual times
+    pub presence: PresenceSettings,
+    /// Date format and timezone for reports and history
+    pub locale: LocaleSettings,
+    /// Local automation socket / named pipe
+    pub control: ControlSettings,
+    /// Router API used to fetch the authoritative client list
+    pub router: RouterSettings,
+    /// Paired remote probe agents
+    pub probes: Vec<RemoteProbe>,
+    /// Background checks between full scans
+    pub monitor: MonitorSettings,
+    /// Opt-in ISP / public IP lookup for network profiles
+    pub wan: WanSettings,
+    /// When unattended scans and monitoring are held back
+    pub quiet_hours: QuietHours,
+    /// Time limit for full scans
+    pub scan_budget: BudgetSettings,
+    /// Size limit of the subnet sweep
+    pub sweep: SweepSettings,
+    /// Strictly passive mode
+    pub safe_mode: SafeModeSettings,
+    /// Scheduled digest reports
+    pub digest: DigestSettings,
+}
+
+/// Load settings, falling back to defaults when none are stored
+pub fn load() -> Result<Settings, DbError> {
+    match database::get_setting(SETTINGS_KEY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Settings::default()),
+    }
+}
+
+/// Persist settings
+pub fn save(settings: &Settings) -> Result<(), DbError> {
+    let json = serde_json::to_string(settings)?;
+    database::set_setting(SETTINGS_KEY, &json)
+}
@@ -0,0 +1,231 @@
+//! Operator profiles
+//!
+//! A household can set up an administrator profile (e.g. a parent) and
+//! read-only viewer profiles (e.g. children). Viewers can look at history
+//! and reports but cannot run active scans or change settings. Commands check
+//! the active profile with [`require`]; with no profiles configured, nothing
+//! is restricted.
+//!
+//! Administrator profiles need a PIN so a viewer cannot simply switch to
+//! them. The app starts in the least privileged profile.
+
+use crate::database::{self, DbError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::sync::RwLock;
+use thiserror::Error;
+
+/// Key under which profiles are stored
+const PROFILES_KEY: &str = "profiles";
+
+/// PBKDF2 iterations for PIN hashes
+const PIN_ITERATIONS: u32 = 100_000;
+
+/// Currently active profile ID
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Profile errors
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("The {0} profile is not allowed to do this")]
+    NotPermitted(String),
+
+    #[error("Wrong PIN")]
+    WrongPin,
+
+    #[error("Unknown profile: {0}")]
+    UnknownProfile(String),
+
+    #[error("Administrator profiles need a PIN")]
+    PinRequired,
+
+    #[error("Create an administrator profile first")]
+    AdminRequired,
+
+    #[error("No secure random source")]
+    Random,
+
+    #[error("Database error: {0}")]
+    Database(#[from] DbError),
+}
+
+/// What a profile may do
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// History and reports only
+    Viewer,
+    /// Everything
+    Admin,
+}
+
+/// Actions restricted to administrators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Level 2/3 scans and anything else that probes devices
+    ActiveScan,
+    /// Settings, credentials, profiles, device metadata and imports
+    ChangeSettings,
+}
+
+impl Role {
+    pub fn allows(&self, permission: Permission) -> bool {
+        match (self, permission) {
+            (Self::Admin, _) => true,
+            (Self::Viewer, Permission::ActiveScan | Permission::ChangeSettings) => false,
+        }
+    }
+}
+
+/// A profile as shown to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorProfile {
+    pub id: String,
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredProfile {
+    #[serde(flatten)]
+    profile: OperatorProfile,
+    /// Base64 salt and PBKDF2-HMAC-SHA256 hash of the PIN
+    pin_salt: Option<String>,
+    pin_hash: Option<String>,
+}
+
+fn load() -> Result<Vec<StoredProfile>, DbError> {
+    match database::get_setting(PROFILES_KEY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save(profiles: &[StoredProfile]) -> Result<(), DbError> {
+    database::set_setting(PROFILES_KEY, &serde_json::to_string(profiles)?)
+}
+
+fn iterations() -> NonZeroU32 {
+    NonZeroU32::new(PIN_ITERATIONS).unwrap_or(NonZeroU32::MIN)
+}
+
+fn hash_pin(pin: &str) -> Result<(String, String), ProfileError> {
+    let mut salt = [0u8; 16];
+    SystemRandom::new().fill(&mut salt).map_err(|_| ProfileError::Random)?;
+    let mut hash = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations(), &salt, pin.as_bytes(), &mut hash);
+    Ok((BASE64.encode(salt), BASE64.encode(hash)))
+}
+
+fn pin_matches(stored: &StoredProfile, pin: Option<&str>) -> bool {
+    let (Some(salt), Some(hash)) = (&stored.pin_salt, &stored.pin_hash) else {
+        // Profiles without a PIN are open to everyone
+        return true;
+    };
+    let (Ok(salt), Ok(hash)) = (BASE64.decode(salt), BASE64.decode(hash)) else {
+        return false;
+    };
+    let pin = pin.unwrap_or_default();
+    pbkdf2::verify(pbkdf2::PBKDF2_HMAC_SHA256, iterations(), &salt, pin.as_bytes(), &hash).is_ok()
+}
+
+/// Start in the least privileged profile
+pub fn init() -> Result<(), DbError> {
+    let first = load()?.into_iter().min_by_key(|p| p.profile.role);
+    *ACTIVE.write().unwrap() = first.map(|p| p.profile.id);
+    Ok(())
+}
+
+/// All profiles
+pub fn list() -> Result<Vec<OperatorProfile>, DbError> {
+    Ok(load()?.into_iter().map(|p| p.profile).collect())
+}
+
+/// The active profile; `None` when no profiles are configured
+pub fn active() -> Result<Option<OperatorProfile>, DbError> {
+    let Some(id) = ACTIVE.read().unwrap().clone() else {
+        return Ok(None);
+    };
+    Ok(load()?.into_iter().map(|p| p.profile).find(|p| p.id == id))
+}
+
+/// Fail unless the active profile may perform `permission`
+pub fn require(permission: Permission) -> Result<(), ProfileError> {
+    match active()? {
+        Some(profile) if !profile.role.allows(permission) => Err(ProfileError::NotPermitted(profile.name)),
+        _ => Ok(()),
+    }
+}
+
+/// Add a profile. The first profile must be an administrator, and
+/// administrators need a PIN.
+pub fn create(name: &str, role: Role, pin: Option<&str>) -> Result<OperatorProfile, ProfileError> {
+    let mut profiles = load()?;
+    if !profiles.is_empty() {
+        require(Permission::ChangeSettings)?;
+    }
+    let pin = pin.filter(|p| !p.is_empty());
+    if role == Role::Admin && pin.is_none() {
+        return Err(ProfileError::PinRequired);
+    }
+    if role == Role::Viewer && !profiles.iter().any(|p| p.profile.role == Role::Admin) {
+        return Err(ProfileError::AdminRequired);
+    }
+
+    let (pin_salt, pin_hash) = match pin {
+        Some(pin) => hash_pin(pin).map(|(salt, hash)| (Some(salt), Some(hash)))?,
+        None => (None, None),
+    };
+    let profile = OperatorProfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        role,
+    };
+    profiles.push(StoredProfile {
+        profile: profile.clone(),
+        pin_salt,
+        pin_hash,
+    });
+    save(&profiles)?;
+
+    // Whoever set up the first profile keeps working as it
+    let mut active = ACTIVE.write().unwrap();
+    if active.is_none() {
+        *active = Some(profile.id.clone());
+    }
+    Ok(profile)
+}
+
+/// Remove a profile; the last administrator can only go with every viewer
+pub fn delete(id: &str) -> Result<(), ProfileError> {
+    require(Permission::ChangeSettings)?;
+    let mut profiles = load()?;
+    profiles.retain(|p| p.profile.id != id);
+    if !profiles.iter().any(|p| p.profile.role == Role::Admin) {
+        profiles.clear();
+    }
+    save(&profiles)?;
+
+    let mut active = ACTIVE.write().unwrap();
+    if active.as_deref() == Some(id) || profiles.is_empty() {
+        *active = profiles.first().map(|p| p.profile.id.clone());
+    }
+    Ok(())
+}
+
+/// Switch to another profile, checking its PIN
+pub fn switch(id: &str, pin: Option<&str>) -> Result<OperatorProfile, ProfileError> {
+    let stored = load()?
+        .into_iter()
+        .find(|p| p.profile.id == id)
+        .ok_or_else(|| ProfileError::UnknownProfile(id.to_string()))?;
+    if !pin_matches(&stored, pin) {
+        return Err(ProfileError::WrongPin);
+    }
+    *ACTIVE.write().unwrap() = Some(stored.profile.id.clone());
+    Ok(stored.profile)
+}
@@ -0,0 +1,161 @@
+//! Simulated network for end-to-end scanner tests (Linux, `netns-tests` feature)
+//!
+//! Each fake device gets its own network namespace, joined to a host-side
+//! bridge by a veth pair, so it has its own MAC address and answers ARP like
+//! real hardware. Dummy responders (HTTP, Telnet, SSDP) run inside the
+//! namespaces. While a [`SimNetwork`] is alive the scanner's seams (see
+//! `scanner::net`) point at the bridge and jitter is disabled.
+//!
+//! Creating namespaces needs root or `CAP_NET_ADMIN`. Interface names are
+//! fixed, so tests using the harness must not run in parallel:
+//!
+//! ```text
+//! sudo -E cargo test --features netns-tests -- --test-threads=1
+//! ```
+
+use crate::scanner::net;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, UdpSocket};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod responders;
+
+pub use responders::FakeService;
+
+/// Bridge the fake devices are attached to
+pub const BRIDGE: &str = "iotd-br0";
+
+/// Address of the host on the bridge; devices get `.10` onwards
+pub const HOST_IP: Ipv4Addr = Ipv4Addr::new(10, 77, 0, 1);
+
+/// A device to simulate
+#[derive(Debug, Clone)]
+pub struct FakeDevice {
+    pub mac: String,
+    pub services: Vec<FakeService>,
+}
+
+impl FakeDevice {
+    pub fn new(mac: &str) -> Self {
+        Self {
+            mac: mac.to_string(),
+            services: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, service: FakeService) -> Self {
+        self.services.push(service);
+        self
+    }
+}
+
+/// A running fake device
+#[derive(Debug, Clone)]
+pub struct SimDevice {
+    pub ip: Ipv4Addr,
+    pub mac: String,
+    pub namespace: String,
+}
+
+/// The simulated network; torn down on drop
+pub struct SimNetwork {
+    devices: Vec<SimDevice>,
+}
+
+/// Sends multicast out of the bridge rather than the default route
+struct BridgeSockets;
+
+impl net::SocketFactory for BridgeSockets {
+    fn tcp_connect(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        TcpStream::connect_timeout(addr, timeout)
+    }
+
+    fn udp_bind(&self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        let socket = UdpSocket::bind(addr)?;
+        socket2::SockRef::from(&socket).set_multicast_if_v4(&HOST_IP)?;
+        Ok(socket)
+    }
+}
+
+fn ip(args: &[&str]) -> io::Result<()> {
+    let status = Command::new("ip").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, format!("ip {} failed", args.join(" "))))
+    }
+}
+
+impl SimNetwork {
+    /// Create the bridge and one namespace per device, start the responders
+    /// and point the scanner at the bridge
+    pub fn start(devices: Vec<FakeDevice>) -> io::Result<Self> {
+        // Leftovers of a run that did not clean up
+        teardown(devices.len());
+
+        ip(&["link", "add", BRIDGE, "type", "bridge"])?;
+        let mut network = Self { devices: Vec::new() };
+        ip(&["addr", "add", &format!("{}/24", HOST_IP), "dev", BRIDGE])?;
+        ip(&["link", "set", BRIDGE, "up"])?;
+
+        for (i, device) in devices.into_iter().enumerate() {
+            let namespace = format!("iotd-dev{}", i);
+            let host_end = format!("iotd-h{}", i);
+            let device_end = format!("iotd-d{}", i);
+            let addr = Ipv4Addr::new(10, 77, 0, 10 + i as u8);
+
+            ip(&["netns", "add", &namespace])?;
+            ip(&["link", "add", &host_end, "type", "veth", "peer", "name", &device_end])?;
+            ip(&["link", "set", &host_end, "master", BRIDGE, "up"])?;
+            ip(&["link", "set", &device_end, "netns", &namespace])?;
+            ip(&["-n", &namespace, "link", "set", &device_end, "address", &device.mac])?;
+            ip(&["-n", &namespace, "addr", "add", &format!("{}/24", addr), "dev", &device_end])?;
+            ip(&["-n", &namespace, "link", "set", &device_end, "up"])?;
+            ip(&["-n", &namespace, "link", "set", "lo", "up"])?;
+
+            responders::spawn(&namespace, addr, &device.services)?;
+            network.devices.push(SimDevice {
+                ip: addr,
+                mac: device.mac.to_lowercase(),
+                namespace,
+            });
+        }
+
+        net::set_interface(Some(BRIDGE.to_string()));
+        net::set_socket_factory(Some(Arc::new(BridgeSockets)));
+        net::set_deterministic(true);
+        Ok(network)
+    }
+
+    pub fn devices(&self) -> &[SimDevice] {
+        &self.devices
+    }
+
+    /// Address of a device on the bridge
+    pub fn addr(&self, index: usize, port: u16) -> SocketAddrV4 {
+        SocketAddrV4::new(self.devices[index].ip, port)
+    }
+}
+
+impl Drop for SimNetwork {
+    fn drop(&mut self) {
+        net::set_interface(None);
+        net::set_socket_factory(None);
+        net::set_deterministic(false);
+        teardown(self.devices.len());
+    }
+}
+
+/// Remove the bridge and device namespaces, ignoring what does not exist
+fn teardown(count: usize) {
+    let quietly = |args: &[&str]| {
+        let _ = Command::new("ip").args(args).stderr(Stdio::null()).status();
+    };
+    for i in 0..count {
+        quietly(&["link", "del", &format!("iotd-h{}", i)]);
+        quietly(&["netns", "del", &format!("iotd-dev{}", i)]);
+    }
+    quietly(&["link", "del", BRIDGE]);
+}
@@ -0,0 +1,145 @@
+//! Dummy services run inside a fake device's namespace
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, UdpSocket};
+use std::os::fd::AsRawFd;
+use std::sync::mpsc;
+
+const SSDP_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+/// A service a fake device offers
+#[derive(Debug, Clone)]
+pub enum FakeService {
+    /// Web interface answering every request with `body`
+    Http { port: u16, server: String, body: String },
+    /// Sends `banner` and a login prompt, then hangs up
+    Telnet { port: u16, banner: String },
+    /// Answers M-SEARCH and serves a device description on `http_port`
+    Ssdp { http_port: u16, friendly_name: String },
+}
+
+impl FakeService {
+    pub fn http(port: u16, server: &str) -> Self {
+        Self::Http {
+            port,
+            server: server.to_string(),
+            body: "<html><title>Login</title></html>".to_string(),
+        }
+    }
+
+    pub fn telnet(port: u16, banner: &str) -> Self {
+        Self::Telnet {
+            port,
+            banner: banner.to_string(),
+        }
+    }
+
+    pub fn ssdp(friendly_name: &str) -> Self {
+        Self::Ssdp {
+            http_port: 49152,
+            friendly_name: friendly_name.to_string(),
+        }
+    }
+}
+
+/// Join the network namespace mounted at `path` (this thread only)
+fn enter(path: &str) -> io::Result<()> {
+    let file = File::open(path)?;
+    // SAFETY: setns only reads the descriptor, which stays open for the call
+    if unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Bind the services inside the namespace and serve them on background
+/// threads. Returns once every socket is listening.
+pub(super) fn spawn(namespace: &str, addr: Ipv4Addr, services: &[FakeService]) -> io::Result<()> {
+    let path = format!("/run/netns/{}", namespace);
+    let services = services.to_vec();
+    let (ready, ready_rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        // Threads spawned from here on start in the namespace too
+        let started = enter(&path).and_then(|()| {
+            for service in services {
+                start(addr, service)?;
+            }
+            Ok(())
+        });
+        let _ = ready.send(started);
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+}
+
+fn start(addr: Ipv4Addr, service: FakeService) -> io::Result<()> {
+    match service {
+        FakeService::Http { port, server, body } => {
+            serve_tcp(SocketAddrV4::new(addr, port), move |_| http_response(&server, "text/html", &body))
+        }
+        FakeService::Telnet { port, banner } => {
+            serve_tcp(SocketAddrV4::new(addr, port), move |_| format!("{}\r\nlogin: ", banner))
+        }
+        FakeService::Ssdp { http_port, friendly_name } => {
+            let description = format!(
+                "<?xml version=\"1.0\"?><root xmlns=\"urn:schemas-upnp-org:device-1-0\"><device>\
+                 <friendlyName>{}</friendlyName></device></root>",
+                friendly_name
+            );
+            serve_tcp(SocketAddrV4::new(addr, http_port), move |_| {
+                http_response("fake-upnp/1.0", "text/xml", &description)
+            })?;
+            serve_ssdp(addr, http_port)
+        }
+    }
+}
+
+fn http_response(server: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.0 200 OK\r\nServer: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        server,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Accept connections forever, answering each with `respond(request)`
+fn serve_tcp(addr: SocketAddrV4, respond: impl Fn(&str) -> String + Send + 'static) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(200)));
+            let mut buf = [0u8; 2048];
+            let len = stream.read(&mut buf).unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..len]);
+            let _ = stream.write_all(respond(&request).as_bytes());
+        }
+    });
+    Ok(())
+}
+
+fn serve_ssdp(addr: Ipv4Addr, http_port: u16) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 1900))?;
+    socket.join_multicast_v4(&SSDP_GROUP, &addr)?;
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            if !buf[..len].starts_with(b"M-SEARCH") {
+                continue;
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\n\
+                 LOCATION: http://{}:{}/description.xml\r\nST: upnp:rootdevice\r\n\
+                 USN: uuid:iot-doctor-fake-{}::upnp:rootdevice\r\n\r\n",
+                addr, http_port, addr
+            );
+            let _ = socket.send_to(response.as_bytes(), from);
+        }
+    });
+    Ok(())
+}
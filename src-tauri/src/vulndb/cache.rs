@@ -0,0 +1,109 @@
+//! Cache of Level 3 vulnerability checks
+//!
+//! The vulnerability phase of a Level 3 scan logs in with default
+//! credentials and probes camera streams, which takes long and can lock
+//! accounts. What it finds depends on the device and what identifies it:
+//! vendor, model, firmware, open ports and service versions. Findings are
+//! stored under that fingerprint so an unchanged device skips the probes on
+//! the next Level 3 scan. Entries expire after a while and are ignored once
+//! the data they were checked against changes (app release, data pack update
+//! or finding language).
+
+use super::content;
+use crate::database;
+use crate::scanner::{datapack, Device, SecurityIssue};
+
+/// How long findings are reused
+const CACHE_TTL_DAYS: i64 = 7;
+
+/// What the findings depend on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// MAC address; two devices of one model may not share a password
+    pub mac: String,
+    /// Whether default credentials were tried
+    pub credentials: bool,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub firmware: Option<String>,
+    /// Sorted open TCP/UDP port numbers
+    pub ports: Vec<u16>,
+    /// "port/version" of the services that reported a version, sorted
+    pub services: Vec<String>,
+}
+
+impl Fingerprint {
+    pub fn of(device: &Device, credentials: bool) -> Self {
+        let mut ports: Vec<u16> = device.open_ports.iter().map(|p| p.number).collect();
+        ports.sort_unstable();
+        ports.dedup();
+        let mut services: Vec<String> = device
+            .open_ports
+            .iter()
+            .filter_map(|p| Some(format!("{}/{}", p.number, p.version.as_deref()?)))
+            .collect();
+        services.sort_unstable();
+        Self {
+            mac: device.mac.to_lowercase(),
+            credentials,
+            vendor: device.vendor.as_ref().map(|v| v.to_lowercase()),
+            model: device
+                .model
+                .clone()
+                .or_else(|| device.name.as_deref().and_then(|n| datapack::match_model(&n.to_lowercase()))),
+            firmware: device
+                .firmware
+                .as_ref()
+                .map(|f| format!("{} {}", f.kind.label(), f.version.as_deref().unwrap_or_default()))
+                .or_else(|| device.onvif.as_ref()?.firmware_version.clone()),
+            ports,
+            services,
+        }
+    }
+
+    fn key(&self) -> String {
+        let ports: Vec<String> = self.ports.iter().map(u16::to_string).collect();
+        [
+            self.mac.as_str(),
+            if self.credentials { "credentials" } else { "" },
+            self.vendor.as_deref().unwrap_or_default(),
+            self.model.as_deref().unwrap_or_default(),
+            self.firmware.as_deref().unwrap_or_default(),
+            &ports.join(","),
+            &self.services.join(","),
+        ]
+        .join("|")
+    }
+}
+
+/// Identifies the data findings were checked against
+fn data_version() -> String {
+    format!(
+        "{}/{}/{:?}",
+        env!("CARGO_PKG_VERSION"),
+        datapack::info().map_or(0, |info| info.version),
+        content::language()
+    )
+}
+
+/// Fresh findings for `fingerprint`, if any
+pub fn get(fingerprint: &Fingerprint) -> Option<Vec<SecurityIssue>> {
+    let (results, version, checked_at) = database::get_vuln_matches(&fingerprint.key()).ok().flatten()?;
+    let fresh = chrono::Utc::now() - checked_at < chrono::Duration::days(CACHE_TTL_DAYS);
+    if !fresh || version != data_version() {
+        return None;
+    }
+    serde_json::from_str(&results).ok()
+}
+
+/// Remember the findings for `fingerprint`
+pub fn put(fingerprint: &Fingerprint, issues: &[SecurityIssue]) {
+    if let Ok(results) = serde_json::to_string(issues) {
+        let _ = database::put_vuln_matches(&fingerprint.key(), &data_version(), &results);
+    }
+}
+
+/// Drop all findings, e.g. after the vulnerability data was updated
+pub fn clear() -> Result<(), database::DbError> {
+    database::clear_vuln_matches()
+}
@@ -0,0 +1,229 @@
+//! Browsable catalog of checks and vulnerabilities
+//!
+//! Lists everything a scan looks for so users can see what was (and was not)
+//! checked, and looks up CVEs by ID, falling back to the NVD for CVEs the
+//! built-in database does not know about.
+
+use super::{check_port_vulnerability, client, content, knowledge, Vulnerability, VulnDbError};
+use crate::scanner::{IssueSeverity, Reference, ScanLevel};
+use serde::{Deserialize, Serialize};
+
+/// NVD CVE API (single CVE lookups need no API key)
+const NVD_CVE_URL: &str = "https://services.nvd.nist.gov/rest/json/cves/2.0?cveId=";
+
+/// NVD CVE API for keyword searches
+const NVD_SEARCH_URL: &str = "https://services.nvd.nist.gov/rest/json/cves/2.0";
+
+/// CVEs taken from one maker and model search
+const MAX_PRODUCT_CVES: usize = 20;
+
+/// (protocol, port) pairs with a built-in vulnerability entry
+const VULNERABLE_PORTS: &[(&str, u16)] = &[("tcp", 23), ("tcp", 21), ("udp", 1900), ("udp", 161), ("udp", 5683), ("udp", 69)];
+
+/// A check performed during scans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckInfo {
+    /// Issue ID the check reports under
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Typical severity; some checks adjust it per device
+    pub severity: IssueSeverity,
+    /// Lowest scan level that runs the check
+    pub level: ScanLevel,
+    pub references: Vec<Reference>,
+}
+
+/// (id, severity, level) of checks raised directly by the scanner; their
+/// title and description are in the content packs
+const CHECKS: &[(&str, IssueSeverity, ScanLevel)] = &[
+    ("personal-hostname", IssueSeverity::Info, ScanLevel::Level1),
+    ("ip-conflict", IssueSeverity::Medium, ScanLevel::Level1),
+    ("mdns-name-conflict", IssueSeverity::High, ScanLevel::Level1),
+    ("mdns-name-moved", IssueSeverity::Medium, ScanLevel::Level1),
+    ("wifi-open", IssueSeverity::High, ScanLevel::Level1),
+    ("wifi-wep", IssueSeverity::High, ScanLevel::Level1),
+    ("wifi-wpa", IssueSeverity::Medium, ScanLevel::Level1),
+    ("wifi-wps", IssueSeverity::Medium, ScanLevel::Level1),
+    ("port-mapping", IssueSeverity::High, ScanLevel::Level2),
+    ("port-forwarded", IssueSeverity::High, ScanLevel::Level2),
+    ("port-forward-reachable", IssueSeverity::Critical, ScanLevel::Level2),
+    ("double-nat", IssueSeverity::Info, ScanLevel::Level2),
+    ("carrier-grade-nat", IssueSeverity::Info, ScanLevel::Level2),
+    ("router-wan-admin", IssueSeverity::High, ScanLevel::Level2),
+    ("router-outdated-server", IssueSeverity::Medium, ScanLevel::Level2),
+    ("login-page-http", IssueSeverity::Medium, ScanLevel::Level2),
+    ("login-page-default-user", IssueSeverity::Low, ScanLevel::Level2),
+    ("login-page-no-lockout", IssueSeverity::Info, ScanLevel::Level2),
+    ("login-page-wps-pin", IssueSeverity::Medium, ScanLevel::Level2),
+    ("dns-rogue-resolver", IssueSeverity::High, ScanLevel::Level2),
+    ("dns-unknown-resolver", IssueSeverity::Low, ScanLevel::Level2),
+    ("dns-rebinding", IssueSeverity::Medium, ScanLevel::Level2),
+    ("dns-unencrypted", IssueSeverity::Info, ScanLevel::Level2),
+    ("ipv6-exposed", IssueSeverity::Critical, ScanLevel::Level2),
+    ("admin-interface-http", IssueSeverity::Medium, ScanLevel::Level2),
+    ("admin-http", IssueSeverity::Medium, ScanLevel::Level2),
+    ("ssh-wan-exposed", IssueSeverity::High, ScanLevel::Level2),
+    ("silent-device", IssueSeverity::Low, ScanLevel::Level2),
+    ("snmp-default-community", IssueSeverity::High, ScanLevel::Level2),
+    ("dropbear-outdated", IssueSeverity::Medium, ScanLevel::Level2),
+    ("telnet-open", IssueSeverity::High, ScanLevel::Level3),
+    ("upnp-enabled", IssueSeverity::Medium, ScanLevel::Level3),
+    ("anonymous-share", IssueSeverity::High, ScanLevel::Level3),
+    ("smbv1-enabled", IssueSeverity::Medium, ScanLevel::Level3),
+    ("rtsp-stream-open", IssueSeverity::Critical, ScanLevel::Level3),
+    ("ble-no-pairing", IssueSeverity::Medium, ScanLevel::Level2),
+    ("default-password", IssueSeverity::Critical, ScanLevel::Level3),
+];
+
+/// Every check a scan performs, described in the current language
+pub fn list_checks() -> Vec<CheckInfo> {
+    CHECKS
+        .iter()
+        .map(|(id, severity, level)| {
+            let (title, description) =
+                content::check_text(id, content::language()).unwrap_or_else(|| (id.to_string(), String::new()));
+            CheckInfo {
+                id: id.to_string(),
+                title,
+                description,
+                severity: *severity,
+                level: *level,
+                references: knowledge::references_for(id),
+            }
+        })
+        .collect()
+}
+
+/// Lowest scan level that runs the check reporting `id`
+pub fn check_level(id: &str) -> Option<ScanLevel> {
+    CHECKS.iter().find(|check| check.0 == id).map(|check| check.2)
+}
+
+/// Vulnerabilities in the built-in database
+pub fn builtin() -> Vec<Vulnerability> {
+    VULNERABLE_PORTS
+        .iter()
+        .filter_map(|&(protocol, port)| check_port_vulnerability(protocol, port))
+        .collect()
+}
+
+/// Whether `text` is a CVE identifier such as "CVE-2021-44228"
+fn is_cve_id(text: &str) -> bool {
+    let mut parts = text.split('-');
+    parts.next().is_some_and(|p| p.eq_ignore_ascii_case("CVE"))
+        && parts.next().is_some_and(|y| y.len() == 4 && y.chars().all(|c| c.is_ascii_digit()))
+        && parts.next().is_some_and(|n| n.len() >= 4 && n.chars().all(|c| c.is_ascii_digit()))
+        && parts.next().is_none()
+}
+
+/// Search the built-in database; a CVE ID not found there is looked up in the NVD
+pub async fn search(query: &str) -> Result<Vec<Vulnerability>, VulnDbError> {
+    let query = query.trim();
+    let lower = query.to_lowercase();
+
+    let matches: Vec<Vulnerability> = builtin()
+        .into_iter()
+        .filter(|v| {
+            lower.is_empty()
+                || v.id.to_lowercase().contains(&lower)
+                || v.cve.as_deref().is_some_and(|c| c.to_lowercase().contains(&lower))
+                || v.title.to_lowercase().contains(&lower)
+                || v.description.to_lowercase().contains(&lower)
+                || v.affected_vendors.iter().any(|x| x.to_lowercase().contains(&lower))
+                || v.affected_products.iter().any(|x| x.to_lowercase().contains(&lower))
+        })
+        .collect();
+
+    if matches.is_empty() && is_cve_id(query) {
+        return Ok(fetch_nvd(query).await?.into_iter().collect());
+    }
+    Ok(matches)
+}
+
+/// Look up a vulnerability by built-in ID or CVE ID
+pub async fn get(id: &str) -> Result<Option<Vulnerability>, VulnDbError> {
+    let id = id.trim();
+    let found = builtin().into_iter().find(|v| {
+        v.id.eq_ignore_ascii_case(id) || v.cve.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(id))
+    });
+    match found {
+        Some(v) => Ok(Some(v)),
+        None if is_cve_id(id) => fetch_nvd(id).await,
+        None => Ok(None),
+    }
+}
+
+/// Fetch a CVE from the NVD
+async fn fetch_nvd(cve_id: &str) -> Result<Option<Vulnerability>, VulnDbError> {
+    let cve_id = cve_id.to_uppercase();
+    let json = client::shared().get_json(&format!("{}{}", NVD_CVE_URL, cve_id)).await?;
+    Ok(json["vulnerabilities"].get(0).and_then(|v| parse_nvd_cve(&v["cve"])))
+}
+
+/// CVEs the NVD lists for a maker and model. A lookup that could not be made
+/// is queued and fails with `VulnDbError::Deferred`.
+pub(super) async fn fetch_nvd_product(vendor: &str, model: &str) -> Result<Vec<Vulnerability>, VulnDbError> {
+    let keywords = format!("{} {}", vendor, model);
+    let per_page = MAX_PRODUCT_CVES.to_string();
+    let url = reqwest::Url::parse_with_params(
+        NVD_SEARCH_URL,
+        &[("keywordSearch", keywords.as_str()), ("resultsPerPage", per_page.as_str())],
+    )
+    .map_err(|e| VulnDbError::LookupFailed(e.to_string()))?;
+    // Only CVEs naming the maker and model together
+    let json = client::shared().get_json(&format!("{}&keywordExactMatch", url)).await?;
+    Ok(json["vulnerabilities"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| parse_nvd_cve(&v["cve"]))
+        .take(MAX_PRODUCT_CVES)
+        .map(|mut vuln| {
+            vuln.affected_vendors = vec![vendor.to_string()];
+            vuln.affected_products = vec![model.to_string()];
+            vuln
+        })
+        .collect())
+}
+
+/// A CVE record of the NVD API
+fn parse_nvd_cve(cve: &serde_json::Value) -> Option<Vulnerability> {
+    let cve_id = cve["id"].as_str()?.to_uppercase();
+    let description = cve["descriptions"]
+        .as_array()
+        .and_then(|d| d.iter().find(|d| d["lang"] == "en"))
+        .and_then(|d| d["value"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let metrics = &cve["metrics"];
+    let base_severity = ["cvssMetricV31", "cvssMetricV30", "cvssMetricV2"]
+        .iter()
+        .find_map(|key| {
+            let metric = metrics[*key].get(0)?;
+            metric["cvssData"]["baseSeverity"]
+                .as_str()
+                .or_else(|| metric["baseSeverity"].as_str())
+        })
+        .unwrap_or_default();
+    let severity = match base_severity {
+        "CRITICAL" => IssueSeverity::Critical,
+        "HIGH" => IssueSeverity::High,
+        "MEDIUM" => IssueSeverity::Medium,
+        "LOW" => IssueSeverity::Low,
+        _ => IssueSeverity::Info,
+    };
+
+    Some(Vulnerability {
+        id: cve_id.clone(),
+        cve: Some(cve_id.clone()),
+        severity,
+        title: cve_id.clone(),
+        description,
+        affected_vendors: Vec::new(),
+        affected_products: Vec::new(),
+        remediation: "メーカーのサポート情報を確認し、修正済みのファームウェアに更新してください。".to_string(),
+        references: vec![knowledge::cve_reference(&cve_id)],
+    })
+}
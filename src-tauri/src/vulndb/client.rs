@@ -0,0 +1,147 @@
+//! Shared HTTP client for vulnerability and enrichment APIs
+//!
+//! All remote lookups (NVD, EPSS, OUI APIs) go through this client so that a
+//! flaky connection is retried with exponential backoff, successful responses
+//! are cached, and requests that still fail are queued and completed later
+//! instead of silently producing an empty ("all clear") result. A scan whose
+//! lookup was queued rates the device `InsufficientData` rather than safe,
+//! and the next scan finds the answer in the cache.
+
+use super::VulnDbError;
+use crate::{database, secrets};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Number of attempts before a request is queued
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled after each attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Per-request timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long cached responses are considered fresh
+const CACHE_TTL_HOURS: i64 = 24;
+
+static CLIENT: OnceLock<ApiClient> = OnceLock::new();
+
+/// Retrying, caching HTTP client
+pub struct ApiClient {
+    http: reqwest::Client,
+}
+
+/// Get the process-wide client
+pub fn shared() -> &'static ApiClient {
+    CLIENT.get_or_init(|| ApiClient {
+        http: reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("iot-doctor/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default(),
+    })
+}
+
+impl ApiClient {
+    /// Fetch a URL, serving a fresh cached copy when available.
+    ///
+    /// If every attempt fails with a transient error, the request is queued
+    /// and `VulnDbError::Deferred` is returned so callers can report the
+    /// result as incomplete. A stale cached copy is preferred over deferring.
+    pub async fn get_text(&self, url: &str) -> Result<String, VulnDbError> {
+        let cached = database::get_cached_response(url).ok().flatten();
+        if let Some((ref body, fetched_at)) = cached {
+            if chrono::Utc::now() - fetched_at < chrono::Duration::hours(CACHE_TTL_HOURS) {
+                return Ok(body.clone());
+            }
+        }
+
+        match self.fetch_with_retry(url).await {
+            Ok(body) => {
+                let _ = database::put_cached_response(url, &body);
+                Ok(body)
+            }
+            Err(VulnDbError::NetworkError(reason)) => {
+                if let Some((body, _)) = cached {
+                    return Ok(body);
+                }
+                database::enqueue_request(url)?;
+                Err(VulnDbError::Deferred(reason))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fetch a URL bypassing the cache and the queue, for downloads the user
+    /// asked for that must be current
+    pub async fn get_fresh(&self, url: &str) -> Result<String, VulnDbError> {
+        self.fetch_with_retry(url).await
+    }
+
+    /// Fetch a URL and parse the body as JSON
+    pub async fn get_json(&self, url: &str) -> Result<serde_json::Value, VulnDbError> {
+        let body = self.get_text(url).await?;
+        serde_json::from_str(&body).map_err(|e| VulnDbError::LookupFailed(e.to_string()))
+    }
+
+    /// Retry queued requests; returns the number that completed
+    pub async fn process_queue(&self) -> Result<usize, VulnDbError> {
+        let mut completed = 0;
+
+        for url in database::get_queued_requests()? {
+            match self.fetch_with_retry(&url).await {
+                Ok(body) => {
+                    database::put_cached_response(&url, &body)?;
+                    database::remove_queued_request(&url)?;
+                    completed += 1;
+                }
+                // Still offline: leave the rest queued for the next attempt
+                Err(VulnDbError::NetworkError(_)) => {
+                    database::enqueue_request(&url)?;
+                    break;
+                }
+                // Permanent failure: drop it so it does not block the queue
+                Err(_) => database::remove_queued_request(&url)?,
+            }
+        }
+
+        Ok(completed)
+    }
+
+    /// Fetch with exponential backoff on transient failures.
+    /// Transient failures surface as `NetworkError`; anything else is permanent.
+    async fn fetch_with_retry(&self, url: &str) -> Result<String, VulnDbError> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut last_error = String::new();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            match self.http.get(url).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        return response
+                            .text()
+                            .await
+                            .map_err(|e| VulnDbError::NetworkError(e.to_string()));
+                    }
+                    if !(status.is_server_error() || status.as_u16() == 429) {
+                        return Err(VulnDbError::LookupFailed(secrets::redact(&format!(
+                            "{} returned {}",
+                            url, status
+                        ))));
+                    }
+                    last_error = format!("{} returned {}", url, status);
+                }
+                Err(e) => last_error = e.to_string(),
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        // URLs may carry API keys in their query string
+        Err(VulnDbError::NetworkError(secrets::redact(&last_error)))
+    }
+}
@@ -0,0 +1,219 @@
+//! Localizable finding text
+//!
+//! Titles, descriptions and remediations of findings live in content packs
+//! (`content/<language>.json`) keyed by finding ID rather than in the code
+//! that raises them. Issues keep the values substituted into their text, so
+//! a stored scan can be rendered again in another language.
+//!
+//! Templates refer to parameters as `{name}`. A parameter whose value is
+//! `@key` is replaced by the pack's phrase `key`, which lets a finding choose
+//! between sentences without storing one language's text in the issue. An
+//! issue with a `variant` parameter uses the `<id>.<variant>` entry.
+//!
+//! The `ports` section explains what a port is commonly used for and why it
+//! matters when open. `checks` describes the catalog's checks, `checklist`
+//! titles the items of router checklists, and `labels` holds the rest of the
+//! interface and report text, e.g. scan phase names and report headings.
+
+use super::{knowledge, Vulnerability};
+use crate::scanner::{Device, IssueSeverity, SecurityIssue};
+use crate::settings::locale::Language;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{OnceLock, RwLock};
+
+/// Language new findings are rendered in
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::Ja);
+
+static PACKS: OnceLock<HashMap<Language, ContentPack>> = OnceLock::new();
+
+/// Text of one finding
+#[derive(Debug, Deserialize)]
+struct FindingText {
+    title: String,
+    description: String,
+    remediation: String,
+}
+
+/// What a catalog check looks for
+#[derive(Debug, Deserialize)]
+struct CheckText {
+    title: String,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentPack {
+    findings: HashMap<String, FindingText>,
+    #[serde(default)]
+    phrases: HashMap<String, String>,
+    /// Port number → what the port is used for and why it matters
+    #[serde(default)]
+    ports: HashMap<u16, String>,
+    #[serde(default)]
+    checks: HashMap<String, CheckText>,
+    /// Checklist item ID → title
+    #[serde(default)]
+    checklist: HashMap<String, String>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+fn pack(language: Language) -> Option<&'static ContentPack> {
+    PACKS
+        .get_or_init(|| {
+            [
+                (Language::Ja, include_str!("../../content/ja.json")),
+                (Language::En, include_str!("../../content/en.json")),
+            ]
+            .into_iter()
+            .filter_map(|(language, json)| match serde_json::from_str(json) {
+                Ok(pack) => Some((language, pack)),
+                Err(e) => {
+                    eprintln!("Invalid {:?} content pack: {}", language, e);
+                    None
+                }
+            })
+            .collect()
+        })
+        .get(&language)
+}
+
+/// Select the language new findings are rendered in
+pub fn set_language(language: Language) {
+    *LANGUAGE.write().unwrap() = language;
+}
+
+/// Language new findings are rendered in
+pub fn language() -> Language {
+    *LANGUAGE.read().unwrap()
+}
+
+fn lookup<'a>(pack: &'a ContentPack, id: &str, params: &BTreeMap<String, String>) -> Option<&'a FindingText> {
+    params
+        .get("variant")
+        .and_then(|variant| pack.findings.get(&format!("{}.{}", id, variant)))
+        .or_else(|| pack.findings.get(id))
+}
+
+/// Substitute `{name}` placeholders, expanding phrases one level deep.
+/// `None` if a parameter is missing, e.g. in an issue stored before its
+/// text moved to the content pack.
+fn render(
+    template: &str,
+    params: &BTreeMap<String, String>,
+    pack: &ContentPack,
+    expand_phrases: bool,
+) -> Option<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            out.push_str(&rest[start..]);
+            return Some(out);
+        };
+        let value = params.get(&rest[start + 1..start + len])?;
+        match value.strip_prefix('@') {
+            Some(key) if expand_phrases => {
+                let phrase = pack.phrases.get(key).map_or(key, String::as_str);
+                out.push_str(&render(phrase, params, pack, false)?);
+            }
+            _ => out.push_str(value),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Build an issue from the content pack in the current language
+pub fn issue(id: &str, severity: IssueSeverity, params: &[(&str, String)]) -> SecurityIssue {
+    let mut issue = SecurityIssue {
+        id: id.to_string(),
+        severity,
+        base_severity: None,
+        title: id.to_string(),
+        description: String::new(),
+        remediation: String::new(),
+        references: knowledge::references_for(id),
+        params: params.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+    };
+    localize(&mut issue, language());
+    issue
+}
+
+/// Render an issue's text in `language`; issues without pack text (e.g. CVEs
+/// looked up online) are left as they are
+pub fn localize(issue: &mut SecurityIssue, language: Language) {
+    let Some(pack) = pack(language) else {
+        return;
+    };
+    let Some(text) = lookup(pack, &issue.id, &issue.params) else {
+        return;
+    };
+    let params = &issue.params;
+    if let (Some(title), Some(description), Some(remediation)) = (
+        render(&text.title, params, pack, true),
+        render(&text.description, params, pack, true),
+        render(&text.remediation, params, pack, true),
+    ) {
+        issue.title = title;
+        issue.description = description;
+        issue.remediation = remediation;
+    }
+}
+
+/// Why an open port matters, from the content pack in `language`
+pub fn port_note(port: u16, language: Language) -> Option<String> {
+    pack(language)?.ports.get(&port).cloned()
+}
+
+/// Title and description of a catalog check in `language`
+pub fn check_text(id: &str, language: Language) -> Option<(String, String)> {
+    let text = pack(language)?.checks.get(id)?;
+    Some((text.title.clone(), text.description.clone()))
+}
+
+/// Title of a router checklist item in `language`, or its ID
+pub fn checklist_title(id: &str, language: Language) -> String {
+    pack(language)
+        .and_then(|pack| pack.checklist.get(id))
+        .cloned()
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Interface or report text in `language`, falling back to Japanese and
+/// then to the key
+pub fn label(key: &str, language: Language) -> String {
+    [language, Language::Ja]
+        .into_iter()
+        .find_map(|language| pack(language)?.labels.get(key).cloned())
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Render the issues, port notes and checklists of all devices in `language`
+pub fn localize_devices(devices: &mut [Device], language: Language) {
+    for device in devices.iter_mut() {
+        for issue in &mut device.issues {
+            localize(issue, language);
+        }
+        for port in &mut device.open_ports {
+            port.risk_note = port_note(port.number, language);
+        }
+        let checks = device.firmware.iter_mut().flat_map(|f| &mut f.checks);
+        for check in checks.chain(device.router_audit.iter_mut().flat_map(|a| &mut a.checks)) {
+            check.title = checklist_title(&check.id, language);
+        }
+    }
+}
+
+/// Render a built-in vulnerability entry in `language`
+pub fn localize_vulnerability(vuln: &mut Vulnerability, language: Language) {
+    let Some(text) = pack(language).and_then(|pack| pack.findings.get(&vuln.id)) else {
+        return;
+    };
+    vuln.title = text.title.clone();
+    vuln.description = text.description.clone();
+    vuln.remediation = text.remediation.clone();
+}
@@ -0,0 +1,57 @@
+//! Knowledge base of learn-more links for issues and vulnerabilities
+
+use crate::scanner::{Reference, ReferenceKind};
+
+/// Plain-language explainer pages keyed by issue or vulnerability ID
+const EXPLAINERS: &[(&str, &str, &str)] = &[
+    ("default-password", "NOTICE（IoT機器のパスワード設定の注意喚起）", "https://notice.go.jp/"),
+    ("default-password", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("rtsp-stream-open", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("telnet-open", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("upnp-enabled", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("port-mapping", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("double-nat", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("ipv6-exposed", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("admin-interface-http", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("admin-http", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("ssh-wan-exposed", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("port-forwarded", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("port-forward-reachable", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("router-wan-admin", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("router-outdated-server", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("dns-rogue-resolver", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("dns-unknown-resolver", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("dns-rebinding", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("dns-unencrypted", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("snmp-default-community", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("ble-no-pairing", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("personal-hostname", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-001", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-002", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-003", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-004", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-005", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+    ("IOTDOC-006", "国民のためのサイバーセキュリティサイト（総務省）", "https://www.soumu.go.jp/main_sosiki/cybersecurity/kokumin/"),
+];
+
+/// Link to the NVD entry for a CVE
+pub fn cve_reference(cve: &str) -> Reference {
+    Reference {
+        kind: ReferenceKind::Cve,
+        title: cve.to_string(),
+        url: format!("https://nvd.nist.gov/vuln/detail/{}", cve),
+    }
+}
+
+/// Learn-more links for an issue or vulnerability ID
+pub fn references_for(id: &str) -> Vec<Reference> {
+    EXPLAINERS
+        .iter()
+        .filter(|(key, _, _)| *key == id)
+        .map(|(_, title, url)| Reference {
+            kind: ReferenceKind::Explainer,
+            title: title.to_string(),
+            url: url.to_string(),
+        })
+        .collect()
+}
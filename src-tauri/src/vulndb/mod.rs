@@ -1,9 +1,16 @@
 //! Vulnerability database and checking
 
-use crate::scanner::{Device, SecurityIssue, IssueSeverity};
+use crate::scanner::{Device, Reference, SecurityIssue, IssueSeverity};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+pub mod cache;
+pub mod catalog;
+pub mod client;
+pub mod content;
+pub mod knowledge;
+
 /// Vulnerability database errors
 #[derive(Error, Debug)]
 pub enum VulnDbError {
@@ -12,6 +19,12 @@ pub enum VulnDbError {
 
     #[error("Network error: {0}")]
     NetworkError(String),
+
+    #[error("Lookup deferred until the network is available: {0}")]
+    Deferred(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] crate::database::DbError),
 }
 
 /// Known vulnerability entry
@@ -25,6 +38,8 @@ pub struct Vulnerability {
     pub affected_vendors: Vec<String>,
     pub affected_products: Vec<String>,
     pub remediation: String,
+    #[serde(default)]
+    pub references: Vec<Reference>,
 }
 
 /// Default credential entry
@@ -53,65 +68,56 @@ pub async fn check_device(device: &Device) -> Result<Vec<Vulnerability>, VulnDbE
 
     // Check for known vulnerable ports
     for port in &device.open_ports {
-        if let Some(vuln) = check_port_vulnerability(port.number) {
+        if let Some(vuln) = check_port_vulnerability(&port.protocol, port.number) {
             vulnerabilities.push(vuln);
         }
     }
 
-    // Check vendor-specific vulnerabilities
-    if let Some(ref vendor) = device.vendor {
-        let vendor_vulns = check_vendor_vulnerabilities(vendor);
-        vulnerabilities.extend(vendor_vulns);
-    }
+    vulnerabilities.extend(check_vendor_vulnerabilities(device).await?);
 
     Ok(vulnerabilities)
 }
 
-fn check_port_vulnerability(port: u16) -> Option<Vulnerability> {
-    match port {
-        23 => Some(Vulnerability {
-            id: "IOTDOC-001".to_string(),
-            cve: None,
-            severity: IssueSeverity::High,
-            title: "Telnetサービスが有効".to_string(),
-            description: "Telnetは暗号化されていない通信を使用するため、\
-                         認証情報が傍受される危険性があります。".to_string(),
-            affected_vendors: vec!["*".to_string()],
-            affected_products: vec!["*".to_string()],
-            remediation: "Telnetを無効化し、SSHなどの暗号化された\
-                         プロトコルを使用してください。".to_string(),
-        }),
-        21 => Some(Vulnerability {
-            id: "IOTDOC-002".to_string(),
-            cve: None,
-            severity: IssueSeverity::Medium,
-            title: "FTPサービスが有効".to_string(),
-            description: "FTPは認証情報を平文で送信するため、\
-                         セキュリティ上のリスクがあります。".to_string(),
-            affected_vendors: vec!["*".to_string()],
-            affected_products: vec!["*".to_string()],
-            remediation: "FTPを無効化し、SFTPやSCPを使用してください。".to_string(),
-        }),
-        1900 => Some(Vulnerability {
-            id: "IOTDOC-003".to_string(),
-            cve: None,
-            severity: IssueSeverity::Medium,
-            title: "UPnPサービスが有効".to_string(),
-            description: "UPnPは自動的にポート転送を設定できるため、\
-                         悪意のあるソフトウェアに悪用される可能性があります。".to_string(),
-            affected_vendors: vec!["*".to_string()],
-            affected_products: vec!["*".to_string()],
-            remediation: "UPnPが不要な場合は、ルーターの設定で\
-                         無効化することを検討してください。".to_string(),
-        }),
+fn check_port_vulnerability(protocol: &str, port: u16) -> Option<Vulnerability> {
+    match (protocol, port) {
+        ("tcp", 23) => Some(builtin_vulnerability("IOTDOC-001", IssueSeverity::High)),
+        ("tcp", 21) => Some(builtin_vulnerability("IOTDOC-002", IssueSeverity::Medium)),
+        (_, 1900) => Some(builtin_vulnerability("IOTDOC-003", IssueSeverity::Medium)),
+        // UDP ports are only reported when the probe was answered: SNMP
+        // answered the "public" community, CoAP served its resource list
+        ("udp", 161) => Some(builtin_vulnerability("IOTDOC-004", IssueSeverity::High)),
+        ("udp", 5683) => Some(builtin_vulnerability("IOTDOC-005", IssueSeverity::Medium)),
+        ("udp", 69) => Some(builtin_vulnerability("IOTDOC-006", IssueSeverity::Medium)),
         _ => None,
     }
 }
 
-fn check_vendor_vulnerabilities(_vendor: &str) -> Vec<Vulnerability> {
-    // TODO: Implement vendor-specific vulnerability checking
-    // This would query a local or remote vulnerability database
-    Vec::new()
+/// Built-in entry affecting every vendor, with text from the content pack
+fn builtin_vulnerability(id: &str, severity: IssueSeverity) -> Vulnerability {
+    let mut vuln = Vulnerability {
+        id: id.to_string(),
+        cve: None,
+        severity,
+        title: id.to_string(),
+        description: String::new(),
+        affected_vendors: vec!["*".to_string()],
+        affected_products: vec!["*".to_string()],
+        remediation: String::new(),
+        references: knowledge::references_for(id),
+    };
+    content::localize_vulnerability(&mut vuln, content::language());
+    vuln
+}
+
+/// CVEs published for the device's maker and model; nothing when either is
+/// unknown, as a maker alone matches too much. Offline, the lookup is queued
+/// and fails with `VulnDbError::Deferred`, so the result is never taken for
+/// "no known vulnerabilities".
+pub async fn check_vendor_vulnerabilities(device: &Device) -> Result<Vec<Vulnerability>, VulnDbError> {
+    match (device.vendor.as_deref(), device.model.as_deref()) {
+        (Some(vendor), Some(model)) => catalog::fetch_nvd_product(vendor, model).await,
+        _ => Ok(Vec::new()),
+    }
 }
 
 /// Get default credentials for a vendor/product
@@ -136,8 +142,17 @@ pub fn vulnerability_to_issue(vuln: &Vulnerability) -> SecurityIssue {
     SecurityIssue {
         id: vuln.id.clone(),
         severity: vuln.severity,
+        base_severity: None,
         title: vuln.title.clone(),
         description: vuln.description.clone(),
         remediation: vuln.remediation.clone(),
+        references: vuln
+            .cve
+            .as_deref()
+            .map(knowledge::cve_reference)
+            .into_iter()
+            .chain(vuln.references.iter().cloned())
+            .collect(),
+        params: BTreeMap::new(),
     }
 }
@@ -0,0 +1,60 @@
+//! End-to-end scanner tests against the simulated network
+//!
+//! sudo -E cargo test --features netns-tests -- --test-threads=1
+
+#![cfg(all(feature = "netns-tests", target_os = "linux"))]
+
+use iot_doctor_lib::scanner::{arp, ports, ssdp, ProgressSink, ScanProgress};
+use iot_doctor_lib::testnet::{FakeDevice, FakeService, SimNetwork};
+use std::time::Duration;
+
+const CAMERA_MAC: &str = "02:00:00:77:00:0a";
+
+fn camera() -> FakeDevice {
+    FakeDevice::new(CAMERA_MAC)
+        .with(FakeService::telnet(23, "BusyBox v1.19.4"))
+        .with(FakeService::http(80, "GoAhead-Webs"))
+        .with(FakeService::ssdp("Fake IP Camera"))
+}
+
+struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _update: &ScanProgress) {}
+}
+
+#[tokio::test]
+async fn sweep_finds_fake_devices() {
+    let _network = SimNetwork::start(vec![camera()]).expect("creating namespaces needs root");
+
+    let discovery = arp::discover_devices(&[], &Default::default(), &[], &NoProgress).await.expect("discovery");
+    assert!(discovery.hosts.iter().any(|(_, mac)| mac.eq_ignore_ascii_case(CAMERA_MAC)));
+}
+
+#[tokio::test]
+async fn port_scan_reports_telnet_and_http() {
+    let network = SimNetwork::start(vec![camera()]).expect("creating namespaces needs root");
+    let ip = network.devices()[0].ip.to_string();
+
+    let config = ports::ScanConfig::default();
+    let port_list = ports::ports_to_scan(&config, None);
+    let limiter = ports::ConnectLimiter::new(&config);
+    let open = ports::scan_ports(&ip, &port_list, &limiter, &Default::default()).await.expect("port scan");
+    let numbers: Vec<u16> = open.iter().map(|p| p.number).collect();
+    assert!(numbers.contains(&23));
+    assert!(numbers.contains(&80));
+    assert!(!numbers.contains(&22));
+
+    let banner = ports::grab_banner(&ip, 23).await;
+    assert_eq!(banner.as_deref(), Some("BusyBox v1.19.4"));
+}
+
+#[tokio::test]
+async fn ssdp_resolves_friendly_name() {
+    let network = SimNetwork::start(vec![camera()]).expect("creating namespaces needs root");
+    let ip = network.devices()[0].ip.to_string();
+
+    let descriptions = ssdp::scan_ssdp(Duration::from_secs(2)).await;
+    let name = descriptions.get(&ip).and_then(|d| d.friendly_name.as_deref());
+    assert_eq!(name, Some("Fake IP Camera"));
+}
@@ -5,6 +5,8 @@ export const scanStatus = writable({
   isScanning: false,
   progress: 0,
   currentPhase: '',
+  // Names of devices mDNS found while discovery still runs
+  discovered: [],
 });
 
 // Devices store
@@ -30,12 +32,14 @@ export const overallScore = derived(devices, ($devices) => {
   return Math.round(scores.reduce((a, b) => a + b, 0) / scores.length);
 });
 
-// Start scan function
-export async function startScan() {
+// Start scan function. `targets` lists interface names and CIDR ranges to
+// sweep; the default interface when omitted.
+export async function startScan(targets = null) {
   scanStatus.set({
     isScanning: true,
     progress: 0,
     currentPhase: '初期化中...',
+    discovered: [],
   });
 
   try {
@@ -50,9 +54,16 @@ export async function startScan() {
       const { phase, progress } = event.payload;
       scanStatus.update((s) => ({ ...s, currentPhase: phase, progress }));
     });
+    const unlistenDiscovered = await listen('mdns-discovered', (event) => {
+      const { ip, name } = event.payload;
+      scanStatus.update((s) => ({
+        ...s,
+        discovered: [...s.discovered.filter((d) => d.ip !== ip), { ip, name: name || ip }],
+      }));
+    });
 
     // Start scan (Level 2 for active scanning)
-    const result = await invoke('start_scan', { level: 'level2' });
+    const result = await invoke('start_scan', { level: 'level2', targets });
     
     // Sort devices by IP for better readability
     result.sort((a, b) => {
@@ -66,12 +77,13 @@ export async function startScan() {
 
     devices.set(result);
     unlisten();
+    unlistenDiscovered();
 
   } catch (error) {
     console.error('Scan failed:', error);
     scanStatus.update(s => ({ 
         ...s, 
-        currentPhase: `エラーが発生しました: ${error}`,
+        currentPhase: `エラーが発生しました: ${error?.message ?? error}${error?.hint ? `（${error.hint}）` : ''}`,
         progress: 100 
     }));
   } finally {